@@ -0,0 +1,130 @@
+//! 高速フーリエ変換の速さを、計画を使い回す今の実装（`realfft`）と、
+//! 毎回計画を立てて複素数の高速フーリエ変換をしていた以前の実装（`rustfft`）で比べます。
+//!
+//! ```shell
+//! $ cargo run --release --example fft_bench
+//! ```
+//! 既定では、解析の窓が4096フレームの時の既定のFFTのサイズ（ポイント数の規模が既定値の場合）と、
+//! FFTのサイズの上限の既定値（`DEFAULT_MAX_FFT_LENGTH`）で計ります。
+//! 引数にサイズを渡すと、そのサイズで計ります。（設定できる上限は`67108864`です。）
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use a_synthe_core::{
+    calculation::fft::{FftBackend, RustFftBackend},
+    params, Float, DEFAULT_MAX_FFT_LENGTH,
+};
+use rustfft::{num_complex::Complex, FftPlanner};
+
+/// 既定のサイズを計る時の解析の窓の長さ
+const WINDOW_LENGTH: usize = 4096;
+/// 一つのサイズで計る時間の目安
+const TARGET: Duration = Duration::from_secs(2);
+
+/// テスト用の音声データ（複数の正弦波を重ねたもの）を作ります。
+fn signal(length: usize) -> Vec<Float> {
+    (0..length)
+        .map(|i| {
+            let t = i as f32 / 48000.;
+            [440., 659.25, 1318.5]
+                .iter()
+                .map(|frequency| Float::from((2. * std::f32::consts::PI * frequency * t).sin()))
+                .sum()
+        })
+        .collect()
+}
+
+/// `run`を繰り返して、一回あたりの時間を計ります。
+/// 最初の一回は準備（計画の作成等）を含むので、別に計ります。
+fn measure(mut run: impl FnMut()) -> (Duration, Duration, u32) {
+    let start = Instant::now();
+    run();
+    let first = start.elapsed();
+
+    let mut iterations = 0;
+    let start = Instant::now();
+    while iterations < 3 || start.elapsed() < TARGET {
+        run();
+        iterations += 1;
+    }
+
+    (first, start.elapsed() / iterations, iterations)
+}
+
+/// 計画を使い回す今の実装です。
+fn cached_realfft(data: &[Float]) -> (Duration, Duration, u32) {
+    let mut backend = RustFftBackend::default();
+    let mut input = vec![0.; data.len()];
+    let mut output = vec![Complex::default(); data.len() / 2 + 1];
+
+    measure(|| {
+        // NOTE: `input`は作業用に書き換えられるので、毎回戻す。
+        input.copy_from_slice(data);
+        backend.process(&mut input, &mut output);
+        black_box(&output);
+    })
+}
+
+/// 以前の実装です。
+/// 呼ばれる度に計画を立てて、虚部を0にした複素数の高速フーリエ変換をしていました。
+fn planned_rustfft(data: &[Float]) -> (Duration, Duration, u32) {
+    measure(|| {
+        let mut buffer: Vec<Complex<Float>> =
+            data.iter().map(|&re| Complex { re, im: 0. }).collect();
+
+        let mut planner = FftPlanner::<Float>::new();
+        let fft = planner.plan_fft_forward(buffer.len());
+        fft.process(&mut buffer);
+
+        black_box(&buffer);
+    })
+}
+
+fn main() {
+    let sizes: Vec<usize> = std::env::args()
+        .skip(1)
+        .map(|size| {
+            let size = size.parse().expect("サイズは整数で指定してください。");
+            assert!(
+                (1..=params::MAX_FFT_LENGTH.max as usize).contains(&size),
+                "サイズは1から{}までで指定してください。",
+                params::MAX_FFT_LENGTH.max
+            );
+            size
+        })
+        .collect();
+    let sizes = if sizes.is_empty() {
+        vec![
+            WINDOW_LENGTH * params::POINT_TIMES.default as usize,
+            DEFAULT_MAX_FFT_LENGTH as usize,
+        ]
+    } else {
+        sizes
+    };
+
+    println!(
+        "{:>10} {:>24} {:>14} {:>14} {:>8}",
+        "size", "backend", "first", "mean", "ratio"
+    );
+    for size in sizes {
+        let data = signal(size);
+
+        let (cached_first, cached, cached_iterations) = cached_realfft(&data);
+        let (planned_first, planned, planned_iterations) = planned_rustfft(&data);
+
+        println!(
+            "{size:>10} {:>24} {:>14?} {:>14?} {:>8} ({cached_iterations}回)",
+            "realfft (cached plan)", cached_first, cached, "1.00"
+        );
+        println!(
+            "{size:>10} {:>24} {:>14?} {:>14?} {:>8.2} ({planned_iterations}回)",
+            "rustfft (plan per call)",
+            planned_first,
+            planned,
+            planned.as_secs_f64() / cached.as_secs_f64()
+        );
+    }
+}
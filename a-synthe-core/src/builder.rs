@@ -0,0 +1,115 @@
+//! `Synthesizer`を、設定をまとめて指定して作るためのモジュールです。
+//! 作った後に`Config`の値を一つずつ変える代わりに、作る前に必要な設定を並べて書けます。
+//!
+//! ```no_run
+//! use a_synthe_core::{RankingStrategy, Synthesizer};
+//!
+//! let synthesizer = Synthesizer::builder(48000.)
+//!     .window_length(4096)
+//!     .point_times(4)
+//!     .ranking_strategy(RankingStrategy::HarmonicWeighted)
+//!     .note_range(40..=88)
+//!     .build();
+//! ```
+
+use std::ops::RangeInclusive;
+
+use super::{params, plugin::Plugin, ConfigSnapshot, NoteContainer, RankingStrategy, Synthesizer};
+
+/// `Synthesizer`を作るためのビルダーです。
+/// 指定しなかった設定は初期値（`settings`を指定した場合はその値）になります。
+/// 値は設定できる範囲に丸め込みます。
+pub struct SynthesizerBuilder {
+    frame_rate: f32,
+    notes: NoteContainer,
+    snapshot: ConfigSnapshot,
+    plugins: Vec<Plugin>,
+}
+
+impl SynthesizerBuilder {
+    /// 入力の音声データのフレームレート（サンプリング周波数、Hz）を指定して、ビルダーを作ります。
+    pub fn new(frame_rate: f32) -> Self {
+        Self {
+            frame_rate,
+            notes: NoteContainer::new(),
+            snapshot: ConfigSnapshot::default(),
+            plugins: Vec::new(),
+        }
+    }
+
+    /// 検出する音階の表を指定します。指定しなかった場合は、ラを440Hzとした平均律の表を使います。
+    /// 基準の周波数（`ConfigSnapshot::reference_pitch`）か音律が変わると表は計算し直されるので、
+    /// 基準の周波数を変えたいだけの場合は、この表ではなく`settings`で指定してください。
+    pub fn notes(mut self, notes: NoteContainer) -> Self {
+        self.notes = notes;
+        self
+    }
+
+    /// 全ての設定を、保存しておいた設定等で置き換えます。
+    /// 先に呼んだ他のメソッドの設定も置き換えるので、他のメソッドより先に呼んでください。
+    pub fn settings(mut self, snapshot: &ConfigSnapshot) -> Self {
+        self.snapshot = snapshot.clone();
+        self
+    }
+
+    /// 解析の窓の長さ（フレーム数）を指定します。
+    /// 0の場合は、`Synthesizer::feed`に渡した音声データをそのまま解析します。
+    pub fn window_length(mut self, frames: u32) -> Self {
+        self.snapshot.analysis_window_length =
+            params::ANALYSIS_WINDOW_LENGTH.clamp(frames as _) as _;
+        self
+    }
+
+    /// 解析の窓を重ねる割合（%）を指定します。
+    pub fn overlap(mut self, percent: u32) -> Self {
+        self.snapshot.analysis_overlap = params::ANALYSIS_OVERLAP.clamp(percent as _) as _;
+        self
+    }
+
+    /// ポイント数の規模（FFTのサイズが音声データの長さの何倍になるか）を指定します。
+    pub fn point_times(mut self, point_times: u16) -> Self {
+        self.snapshot.point_times = params::POINT_TIMES.clamp(point_times as _) as _;
+        self
+    }
+
+    /// 音階の候補の順位付けの方法を指定します。
+    pub fn ranking_strategy(mut self, ranking_strategy: RankingStrategy) -> Self {
+        self.snapshot.ranking_strategy = RankingStrategy::ALL
+            .iter()
+            .position(|strategy| *strategy == ranking_strategy)
+            .unwrap_or_default() as _;
+        self
+    }
+
+    /// 検出方法のプラグインを使えるようにします。
+    pub fn plugins(mut self, plugins: Vec<Plugin>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// `plugins`で渡したプラグインのうち、`index`番目のものを順位付けの方法に使います。
+    pub fn plugin(mut self, index: usize) -> Self {
+        self.snapshot.ranking_strategy =
+            params::RANKING_STRATEGY.clamp((RankingStrategy::ALL.len() + index) as _) as _;
+        self
+    }
+
+    /// 検出する音の範囲（MIDIの番号）を指定します。
+    pub fn note_range(mut self, range: RangeInclusive<u8>) -> Self {
+        let (start, end) = range.into_inner();
+        self.snapshot.min_note = params::MIN_NOTE.clamp(start.min(end) as _) as _;
+        self.snapshot.max_note = params::MAX_NOTE.clamp(start.max(end) as _) as _;
+        self
+    }
+
+    /// `Synthesizer`を作ります。
+    pub fn build(self) -> Synthesizer {
+        let synthesizer = Synthesizer::new(self.notes, self.frame_rate);
+        synthesizer.config.restore(&self.snapshot);
+        if !self.plugins.is_empty() {
+            let _ = synthesizer.config.plugins.set(self.plugins);
+        };
+
+        synthesizer
+    }
+}
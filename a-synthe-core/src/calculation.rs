@@ -0,0 +1,1104 @@
+use std::{collections::VecDeque, sync::Arc};
+
+/// 解析に使う浮動小数点数の型です。
+/// `f64`フィーチャーを有効にすると`f64`となり、長い音声データでの計算誤差が小さくなります。
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
+/// 解析に使う浮動小数点数の型です。
+/// `f64`フィーチャーを有効にすると`f64`となり、長い音声データでの計算誤差が小さくなります。
+#[cfg(feature = "f64")]
+pub type Float = f64;
+
+/// 解析に使う浮動小数点数の型での円周率
+#[cfg(not(feature = "f64"))]
+const PI: Float = std::f32::consts::PI;
+/// 解析に使う浮動小数点数の型での円周率
+#[cfg(feature = "f64")]
+const PI: Float = std::f64::consts::PI;
+
+/// 解析に使う浮動小数点数を、SIMDでまとめて計算するための型です。
+#[cfg(not(feature = "f64"))]
+type Lanes = wide::f32x8;
+/// 解析に使う浮動小数点数を、SIMDでまとめて計算するための型です。
+#[cfg(feature = "f64")]
+type Lanes = wide::f64x4;
+/// `Lanes`でまとめて計算する値の数
+const LANES: usize = std::mem::size_of::<Lanes>() / std::mem::size_of::<Float>();
+
+/// スライスの先頭から`LANES`個の値を`Lanes`にします。
+#[inline(always)]
+fn load(values: &[Float]) -> Lanes {
+    Lanes::from(<[Float; LANES]>::try_from(&values[..LANES]).unwrap())
+}
+
+/// 窓関数の種類です。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WindowKind {
+    /// ハン窓
+    Hann,
+    /// テューキー窓（両端の`TUKEY_ALPHA / 2`の部分だけを滑らかに減衰させる、平らな部分の広い窓関数）
+    Tukey,
+    /// ハミング窓
+    Hamming,
+    /// ブラックマン・ハリス窓（サイドローブが小さく、近くの強い音に埋もれにくい窓関数）
+    BlackmanHarris,
+    /// フラットトップ窓（山の高さが正確に出る窓関数）
+    FlatTop,
+}
+
+/// テューキー窓の、減衰させる部分の割合
+const TUKEY_ALPHA: Float = 0.5;
+/// ブラックマン・ハリス窓の係数（四項）
+const BLACKMAN_HARRIS_COEFFICIENTS: [Float; 4] = [0.35875, 0.48829, 0.14128, 0.01168];
+/// フラットトップ窓の係数
+const FLAT_TOP_COEFFICIENTS: [Float; 5] =
+    [0.21557895, 0.41663158, 0.27726316, 0.08357895, 0.006947368];
+
+/// 係数`a`の余弦の和で表される窓関数の、長さ`length`の`i`番目の係数を計算します。
+/// `a[0] - a[1] cos(x) + a[2] cos(2x) - …`の形です。
+fn cosine_sum(a: &[Float], i: usize, length: usize) -> Float {
+    let x = 2. * PI * i as Float / length as Float;
+    a.iter()
+        .enumerate()
+        .map(|(k, a)| {
+            let term = a * (k as Float * x).cos();
+            if k % 2 == 0 {
+                term
+            } else {
+                -term
+            }
+        })
+        .sum()
+}
+
+impl WindowKind {
+    /// 長さ`length`の窓関数の`i`番目の係数を計算します。
+    fn coefficient(self, i: usize, length: usize) -> Float {
+        let float_length = length as Float;
+        match self {
+            // NOTE: 参考文献：https://cognicull.com/ja/7r5k6y75
+            Self::Hann => 0.5 * (1. - (2. * PI * i as Float / float_length).cos()),
+            // NOTE: 参考文献：https://en.wikipedia.org/wiki/Window_function#Tukey_window
+            Self::Tukey => {
+                let edge = TUKEY_ALPHA * float_length / 2.;
+                let distance = (i as Float).min(float_length - i as Float);
+                if distance < edge {
+                    0.5 * (1. - (PI * distance / edge).cos())
+                } else {
+                    1.
+                }
+            }
+            // NOTE: 参考文献：https://en.wikipedia.org/wiki/Window_function#Cosine-sum_windows
+            Self::Hamming => cosine_sum(&[0.54, 0.46], i, length),
+            Self::BlackmanHarris => cosine_sum(&BLACKMAN_HARRIS_COEFFICIENTS, i, length),
+            Self::FlatTop => cosine_sum(&FLAT_TOP_COEFFICIENTS, i, length),
+        }
+    }
+
+    /// 長さ`length`の窓関数の係数を全て計算します。
+    fn coefficients(self, length: usize) -> Arc<[Float]> {
+        (0..length).map(|i| self.coefficient(i, length)).collect()
+    }
+}
+
+/// 窓関数の係数を、窓関数の種類と長さをキーにして保持しておくキャッシュです。
+/// 最後に使われてから最も時間が経ったものから捨てられます。（LRU）
+///
+/// 解析を行うスレッドが所有して使うことを想定しているため、ロックは行いません。
+pub struct WindowCache {
+    capacity: usize,
+    /// 最近使われた順に並んだ係数
+    entries: VecDeque<(WindowKind, usize, Arc<[Float]>)>,
+}
+
+impl WindowCache {
+    /// インスタンスを作ります。`capacity`は保持する係数の最大の個数です。
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: VecDeque::with_capacity(capacity.max(1)),
+        }
+    }
+
+    /// 窓関数の係数を取得します。キャッシュにないなら計算して保持します。
+    pub fn get(&mut self, kind: WindowKind, length: usize) -> Arc<[Float]> {
+        if let Some(index) = self
+            .entries
+            .iter()
+            .position(|(k, l, _)| *k == kind && *l == length)
+        {
+            let entry = self.entries.remove(index).unwrap();
+            let coefficients = Arc::clone(&entry.2);
+            self.entries.push_front(entry);
+            return coefficients;
+        };
+
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_back();
+        };
+
+        let coefficients = kind.coefficients(length);
+        self.entries
+            .push_front((kind, length, Arc::clone(&coefficients)));
+        coefficients
+    }
+}
+
+/// 窓関数の係数を音声データに掛けます。
+/// FFTのサイズが大きいと時間がかかる所なので、SIMDでまとめて計算します。
+pub fn apply_window(data: &mut [Float], coefficients: &[Float]) {
+    let length = data.len().min(coefficients.len());
+    let (data, coefficients) = (&mut data[..length], &coefficients[..length]);
+
+    let mut data_chunks = data.chunks_exact_mut(LANES);
+    let mut coefficient_chunks = coefficients.chunks_exact(LANES);
+    for (values, coefficients) in (&mut data_chunks).zip(&mut coefficient_chunks) {
+        values.copy_from_slice(&(load(values) * load(coefficients)).to_array());
+    }
+
+    for (value, coefficient) in data_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(coefficient_chunks.remainder())
+    {
+        *value *= coefficient;
+    }
+}
+
+/// 二つの窓関数の係数`first`と`second`を、`second`を`mix`の割合で混ぜて音声データに掛けます。
+pub fn apply_mixed_window(data: &mut [Float], first: &[Float], second: &[Float], mix: Float) {
+    let length = data.len().min(first.len()).min(second.len());
+    let (data, first, second) = (&mut data[..length], &first[..length], &second[..length]);
+
+    let mut data_chunks = data.chunks_exact_mut(LANES);
+    let mut first_chunks = first.chunks_exact(LANES);
+    let mut second_chunks = second.chunks_exact(LANES);
+    let mix_lanes = Lanes::splat(mix);
+    for ((values, first), second) in (&mut data_chunks)
+        .zip(&mut first_chunks)
+        .zip(&mut second_chunks)
+    {
+        let (first, second) = (load(first), load(second));
+        let coefficients = (second - first).mul_add(mix_lanes, first);
+        values.copy_from_slice(&(load(values) * coefficients).to_array());
+    }
+
+    for ((value, first), second) in data_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(first_chunks.remainder())
+        .zip(second_chunks.remainder())
+    {
+        *value *= first + (second - first) * mix;
+    }
+}
+
+/// スペクトルの`index`の値とその両隣の値を通る放物線から、本当の山の頂点の位置と高さを求めます。
+/// 位置は`index`からのずれ（-0.5〜0.5）です。両端や山になっていない場合は`index`の値をそのまま使います。
+pub fn parabolic_peak(spectrum: &[Float], index: usize) -> (Float, Float) {
+    let center = spectrum.get(index).copied().unwrap_or(0.);
+    let (Some(left), Some(right)) = (
+        index.checked_sub(1).and_then(|index| spectrum.get(index)),
+        spectrum.get(index + 1),
+    ) else {
+        return (0., center);
+    };
+    if *left <= 0. || center <= 0. || *right <= 0. {
+        return (0., center);
+    };
+
+    // 窓関数を掛けた場合の山の形は、対数を取ると放物線に近くなるので、対数の値で補間する。
+    // NOTE: 参考文献：https://ccrma.stanford.edu/~jos/sasp/Quadratic_Interpolation_Spectral_Peaks.html
+    let (left, center, right) = (left.ln(), center.ln(), right.ln());
+    let curvature = left - 2. * center + right;
+    if curvature >= 0. {
+        return (0., center.exp());
+    };
+    let offset = (0.5 * (left - right) / curvature).clamp(-0.5, 0.5);
+    (offset, (center - 0.25 * (left - right) * offset).exp())
+}
+
+/// ゲルツェルのアルゴリズムで、音声データの`frequency`の成分の大きさを求めます。
+fn goertzel(data: &[Float], frequency: Float, frame_rate: Float) -> Float {
+    let coefficient = 2. * (2. * PI * frequency / frame_rate).cos();
+    let (mut first, mut second) = (0., 0.);
+
+    for value in data {
+        (first, second) = (value + coefficient * first - second, first);
+    }
+
+    (first * first + second * second - coefficient * first * second)
+        .max(0.)
+        .sqrt()
+}
+
+/// `center`の周辺（±`span`セント）だけを`magnitudes`の長さの数の点に分けて解析し、一番成分が大きい周波数を求めます。（ズームFFT）
+/// 全体のFFTのサイズを大きくしなくても、狭い範囲だけをとても細かい解像度で調べられます。
+/// 分けた点の数が少ないので、FFTではなく各点の成分を直接求めています。
+/// `magnitudes`は作業用のバッファで、各点の成分の大きさが書き込まれます。
+pub fn zoom_peak(
+    data: &[Float],
+    frame_rate: f32,
+    center: f32,
+    span: f32,
+    magnitudes: &mut [Float],
+) -> f32 {
+    let points = magnitudes.len();
+    let frequency_of = |point: Float| {
+        center as Float
+            * (2. as Float).powf((point / (points - 1) as Float * 2. - 1.) * span as Float / 1200.)
+    };
+    for (point, magnitude) in magnitudes.iter_mut().enumerate() {
+        *magnitude = goertzel(data, frequency_of(point as Float), frame_rate as Float);
+    }
+
+    let index = magnitudes
+        .iter()
+        .enumerate()
+        .fold(0, |peak, (index, value)| {
+            if *value > magnitudes[peak] {
+                index
+            } else {
+                peak
+            }
+        });
+    let (offset, _) = parabolic_peak(magnitudes, index);
+    frequency_of(index as Float + offset) as _
+}
+
+/// 二乗平均平方根（RMS）を取得します。
+pub fn get_rms(data: &[f32]) -> Float {
+    // NOTE: 参考：https://detail.chiebukuro.yahoo.co.jp/qa/question_detail/q1446027909
+    (data.iter().map(|x| (*x as Float).powi(2)).sum::<Float>() / data.len() as Float).sqrt()
+}
+
+/// 音量（dB）を取得します。
+/// 周波数による重み付けはしていません。聞こえ方に合わせる場合は`AWeighting`を使ってください。
+pub fn get_db(data: &[f32]) -> Float {
+    // NOTE: 参考になると思うページは以下。
+    //   - 要約
+    //     - 前提として二乗平均平方根（RMS）：https://detail.chiebukuro.yahoo.co.jp/qa/question_detail/q1446027909
+    //     - http://hsp.tv/play/pforum.php?mode=pastwch&num=47066
+    //   - デシベルについて
+    //     - https://mathwords.net/decibel
+    //     - 詳細：https://ja.wikipedia.org/wiki/%E3%83%87%E3%82%B7%E3%83%99%E3%83%AB
+    20. * get_rms(data).log10()
+}
+
+/// 二次のIIRフィルタ（双二次フィルタ）です。
+#[derive(Clone, Copy, Default)]
+struct Biquad {
+    /// 分子の係数
+    b: [Float; 3],
+    /// 分母の係数（`a0`で割って、`a0`を除いたもの）
+    a: [Float; 2],
+    /// 転置直接形IIの状態
+    state: [Float; 2],
+}
+
+impl Biquad {
+    /// アナログの伝達関数`(n[0]s² + n[1]s + n[2]) / (d[0]s² + d[1]s + d[2])`を、双一次変換でデジタルのフィルタにします。
+    fn from_analog(n: [f64; 3], d: [f64; 3], frame_rate: f64) -> Self {
+        let k = 2. * frame_rate;
+        let k2 = k * k;
+        let a0 = d[0] * k2 + d[1] * k + d[2];
+
+        Self {
+            b: [
+                ((n[0] * k2 + n[1] * k + n[2]) / a0) as _,
+                (2. * (n[2] - n[0] * k2) / a0) as _,
+                ((n[0] * k2 - n[1] * k + n[2]) / a0) as _,
+            ],
+            a: [
+                (2. * (d[2] - d[0] * k2) / a0) as _,
+                ((d[0] * k2 - d[1] * k + d[2]) / a0) as _,
+            ],
+            state: [0.; 2],
+        }
+    }
+
+    /// 周波数`frequency`での振幅の倍率を計算します。
+    fn response(&self, frequency: Float, frame_rate: Float) -> Float {
+        let omega = 2. * PI * frequency / frame_rate;
+        let magnitude = |c: [Float; 3]| {
+            let re = c[0] + c[1] * omega.cos() + c[2] * (2. * omega).cos();
+            let im = c[1] * omega.sin() + c[2] * (2. * omega).sin();
+            (re * re + im * im).sqrt()
+        };
+        magnitude(self.b) / magnitude([1., self.a[0], self.a[1]])
+    }
+
+    fn process(&mut self, input: Float) -> Float {
+        let output = self.b[0] * input + self.state[0];
+        self.state[0] = self.b[1] * input - self.a[0] * output + self.state[1];
+        self.state[1] = self.b[2] * input - self.a[1] * output;
+        output
+    }
+}
+
+/// A特性の極の周波数（Hz）
+/// NOTE: 参考文献：https://en.wikipedia.org/wiki/A-weighting
+const A_WEIGHTING_POLES: [f64; 4] = [20.598997, 107.65265, 737.86223, 12194.217];
+
+/// A特性の重み付けをした音量を測るためのフィルタです。
+/// 人の耳の聞こえ方に近くなるように、低い音と高い音を小さく見積もります。（1kHzで0dB）
+/// 双一次変換で作っているため、ナイキスト周波数に近い高い音では少しずれます。
+pub struct AWeighting {
+    sections: [Biquad; 3],
+    gain: Float,
+    /// 重み付けした最近の音声データ
+    history: Vec<Float>,
+}
+
+impl AWeighting {
+    /// インスタンスを作ります。
+    pub fn new(frame_rate: f32) -> Self {
+        let frame_rate = frame_rate as f64;
+        let omega = A_WEIGHTING_POLES.map(|frequency| 2. * std::f64::consts::PI * frequency);
+
+        // s⁴ / ((s + ω1)² (s + ω2) (s + ω3) (s + ω4)²) を三つの双二次フィルタに分ける。
+        let sections = [
+            Biquad::from_analog(
+                [1., 0., 0.],
+                [1., 2. * omega[0], omega[0] * omega[0]],
+                frame_rate,
+            ),
+            Biquad::from_analog(
+                [1., 0., 0.],
+                [1., omega[1] + omega[2], omega[1] * omega[2]],
+                frame_rate,
+            ),
+            Biquad::from_analog(
+                [0., 0., 1.],
+                [1., 2. * omega[3], omega[3] * omega[3]],
+                frame_rate,
+            ),
+        ];
+        let response: Float = sections
+            .iter()
+            .map(|section| section.response(1000., frame_rate as _))
+            .product();
+
+        Self {
+            sections,
+            gain: 1. / response,
+            history: Vec::new(),
+        }
+    }
+
+    /// フィルタの状態と、重み付けした音声データを捨てます。
+    pub fn reset(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.state = [0.; 2];
+        }
+        self.history.clear();
+    }
+
+    /// `data`の最後の`new_frames`個（前回から進んだ分）をフィルタに通し、
+    /// 最近の`data`の長さ分の重み付けした音量（dB）を取得します。
+    /// フィルタの状態を引き継ぐので、続いている音声データを順番に渡してください。
+    pub fn level(&mut self, data: &[f32], new_frames: usize) -> Float {
+        let new_frames = new_frames.min(data.len());
+        for value in &data[data.len() - new_frames..] {
+            let weighted = self
+                .sections
+                .iter_mut()
+                .fold(*value as Float * self.gain, |value, section| {
+                    section.process(value)
+                });
+            self.history.push(weighted);
+        }
+
+        let excess = self.history.len().saturating_sub(data.len());
+        self.history.drain(..excess);
+
+        let mean_square = self
+            .history
+            .iter()
+            .map(|value| value * value)
+            .sum::<Float>()
+            / self.history.len().max(1) as Float;
+        10. * mean_square.log10()
+    }
+}
+
+/// 高域通過フィルタの鋭さ（Q、バターワース特性）
+const HIGH_PASS_Q: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+/// 直流成分や、空調や振動等の低い雑音を取り除くための、二次の高域通過フィルタです。
+/// 直流成分があると音量が大きく測られ、低い音の周波数帯の値も大きくなるので、FFTの前に取り除きます。
+pub struct HighPassFilter {
+    section: Biquad,
+    frame_rate: f32,
+    /// 今の係数の遮断周波数（Hz）（まだ係数を作っていない場合は0）
+    cutoff: u32,
+    /// フィルタに通した最近の音声データ
+    history: Vec<Float>,
+}
+
+impl HighPassFilter {
+    /// インスタンスを作ります。
+    pub fn new(frame_rate: f32) -> Self {
+        Self {
+            section: Biquad::default(),
+            frame_rate,
+            cutoff: 0,
+            history: Vec::new(),
+        }
+    }
+
+    /// フィルタの状態と、フィルタに通した音声データを捨てます。
+    pub fn reset(&mut self) {
+        self.section.state = [0.; 2];
+        self.history.clear();
+    }
+
+    /// `data`の最後の`new_frames`個（前回から進んだ分）を遮断周波数`cutoff`（Hz）のフィルタに通し、
+    /// 最近の`data`の長さ分のフィルタに通した音声データを`output`に書き込みます。
+    /// フィルタの状態を引き継ぐので、続いている音声データを順番に渡してください。
+    pub fn process(&mut self, data: &[f32], new_frames: usize, cutoff: u32, output: &mut Vec<f32>) {
+        if cutoff != self.cutoff {
+            // s² / (s² + (ω/Q)s + ω²)
+            let omega = 2. * std::f64::consts::PI * cutoff as f64;
+            self.section = Biquad::from_analog(
+                [1., 0., 0.],
+                [1., omega / HIGH_PASS_Q, omega * omega],
+                self.frame_rate as _,
+            );
+            self.cutoff = cutoff;
+            self.history.clear();
+        };
+
+        // 前に通した音声データがない場合は、全てを通す。
+        let new_frames = if self.history.is_empty() {
+            data.len()
+        } else {
+            new_frames.min(data.len())
+        };
+        for value in &data[data.len() - new_frames..] {
+            self.history.push(self.section.process(*value as Float));
+        }
+
+        let excess = self.history.len().saturating_sub(data.len());
+        self.history.drain(..excess);
+
+        output.clear();
+        for value in &self.history {
+            output.push(*value as _);
+        }
+    }
+}
+
+/// 音量の包絡線を求めるための構造体です。
+/// 音量が上がる時と下がる時で、それぞれ別の時定数で追従します。
+pub struct EnvelopeFollower {
+    /// 音量が上がる時の時定数（秒）
+    attack: Float,
+    /// 音量が下がる時の時定数（秒）
+    release: Float,
+    value: Float,
+}
+
+impl EnvelopeFollower {
+    /// インスタンスを作ります。
+    pub fn new(attack: Float, release: Float) -> Self {
+        Self {
+            attack,
+            release,
+            value: 0.,
+        }
+    }
+
+    /// `duration`秒分の音声データの音量（RMS等）を渡して、包絡線の値を更新します。
+    pub fn process(&mut self, level: Float, duration: Float) -> Float {
+        let time_constant = if level > self.value {
+            self.attack
+        } else {
+            self.release
+        };
+
+        self.value += (level - self.value) * (1. - (-duration / time_constant).exp());
+        self.value
+    }
+
+    /// 包絡線の現在の値を取得します。
+    pub fn value(&self) -> Float {
+        self.value
+    }
+}
+
+/// 音量が閾値を下回った時に検出を止めるための、ノイズゲートです。
+/// 閾値を超えたらアタックの時間をかけて開き、下回ったらホールドの時間だけ待ってから、リリースの時間をかけて閉じます。
+/// トレモロや減衰していく音で、音量が閾値付近を行き来しても検出が途切れないようにするためのものです。
+#[derive(Default)]
+pub struct NoiseGate {
+    /// ゲートの開き具合（0〜1）
+    gain: Float,
+    /// 音量が閾値を下回ってからの時間（秒）
+    below_for: Float,
+}
+
+impl NoiseGate {
+    /// `duration`秒分の音声データの音量が閾値を超えているかどうかを渡して、ゲートの開き具合を更新します。
+    /// 時間は全て秒で、0の場合はすぐに開いたり閉じたりします。
+    pub fn process(
+        &mut self,
+        is_above: bool,
+        duration: Float,
+        attack: Float,
+        hold: Float,
+        release: Float,
+    ) -> Float {
+        if is_above {
+            self.below_for = 0.;
+            self.gain = if attack > 0. {
+                (self.gain + duration / attack).min(1.)
+            } else {
+                1.
+            };
+        } else {
+            // ホールドの時間を過ぎた分だけ閉じていく。
+            let releasing = (self.below_for + duration - hold).clamp(0., duration);
+            self.below_for += duration;
+            if releasing > 0. {
+                self.gain = if release > 0. {
+                    (self.gain - releasing / release).max(0.)
+                } else {
+                    0.
+                };
+            };
+        };
+        self.gain
+    }
+
+    /// ゲートが少しでも開いているかどうかを取得します。
+    pub fn is_open(&self) -> bool {
+        self.gain > 0.
+    }
+
+    /// ゲートの開き具合（0〜1）を取得します。
+    pub fn gain(&self) -> Float {
+        self.gain
+    }
+}
+
+/// 自動音量調整で、音量が上がった時に追従する時定数（秒）
+const AGC_ATTACK: Float = 0.05;
+/// 自動音量調整で、音量が下がった時に追従する時定数（秒）
+const AGC_RELEASE: Float = 1.5;
+/// 自動音量調整で、持ち上げる量と下げる量の上限（dB）
+const AGC_MAX_GAIN: Float = 24.;
+const AGC_MAX_CUT: Float = 24.;
+/// 自動音量調整で、これより小さい音量（dB）は無音とみなして追従しない
+const AGC_FLOOR: Float = -70.;
+
+/// 音声データの音量を目標の音量に近づける、自動音量調整（AGC）です。
+/// マイクの感度によらず、同じ閾値の設定で検出できるようにするためのものです。
+/// 音量の変化にゆっくり追従するので、一つの音の中の強弱はある程度残ります。
+#[derive(Default)]
+pub struct AutoGainControl {
+    /// 追従している音量（dB）（まだ音を受け取っていない場合は`None`）
+    level: Option<Float>,
+}
+
+impl AutoGainControl {
+    /// 追従している音量を忘れます。
+    pub fn reset(&mut self) {
+        self.level = None;
+    }
+
+    /// `duration`秒分の音声データの音量（dB）を渡して追従し、目標の音量（dB）に近づけるために掛ける倍率を返します。
+    pub fn process(&mut self, level: Float, duration: Float, target: Float) -> Float {
+        if level > AGC_FLOOR {
+            self.level = Some(match self.level {
+                Some(current) => {
+                    let time_constant = if level > current {
+                        AGC_ATTACK
+                    } else {
+                        AGC_RELEASE
+                    };
+                    current + (level - current) * (1. - (-duration / time_constant).exp())
+                }
+                None => level,
+            });
+        };
+
+        let Some(level) = self.level else {
+            return 1.;
+        };
+        let gain = (target - level).clamp(-AGC_MAX_CUT, AGC_MAX_GAIN);
+        (10 as Float).powf(gain / 20.)
+    }
+}
+
+/// 雑音の大きさを求めるのに、スペクトルの最小値を探す期間（秒）
+/// 短いと伸ばしている音まで雑音とみなしてしまうので、音楽向けに長めにしています。
+const NOISE_WINDOW: Float = 5.;
+/// 最小値を探す期間を分ける数
+/// 期間を分けて最小値を覚えておき、古いものから捨てることで、雑音が小さくなった時にも追従します。
+const NOISE_SUBWINDOWS: usize = 8;
+/// スペクトルを平らにする時定数（秒）
+const NOISE_SMOOTHING: Float = 0.1;
+/// 最小値は雑音の平均より小さくなるので、それを補うために掛ける倍率
+const NOISE_BIAS: Float = 1.5;
+/// 差し引いた後に残す、元の値に対する割合
+/// 全て0にすると、ばらばらに残った所が耳障りな雑音（ミュージカルノイズ）となって誤検出の元になるためです。
+const NOISE_SPECTRAL_FLOOR: Float = 0.05;
+
+/// スペクトルの最小値の推移から雑音の大きさを求め、それを差し引く、スペクトル減算の雑音除去です。（Minimum Statistics）
+/// 無音データと違って雑音の大きさを常に追従するので、途中で変わる換気扇やハム等の雑音も取り除けます。
+/// ただし、`NOISE_WINDOW`より長く伸ばした音は、雑音とみなされて小さくなります。
+#[derive(Default)]
+pub struct NoiseFloorTracker {
+    /// 平らにしたスペクトル
+    smoothed: Vec<Float>,
+    /// 今の区間での、平らにしたスペクトルの最小値
+    current_minimum: Vec<Float>,
+    /// 今の区間の経過時間（秒）
+    elapsed: Float,
+    /// 過去の区間での最小値（古い順、まだ区間が終わっていないものは無限大）
+    /// NOTE: 解析中にメモリを確保しないで済むように、FFTのサイズが変わるまではバッファを使い回します。
+    minima: VecDeque<Vec<Float>>,
+}
+
+impl NoiseFloorTracker {
+    /// 追従している雑音の大きさを忘れます。
+    /// 次に`process`を呼んだ時に、最初からやり直します。
+    pub fn reset(&mut self) {
+        self.smoothed.clear();
+        self.elapsed = 0.;
+    }
+
+    /// `duration`秒分進んだスペクトルを渡して雑音の大きさを追従し、その`over_subtraction`倍をスペクトルから差し引きます。
+    pub fn process(&mut self, spectrum: &mut [Float], duration: Float, over_subtraction: Float) {
+        if self.smoothed.len() != spectrum.len() {
+            // 忘れた後か、FFTのサイズが変わって周波数が対応しないのなら、最初からやり直す。
+            self.reset();
+            self.smoothed.extend_from_slice(spectrum);
+            self.current_minimum.clear();
+            self.current_minimum.extend_from_slice(spectrum);
+            self.minima.resize_with(NOISE_SUBWINDOWS - 1, Vec::new);
+            for minima in self.minima.iter_mut() {
+                minima.clear();
+                minima.resize(spectrum.len(), Float::INFINITY);
+            }
+        };
+
+        // スペクトルを平らにして、今の区間の最小値を更新する。
+        let smoothing = (-duration / NOISE_SMOOTHING).exp();
+        for ((smoothed, minimum), value) in self
+            .smoothed
+            .iter_mut()
+            .zip(self.current_minimum.iter_mut())
+            .zip(spectrum.iter())
+        {
+            *smoothed = *smoothed * smoothing + value * (1. - smoothing);
+            *minimum = minimum.min(*smoothed);
+        }
+
+        // 区間が終わったのなら最小値を覚えて、古い区間のものを捨てる。
+        self.elapsed += duration;
+        if self.elapsed >= NOISE_WINDOW / NOISE_SUBWINDOWS as Float {
+            self.elapsed = 0.;
+            // 一番古い区間のバッファに今の区間の最小値を上書きして、新しい区間のものとして使い回す。
+            if let Some(mut oldest) = self.minima.pop_front() {
+                oldest.copy_from_slice(&self.current_minimum);
+                self.minima.push_back(oldest);
+            };
+            self.current_minimum.copy_from_slice(&self.smoothed);
+        };
+
+        // 全ての区間の最小値を雑音の大きさとして、差し引く。
+        for (index, value) in spectrum.iter_mut().enumerate() {
+            let noise = self
+                .minima
+                .iter()
+                .map(|minima| minima[index])
+                .fold(self.current_minimum[index], Float::min)
+                * NOISE_BIAS;
+            *value = (*value - noise * over_subtraction).max(*value * NOISE_SPECTRAL_FLOOR);
+        }
+    }
+}
+
+/// スペクトルフラックスを使って、音の立ち上がり（オンセット）を検出するための構造体です。
+pub struct OnsetDetector {
+    /// 前回のスペクトル
+    previous: Vec<Float>,
+    /// スペクトルフラックスの移動平均
+    average: Float,
+    /// 移動平均を更新する割合
+    smoothing: Float,
+    /// 直前のスペクトルフラックスの、移動平均に対する比
+    flux_ratio: Float,
+}
+
+impl OnsetDetector {
+    /// インスタンスを作ります。
+    pub fn new(smoothing: Float) -> Self {
+        Self {
+            previous: Vec::new(),
+            average: 0.,
+            smoothing,
+            flux_ratio: 0.,
+        }
+    }
+
+    /// 直前に渡したスペクトルのスペクトルフラックスが、その前までの移動平均の何倍だったかを取得します。
+    pub fn flux_ratio(&self) -> Float {
+        self.flux_ratio
+    }
+
+    /// スペクトルを渡して、音の立ち上がりかどうかを調べます。
+    /// スペクトルフラックスが移動平均の`threshold`倍を超えたら、音の立ち上がりとみなします。
+    pub fn process(&mut self, spectrum: &[Float], threshold: Float) -> bool {
+        if self.previous.len() != spectrum.len() {
+            // スペクトルの長さが変わったのなら、比較できないので最初からやり直す。
+            self.previous.clear();
+            self.previous.extend_from_slice(spectrum);
+            self.average = 0.;
+            self.flux_ratio = 0.;
+            return false;
+        };
+
+        // 前回から大きくなった分だけを足し合わせる。
+        let flux = spectrum
+            .iter()
+            .zip(self.previous.iter())
+            .map(|(value, previous)| (value - previous).max(0.))
+            .sum::<Float>()
+            / spectrum.len() as Float;
+        self.previous.copy_from_slice(spectrum);
+
+        self.flux_ratio = if self.average > 0. {
+            flux / self.average
+        } else {
+            0.
+        };
+        let is_onset = self.average > 0. && flux > self.average * threshold;
+        self.average += (flux - self.average) * self.smoothing;
+        is_onset
+    }
+}
+
+pub mod fft {
+    use std::sync::Arc;
+
+    use realfft::{RealFftPlanner, RealToComplex};
+    use rustfft::num_complex::Complex;
+
+    use super::{Float, Lanes, LANES};
+
+    /// 高速フーリエ変換に使う作業用のバッファです。
+    /// 毎回リソースを確保しないように、解析を行う側（`Synthesizer`等）が一つずつ持って使い回します。
+    #[derive(Default)]
+    pub struct Workspace {
+        /// 音声データの後ろを0で埋めたもの
+        buffer: Vec<Float>,
+        /// 高速フーリエ変換の結果
+        spectrum: Vec<Complex<Float>>,
+    }
+
+    /// 高速フーリエ変換の実装を差し替えるためのトレイトです。
+    pub trait FftBackend {
+        /// 実数の`input`に対して順方向の高速フーリエ変換を行い、
+        /// 周波数が0からナイキスト周波数までの成分（`input.len() / 2 + 1`個）を`output`に書き込みます。
+        /// `input`は作業用に書き換えられることがあります。
+        fn process(&mut self, input: &mut [Float], output: &mut [Complex<Float>]);
+    }
+
+    /// `rustfft`（実数用の`realfft`）を使った実装です。
+    /// 高速フーリエ変換の計画は、長さが変わった時（ポイント数の規模や解析の窓の長さが変わった時）だけ立て直します。
+    pub struct RustFftBackend {
+        planner: RealFftPlanner<Float>,
+        /// 今の長さ用の計画
+        plan: Option<Arc<dyn RealToComplex<Float>>>,
+        /// 計算の作業用のバッファ
+        scratch: Vec<Complex<Float>>,
+    }
+
+    impl Default for RustFftBackend {
+        fn default() -> Self {
+            Self {
+                planner: RealFftPlanner::new(),
+                plan: None,
+                scratch: Vec::new(),
+            }
+        }
+    }
+
+    impl FftBackend for RustFftBackend {
+        fn process(&mut self, input: &mut [Float], output: &mut [Complex<Float>]) {
+            let plan = match &self.plan {
+                Some(plan) if plan.len() == input.len() => plan,
+                _ => {
+                    let plan = self.planner.plan_fft_forward(input.len());
+                    self.scratch = plan.make_scratch_vec();
+                    self.plan.insert(plan)
+                }
+            };
+
+            plan.process_with_scratch(input, output, &mut self.scratch)
+                .unwrap();
+        }
+    }
+
+    #[cfg(all(feature = "accelerate", target_os = "macos", not(feature = "f64")))]
+    pub use accelerate::AccelerateBackend as DefaultFftBackend;
+    #[cfg(not(all(feature = "accelerate", target_os = "macos", not(feature = "f64"))))]
+    pub use RustFftBackend as DefaultFftBackend;
+
+    /// macOSのAccelerateフレームワーク（vDSP）を使った実装です。
+    /// vDSPの単精度の関数を使うため、`f64`フィーチャーとは併用できません。
+    #[cfg(all(feature = "accelerate", target_os = "macos", not(feature = "f64")))]
+    mod accelerate {
+        use std::os::raw::{c_int, c_long, c_ulong, c_void};
+
+        use rustfft::num_complex::Complex32;
+
+        use super::{FftBackend, RustFftBackend};
+
+        #[repr(C)]
+        struct DSPSplitComplex {
+            realp: *mut f32,
+            imagp: *mut f32,
+        }
+
+        const FFT_RADIX2: c_int = 0;
+        const FFT_DIRECTION_FORWARD: c_int = 1;
+
+        #[link(name = "Accelerate", kind = "framework")]
+        extern "C" {
+            fn vDSP_create_fftsetup(log2n: c_ulong, radix: c_int) -> *mut c_void;
+            fn vDSP_destroy_fftsetup(setup: *mut c_void);
+            fn vDSP_fft_zrip(
+                setup: *mut c_void,
+                c: *const DSPSplitComplex,
+                stride: c_long,
+                log2n: c_ulong,
+                direction: c_int,
+            );
+        }
+
+        /// vDSPは長さが2の累乗のものしか扱えないので、それ以外の長さの場合は`rustfft`を使います。
+        /// vDSPの準備ができなかった場合（メモリが足りない場合や、vDSPが扱えない長さの場合）も`rustfft`を使います。
+        #[derive(Default)]
+        pub struct AccelerateBackend {
+            setup: Option<(c_ulong, *mut c_void)>,
+            /// 偶数番目の音声データ（結果の実部）
+            real: Vec<f32>,
+            /// 奇数番目の音声データ（結果の虚部）
+            imaginary: Vec<f32>,
+            fallback: RustFftBackend,
+        }
+
+        // NOTE: `setup`はこの構造体だけが持つもので、他と共有されないので他のスレッドに送っても問題ない。
+        unsafe impl Send for AccelerateBackend {}
+
+        impl AccelerateBackend {
+            /// `log2n`用のvDSPの準備をしたものを取得します。準備ができなかった場合は`None`を返します。
+            fn setup(&mut self, log2n: c_ulong) -> Option<*mut c_void> {
+                match self.setup {
+                    Some((setup_log2n, setup)) if setup_log2n == log2n => Some(setup),
+                    _ => {
+                        self.destroy_setup();
+                        let setup = unsafe { vDSP_create_fftsetup(log2n, FFT_RADIX2) };
+                        if setup.is_null() {
+                            return None;
+                        };
+                        self.setup = Some((log2n, setup));
+                        Some(setup)
+                    }
+                }
+            }
+
+            fn destroy_setup(&mut self) {
+                if let Some((_, setup)) = self.setup.take() {
+                    unsafe { vDSP_destroy_fftsetup(setup) };
+                };
+            }
+        }
+
+        impl Drop for AccelerateBackend {
+            fn drop(&mut self) {
+                self.destroy_setup();
+            }
+        }
+
+        impl FftBackend for AccelerateBackend {
+            fn process(&mut self, input: &mut [f32], output: &mut [Complex32]) {
+                let setup = if input.len() >= 2 && input.len().is_power_of_two() {
+                    self.setup(input.len().trailing_zeros() as c_ulong)
+                } else {
+                    None
+                };
+                let Some(setup) = setup else {
+                    self.fallback.process(input, output);
+                    return;
+                };
+                let log2n = input.len().trailing_zeros() as c_ulong;
+                let half = input.len() / 2;
+
+                // 実数の高速フーリエ変換（`vDSP_fft_zrip`）は、偶数番目と奇数番目に分けたものを渡す。
+                self.real.clear();
+                self.real.extend(input.iter().step_by(2));
+                self.imaginary.clear();
+                self.imaginary.extend(input.iter().skip(1).step_by(2));
+
+                let split = DSPSplitComplex {
+                    realp: self.real.as_mut_ptr(),
+                    imagp: self.imaginary.as_mut_ptr(),
+                };
+                unsafe { vDSP_fft_zrip(setup, &split, 1, log2n, FFT_DIRECTION_FORWARD) };
+
+                // NOTE: 結果は2倍になっていて、0番目の虚部にはナイキスト周波数の成分が入っている。
+                output[0] = Complex32::new(self.real[0] / 2., 0.);
+                output[half] = Complex32::new(self.imaginary[0] / 2., 0.);
+                for (c, (re, im)) in output[1..half]
+                    .iter_mut()
+                    .zip(self.real[1..].iter().zip(self.imaginary[1..].iter()))
+                {
+                    c.re = re / 2.;
+                    c.im = im / 2.;
+                }
+            }
+        }
+    }
+
+    pub struct ResultInfo {
+        /// 計算結果の解像度
+        /// これは、各値が前の値からどれだけの周波数分だけ離れているかです。
+        /// 例えば、`[2, 2, 4, 5, 5, 6, 5, 3, 2, 1]`のようなバッファとなり、それの解像度が2の場合を考えてみましょう。
+        /// その場合は、バッファの各値の周波数の差が12Hzということですので、各値の成分は左から純に`0, 2, 4, 6, 8, 10, 12, ...`の周波数の音の大きさとなります。
+        pub resolution: f32,
+        /// 高速フーリエ変換をしたデータの長さ
+        /// NOTE: 計算結果のバッファには、この半分（ナイキスト周波数）までの成分が入ります。
+        pub buffer_length: usize,
+    }
+
+    /// 複素数の大きさ（絶対値）を`output`に書き込みます。
+    /// FFTのサイズが大きいと時間がかかる所なので、SIMDでまとめて計算します。
+    fn magnitudes(spectrum: &[Complex<Float>], output: &mut [Float]) {
+        let mut output_chunks = output.chunks_exact_mut(LANES);
+        let mut spectrum_chunks = spectrum.chunks_exact(LANES);
+        for (outputs, values) in (&mut output_chunks).zip(&mut spectrum_chunks) {
+            let re = Lanes::from(std::array::from_fn(|index| values[index].re));
+            let im = Lanes::from(std::array::from_fn(|index| values[index].im));
+            outputs.copy_from_slice(&(re * re + im * im).sqrt().to_array());
+        }
+
+        for (output, c) in output_chunks
+            .into_remainder()
+            .iter_mut()
+            .zip(spectrum_chunks.remainder())
+        {
+            *output = c.norm();
+        }
+    }
+
+    /// FFTのサイズ（`data_length`×`point_times`）が`max_length`を超えないように、`point_times`を制限します。
+    /// ただし、`point_times`は最低でも1となります。
+    pub fn limit_point_times(data_length: usize, point_times: usize, max_length: usize) -> usize {
+        if data_length == 0 {
+            return point_times.max(1);
+        };
+
+        point_times.min(max_length / data_length).max(1)
+    }
+
+    /// 高速フーリエ変換を行い、各周波数あたりの音の成分の大きさを割り出します。
+    ///
+    /// # Arguments
+    /// - `data`: 処理する音声データ
+    /// - `frame_rate`: 渡した処理対象の音声データのフレームレート
+    ///   返り値の解像度の計算に使われます。
+    /// - `point_times`: 計算結果の規模を何倍にするか
+    ///   これをするとバッファが自動で音声データの長さをこの数値で乗算した数の長さまで拡張され、そのサイズ分のフーリエ変換を行います。
+    ///   つまり、フーリエ変換の精度が上がります。（その分、処理が大変になります。）
+    ///   NOTE: 詳細は次のページをご確認ください：https://www.logical-arts.jp/archives/112
+    /// - `result_buffer`: 計算結果を代入するバッファ
+    ///   周波数が0からナイキスト周波数までの成分が入ります。（長さはFFTのサイズの半分に1を足したものです。）
+    ///   NOTE: 自動でリサイズされるので、あらかじめ大きい数を割り当てるといったことはしなくても良いです。
+    /// - `workspace`: 作業用のバッファ
+    /// - `backend`: 高速フーリエ変換の実装
+    #[inline(always)]
+    pub fn process(
+        data: &[Float],
+        frame_rate: f32,
+        point_times: usize,
+        result_buffer: &mut Vec<Float>,
+        workspace: &mut Workspace,
+        backend: &mut impl FftBackend,
+    ) -> ResultInfo {
+        let original_data_length = data.len();
+        let buffer_length = original_data_length * point_times;
+
+        let spectrum_length = buffer_length / 2 + 1;
+
+        // バッファの初期化を行う。
+        let Workspace { buffer, spectrum } = workspace;
+        if buffer.len() != buffer_length {
+            buffer.resize_with(buffer_length, Default::default);
+        };
+        if spectrum.len() != spectrum_length {
+            spectrum.resize_with(spectrum_length, Default::default);
+        };
+        if spectrum_length != result_buffer.len() {
+            result_buffer.resize_with(spectrum_length, Default::default)
+        };
+
+        // 初期化を行う。具体的には、録音したデータの設定と、前のデータの削除です。
+        // NOTE: 作業用に書き換えられるので、毎回全て設定し直す。
+        buffer[..original_data_length].copy_from_slice(data);
+        buffer[original_data_length..].fill(0.);
+
+        // 高速フーリエ変換を実行する。
+        backend.process(buffer, spectrum);
+
+        // 結果を書き込む。
+        magnitudes(spectrum, result_buffer);
+
+        ResultInfo {
+            resolution: frame_rate / buffer_length as f32,
+            buffer_length,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_floor_tracker_reuses_buffers() {
+        let mut tracker = NoiseFloorTracker::default();
+        let mut spectrum = vec![1.; 64];
+        let subwindow = NOISE_WINDOW / NOISE_SUBWINDOWS as Float;
+
+        tracker.process(&mut spectrum, subwindow, 1.);
+        let mut buffers = tracker
+            .minima
+            .iter()
+            .map(|minima| minima.as_ptr())
+            .collect::<Vec<_>>();
+
+        // 区間が何回終わっても、最初に用意したバッファを使い回す。
+        for _ in 0..NOISE_SUBWINDOWS * 3 {
+            spectrum.fill(1.);
+            tracker.process(&mut spectrum, subwindow, 1.);
+        }
+        let mut reused = tracker
+            .minima
+            .iter()
+            .map(|minima| minima.as_ptr())
+            .collect::<Vec<_>>();
+        buffers.sort();
+        reused.sort();
+        assert_eq!(buffers, reused);
+
+        // 一定の雑音は、残す割合まで差し引かれる。
+        assert!(spectrum
+            .iter()
+            .all(|value| (*value - NOISE_SPECTRAL_FLOOR).abs() < 1e-6));
+    }
+}
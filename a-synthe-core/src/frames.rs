@@ -0,0 +1,141 @@
+//! 入力デバイスから受け取る長さのばらばらな音声データを、決まった長さの解析用のフレームにまとめるためのモジュールです。
+
+/// 受け取った音声データをためて、決まった長さのフレームを決まった間隔で切り出すための構造体です。
+/// フレームの間隔をフレームの長さより短くすると、フレーム同士が重なります。
+#[derive(Default, Debug)]
+pub struct FrameAssembler {
+    /// まだフレームとして切り出し終えていない音声データ
+    pending: Vec<f32>,
+    /// フレームの間隔がフレームの長さより長い場合に、次に受け取った音声データから捨てる長さ
+    skip: usize,
+}
+
+impl FrameAssembler {
+    /// インスタンスを作ります。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 音声データを加え、揃ったフレームを順番に`on_frame`に渡します。
+    /// フレームは長さ`frame_length`で、`hop_length`ずつずらして切り出します。
+    /// 一回の呼び出しでフレームが一つも揃わないことも、複数揃うこともあります。
+    pub fn push(
+        &mut self,
+        data: &[f32],
+        frame_length: usize,
+        hop_length: usize,
+        mut on_frame: impl FnMut(&[f32]),
+    ) {
+        let hop_length = hop_length.max(1);
+
+        let skipped = self.skip.min(data.len());
+        self.skip -= skipped;
+        self.pending.extend_from_slice(&data[skipped..]);
+
+        let mut start = 0;
+        while start + frame_length <= self.pending.len() {
+            on_frame(&self.pending[start..start + frame_length]);
+            start += hop_length;
+        }
+
+        let consumed = start.min(self.pending.len());
+        self.skip += start - consumed;
+        self.pending.drain(..consumed);
+    }
+
+    /// ためている音声データを捨てます。
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.skip = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_LENGTH: usize = 200;
+
+    /// 再現できるように、決まった種から作った擬似乱数で音声データを分ける長さを作ります。
+    fn chunk_lengths(seed: u32, max_length: usize) -> impl Iterator<Item = usize> {
+        let mut state = seed;
+        std::iter::repeat_with(move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as usize % max_length + 1
+        })
+    }
+
+    /// 音声データをまとめて切り出した場合のフレームを作ります。
+    fn sliced(data: &[f32], hop_length: usize) -> Vec<Vec<f32>> {
+        (0..)
+            .map(|index| index * hop_length)
+            .take_while(|start| start + FRAME_LENGTH <= data.len())
+            .map(|start| data[start..start + FRAME_LENGTH].to_vec())
+            .collect()
+    }
+
+    /// 音声データを分けて渡した場合のフレームを作ります。
+    fn assembled(
+        assembler: &mut FrameAssembler,
+        data: &[f32],
+        hop_length: usize,
+        lengths: impl Iterator<Item = usize>,
+    ) -> Vec<Vec<f32>> {
+        let mut frames = Vec::new();
+        let mut rest = data;
+        for length in lengths {
+            if rest.is_empty() {
+                break;
+            };
+            let (chunk, next) = rest.split_at(length.min(rest.len()));
+            assembler.push(chunk, FRAME_LENGTH, hop_length, |frame| {
+                frames.push(frame.to_vec())
+            });
+            rest = next;
+        }
+        frames
+    }
+
+    #[test]
+    fn matches_one_shot_slicing() {
+        let data = (0..5000).map(|index| index as f32).collect::<Vec<_>>();
+
+        // 重なり無し、最大の重なり（99%）、フレームの間に隙間がある場合
+        for hop_length in [FRAME_LENGTH, FRAME_LENGTH / 100, FRAME_LENGTH + 7] {
+            let expected = sliced(&data, hop_length);
+
+            // 一つずつ渡す場合から、フレームより長く渡す場合まで
+            for max_length in [1, 2, FRAME_LENGTH - 1, FRAME_LENGTH, 3 * FRAME_LENGTH + 5] {
+                for seed in 0..4 {
+                    let frames = assembled(
+                        &mut FrameAssembler::new(),
+                        &data,
+                        hop_length,
+                        chunk_lengths(seed, max_length),
+                    );
+                    assert_eq!(
+                        frames, expected,
+                        "hop: {hop_length}, max chunk: {max_length}, seed: {seed}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn clear_discards_pending_data() {
+        let data = (0..1000).map(|index| index as f32).collect::<Vec<_>>();
+        let hop_length = FRAME_LENGTH + 7;
+        let mut assembler = FrameAssembler::new();
+
+        // 途中までためた状態と、フレームの間の隙間を捨てている途中の状態を作ってから捨てる。
+        for prefix in [FRAME_LENGTH / 2, FRAME_LENGTH + 3] {
+            assembler.push(&data[..prefix], FRAME_LENGTH, hop_length, |_| ());
+            assembler.clear();
+
+            let frames = assembled(&mut assembler, &data, hop_length, chunk_lengths(1, 50));
+            assert_eq!(frames, sliced(&data, hop_length));
+            assembler.clear();
+        }
+    }
+}
@@ -0,0 +1,1690 @@
+//! aSyntheの音階検出の部分です。
+//! 入力の音声データから音階を検出する`Synthesizer`と、その設定の`Config`、音階の表の`NoteContainer`、
+//! 高速フーリエ変換等の計算の`calculation`を、他のRustのプロジェクトからも使えるようにまとめています。
+//!
+//! ```no_run
+//! use a_synthe_core::{ConfigSnapshot, Synthesizer};
+//!
+//! // ラ（A4）を442Hzとして検出する。
+//! let mut synthesizer = Synthesizer::builder(44100.)
+//!     .settings(&ConfigSnapshot {
+//!         reference_pitch: 442,
+//!         ..Default::default()
+//!     })
+//!     .build();
+//! let samples = vec![0f32; 1024];
+//! // 音量が大きい順に、上位五つの音階を検出する。
+//! if let Some(notes) = synthesizer.synthe::<5>(&samples) {
+//!     println!("{}", notes[0].get_name());
+//! };
+//! ```
+
+use std::{
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicU16, AtomicU32, AtomicU8, Ordering::SeqCst},
+        Arc, Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+pub mod builder;
+pub mod calculation;
+pub mod frames;
+pub mod mic_profile;
+pub mod note;
+pub mod offline;
+pub mod params;
+pub mod plugin;
+pub mod ring;
+pub mod scala;
+pub mod silence;
+pub mod stats;
+// NOTE: 組み込んで使う場合のためのもので、アプリ自体では使っていない。
+pub mod stream;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use builder::SynthesizerBuilder;
+pub use calculation::Float;
+pub use note::{Note, NoteContainer, NoteNaming, DEFAULT_REFERENCE_PITCH};
+pub use offline::RankedNote;
+pub use stats::StreamStats;
+
+/// 窓関数の係数のキャッシュに保持する個数
+const WINDOW_CACHE_CAPACITY: usize = 8;
+/// 音量の包絡線が音量の上昇に追従する時定数（秒）
+const ENVELOPE_ATTACK: Float = 0.01;
+/// 音量の包絡線が音量の下降に追従する時定数（秒）
+const ENVELOPE_RELEASE: Float = 0.15;
+/// 音の立ち上がりの検出で、スペクトルフラックスの移動平均を更新する割合
+const ONSET_SMOOTHING: Float = 0.1;
+/// 音割れ（クリップ）しているとみなすサンプルの大きさ
+/// 整数の形式のデバイスでは最大値でも1.0にならないので、少しだけ下げています。
+const CLIP_LEVEL: f32 = 0.99;
+
+/// スレッド間で共有する値を入れるための構造体
+/// 音階検出の設定だけを持ちます。MIDIの出力等、検出した結果の使い方の設定は使う側で持ってください。
+pub struct Config {
+    pub min_volume: AtomicI32,
+    /// FFTの前に直流成分や低い雑音を取り除く、高域通過フィルタの遮断周波数（Hz、0なら取り除かない）
+    pub high_pass_frequency: AtomicU32,
+    /// 音量をA特性で重み付けして測るかどうか
+    pub use_a_weighting: AtomicBool,
+    /// 音声データの音量を自動で調整してから解析するかどうか
+    pub use_agc: AtomicBool,
+    /// 自動で音量を調整する場合の目標の音量（dB）
+    pub agc_target: AtomicI32,
+    /// ノイズゲートが開き切るまでの時間（ミリ秒）
+    pub gate_attack: AtomicU32,
+    /// 音量が閾値を下回ってから、ノイズゲートが閉じ始めるまでの時間（ミリ秒）
+    pub gate_hold: AtomicU32,
+    /// ノイズゲートが閉じ始めてから閉じ切るまでの時間（ミリ秒）
+    pub gate_release: AtomicU32,
+    /// 雑音の大きさを追従して、スペクトルから差し引くかどうか
+    pub use_noise_reduction: AtomicBool,
+    /// 追従した雑音を差し引く倍率（%）
+    pub noise_over_subtraction: AtomicU32,
+    /// 検出を止める音量を`min_volume`からどれだけ下げるか（dB）
+    pub volume_hysteresis: AtomicI32,
+    pub point_times: AtomicU16,
+    /// 解析の窓の長さ（フレーム数、0の場合は入力デバイスから一回に受け取る分をそのまま解析する）
+    pub analysis_window_length: AtomicU32,
+    /// 解析の窓を重ねる割合（%）
+    pub analysis_overlap: AtomicU32,
+    /// FFTのサイズ（音声データの長さ×`point_times`）の上限
+    pub max_fft_length: AtomicU32,
+    /// 使う窓関数（`WindowFunction::ALL`での位置）
+    pub window_function: AtomicU8,
+    /// 無音データを差し引くかどうか（無音データがまだ無い場合は、次の解析の音声データから取る）
+    pub use_silent: AtomicBool,
+    pub adjustment_rate: AtomicI32,
+    /// 基準とするラ（A4）の周波数（Hz）
+    pub reference_pitch: AtomicU32,
+    /// 一番目の音の周波数を、その周辺だけを細かく解析（ズームFFT）して求めるかどうか
+    pub use_zoom_fft: AtomicBool,
+    /// 検出する一番低い音（MIDIの番号）
+    pub min_note: AtomicU8,
+    /// 検出する一番高い音（MIDIの番号）
+    pub max_note: AtomicU8,
+    /// 基準音での校正で求めた、周波数の補正（ppm）
+    pub frequency_correction: AtomicI32,
+    /// 音階の候補の順位付けの方法（`RankingStrategy::ALL`での位置、それ以降は`plugins`での位置）
+    pub ranking_strategy: AtomicU8,
+    /// 検出したとみなす最低の信頼度（dB）
+    pub min_confidence: AtomicI32,
+    /// 上位二つの候補の差がこれより小さい場合に曖昧とみなす差（dB、0なら曖昧とみなさない）
+    pub ambiguity_margin: AtomicU32,
+    /// 検出が曖昧な場合の扱い（`AmbiguityPolicy::ALL`での位置）
+    pub ambiguity_policy: AtomicU8,
+    /// フレーズの最初に検出した音のオクターブに、その後の音を合わせるかどうか
+    pub use_octave_lock: AtomicBool,
+    /// オクターブの固定をやめるまでの、検出しない時間（ミリ秒）
+    pub octave_lock_reset: AtomicU32,
+    /// 弾き直しとみなすスペクトルフラックスの、移動平均に対する割合（%）
+    pub onset_threshold: AtomicU32,
+    /// Scalaの音律ファイルから読み込んだ音律（`None`なら平均律）
+    /// NOTE: アトミックな値にできないので、`ConfigSnapshot`には含めていません。
+    ///   解析中にロックしないで済むように、変更は`set_scale`で行ってください。
+    pub scale: Mutex<Option<Arc<scala::Scale>>>,
+    /// `scale`を変更した回数
+    pub scale_revision: AtomicU32,
+    /// 補正に使うマイクの周波数特性（`None`なら補正しない）
+    /// NOTE: `scale`と同じ理由で`ConfigSnapshot`には含めていません。変更は`set_mic_profile`で行ってください。
+    pub mic_profile: Mutex<Option<Arc<mic_profile::MicProfile>>>,
+    /// `mic_profile`を変更した回数
+    pub mic_profile_revision: AtomicU32,
+    /// 無音データ（`None`ならまだ取っていない）
+    /// NOTE: `scale`と同じ理由で`ConfigSnapshot`には含めていません。
+    ///   取った無音データは解析を行うスレッドが設定します。読み込んだり忘れたりする場合は`set_silence`で行ってください。
+    pub silence: Mutex<Option<Arc<silence::SilenceProfile>>>,
+    /// `set_silence`で`silence`を変更した回数
+    pub silence_revision: AtomicU32,
+    /// 読み込んだ検出方法のプラグイン（`ranking_strategy`の`RankingStrategy::ALL`の後ろに続く）
+    /// 起動時に一度だけ設定します。
+    pub plugins: OnceLock<Vec<plugin::Plugin>>,
+}
+
+/// `Config`の設定の値を、ある時点で写し取ったものです。
+/// 設定の変更を元に戻すのに使います。（無音データのような操作は含みません。）
+#[derive(Clone, PartialEq, Debug)]
+pub struct ConfigSnapshot {
+    pub min_volume: i32,
+    pub high_pass_frequency: u32,
+    pub use_a_weighting: bool,
+    pub use_agc: bool,
+    pub agc_target: i32,
+    pub gate_attack: u32,
+    pub gate_hold: u32,
+    pub gate_release: u32,
+    pub use_noise_reduction: bool,
+    pub noise_over_subtraction: u32,
+    pub volume_hysteresis: i32,
+    pub point_times: u16,
+    pub analysis_window_length: u32,
+    pub analysis_overlap: u32,
+    pub max_fft_length: u32,
+    pub window_function: u8,
+    pub adjustment_rate: i32,
+    pub reference_pitch: u32,
+    pub use_zoom_fft: bool,
+    pub min_note: u8,
+    pub max_note: u8,
+    pub frequency_correction: i32,
+    pub ranking_strategy: u8,
+    pub min_confidence: i32,
+    pub ambiguity_margin: u32,
+    pub ambiguity_policy: u8,
+    pub use_octave_lock: bool,
+    pub octave_lock_reset: u32,
+    pub onset_threshold: u32,
+}
+
+impl Config {
+    /// 音律を設定します。（`None`なら平均律）
+    pub fn set_scale(&self, scale: Option<Arc<scala::Scale>>) {
+        *self.scale.lock().unwrap() = scale;
+        self.scale_revision.fetch_add(1, SeqCst);
+    }
+
+    /// 無音データを設定します。（`None`なら忘れる）
+    pub fn set_silence(&self, silence: Option<Arc<silence::SilenceProfile>>) {
+        *self.silence.lock().unwrap() = silence;
+        self.silence_revision.fetch_add(1, SeqCst);
+    }
+
+    /// 補正に使うマイクの周波数特性を設定します。（`None`なら補正しない）
+    pub fn set_mic_profile(&self, profile: Option<Arc<mic_profile::MicProfile>>) {
+        *self.mic_profile.lock().unwrap() = profile;
+        self.mic_profile_revision.fetch_add(1, SeqCst);
+    }
+
+    /// 今の設定の値を写し取ります。
+    pub fn snapshot(&self) -> ConfigSnapshot {
+        ConfigSnapshot {
+            min_volume: self.min_volume.load(SeqCst),
+            high_pass_frequency: self.high_pass_frequency.load(SeqCst),
+            use_a_weighting: self.use_a_weighting.load(SeqCst),
+            use_agc: self.use_agc.load(SeqCst),
+            agc_target: self.agc_target.load(SeqCst),
+            gate_attack: self.gate_attack.load(SeqCst),
+            gate_hold: self.gate_hold.load(SeqCst),
+            gate_release: self.gate_release.load(SeqCst),
+            use_noise_reduction: self.use_noise_reduction.load(SeqCst),
+            noise_over_subtraction: self.noise_over_subtraction.load(SeqCst),
+            volume_hysteresis: self.volume_hysteresis.load(SeqCst),
+            point_times: self.point_times.load(SeqCst),
+            analysis_window_length: self.analysis_window_length.load(SeqCst),
+            analysis_overlap: self.analysis_overlap.load(SeqCst),
+            max_fft_length: self.max_fft_length.load(SeqCst),
+            window_function: self.window_function.load(SeqCst),
+            adjustment_rate: self.adjustment_rate.load(SeqCst),
+            reference_pitch: self.reference_pitch.load(SeqCst),
+            use_zoom_fft: self.use_zoom_fft.load(SeqCst),
+            min_note: self.min_note.load(SeqCst),
+            max_note: self.max_note.load(SeqCst),
+            frequency_correction: self.frequency_correction.load(SeqCst),
+            ranking_strategy: self.ranking_strategy.load(SeqCst),
+            min_confidence: self.min_confidence.load(SeqCst),
+            ambiguity_margin: self.ambiguity_margin.load(SeqCst),
+            ambiguity_policy: self.ambiguity_policy.load(SeqCst),
+            use_octave_lock: self.use_octave_lock.load(SeqCst),
+            octave_lock_reset: self.octave_lock_reset.load(SeqCst),
+            onset_threshold: self.onset_threshold.load(SeqCst),
+        }
+    }
+
+    /// 写し取った設定の値に戻します。
+    /// 検出する音の範囲が逆になっている場合（一番低い音が一番高い音より高い場合）は、二つを入れ替えます。
+    pub fn restore(&self, snapshot: &ConfigSnapshot) {
+        self.min_volume.store(snapshot.min_volume, SeqCst);
+        self.high_pass_frequency
+            .store(snapshot.high_pass_frequency, SeqCst);
+        self.use_a_weighting.store(snapshot.use_a_weighting, SeqCst);
+        self.use_agc.store(snapshot.use_agc, SeqCst);
+        self.agc_target.store(snapshot.agc_target, SeqCst);
+        self.gate_attack.store(snapshot.gate_attack, SeqCst);
+        self.gate_hold.store(snapshot.gate_hold, SeqCst);
+        self.gate_release.store(snapshot.gate_release, SeqCst);
+        self.use_noise_reduction
+            .store(snapshot.use_noise_reduction, SeqCst);
+        self.noise_over_subtraction
+            .store(snapshot.noise_over_subtraction, SeqCst);
+        self.volume_hysteresis
+            .store(snapshot.volume_hysteresis, SeqCst);
+        self.point_times.store(snapshot.point_times, SeqCst);
+        self.analysis_window_length
+            .store(snapshot.analysis_window_length, SeqCst);
+        self.analysis_overlap
+            .store(snapshot.analysis_overlap, SeqCst);
+        self.max_fft_length.store(snapshot.max_fft_length, SeqCst);
+        self.window_function.store(snapshot.window_function, SeqCst);
+        self.adjustment_rate.store(snapshot.adjustment_rate, SeqCst);
+        self.reference_pitch.store(snapshot.reference_pitch, SeqCst);
+        self.use_zoom_fft.store(snapshot.use_zoom_fft, SeqCst);
+        let (min_note, max_note) = snapshot.note_range();
+        self.min_note.store(min_note, SeqCst);
+        self.max_note.store(max_note, SeqCst);
+        self.frequency_correction
+            .store(snapshot.frequency_correction, SeqCst);
+        self.ranking_strategy
+            .store(snapshot.ranking_strategy, SeqCst);
+        self.min_confidence.store(snapshot.min_confidence, SeqCst);
+        self.ambiguity_margin
+            .store(snapshot.ambiguity_margin, SeqCst);
+        self.ambiguity_policy
+            .store(snapshot.ambiguity_policy, SeqCst);
+        self.use_octave_lock.store(snapshot.use_octave_lock, SeqCst);
+        self.octave_lock_reset
+            .store(snapshot.octave_lock_reset, SeqCst);
+        self.onset_threshold.store(snapshot.onset_threshold, SeqCst);
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            min_volume: AtomicI32::new(params::MIN_VOLUME.default as _),
+            high_pass_frequency: AtomicU32::new(params::HIGH_PASS_FREQUENCY.default as _),
+            use_a_weighting: AtomicBool::new(false),
+            use_agc: AtomicBool::new(false),
+            agc_target: AtomicI32::new(params::AGC_TARGET.default as _),
+            gate_attack: AtomicU32::new(params::GATE_ATTACK.default as _),
+            gate_hold: AtomicU32::new(params::GATE_HOLD.default as _),
+            gate_release: AtomicU32::new(params::GATE_RELEASE.default as _),
+            use_noise_reduction: AtomicBool::new(false),
+            noise_over_subtraction: AtomicU32::new(params::NOISE_OVER_SUBTRACTION.default as _),
+            volume_hysteresis: AtomicI32::new(params::VOLUME_HYSTERESIS.default as _),
+            point_times: AtomicU16::new(params::POINT_TIMES.default as _),
+            analysis_window_length: AtomicU32::new(params::ANALYSIS_WINDOW_LENGTH.default as _),
+            analysis_overlap: AtomicU32::new(params::ANALYSIS_OVERLAP.default as _),
+            max_fft_length: AtomicU32::new(params::MAX_FFT_LENGTH.default as _),
+            window_function: AtomicU8::new(params::WINDOW_FUNCTION.default as _),
+            use_silent: AtomicBool::new(false),
+            adjustment_rate: AtomicI32::new(params::ADJUSTMENT_RATE.default as _),
+            reference_pitch: AtomicU32::new(params::REFERENCE_PITCH.default as _),
+            use_zoom_fft: AtomicBool::new(false),
+            min_note: AtomicU8::new(params::MIN_NOTE.default as _),
+            max_note: AtomicU8::new(params::MAX_NOTE.default as _),
+            frequency_correction: AtomicI32::new(params::FREQUENCY_CORRECTION.default as _),
+            ranking_strategy: AtomicU8::new(params::RANKING_STRATEGY.default as _),
+            min_confidence: AtomicI32::new(params::MIN_CONFIDENCE.default as _),
+            ambiguity_margin: AtomicU32::new(params::AMBIGUITY_MARGIN.default as _),
+            ambiguity_policy: AtomicU8::new(params::AMBIGUITY_POLICY.default as _),
+            use_octave_lock: AtomicBool::new(false),
+            octave_lock_reset: AtomicU32::new(params::OCTAVE_LOCK_RESET.default as _),
+            onset_threshold: AtomicU32::new(params::ONSET_THRESHOLD.default as _),
+            scale: Mutex::new(None),
+            scale_revision: AtomicU32::new(0),
+            mic_profile: Mutex::new(None),
+            mic_profile_revision: AtomicU32::new(0),
+            silence: Mutex::new(None),
+            silence_revision: AtomicU32::new(0),
+            plugins: OnceLock::new(),
+        }
+    }
+}
+
+impl Default for ConfigSnapshot {
+    fn default() -> Self {
+        Config::default().snapshot()
+    }
+}
+
+impl ConfigSnapshot {
+    /// 検出する音の範囲を、一番低い音と一番高い音の組で取得します。
+    /// 逆になっている場合（一番低い音が一番高い音より高い場合）は、二つを入れ替えます。
+    // NOTE: プリセットや`--set`は一行ずつ読み込むので、どちらを先に読み込んでも良いように`apply_line`では揃えない。
+    pub fn note_range(&self) -> (u8, u8) {
+        (
+            self.min_note.min(self.max_note),
+            self.min_note.max(self.max_note),
+        )
+    }
+
+    /// `名前=値`の行を並べた文字列にします。
+    pub fn to_text(&self) -> String {
+        format!(
+            concat!(
+                "min_volume={}\n",
+                "high_pass_frequency={}\n",
+                "use_a_weighting={}\n",
+                "use_agc={}\n",
+                "agc_target={}\n",
+                "gate_attack={}\n",
+                "gate_hold={}\n",
+                "gate_release={}\n",
+                "use_noise_reduction={}\n",
+                "noise_over_subtraction={}\n",
+                "volume_hysteresis={}\n",
+                "point_times={}\n",
+                "analysis_window_length={}\n",
+                "analysis_overlap={}\n",
+                "max_fft_length={}\n",
+                "window_function={}\n",
+                "adjustment_rate={}\n",
+                "reference_pitch={}\n",
+                "use_zoom_fft={}\n",
+                "min_note={}\n",
+                "max_note={}\n",
+                "frequency_correction={}\n",
+                "ranking_strategy={}\n",
+                "min_confidence={}\n",
+                "ambiguity_margin={}\n",
+                "ambiguity_policy={}\n",
+                "use_octave_lock={}\n",
+                "octave_lock_reset={}\n",
+                "onset_threshold={}\n",
+            ),
+            self.min_volume,
+            self.high_pass_frequency,
+            self.use_a_weighting,
+            self.use_agc,
+            self.agc_target,
+            self.gate_attack,
+            self.gate_hold,
+            self.gate_release,
+            self.use_noise_reduction,
+            self.noise_over_subtraction,
+            self.volume_hysteresis,
+            self.point_times,
+            self.analysis_window_length,
+            self.analysis_overlap,
+            self.max_fft_length,
+            self.window_function,
+            self.adjustment_rate,
+            self.reference_pitch,
+            self.use_zoom_fft,
+            self.min_note,
+            self.max_note,
+            self.frequency_correction,
+            self.ranking_strategy,
+            self.min_confidence,
+            self.ambiguity_margin,
+            self.ambiguity_policy,
+            self.use_octave_lock,
+            self.octave_lock_reset,
+            self.onset_threshold,
+        )
+    }
+
+    /// `to_text`で作った文字列の一行を読み込み、設定の値を書き換えます。
+    /// 知らない名前や読み込めない値の行は無視します。
+    pub fn apply_line(&mut self, line: &str) {
+        fn parse<T: std::str::FromStr>(value: &str, target: &mut T) {
+            if let Ok(value) = value.trim().parse() {
+                *target = value;
+            };
+        }
+
+        let Some((name, value)) = line.split_once('=') else {
+            return;
+        };
+
+        // 数値の値は、範囲の外なら範囲に丸め込む。
+        let clamped;
+        let value = match params::find(name.trim()) {
+            Some(param) => match value.trim().parse() {
+                Ok(number) => {
+                    clamped = param.clamp(number).to_string();
+                    clamped.as_str()
+                }
+                Err(_) => return,
+            },
+            None => value,
+        };
+
+        match name.trim() {
+            "min_volume" => parse(value, &mut self.min_volume),
+            "high_pass_frequency" => parse(value, &mut self.high_pass_frequency),
+            "use_a_weighting" => parse(value, &mut self.use_a_weighting),
+            "use_agc" => parse(value, &mut self.use_agc),
+            "agc_target" => parse(value, &mut self.agc_target),
+            "gate_attack" => parse(value, &mut self.gate_attack),
+            "gate_hold" => parse(value, &mut self.gate_hold),
+            "gate_release" => parse(value, &mut self.gate_release),
+            "use_noise_reduction" => parse(value, &mut self.use_noise_reduction),
+            "noise_over_subtraction" => parse(value, &mut self.noise_over_subtraction),
+            "volume_hysteresis" => parse(value, &mut self.volume_hysteresis),
+            "point_times" => parse(value, &mut self.point_times),
+            "analysis_window_length" => parse(value, &mut self.analysis_window_length),
+            "analysis_overlap" => parse(value, &mut self.analysis_overlap),
+            "max_fft_length" => parse(value, &mut self.max_fft_length),
+            "window_function" => parse(value, &mut self.window_function),
+            // 窓関数を使うかどうかだけを設定していた頃のプリセット
+            "use_window_flag" => {
+                let mut use_window = false;
+                parse(value, &mut use_window);
+                self.window_function = use_window as u8;
+            }
+            "adjustment_rate" => parse(value, &mut self.adjustment_rate),
+            "reference_pitch" => parse(value, &mut self.reference_pitch),
+            "use_zoom_fft" => parse(value, &mut self.use_zoom_fft),
+            "min_note" => parse(value, &mut self.min_note),
+            "max_note" => parse(value, &mut self.max_note),
+            "frequency_correction" => parse(value, &mut self.frequency_correction),
+            "ranking_strategy" => parse(value, &mut self.ranking_strategy),
+            "min_confidence" => parse(value, &mut self.min_confidence),
+            "ambiguity_margin" => parse(value, &mut self.ambiguity_margin),
+            "ambiguity_policy" => parse(value, &mut self.ambiguity_policy),
+            "use_octave_lock" => parse(value, &mut self.use_octave_lock),
+            "octave_lock_reset" => parse(value, &mut self.octave_lock_reset),
+            "onset_threshold" => parse(value, &mut self.onset_threshold),
+            _ => (),
+        };
+    }
+}
+
+/// 窓関数が自動の場合に、スペクトルフラックスが移動平均の何倍になったらテューキー窓だけを使うか
+const TRANSIENT_FLUX_RATIO: Float = 3.;
+/// 窓関数が自動の場合に、窓関数を切り替える速さ（一回の解析で近付ける割合）
+const WINDOW_SWITCH_SMOOTHING: Float = 0.2;
+
+/// ズームFFTで解析する範囲（一番目の音の周波数から±セント）
+const ZOOM_FFT_SPAN: f32 = 50.;
+/// ズームFFTで解析する範囲を分ける点の数
+const ZOOM_FFT_POINTS: usize = 64;
+
+/// FFTのサイズの上限の初期値
+pub const DEFAULT_MAX_FFT_LENGTH: u32 = 1 << 18;
+
+/// 音階の数がこれ以上の場合は、周波数帯の音量の計算と候補の選択を複数のスレッドで行う。
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_BANDS: usize = 512;
+
+/// 倍音を重視した順位付けで使う、倍音の音階の差（半音単位）と重み
+const HARMONIC_WEIGHTS: [(usize, Float); 3] = [(12, 0.5), (19, 0.33), (24, 0.25)];
+
+/// 音階の候補の順位付けの方法です。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RankingStrategy {
+    /// 周波数帯の平均の音量
+    BandAverage,
+    /// 周波数帯の最大の音量
+    BandPeak,
+    /// 周波数帯の平均の音量に倍音の周波数帯の音量を重み付けして足したもの
+    HarmonicWeighted,
+}
+
+impl RankingStrategy {
+    pub const ALL: [Self; 3] = [Self::BandAverage, Self::BandPeak, Self::HarmonicWeighted];
+
+    /// UIに表示する名前を取得します。
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::BandAverage => "周波数帯の平均",
+            Self::BandPeak => "周波数帯の最大",
+            Self::HarmonicWeighted => "倍音で重み付け",
+        }
+    }
+
+    /// `ALL`での位置から取得します。
+    pub fn from_index(index: u8) -> Self {
+        Self::ALL
+            .get(index as usize)
+            .copied()
+            .unwrap_or(Self::BandAverage)
+    }
+}
+
+/// 解析の前に音声データに掛ける窓関数の選び方です。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WindowFunction {
+    /// 窓関数を使わない
+    None,
+    /// ハン窓
+    Hann,
+    /// 持続している音にはハン窓、立ち上がりの多い音には平らな部分の広いテューキー窓を使う
+    Auto,
+    /// ハミング窓
+    Hamming,
+    /// ブラックマン・ハリス窓
+    BlackmanHarris,
+    /// フラットトップ窓
+    FlatTop,
+}
+
+impl WindowFunction {
+    // NOTE: 保存された設定の位置が変わらないように、新しいものは後ろに追加する。
+    pub const ALL: [Self; 6] = [
+        Self::None,
+        Self::Hann,
+        Self::Auto,
+        Self::Hamming,
+        Self::BlackmanHarris,
+        Self::FlatTop,
+    ];
+
+    /// UIに表示する名前を取得します。
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "なし",
+            Self::Hann => "ハン窓",
+            Self::Auto => "自動",
+            Self::Hamming => "ハミング窓",
+            Self::BlackmanHarris => "ブラックマン・ハリス窓",
+            Self::FlatTop => "フラットトップ窓",
+        }
+    }
+
+    /// `ALL`での位置から取得します。
+    pub fn from_index(index: u8) -> Self {
+        Self::ALL.get(index as usize).copied().unwrap_or(Self::None)
+    }
+}
+
+/// 上位二つの候補の差が小さく、検出が曖昧な場合の扱いです。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AmbiguityPolicy {
+    /// 前回と同じ音の候補があれば、それを選ぶ
+    KeepPrevious,
+    /// 低い方の音を選ぶ
+    PickLower,
+    /// 検出しなかったことにする
+    Suppress,
+}
+
+impl AmbiguityPolicy {
+    pub const ALL: [Self; 3] = [Self::KeepPrevious, Self::PickLower, Self::Suppress];
+
+    /// UIに表示する名前を取得します。
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::KeepPrevious => "前回の音を保つ",
+            Self::PickLower => "低い方の音を選ぶ",
+            Self::Suppress => "検出しない",
+        }
+    }
+
+    /// `ALL`での位置から取得します。
+    pub fn from_index(index: u8) -> Self {
+        Self::ALL
+            .get(index as usize)
+            .copied()
+            .unwrap_or(Self::KeepPrevious)
+    }
+}
+
+/// `f`を実行して、その結果とかかった時間を返します。
+/// ブラウザ（wasm32-unknown-unknown）では`Instant::now`が使えないので、時間は測らずに0とします。
+fn measure<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    {
+        let started_at = std::time::Instant::now();
+        let value = f();
+        (value, started_at.elapsed())
+    }
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    {
+        (f(), Duration::ZERO)
+    }
+}
+
+/// 生の音階データを格納するための構造体
+/// 一つ目の値は`NoteContainer`での位置です。
+#[derive(PartialEq)]
+struct RawNote(usize, Float);
+
+/// `data`の`before_frequency`から`after_frequency`までの周波数帯について、順位付けに使う音量と、
+/// 一番音量が高い所を補間して求めた音量と`data`での位置（小数点以下を含む）を求めます。
+fn band_value(
+    data: &[Float],
+    resolution: f32,
+    before_frequency: f32,
+    after_frequency: f32,
+    ranking_strategy: RankingStrategy,
+) -> (Float, (Float, Float)) {
+    // ナイキスト周波数を超える部分は計算結果にないので、その手前までにする。
+    let end = ((after_frequency / resolution) as usize).min(data.len());
+    let start = ((before_frequency / resolution) as usize).min(end);
+    let stack = &data[start..end];
+    let peak = stack
+        .iter()
+        .copied()
+        .enumerate()
+        .fold((0, Float::NAN), |peak, (index, value)| {
+            if value > peak.1 || peak.1.is_nan() {
+                (index, value)
+            } else {
+                peak
+            }
+        });
+
+    // 一番音量が高い所の両隣の値も使って、FFTの解像度より細かく山の頂点を求める。
+    let (offset, height) = if peak.1.is_nan() {
+        (0., Float::NAN)
+    } else {
+        calculation::parabolic_peak(data, start + peak.0)
+    };
+    let value = match ranking_strategy {
+        RankingStrategy::BandPeak => height,
+        _ => stack.iter().sum::<Float>() / stack.len() as Float,
+    };
+
+    (value, (height, (start + peak.0) as Float + offset))
+}
+
+/// `index`番目の音階を候補として比べるための値を求めます。
+/// 倍音を重視した順位付けの場合は、倍音の周波数帯の音量も重みを付けて足します。
+fn candidate_value(
+    band_values: &[Float],
+    index: usize,
+    ranking_strategy: RankingStrategy,
+) -> Float {
+    let mut value = band_values[index];
+    if ranking_strategy == RankingStrategy::HarmonicWeighted && !value.is_nan() {
+        for (offset, weight) in HARMONIC_WEIGHTS {
+            if let Some(harmonic_value) = band_values.get(index + offset) {
+                if !harmonic_value.is_nan() {
+                    value += harmonic_value * weight;
+                };
+            };
+        }
+    };
+    value
+}
+
+/// 値の大きい順に並んだ`candidates`に`raw_note`を挿入し、上位`limit`個だけを残します。
+/// 値が同じ場合は、先に挿入したものを上位にします。
+/// `candidates`の容量が`limit`以上あれば、メモリは確保しません。
+fn insert_top(candidates: &mut Vec<RawNote>, raw_note: RawNote, limit: usize) {
+    let position = candidates.partition_point(|candidate| candidate.1 >= raw_note.1);
+    if position >= limit {
+        return;
+    };
+
+    if candidates.len() >= limit {
+        candidates.pop();
+    };
+    candidates.insert(position, raw_note);
+}
+
+// NOTE: ここからは、リアルタイムの検出（`Synthesizer::detect`）と一度だけの解析（`Synthesizer::process_buffer`）で
+//   共通の処理です。前回までの解析の状態は持たないので、状態の扱いはそれぞれで行います。
+
+/// 基準の周波数（Hz）と音律（`None`なら平均律）から、音階の表を作ります。
+fn make_notes(scale: Option<&scala::Scale>, reference_pitch: u32) -> NoteContainer {
+    match scale {
+        Some(scale) => NoteContainer::with_scale(scale, reference_pitch as _),
+        None => NoteContainer::with_reference_pitch(reference_pitch as _),
+    }
+}
+
+/// 設定された窓関数の種類を取得します。
+/// 窓関数を使わない場合と、解析の状態によって窓関数を変える自動の場合は`None`を返します。
+fn window_kind(window_function: WindowFunction) -> Option<calculation::WindowKind> {
+    // NOTE: 窓関数を使う理由は次のウェブページが参考になると思います。
+    //   https://www.logical-arts.jp/archives/124
+    match window_function {
+        WindowFunction::None | WindowFunction::Auto => None,
+        WindowFunction::Hann => Some(calculation::WindowKind::Hann),
+        WindowFunction::Hamming => Some(calculation::WindowKind::Hamming),
+        WindowFunction::BlackmanHarris => Some(calculation::WindowKind::BlackmanHarris),
+        WindowFunction::FlatTop => Some(calculation::WindowKind::FlatTop),
+    }
+}
+
+/// プラグインの検出方法が選ばれているのなら、プラグインに候補を`capacity`個まで探させて`candidates`に入れます。
+/// プラグインを使ったかどうかを返します。
+fn detect_with_plugin(
+    config: &Config,
+    data: &[f32],
+    frame_rate: f32,
+    candidates: &mut Vec<plugin::Candidate>,
+    capacity: usize,
+) -> bool {
+    let plugin_index =
+        (config.ranking_strategy.load(SeqCst) as usize).checked_sub(RankingStrategy::ALL.len());
+    match plugin_index.and_then(|index| config.plugins.get().and_then(|plugins| plugins.get(index)))
+    {
+        Some(plugin) => {
+            plugin.detect(data, frame_rate, candidates, capacity);
+            true
+        }
+        None => false,
+    }
+}
+
+/// FFTの結果から、無音データのスペクトルを差し引きます。
+fn subtract_silence(buffer: &mut [Float], silence: &silence::SilenceProfile) {
+    for (value, silence) in buffer.iter_mut().zip(silence.values.iter()) {
+        *value = (*value - silence).max(0.);
+    }
+}
+
+/// FFTの結果に、マイクの周波数特性を補正する倍率を掛けます。
+fn apply_gains(buffer: &mut [Float], gains: &[Float]) {
+    for (value, gain) in buffer.iter_mut().zip(gains.iter()) {
+        *value *= gain;
+    }
+}
+
+/// `number`の音階の周波数帯の音量を`band_value`で求めます。
+/// 検出する音の範囲外の音階の周波数帯は計算せず、候補にならないように`NAN`にします。
+fn note_band_value(
+    data: &[Float],
+    resolution: f32,
+    ranking_strategy: RankingStrategy,
+    note_range: &RangeInclusive<u8>,
+    (before_frequency, after_frequency): (f32, f32),
+    number: u8,
+) -> (Float, (Float, Float)) {
+    if note_range.contains(&number) {
+        band_value(
+            data,
+            resolution,
+            before_frequency,
+            after_frequency,
+            ranking_strategy,
+        )
+    } else {
+        (Float::NAN, (Float::NAN, Float::NAN))
+    }
+}
+
+/// 各音階の周波数帯の音量から、上位`limit`個の候補を値の大きい順に`candidates`に入れます。
+fn rank_bands(
+    band_values: &[Float],
+    ranking_strategy: RankingStrategy,
+    limit: usize,
+    candidates: &mut Vec<RawNote>,
+) {
+    for index in 0..band_values.len() {
+        let value = candidate_value(band_values, index, ranking_strategy);
+        if !value.is_nan() {
+            insert_top(candidates, RawNote(index, value), limit);
+        };
+    }
+}
+
+/// プラグインが見つけた候補を、その周波数を含む周波数帯の音階にして、上位`limit`個を値の大きい順に`candidates`に入れます。
+fn rank_plugin_candidates(
+    notes: &NoteContainer,
+    note_range: &RangeInclusive<u8>,
+    plugin_candidates: &[plugin::Candidate],
+    limit: usize,
+    candidates: &mut Vec<RawNote>,
+) {
+    for candidate in plugin_candidates {
+        if candidate.strength.is_nan() {
+            continue;
+        };
+
+        let index = notes
+            .before_frequencies
+            .iter()
+            .zip(notes.after_frequencies.iter())
+            .position(|(before, after)| (*before..*after).contains(&candidate.frequency));
+        if let Some(index) = index.filter(|index| note_range.contains(&notes.numbers[*index])) {
+            insert_top(candidates, RawNote(index, candidate.strength as _), limit);
+        };
+    }
+}
+
+/// 信頼度の計算のために、スペクトル全体（ナイキスト周波数まで）の平均を求めます。
+fn spectrum_mean(buffer: &[Float], buffer_length: usize) -> Float {
+    let spectrum = &buffer[..buffer_length / 2];
+    spectrum.iter().sum::<Float>() / spectrum.len() as Float
+}
+
+/// 候補を、検出した音階にします。
+/// 周波数は、補間して求めた山の頂点の位置から求めます。
+fn to_note(
+    notes: &NoteContainer,
+    raw_note: &RawNote,
+    band_peaks: &[(Float, Float)],
+    resolution: f32,
+    adjustment_rate: i32,
+    spectrum_mean: Float,
+) -> Note {
+    let (peak, position) = band_peaks[raw_note.0];
+    let position: f32 = position as _;
+    let frequency = position * resolution;
+    Note {
+        number: (notes.numbers[raw_note.0] as i32 + adjustment_rate).clamp(0, 127) as _,
+        frequency,
+        cents: 1200. * (frequency / notes.frequencies[raw_note.0]).log2(),
+        confidence: (20. * (peak / spectrum_mean).log10()) as _,
+    }
+}
+
+/// 音の周波数を、その周辺だけを細かく解析（ズームFFT）して求め直します。
+/// `note_frequency`は、候補の音階の周波数（Hz）です。
+fn refine_with_zoom(
+    note: &mut Note,
+    note_frequency: f32,
+    analysis_data: &[Float],
+    frame_rate: f32,
+    magnitudes: &mut [Float],
+) {
+    note.frequency = calculation::zoom_peak(
+        analysis_data,
+        frame_rate,
+        note.frequency,
+        ZOOM_FFT_SPAN,
+        magnitudes,
+    );
+    note.cents = 1200. * (note.frequency / note_frequency).log2();
+}
+
+/// 音階を検出するためのものを実装した構造体
+pub struct Synthesizer {
+    notes: NoteContainer,
+    /// `notes`の基準としているラ（A4）の周波数（Hz）
+    reference_pitch: u32,
+    /// `notes`の音律
+    scale: Option<Arc<scala::Scale>>,
+    /// `scale`を反映した時の`Config::scale_revision`
+    scale_revision: u32,
+    /// マイクの周波数特性（`None`なら補正しない）
+    mic_profile: Option<Arc<mic_profile::MicProfile>>,
+    /// `mic_profile`を反映した時の`Config::mic_profile_revision`
+    mic_profile_revision: u32,
+    /// FFTの結果のバッファの各値に掛ける、マイクの周波数特性の補正の倍率と、それを求めた時の解像度
+    mic_gains: Vec<Float>,
+    mic_gains_resolution: f32,
+    frame_rate: f32,
+    silence: Option<Arc<silence::SilenceProfile>>,
+    /// `silence`を反映した時の`Config::silence_revision`
+    silence_revision: u32,
+    /// `silence`を`Config::silence`に設定済みかどうか
+    is_silence_shared: bool,
+    /// 無音データを取っている途中の状態（取っていない時は`None`）
+    silence_capture: Option<silence::SilenceCapture>,
+    /// 解析の窓の長さが設定されている場合に、受け取った音声データを解析の窓にまとめるためのもの
+    frame_assembler: frames::FrameAssembler,
+    buffer: Vec<Float>,
+    window_cache: calculation::WindowCache,
+    analysis_data: Vec<Float>,
+    fft_workspace: calculation::fft::Workspace,
+    fft_backend: calculation::fft::DefaultFftBackend,
+    band_values: Vec<Float>,
+    /// 各音階の周波数帯で一番音量が高い所を補間して求めた音量と、`buffer`での位置（小数点以下を含む）
+    band_peaks: Vec<(Float, Float)>,
+    /// 上位の候補（値の大きい順）
+    candidates: Vec<RawNote>,
+    /// プラグインが検出した候補
+    plugin_candidates: Vec<plugin::Candidate>,
+    /// ズームFFTで解析した各点の成分の大きさ
+    zoom_magnitudes: [Float; ZOOM_FFT_POINTS],
+    /// 前回一番目だった候補の`NoteContainer`での位置
+    previous_top: Option<usize>,
+    /// オクターブを固定している場合の、フレーズの最初に検出した音（MIDIの番号）
+    locked_note: Option<u8>,
+    /// オクターブを固定してから、続けて検出しなかった時間（秒）
+    octave_lock_silence: f32,
+    limited_point_times: Option<usize>,
+    /// 直前の解析での`buffer`の解像度
+    resolution: f32,
+    /// 窓関数が自動の場合に、テューキー窓を混ぜる割合
+    transient_mix: Float,
+    /// 音量が閾値を下回った時に検出を止めるためのもの
+    gate: calculation::NoiseGate,
+    envelope: calculation::EnvelopeFollower,
+    /// 自動で音量を調整するためのもの
+    agc: calculation::AutoGainControl,
+    /// 自動で音量を調整した音声データ
+    agc_data: Vec<f32>,
+    /// 雑音の大きさを追従して差し引くためのもの
+    noise_tracker: calculation::NoiseFloorTracker,
+    /// A特性の重み付けをした音量を測るためのもの
+    a_weighting: calculation::AWeighting,
+    /// 直流成分や低い雑音を取り除くためのものと、取り除いた音声データ
+    high_pass: calculation::HighPassFilter,
+    high_pass_data: Vec<f32>,
+    onset_detector: calculation::OnsetDetector,
+    /// 最後の検出で音の立ち上がりを検出したかどうか
+    is_onset: bool,
+    /// 直前の解析にかかった時間
+    processing_time: Duration,
+    /// 直前の解析で閾値と比べた音量（dB）
+    volume: Float,
+    /// 直前の解析で新しく届いた音声データが音割れしていたかどうか
+    is_clipped: bool,
+    pub config: Arc<Config>,
+}
+
+impl Synthesizer {
+    /// インスタンスを作ります。
+    /// 解析中にメモリを確保しないで済むように、音階の数に応じた作業用のバッファをここで確保しておきます。
+    pub fn new(notes: NoteContainer, frame_rate: f32) -> Self {
+        let number_of_notes = notes.numbers.len();
+
+        Self {
+            notes,
+            reference_pitch: DEFAULT_REFERENCE_PITCH,
+            scale: None,
+            scale_revision: 0,
+            mic_profile: None,
+            mic_profile_revision: 0,
+            mic_gains: Vec::new(),
+            mic_gains_resolution: 0.,
+            frame_rate,
+            silence: None,
+            silence_revision: 0,
+            is_silence_shared: true,
+            silence_capture: None,
+            frame_assembler: frames::FrameAssembler::new(),
+            buffer: Vec::new(),
+            window_cache: calculation::WindowCache::new(WINDOW_CACHE_CAPACITY),
+            analysis_data: Vec::new(),
+            fft_workspace: Default::default(),
+            fft_backend: Default::default(),
+            band_values: Vec::with_capacity(number_of_notes),
+            band_peaks: Vec::with_capacity(number_of_notes),
+            candidates: Vec::with_capacity(number_of_notes),
+            plugin_candidates: Vec::new(),
+            zoom_magnitudes: [0.; ZOOM_FFT_POINTS],
+            previous_top: None,
+            locked_note: None,
+            octave_lock_silence: 0.,
+            limited_point_times: None,
+            resolution: 0.,
+            transient_mix: 0.,
+            gate: Default::default(),
+            envelope: calculation::EnvelopeFollower::new(ENVELOPE_ATTACK, ENVELOPE_RELEASE),
+            agc: Default::default(),
+            noise_tracker: Default::default(),
+            agc_data: Vec::new(),
+            a_weighting: calculation::AWeighting::new(frame_rate),
+            high_pass: calculation::HighPassFilter::new(frame_rate),
+            high_pass_data: Vec::new(),
+            onset_detector: calculation::OnsetDetector::new(ONSET_SMOOTHING),
+            is_onset: false,
+            processing_time: Duration::ZERO,
+            volume: Float::NEG_INFINITY,
+            is_clipped: false,
+            config: Arc::new(Config::default()),
+        }
+    }
+
+    /// 設定をまとめて指定して作るためのビルダーを作ります。
+    pub fn builder(frame_rate: f32) -> SynthesizerBuilder {
+        SynthesizerBuilder::new(frame_rate)
+    }
+
+    /// FFTのサイズの上限によってポイント数の規模が制限されている場合、実際に使われた規模を返します。
+    pub fn limited_point_times(&self) -> Option<usize> {
+        self.limited_point_times
+    }
+
+    /// 無音データを取っている途中なら、あと何回分のデータが必要かを取得します。
+    pub fn silence_frames_left(&self) -> Option<u32> {
+        self.silence_capture
+            .as_ref()
+            .map(silence::SilenceCapture::frames_left)
+    }
+
+    /// 入力の音量（RMS）の包絡線の値を取得します。
+    /// ノイズゲートが開き切っていない間は、その開き具合を掛けた値になります。
+    pub fn envelope(&self) -> f32 {
+        (self.envelope.value() * self.gate.gain()) as _
+    }
+
+    /// 直前の解析にかかった時間を取得します。
+    pub fn processing_time(&self) -> Duration {
+        self.processing_time
+    }
+
+    /// 直前の解析で検出の閾値と比べた音量（dB）を取得します。
+    /// A特性や自動音量調整が有効な場合は、それを反映した音量です。
+    pub fn volume(&self) -> f32 {
+        self.volume as _
+    }
+
+    /// 直前の解析で新しく届いた音声データが、音割れする大きさに達していたかどうかを取得します。
+    pub fn is_clipped(&self) -> bool {
+        self.is_clipped
+    }
+
+    /// 最後の検出で音の立ち上がり（同じ音の弾き直し等）を検出したかどうかを取得します。
+    pub fn is_onset(&self) -> bool {
+        self.is_onset
+    }
+
+    /// 直前の解析での周波数ごとの音の大きさ（dB）を、`min_frequency`から`max_frequency`までを
+    /// 対数の周波数軸で`output`の長さの数に分けた区間ごとの最大値にまとめて、`output`に書き込みます。
+    /// 音量が閾値を下回っていて解析を行わなかった場合は、何も書き込まずに`false`を返します。
+    pub fn log_spectrum(&self, min_frequency: f32, max_frequency: f32, output: &mut [f32]) -> bool {
+        let spectrum = self.buffer.as_slice();
+        if !self.gate.is_open() || spectrum.is_empty() {
+            return false;
+        };
+        let bins = output.len();
+
+        let rate = max_frequency / min_frequency;
+        let index = |bin: usize| {
+            (min_frequency * rate.powf(bin as f32 / bins as f32) / self.resolution) as usize
+        };
+
+        for (bin, value) in output.iter_mut().enumerate() {
+            // 低い周波数では区間が狭いので、少なくとも一つの値を含むようにする。
+            let start = index(bin).min(spectrum.len() - 1);
+            let end = index(bin + 1).clamp(start + 1, spectrum.len());
+            let peak = spectrum[start..end]
+                .iter()
+                .copied()
+                .fold(Float::EPSILON, Float::max);
+            *value = (20. * peak.log10()) as _;
+        }
+        true
+    }
+
+    /// 直前の解析でのスペクトルの山を、大きい順に`max_n`個まで取得します。
+    /// 音階の周波数帯とは関係なく、極大になっている所をFFTの解像度より細かく補間して、
+    /// 周波数（Hz）と大きさの組にして返します。倍音の解析等、音階にまとめない使い方のためのものです。
+    /// `synthe`か`feed`で解析した後に呼んでください。
+    /// 音量が閾値を下回っていて解析を行わなかった場合は、空の`Vec`を返します。
+    pub fn peaks(&self, max_n: usize) -> Vec<(f32, f32)> {
+        let spectrum = self.buffer.as_slice();
+        if !self.gate.is_open() || spectrum.len() < 3 {
+            return Vec::new();
+        };
+
+        let mut peaks: Vec<(f32, f32)> = (1..spectrum.len() - 1)
+            .filter(|index| {
+                spectrum[*index] > spectrum[index - 1] && spectrum[*index] >= spectrum[index + 1]
+            })
+            .map(|index| {
+                let (offset, height) = calculation::parabolic_peak(spectrum, index);
+                let position: f32 = (index as Float + offset) as _;
+                (position * self.resolution, height as _)
+            })
+            .collect();
+        peaks.sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+        peaks.truncate(max_n);
+
+        peaks
+    }
+
+    /// 入力デバイスから受け取った音声データをためて、設定された窓の長さと重なりで音階検出の処理を行います。
+    /// 解析を行う度に、その結果を`on_result`に渡します。（一回も解析しないことも、複数回解析することもあります。）
+    /// 解析の窓の長さが0の場合は、受け取った音声データをそのまま解析します。
+    pub fn feed<const N: usize>(
+        &mut self,
+        data: &[f32],
+        mut on_result: impl FnMut(&mut Self, Option<[Note; N]>),
+    ) {
+        let window_length = self.config.analysis_window_length.load(SeqCst) as usize;
+        if window_length == 0 {
+            self.frame_assembler.clear();
+            let (result, processing_time) = measure(|| self.analyze(data, data.len()));
+            self.processing_time = processing_time;
+            on_result(self, result);
+            return;
+        };
+
+        // 窓を重ねる割合から、次の解析までに進める長さを求める。
+        let overlap = self.config.analysis_overlap.load(SeqCst).min(99) as usize;
+        let hop_length = (window_length * (100 - overlap) / 100).max(1);
+
+        // NOTE: 解析の間は`self`を借りるので、その間だけ取り出しておく。
+        let mut frame_assembler = std::mem::take(&mut self.frame_assembler);
+        frame_assembler.push(data, window_length, hop_length, |frame| {
+            let (result, processing_time) = measure(|| self.analyze(frame, hop_length));
+            self.processing_time = processing_time;
+            on_result(self, result);
+        });
+        self.frame_assembler = frame_assembler;
+    }
+
+    /// 音階検出の処理を行います。
+    #[inline]
+    pub fn synthe<const N: usize>(&mut self, data: &[f32]) -> Option<[Note; N]> {
+        self.analyze(data, data.len())
+    }
+
+    /// 音階検出の処理を行います。
+    /// `new_frames`は、前回の解析から進んだフレーム数です。（窓を重ねている場合は`data`より短くなります。）
+    fn analyze<const N: usize>(&mut self, data: &[f32], new_frames: usize) -> Option<[Note; N]> {
+        self.is_clipped = data[data.len().saturating_sub(new_frames)..]
+            .iter()
+            .any(|value| value.abs() >= CLIP_LEVEL);
+
+        let result = self.apply_high_pass(data, new_frames);
+        self.lock_octave(result, new_frames)
+    }
+
+    /// 高域通過フィルタが有効なら低い音を取り除いて、音階検出の処理を行います。
+    fn apply_high_pass<const N: usize>(
+        &mut self,
+        data: &[f32],
+        new_frames: usize,
+    ) -> Option<[Note; N]> {
+        let cutoff = self.config.high_pass_frequency.load(SeqCst);
+        if cutoff == 0 {
+            self.high_pass.reset();
+            return self.adjust_gain(data, new_frames);
+        };
+
+        // 直流成分や低い雑音を取り除いた音声データを解析する。
+        // NOTE: 解析の間は`self`を借りるので、その間だけ取り出しておく。
+        let mut high_pass_data = std::mem::take(&mut self.high_pass_data);
+        self.high_pass
+            .process(data, new_frames, cutoff, &mut high_pass_data);
+        let result = self.adjust_gain(&high_pass_data, new_frames);
+        self.high_pass_data = high_pass_data;
+        result
+    }
+
+    /// オクターブを固定する設定なら、一番目の音をフレーズの最初に検出した音から一番近い同じ音名の音にします。
+    /// 検出しない時間が設定した時間を超えたら、フレーズが終わったとみなして固定をやめます。
+    fn lock_octave<const N: usize>(
+        &mut self,
+        result: Option<[Note; N]>,
+        new_frames: usize,
+    ) -> Option<[Note; N]> {
+        if !self.config.use_octave_lock.load(SeqCst) {
+            self.locked_note = None;
+            return result;
+        };
+
+        let Some(mut notes) = result else {
+            self.octave_lock_silence += new_frames as f32 / self.frame_rate;
+            if self.octave_lock_silence * 1000. >= self.config.octave_lock_reset.load(SeqCst) as f32
+            {
+                self.locked_note = None;
+            };
+            return None;
+        };
+        self.octave_lock_silence = 0.;
+
+        let locked_note = *self.locked_note.get_or_insert(notes[0].number) as i32;
+        let difference = locked_note - notes[0].number as i32;
+        let octaves = (difference as f32 / 12.).round() as i32;
+        if octaves != 0 {
+            let note = &mut notes[0];
+            *note = note.transposed(octaves * 12);
+            note.frequency *= 2f32.powi(octaves);
+        };
+
+        Some(notes)
+    }
+
+    /// 自動音量調整が有効なら音量を調整して、音階検出の処理を行います。
+    fn adjust_gain<const N: usize>(
+        &mut self,
+        data: &[f32],
+        new_frames: usize,
+    ) -> Option<[Note; N]> {
+        if !self.config.use_agc.load(SeqCst) {
+            self.agc.reset();
+            return self.detect(data, new_frames);
+        };
+
+        // 自動で音量を調整する場合は、目標の音量に近づけた音声データを解析する。
+        let gain = self.agc.process(
+            calculation::get_db(data),
+            new_frames as Float / self.frame_rate as Float,
+            self.config.agc_target.load(SeqCst) as _,
+        ) as f32;
+        // NOTE: 解析の間は`self`を借りるので、その間だけ取り出しておく。
+        let mut agc_data = std::mem::take(&mut self.agc_data);
+        agc_data.clear();
+        agc_data.extend(data.iter().map(|value| value * gain));
+        let result = self.detect(&agc_data, new_frames);
+        self.agc_data = agc_data;
+        result
+    }
+
+    /// 音階検出の処理を、音量の調整を済ませた音声データで行います。
+    fn detect<const N: usize>(&mut self, data: &[f32], new_frames: usize) -> Option<[Note; N]> {
+        self.is_onset = false;
+        self.envelope.process(
+            calculation::get_rms(data),
+            new_frames as Float / self.frame_rate as Float,
+        );
+
+        // 音量が閾値付近の時に検出の有無が頻繁に切り替わらないように、
+        // 一度検出し始めたら、閾値からヒステリシスの分だけ下回るまで検出を続ける。
+        let mut min_volume = self.config.min_volume.load(SeqCst);
+        if self.gate.is_open() {
+            min_volume -= self.config.volume_hysteresis.load(SeqCst);
+        };
+
+        let volume = if self.config.use_a_weighting.load(SeqCst) {
+            self.a_weighting.level(data, new_frames)
+        } else {
+            self.a_weighting.reset();
+            calculation::get_db(data)
+        };
+        self.volume = volume;
+        let milliseconds = |value: &AtomicU32| value.load(SeqCst) as Float / 1000.;
+        self.gate.process(
+            volume as i32 > min_volume,
+            new_frames as Float / self.frame_rate as Float,
+            milliseconds(&self.config.gate_attack),
+            milliseconds(&self.config.gate_hold),
+            milliseconds(&self.config.gate_release),
+        );
+        if !self.gate.is_open() {
+            self.previous_top = None;
+            return None;
+        };
+
+        // 基準の周波数か音律が変わったのなら、音階の周波数を計算し直す。
+        // 音律は変更された時だけ取り出し、他のスレッドが変更している途中なら次の機会に反映する。
+        let reference_pitch = self.config.reference_pitch.load(SeqCst);
+        let scale_revision = self.config.scale_revision.load(SeqCst);
+        let mut is_scale_changed = false;
+        if scale_revision != self.scale_revision {
+            if let Ok(scale) = self.config.scale.try_lock() {
+                self.scale_revision = scale_revision;
+                self.scale = scale.clone();
+                is_scale_changed = true;
+            };
+        };
+
+        if is_scale_changed || reference_pitch != self.reference_pitch {
+            self.reference_pitch = reference_pitch;
+            self.notes = make_notes(self.scale.as_deref(), reference_pitch);
+        };
+
+        // FFTのサイズが上限を超えないようにポイント数の規模を制限する。
+        let point_times = self.config.point_times.load(SeqCst) as usize;
+        let limited_point_times = calculation::fft::limit_point_times(
+            data.len(),
+            point_times,
+            self.config.max_fft_length.load(SeqCst) as _,
+        );
+        self.limited_point_times =
+            (limited_point_times != point_times).then_some(limited_point_times);
+
+        // 解析に使う型に変換する。
+        self.analysis_data.clear();
+        self.analysis_data
+            .extend(data.iter().map(|value| *value as Float));
+
+        let window_function = WindowFunction::from_index(self.config.window_function.load(SeqCst));
+        match window_kind(window_function) {
+            Some(kind) => {
+                let coefficients = self.window_cache.get(kind, data.len());
+                calculation::apply_window(&mut self.analysis_data, &coefficients);
+            }
+            None if window_function == WindowFunction::Auto => {
+                // 窓関数が急に切り替わらないように、二つの窓関数を`transient_mix`の割合で混ぜて使う。
+                let hann = self
+                    .window_cache
+                    .get(calculation::WindowKind::Hann, data.len());
+                let tukey = self
+                    .window_cache
+                    .get(calculation::WindowKind::Tukey, data.len());
+                calculation::apply_mixed_window(
+                    &mut self.analysis_data,
+                    &hann,
+                    &tukey,
+                    self.transient_mix,
+                );
+            }
+            None => (),
+        };
+
+        // 校正で求めた補正を掛けたフレームレートを使って、周波数の計算をずらす。
+        let frame_rate = self.frame_rate
+            * (1. + self.config.frequency_correction.load(SeqCst) as f32 / 1_000_000.);
+
+        // プラグインの検出方法が選ばれているのなら、プラグインに候補を探させる。
+        let is_plugin_used = detect_with_plugin(
+            &self.config,
+            data,
+            frame_rate,
+            &mut self.plugin_candidates,
+            N.max(2),
+        );
+
+        // FFTで周波数の計算をする。
+        let info = calculation::fft::process(
+            &self.analysis_data,
+            frame_rate,
+            limited_point_times,
+            &mut self.buffer,
+            &mut self.fft_workspace,
+            &mut self.fft_backend,
+        );
+        self.resolution = info.resolution;
+        let data = &mut self.buffer;
+
+        // 無音データが読み込まれたり忘れられたりしたのなら反映する。
+        // 他のスレッドが変更している途中なら次の機会に反映する。
+        let silence_revision = self.config.silence_revision.load(SeqCst);
+        if silence_revision != self.silence_revision {
+            if let Ok(silence) = self.config.silence.try_lock() {
+                self.silence_revision = silence_revision;
+                self.silence = silence.clone();
+                self.is_silence_shared = true;
+                self.silence_capture = None;
+            };
+        };
+
+        // 無音データの処理をする。
+        if self.config.use_silent.load(SeqCst) {
+            if let Some(silence) = &self.silence {
+                // 無音時のデータがあるのなら、無音データのサンプルをこのときのデータから差し引く。
+                // 取った時とFFTのサイズ等が違う場合は、周波数が対応しないので差し引かない。
+                if silence.fits(self.frame_rate, info.buffer_length, data.len()) {
+                    subtract_silence(data, silence);
+                };
+
+                // 取った無音データを保存できるように共有する。ロックできなければ次の機会にする。
+                if !self.is_silence_shared {
+                    if let Ok(mut shared) = self.config.silence.try_lock() {
+                        *shared = Some(Arc::clone(silence));
+                        self.is_silence_shared = true;
+                    };
+                };
+            } else {
+                // 無音データが設定されてないなら、何回分かのデータを平均して設定する。
+                // 途中でFFTのサイズ等が変わった場合は、初めから取り直す。
+                let capture = match self.silence_capture.take() {
+                    Some(capture) if capture.fits(info.buffer_length, data.len()) => capture,
+                    _ => silence::SilenceCapture::new(info.buffer_length, data.len()),
+                };
+                let capture = self.silence_capture.insert(capture);
+                capture.add(data);
+
+                if capture.frames_left() == 0 {
+                    if let Some(capture) = self.silence_capture.take() {
+                        self.silence = Some(Arc::new(capture.finish(self.frame_rate)));
+                        self.is_silence_shared = false;
+                    };
+                };
+                return None;
+            };
+        };
+
+        // 雑音の大きさを追従して差し引く。止めた時は、次に使う時に古い雑音の大きさを使わないように忘れる。
+        if self.config.use_noise_reduction.load(SeqCst) {
+            self.noise_tracker.process(
+                data,
+                new_frames as Float / self.frame_rate as Float,
+                self.config.noise_over_subtraction.load(SeqCst) as Float / 100.,
+            );
+        } else {
+            self.noise_tracker.reset();
+        };
+
+        // マイクの周波数特性を補正する。
+        // 周波数特性は変更された時だけ取り出し、他のスレッドが変更している途中なら次の機会に反映する。
+        let mic_profile_revision = self.config.mic_profile_revision.load(SeqCst);
+        let mut is_mic_profile_changed = false;
+        if mic_profile_revision != self.mic_profile_revision {
+            if let Ok(profile) = self.config.mic_profile.try_lock() {
+                self.mic_profile_revision = mic_profile_revision;
+                self.mic_profile = profile.clone();
+                is_mic_profile_changed = true;
+            };
+        };
+        if let Some(profile) = &self.mic_profile {
+            if is_mic_profile_changed
+                || self.mic_gains.len() != data.len()
+                || self.mic_gains_resolution != info.resolution
+            {
+                profile.fill_gains(&mut self.mic_gains, data.len(), info.resolution);
+                self.mic_gains_resolution = info.resolution;
+            };
+            apply_gains(data, &self.mic_gains);
+        };
+
+        // 音の立ち上がりを検出する。
+        self.is_onset = self.onset_detector.process(
+            &data[..info.buffer_length / 2],
+            self.config.onset_threshold.load(SeqCst) as Float / 100.,
+        );
+
+        // スペクトルフラックスが大きいほど、次の解析で平らな窓関数を使う割合を増やす。
+        let transient =
+            ((self.onset_detector.flux_ratio() - 1.) / (TRANSIENT_FLUX_RATIO - 1.)).clamp(0., 1.);
+        self.transient_mix += (transient - self.transient_mix) * WINDOW_SWITCH_SMOOTHING;
+
+        // 各音階の周波数帯の音量を計算する。
+        let ranking_strategy =
+            RankingStrategy::from_index(self.config.ranking_strategy.load(SeqCst));
+        self.band_values.clear();
+        self.band_peaks.clear();
+        let note_range = self.config.min_note.load(SeqCst)..=self.config.max_note.load(SeqCst);
+        let band = |((before_frequency, after_frequency), number): ((&f32, &f32), &u8)| {
+            note_band_value(
+                data,
+                info.resolution,
+                ranking_strategy,
+                &note_range,
+                (*before_frequency, *after_frequency),
+                *number,
+            )
+        };
+
+        // 音階の数が多い場合（細かい音律等）は、複数のスレッドで計算する。
+        #[cfg(feature = "parallel")]
+        let is_parallel = self.notes.numbers.len() >= PARALLEL_MIN_BANDS;
+        #[cfg(not(feature = "parallel"))]
+        let is_parallel = false;
+
+        if is_parallel {
+            #[cfg(feature = "parallel")]
+            {
+                use rayon::prelude::*;
+
+                self.notes
+                    .before_frequencies
+                    .par_iter()
+                    .zip(self.notes.after_frequencies.par_iter())
+                    .zip(self.notes.numbers.par_iter())
+                    .map(band)
+                    .unzip_into_vecs(&mut self.band_values, &mut self.band_peaks);
+            }
+        } else {
+            for (value, peak) in self
+                .notes
+                .before_frequencies
+                .iter()
+                .zip(self.notes.after_frequencies.iter())
+                .zip(self.notes.numbers.iter())
+                .map(band)
+            {
+                self.band_values.push(value);
+                self.band_peaks.push(peak);
+            }
+        };
+
+        // 一番音量が高い周波数の音階を探し、上位の候補を取り出す。
+        // 曖昧かどうかを調べるため、少なくとも二つは取り出す。
+        let limit = N.max(2);
+        self.candidates.clear();
+
+        if is_plugin_used {
+            rank_plugin_candidates(
+                &self.notes,
+                &note_range,
+                &self.plugin_candidates,
+                limit,
+                &mut self.candidates,
+            );
+        } else if is_parallel {
+            #[cfg(feature = "parallel")]
+            {
+                use rayon::prelude::*;
+
+                // スレッドごとに上位の候補を選んでから、それらをまとめる。
+                let band_values = &self.band_values;
+                let top = (0..band_values.len())
+                    .into_par_iter()
+                    .fold(
+                        || Vec::with_capacity(limit),
+                        |mut top, index| {
+                            let value = candidate_value(band_values, index, ranking_strategy);
+                            if !value.is_nan() {
+                                insert_top(&mut top, RawNote(index, value), limit);
+                            };
+                            top
+                        },
+                    )
+                    .reduce(Vec::new, |mut top, other| {
+                        for raw_note in other {
+                            insert_top(&mut top, raw_note, limit);
+                        }
+                        top
+                    });
+                self.candidates.extend(top);
+            }
+        } else {
+            rank_bands(
+                &self.band_values,
+                ranking_strategy,
+                limit,
+                &mut self.candidates,
+            );
+        };
+
+        let spectrum_mean = spectrum_mean(data, info.buffer_length);
+
+        // 上位二つの候補の差が小さい場合は、設定に従って扱う。
+        let ambiguity_margin = self.config.ambiguity_margin.load(SeqCst);
+        let top_two = match self.candidates.as_slice() {
+            [first, second, ..]
+                if ambiguity_margin > 0
+                    && 20. * (first.1 / second.1).log10() < ambiguity_margin as Float =>
+            {
+                Some((first.0, second.0))
+            }
+            _ => None,
+        };
+
+        if let Some((first, second)) = top_two {
+            match AmbiguityPolicy::from_index(self.config.ambiguity_policy.load(SeqCst)) {
+                AmbiguityPolicy::KeepPrevious => {
+                    if self.previous_top == Some(second) {
+                        self.candidates.swap(0, 1);
+                    };
+                }
+                AmbiguityPolicy::PickLower => {
+                    if self.notes.numbers[second] < self.notes.numbers[first] {
+                        self.candidates.swap(0, 1);
+                    };
+                }
+                AmbiguityPolicy::Suppress => {
+                    self.previous_top = None;
+                    return None;
+                }
+            };
+        };
+        self.previous_top = self.candidates.first().map(|raw_note| raw_note.0);
+
+        // メインスレッドに検出した音階を送信する。
+        let adjustment_rate = self.config.adjustment_rate.load(SeqCst);
+        let mut result = [Note::NULL; N];
+        for (note, raw_note) in result.iter_mut().zip(self.candidates.iter()) {
+            *note = to_note(
+                &self.notes,
+                raw_note,
+                &self.band_peaks,
+                info.resolution,
+                adjustment_rate,
+                spectrum_mean,
+            );
+        }
+
+        // 一番目の音の周波数を、その周辺だけを細かく解析して求め直す。
+        // NOTE: 検出する音の範囲に音階が無い場合等は、候補が一つも無いことがある。
+        if self.config.use_zoom_fft.load(SeqCst) {
+            if let (Some(note), Some(raw_note)) = (result.first_mut(), self.candidates.first()) {
+                refine_with_zoom(
+                    note,
+                    self.notes.frequencies[raw_note.0],
+                    &self.analysis_data,
+                    frame_rate,
+                    &mut self.zoom_magnitudes,
+                );
+            };
+        };
+
+        // 候補が無いか、信頼度が低いのなら、検出しなかったことにする。
+        if self.candidates.is_empty()
+            || result[0].confidence < self.config.min_confidence.load(SeqCst) as f32
+        {
+            return None;
+        };
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_RATE: f32 = 48000.;
+
+    /// `frequency`（Hz）の正弦波を作ります。
+    fn sine(frequency: f32, length: usize) -> Vec<f32> {
+        (0..length)
+            .map(|index| {
+                0.5 * (2. * std::f32::consts::PI * frequency * index as f32 / FRAME_RATE).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn restore_swaps_inverted_note_range() {
+        let config = Config::default();
+
+        let mut snapshot = ConfigSnapshot::default();
+        snapshot.apply_line("min_note=90");
+        snapshot.apply_line("max_note=40");
+        assert_eq!(snapshot.note_range(), (40, 90));
+        config.restore(&snapshot);
+        assert_eq!(config.min_note.load(SeqCst), 40);
+        assert_eq!(config.max_note.load(SeqCst), 90);
+
+        // 逆になっていない場合は、そのまま使う。
+        let mut snapshot = config.snapshot();
+        snapshot.apply_line("max_note=60");
+        config.restore(&snapshot);
+        assert_eq!(config.min_note.load(SeqCst), 40);
+        assert_eq!(config.max_note.load(SeqCst), 60);
+
+        // 一つだけ変えて逆になった場合も、入れ替える。
+        let mut snapshot = config.snapshot();
+        snapshot.apply_line("min_note=72");
+        config.restore(&snapshot);
+        assert_eq!(config.min_note.load(SeqCst), 60);
+        assert_eq!(config.max_note.load(SeqCst), 72);
+    }
+
+    #[test]
+    fn peaks_after_synthe() {
+        let mut synthesizer = Synthesizer::builder(FRAME_RATE).build();
+        assert!(synthesizer.peaks(1).is_empty());
+
+        let _ = synthesizer.synthe::<1>(&sine(440., 4096));
+        let peaks = synthesizer.peaks(3);
+        assert!(!peaks.is_empty() && peaks.len() <= 3);
+        assert!((peaks[0].0 - 440.).abs() < 1., "{peaks:?}");
+        assert!(peaks.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+
+    #[test]
+    fn zoom_fft_with_silent_frame() {
+        let mut synthesizer = Synthesizer::builder(FRAME_RATE).build();
+        synthesizer.config.use_zoom_fft.store(true, SeqCst);
+        synthesizer
+            .config
+            .min_volume
+            .store(params::MIN_VOLUME.min, SeqCst);
+
+        // ノイズゲートが開いている間に、無音の音声データが届いた場合
+        assert!(synthesizer.synthe::<1>(&sine(440., 4096)).is_some());
+        let _ = synthesizer.synthe::<1>(&[0.; 4096]);
+        assert!(synthesizer.synthe::<1>(&[0.; 4096]).is_none());
+    }
+
+    #[test]
+    fn zoom_fft_without_candidates() {
+        let mut synthesizer = Synthesizer::builder(FRAME_RATE).build();
+        synthesizer.config.use_zoom_fft.store(true, SeqCst);
+
+        // 検出する音の範囲が逆になっていて、候補が一つも無い場合
+        synthesizer.config.min_note.store(80, SeqCst);
+        synthesizer.config.max_note.store(40, SeqCst);
+        assert!(synthesizer.synthe::<1>(&sine(440., 4096)).is_none());
+    }
+}
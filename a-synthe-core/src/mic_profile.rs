@@ -0,0 +1,80 @@
+//! マイクの周波数特性のファイル（CSV）を読み込み、その逆の特性でスペクトルを補正するためのモジュールです。
+//! 低い音が弱い安いマイクで、検出が高いオクターブに偏らないようにするためのものです。
+//! 一行に周波数（Hz）と感度（dB）をカンマ、セミコロン、タブまたは空白で区切って書いた形式です。（三列目以降は無視します。）
+//! 数値として読み込めない行（見出し等）は飛ばします。
+
+use super::calculation::Float;
+
+/// 補正で持ち上げる量の上限（dB）
+/// 感度がとても低い所を持ち上げすぎて、雑音まで大きくしないようにするためのものです。
+const MAX_BOOST: f32 = 20.;
+
+/// マイクの周波数特性です。
+#[derive(Debug, PartialEq)]
+pub struct MicProfile {
+    /// 周波数（Hz）と感度（dB）の組（周波数の低い順）
+    points: Vec<(f32, f32)>,
+}
+
+impl MicProfile {
+    /// 周波数特性のファイルの内容を読み込みます。
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut points = text
+            .lines()
+            .filter_map(|line| {
+                let mut values = line
+                    .split([',', ';', '\t', ' '])
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.trim().parse::<f32>());
+                match (values.next(), values.next()) {
+                    (Some(Ok(frequency)), Some(Ok(level)))
+                        if frequency.is_finite() && frequency > 0. && level.is_finite() =>
+                    {
+                        Some((frequency, level))
+                    }
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        if points.is_empty() {
+            return Err("周波数と感度の行がありません。".to_string());
+        };
+
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points.dedup_by(|a, b| a.0 == b.0);
+        Ok(Self { points })
+    }
+
+    /// 周波数特性に含まれる点の数を取得します。
+    pub fn point_count(&self) -> usize {
+        self.points.len()
+    }
+
+    /// `frequency`での感度（dB）を取得します。
+    /// 点の間は対数の周波数軸で直線で補間し、範囲の外は端の点の感度とします。
+    pub fn response(&self, frequency: f32) -> f32 {
+        let index = self.points.partition_point(|(point, _)| *point < frequency);
+        let (Some(&(low_frequency, low)), Some(&(high_frequency, high))) = (
+            index
+                .checked_sub(1)
+                .and_then(|index| self.points.get(index)),
+            self.points.get(index),
+        ) else {
+            let (_, level) = self.points[index.min(self.points.len() - 1)];
+            return level;
+        };
+
+        let position = (frequency / low_frequency).ln() / (high_frequency / low_frequency).ln();
+        low + (high - low) * position
+    }
+
+    /// FFTの結果のバッファの各値に掛ける補正の倍率を、`gains`に書き込みます。
+    /// `resolution`はバッファの各値の周波数の間隔（Hz）です。
+    pub fn fill_gains(&self, gains: &mut Vec<Float>, length: usize, resolution: f32) {
+        gains.clear();
+        gains.extend((0..length).map(|index| {
+            let correction = (-self.response(index as f32 * resolution)).min(MAX_BOOST);
+            10f32.powf(correction / 20.) as Float
+        }));
+    }
+}
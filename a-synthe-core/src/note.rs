@@ -0,0 +1,236 @@
+use super::scala::Scale;
+
+/// 基準とするラ（A4）の周波数の初期値（Hz）
+/// `notes.csv`はこの周波数を基準にして作られています。
+pub const DEFAULT_REFERENCE_PITCH: u32 = 440;
+/// `notes.csv`に入っている音階の数
+const NUMBER_OF_NOTES: u8 = 127;
+
+/// 音階と音階に対応する周波数を格納するための構造体です。
+#[derive(Default)]
+pub struct NoteContainer {
+    pub numbers: Vec<u8>,
+    pub frequencies: Vec<f32>,
+    pub before_frequencies: Vec<f32>,
+    pub after_frequencies: Vec<f32>,
+}
+
+impl NoteContainer {
+    /// 音階等が書き込まれたファイルから音階等を読み込みます。
+    pub fn new() -> Self {
+        let mut notes = Self::default();
+        let mut row;
+
+        for line in include_str!("notes.csv").lines() {
+            row = line.split_whitespace();
+            notes.numbers.push(row.next().unwrap().parse().unwrap());
+            notes.frequencies.push(row.next().unwrap().parse().unwrap());
+            notes
+                .before_frequencies
+                .push(row.next().unwrap().parse().unwrap());
+            notes
+                .after_frequencies
+                .push(row.next().unwrap().parse().unwrap());
+        }
+
+        notes
+    }
+
+    /// ラ（A4）の周波数を`reference_pitch`とした平均律で、音階等を計算して作ります。
+    pub fn with_reference_pitch(reference_pitch: f32) -> Self {
+        Self::from_frequencies(
+            (0..NUMBER_OF_NOTES)
+                .map(|number| reference_pitch * 2f32.powf((number as f32 - 69.) / 12.)),
+        )
+    }
+
+    /// Scalaの音律ファイルの音律で、音階等を計算して作ります。
+    /// 音律の一つ目の音をラ（A4）として`reference_pitch`の周波数にし、そこから順番に音階を割り当てます。
+    pub fn with_scale(scale: &Scale, reference_pitch: f32) -> Self {
+        Self::from_frequencies(
+            (0..NUMBER_OF_NOTES).map(|number| reference_pitch * scale.ratio(number as i32 - 69)),
+        )
+    }
+
+    /// MIDIの番号の順に並んだ各音階の周波数から作ります。
+    /// 各音階の周波数帯は、両隣の音階との中間（周波数の比の上での中間）までです。
+    fn from_frequencies(frequencies: impl Iterator<Item = f32>) -> Self {
+        let mut notes = Self::default();
+        notes.frequencies.extend(frequencies);
+        notes.numbers.extend(0..notes.frequencies.len() as u8);
+
+        for (index, frequency) in notes.frequencies.iter().enumerate() {
+            // 両端の音階は、反対側の隣の音階との比と同じ比だけ離れた所に隣の音階があるものとする。
+            let before = match index.checked_sub(1) {
+                Some(before) => notes.frequencies[before],
+                None => frequency * frequency / notes.frequencies[index + 1],
+            };
+            let after = notes
+                .frequencies
+                .get(index + 1)
+                .copied()
+                .unwrap_or(frequency * frequency / before);
+
+            notes.before_frequencies.push((before * frequency).sqrt());
+            notes.after_frequencies.push((frequency * after).sqrt());
+        }
+
+        notes
+    }
+}
+
+/// 音階の名前の表し方です。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NoteNaming {
+    /// カタカナと英語の両方（ド/C）
+    Both,
+    /// カタカナ（ド）
+    Katakana,
+    /// 英語（C）
+    English,
+    /// ドイツ語（シはH、シ♭はB）
+    German,
+    /// 固定ド（Do）
+    FixedDo,
+}
+
+impl NoteNaming {
+    pub const ALL: [Self; 5] = [
+        Self::Both,
+        Self::Katakana,
+        Self::English,
+        Self::German,
+        Self::FixedDo,
+    ];
+
+    /// UIに表示する名前を取得します。
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Both => "カタカナと英語（ド/C）",
+            Self::Katakana => "カタカナ（ド）",
+            Self::English => "英語（C）",
+            Self::German => "ドイツ語（C、H）",
+            Self::FixedDo => "固定ド（Do）",
+        }
+    }
+
+    /// `ALL`での位置から取得します。
+    pub fn from_index(index: u8) -> Self {
+        Self::ALL.get(index as usize).copied().unwrap_or(Self::Both)
+    }
+
+    /// ドから順番に並べた、各音階の名前を取得します。
+    fn names(self) -> [&'static str; 12] {
+        match self {
+            Self::Both => [
+                "ド/C",
+                "ド♯/C♯",
+                "レ/D",
+                "レ♯/D♯",
+                "ミ/E",
+                "ファ/F",
+                "ファ♯/F♯",
+                "ソ/G",
+                "ソ♯/G♯",
+                "ラ/A",
+                "ラ♯/A♯",
+                "シ/B",
+            ],
+            Self::Katakana => [
+                "ド",
+                "ド♯",
+                "レ",
+                "レ♯",
+                "ミ",
+                "ファ",
+                "ファ♯",
+                "ソ",
+                "ソ♯",
+                "ラ",
+                "ラ♯",
+                "シ",
+            ],
+            Self::English => [
+                "C", "C♯", "D", "D♯", "E", "F", "F♯", "G", "G♯", "A", "A♯", "B",
+            ],
+            Self::German => [
+                "C", "Cis", "D", "Dis", "E", "F", "Fis", "G", "Gis", "A", "B", "H",
+            ],
+            Self::FixedDo => [
+                "Do", "Do♯", "Re", "Re♯", "Mi", "Fa", "Fa♯", "Sol", "Sol♯", "La", "La♯", "Si",
+            ],
+        }
+    }
+}
+
+/// 音階情報を入れるための構造体です。
+#[derive(Clone)]
+pub struct Note {
+    /// MIDIの音階の番号
+    pub number: u8,
+    /// 検出した周波数（Hz）
+    pub frequency: f32,
+    /// 音階の周波数からのずれ（セント）
+    pub cents: f32,
+    /// 検出の信頼度（dB）
+    /// 周波数帯の最大の音量と、スペクトル全体の平均の音量の比です。
+    pub confidence: f32,
+}
+impl Note {
+    pub const NULL: Self = Self {
+        number: 0,
+        frequency: 0.,
+        cents: 0.,
+        confidence: 0.,
+    };
+
+    /// 音階を`semitones`半音ずらしたものを取得します。（MIDIの番号の範囲に収めます。）
+    /// 移調楽器の記譜上の音を求めるのに使います。
+    pub fn transposed(&self, semitones: i32) -> Self {
+        Self {
+            number: (self.number as i32 + semitones).clamp(0, 127) as u8,
+            ..self.clone()
+        }
+    }
+
+    /// 音階の名前を文字列で取得します。
+    pub fn get_name(&self) -> String {
+        self.get_name_with(NoteNaming::Both)
+    }
+
+    /// 音階の名前を、指定した表し方の文字列で取得します。
+    pub fn get_name_with(&self, naming: NoteNaming) -> String {
+        format!(
+            "{} {}",
+            self.get_pitch_class_name_with(naming),
+            self.get_octave()
+        )
+    }
+
+    /// オクターブを除いた音階の名前を取得します。
+    pub fn get_pitch_class_name(&self) -> &'static str {
+        self.get_pitch_class_name_with(NoteNaming::Both)
+    }
+
+    /// オクターブを除いた音階の名前を、指定した表し方で取得します。
+    pub fn get_pitch_class_name_with(&self, naming: NoteNaming) -> &'static str {
+        naming.names()[(self.number % 12) as usize]
+    }
+
+    /// オクターブを取得します。
+    pub fn get_octave(&self) -> isize {
+        (self.number / 12) as isize - 1
+    }
+}
+
+impl From<Note> for u8 {
+    fn from(note: Note) -> Self {
+        note.number
+    }
+}
+
+impl AsRef<u8> for Note {
+    fn as_ref(&self) -> &u8 {
+        &self.number
+    }
+}
@@ -0,0 +1,259 @@
+//! 音声データを一度だけ解析して、全ての音階を順位付けした結果を得るためのモジュールです。
+//! リアルタイムの検出と違って前回までの解析の状態を使わないので、同じ音声データからは毎回同じ結果になります。
+//! 作った正弦波で検出を確かめたり、録音したものをまとめて解析したりするのに使えます。
+
+use std::sync::atomic::Ordering::SeqCst;
+
+use super::{
+    apply_gains, calculation, detect_with_plugin, make_notes, note_band_value, rank_bands,
+    rank_plugin_candidates, refine_with_zoom, spectrum_mean, subtract_silence, to_note,
+    window_kind, Float, Note, RankingStrategy, Synthesizer, WindowFunction, ZOOM_FFT_POINTS,
+};
+
+/// 順位付けした候補の一つです。
+#[derive(Clone)]
+pub struct RankedNote {
+    pub note: Note,
+    /// 周波数帯で一番大きい所の成分の大きさ（FFTの結果の値）
+    pub amplitude: f32,
+}
+
+impl Synthesizer {
+    /// `frame_rate`（Hz）の音声データ`data`を一度だけ解析して、検出する範囲の全ての音階を候補として、
+    /// 順位の高い順に並べて返します。
+    ///
+    /// 窓関数や順位付けの方法、ポイント数の規模、検出する音の範囲等は`Config`の設定を使いますが、
+    /// リアルタイムの検出とは別に解析するので、`feed`や`synthe`の状態は変えません。
+    /// 前回までの解析に左右されないように、次のものは使いません。
+    /// - ノイズゲート（音量の閾値）、自動音量調整、雑音の追従
+    /// - 曖昧な場合の扱いと信頼度の下限（候補を捨てずに全て返します。）
+    /// - オクターブの固定
+    /// - 入力デバイスの校正で求めた補正
+    ///
+    /// 窓関数が自動の場合は、ハン窓を使います。
+    pub fn process_buffer(&self, data: &[f32], frame_rate: f32) -> Vec<RankedNote> {
+        if data.is_empty() {
+            return Vec::new();
+        };
+        let config = &self.config;
+
+        // 直流成分や低い雑音を取り除く。
+        let cutoff = config.high_pass_frequency.load(SeqCst);
+        let mut high_pass_data = Vec::new();
+        let data = if cutoff == 0 {
+            data
+        } else {
+            calculation::HighPassFilter::new(frame_rate).process(
+                data,
+                data.len(),
+                cutoff,
+                &mut high_pass_data,
+            );
+            &high_pass_data
+        };
+
+        // 基準の周波数か音律が変わっているのなら、その音階の表を使う。
+        let reference_pitch = config.reference_pitch.load(SeqCst);
+        let changed_notes;
+        let notes = if reference_pitch == self.reference_pitch
+            && config.scale_revision.load(SeqCst) == self.scale_revision
+        {
+            &self.notes
+        } else {
+            let scale = config.scale.lock().ok().and_then(|scale| scale.clone());
+            changed_notes = make_notes(scale.as_deref(), reference_pitch);
+            &changed_notes
+        };
+
+        // 窓関数を掛ける。自動の場合は、前回までの解析に左右されないようにハン窓を使う。
+        let mut analysis_data = data.iter().map(|value| *value as Float).collect::<Vec<_>>();
+        let window_kind = match WindowFunction::from_index(config.window_function.load(SeqCst)) {
+            WindowFunction::Auto => Some(calculation::WindowKind::Hann),
+            window_function => window_kind(window_function),
+        };
+        if let Some(kind) = window_kind {
+            let coefficients = calculation::WindowCache::new(1).get(kind, data.len());
+            calculation::apply_window(&mut analysis_data, &coefficients);
+        };
+
+        // プラグインの検出方法が選ばれているのなら、プラグインに候補を探させる。
+        let mut plugin_candidates = Vec::new();
+        let is_plugin_used = detect_with_plugin(
+            config,
+            data,
+            frame_rate,
+            &mut plugin_candidates,
+            notes.numbers.len(),
+        );
+
+        // FFTで周波数の計算をする。
+        let point_times = calculation::fft::limit_point_times(
+            data.len(),
+            config.point_times.load(SeqCst) as _,
+            config.max_fft_length.load(SeqCst) as _,
+        );
+        let mut buffer = Vec::new();
+        let info = calculation::fft::process(
+            &analysis_data,
+            frame_rate,
+            point_times,
+            &mut buffer,
+            &mut Default::default(),
+            &mut calculation::fft::DefaultFftBackend::default(),
+        );
+
+        // 無音データが設定されていれば差し引く。
+        if config.use_silent.load(SeqCst) {
+            if let Some(silence) = config
+                .silence
+                .lock()
+                .ok()
+                .and_then(|silence| silence.clone())
+            {
+                if silence.fits(frame_rate, info.buffer_length, buffer.len()) {
+                    subtract_silence(&mut buffer, &silence);
+                };
+            };
+        };
+
+        // マイクの周波数特性を補正する。
+        if let Some(profile) = config
+            .mic_profile
+            .lock()
+            .ok()
+            .and_then(|profile| profile.clone())
+        {
+            let mut gains = Vec::new();
+            profile.fill_gains(&mut gains, buffer.len(), info.resolution);
+            apply_gains(&mut buffer, &gains);
+        };
+
+        // 各音階の周波数帯の音量を計算する。
+        let ranking_strategy = RankingStrategy::from_index(config.ranking_strategy.load(SeqCst));
+        let note_range = config.min_note.load(SeqCst)..=config.max_note.load(SeqCst);
+        let (band_values, band_peaks): (Vec<_>, Vec<_>) = notes
+            .before_frequencies
+            .iter()
+            .zip(notes.after_frequencies.iter())
+            .zip(notes.numbers.iter())
+            .map(|((before_frequency, after_frequency), number)| {
+                note_band_value(
+                    &buffer,
+                    info.resolution,
+                    ranking_strategy,
+                    &note_range,
+                    (*before_frequency, *after_frequency),
+                    *number,
+                )
+            })
+            .unzip();
+
+        // 全ての候補を順位の高い順に並べる。
+        let limit = notes.numbers.len();
+        let mut candidates = Vec::with_capacity(limit);
+        if is_plugin_used {
+            rank_plugin_candidates(
+                notes,
+                &note_range,
+                &plugin_candidates,
+                limit,
+                &mut candidates,
+            );
+        } else {
+            rank_bands(&band_values, ranking_strategy, limit, &mut candidates);
+        };
+
+        let spectrum_mean = spectrum_mean(&buffer, info.buffer_length);
+        let adjustment_rate = config.adjustment_rate.load(SeqCst);
+        let mut ranked = candidates
+            .iter()
+            .map(|raw_note| RankedNote {
+                note: to_note(
+                    notes,
+                    raw_note,
+                    &band_peaks,
+                    info.resolution,
+                    adjustment_rate,
+                    spectrum_mean,
+                ),
+                amplitude: band_peaks[raw_note.0].0 as _,
+            })
+            .collect::<Vec<_>>();
+
+        // 一番目の音の周波数を、その周辺だけを細かく解析して求め直す。
+        if config.use_zoom_fft.load(SeqCst) {
+            if let (Some(first), Some(raw_note)) = (ranked.first_mut(), candidates.first()) {
+                refine_with_zoom(
+                    &mut first.note,
+                    notes.frequencies[raw_note.0],
+                    &analysis_data,
+                    frame_rate,
+                    &mut [0.; ZOOM_FFT_POINTS],
+                );
+            };
+        };
+
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_RATE: f32 = 48000.;
+
+    /// `frequencies`（Hz）の正弦波を同じ大きさで重ねた音声データを作ります。
+    fn render(frequencies: &[f32], length: usize) -> Vec<f32> {
+        (0..length)
+            .map(|index| {
+                frequencies
+                    .iter()
+                    .map(|frequency| {
+                        (2. * std::f32::consts::PI * frequency * index as f32 / FRAME_RATE).sin()
+                    })
+                    .sum::<f32>()
+                    / frequencies.len() as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detects_a4() {
+        let synthesizer = Synthesizer::builder(FRAME_RATE).build();
+        let ranked = synthesizer.process_buffer(&render(&[440.], 8192), FRAME_RATE);
+
+        let first = &ranked[0].note;
+        assert_eq!(first.number, 69);
+        assert!(first.cents.abs() < 10., "{} cents", first.cents);
+    }
+
+    #[test]
+    fn detects_c_major_triad() {
+        let synthesizer = Synthesizer::builder(FRAME_RATE).build();
+        // ド/C4、ミ/E4、ソ/G4
+        let ranked = synthesizer.process_buffer(&render(&[261.63, 329.63, 392.], 8192), FRAME_RATE);
+
+        let mut numbers = ranked[..3]
+            .iter()
+            .map(|ranked| ranked.note.number)
+            .collect::<Vec<_>>();
+        numbers.sort();
+        assert_eq!(numbers, [60, 64, 67]);
+    }
+
+    #[test]
+    fn zoom_fft_refines_first_note() {
+        let synthesizer = Synthesizer::builder(FRAME_RATE).build();
+        synthesizer.config.use_zoom_fft.store(true, SeqCst);
+        let ranked = synthesizer.process_buffer(&render(&[445.], 8192), FRAME_RATE);
+
+        let first = &ranked[0].note;
+        assert_eq!(first.number, 69);
+        assert!(
+            (first.frequency - 445.).abs() < 1.,
+            "{} Hz",
+            first.frequency
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! 設定できる数値の値の名前、範囲、初期値、単位をまとめたモジュールです。
+//! UI、コマンドライン引数、プリセットのファイルのどこから設定する場合も、ここの範囲で検証や丸め込みを行います。
+
+/// 設定できる数値の値の定義です。
+#[derive(Debug)]
+pub struct Param {
+    /// プリセットのファイルやコマンドライン引数で使う名前
+    pub name: &'static str,
+    /// UIに表示する名前（翻訳する前の日本語）
+    pub label: &'static str,
+    pub min: i32,
+    pub max: i32,
+    pub default: i32,
+    /// 単位（ない場合は空）
+    pub unit: &'static str,
+}
+
+impl Param {
+    /// 値を範囲に丸め込みます。
+    pub fn clamp(&self, value: i64) -> i32 {
+        value.clamp(self.min as _, self.max as _) as _
+    }
+
+    /// 初期値に戻すための`名前=値`の行を作ります。
+    pub fn default_line(&self) -> String {
+        format!("{}={}", self.name, self.default)
+    }
+
+    /// 文字列の値を読み込み、範囲内であることを確かめます。
+    pub fn validate(&self, value: &str) -> Result<i32, String> {
+        let value: i64 = value.trim().parse().map_err(|_| {
+            format!(
+                "{}の値「{}」は整数ではありません。",
+                self.name,
+                value.trim()
+            )
+        })?;
+
+        if (self.min as i64..=self.max as i64).contains(&value) {
+            Ok(value as _)
+        } else {
+            Err(format!(
+                "{}の値は{}{unit}から{}{unit}の範囲で指定してください。",
+                self.name,
+                self.min,
+                self.max,
+                unit = self.unit
+            ))
+        }
+    }
+}
+
+pub const MIN_VOLUME: Param = Param {
+    name: "min_volume",
+    label: "検出対象とする最低音量（dB）",
+    min: -80,
+    max: 0,
+    default: -30,
+    unit: "dB",
+};
+pub const HIGH_PASS_FREQUENCY: Param = Param {
+    name: "high_pass_frequency",
+    label: "低い雑音を取り除く周波数（Hz、0で取り除かない）",
+    min: 0,
+    max: 200,
+    default: 20,
+    unit: "Hz",
+};
+pub const VOLUME_HYSTERESIS: Param = Param {
+    name: "volume_hysteresis",
+    label: "音量のヒステリシス（dB）",
+    min: 0,
+    max: 40,
+    default: 0,
+    unit: "dB",
+};
+pub const AGC_TARGET: Param = Param {
+    name: "agc_target",
+    label: "自動調整の目標の音量（dB）",
+    min: -60,
+    max: 0,
+    default: -20,
+    unit: "dB",
+};
+pub const GATE_ATTACK: Param = Param {
+    name: "gate_attack",
+    label: "ゲートのアタック（ミリ秒）",
+    min: 0,
+    max: 500,
+    default: 5,
+    unit: "ms",
+};
+pub const GATE_HOLD: Param = Param {
+    name: "gate_hold",
+    label: "ゲートのホールド（ミリ秒）",
+    min: 0,
+    max: 2000,
+    default: 50,
+    unit: "ms",
+};
+pub const GATE_RELEASE: Param = Param {
+    name: "gate_release",
+    label: "ゲートのリリース（ミリ秒）",
+    min: 0,
+    max: 2000,
+    default: 20,
+    unit: "ms",
+};
+pub const NOISE_OVER_SUBTRACTION: Param = Param {
+    name: "noise_over_subtraction",
+    label: "追従した雑音を差し引く倍率（%）",
+    min: 50,
+    max: 400,
+    default: 200,
+    unit: "%",
+};
+pub const POINT_TIMES: Param = Param {
+    name: "point_times",
+    label: "ポイント数の規模",
+    min: 1,
+    max: u16::MAX as _,
+    default: 8,
+    unit: "",
+};
+pub const ANALYSIS_WINDOW_LENGTH: Param = Param {
+    name: "analysis_window_length",
+    label: "解析の窓の長さ（フレーム数、0で入力に合わせる）",
+    min: 0,
+    max: 1 << 16,
+    default: 0,
+    unit: "",
+};
+pub const ANALYSIS_OVERLAP: Param = Param {
+    name: "analysis_overlap",
+    label: "解析の窓の重なり（%）",
+    min: 0,
+    max: 95,
+    default: 75,
+    unit: "%",
+};
+pub const MAX_FFT_LENGTH: Param = Param {
+    name: "max_fft_length",
+    label: "FFTのサイズの上限（×1024）",
+    min: 1024,
+    max: 1 << 26,
+    default: super::DEFAULT_MAX_FFT_LENGTH as _,
+    unit: "",
+};
+pub const WINDOW_FUNCTION: Param = Param {
+    name: "window_function",
+    label: "窓関数",
+    min: 0,
+    max: super::WindowFunction::ALL.len() as i32 - 1,
+    default: 0,
+    unit: "",
+};
+pub const ADJUSTMENT_RATE: Param = Param {
+    name: "adjustment_rate",
+    label: "音階調節",
+    min: -127,
+    max: 127,
+    default: 0,
+    unit: "半音",
+};
+pub const REFERENCE_PITCH: Param = Param {
+    name: "reference_pitch",
+    label: "基準のラ（A4）の周波数（Hz）",
+    min: 415,
+    max: 466,
+    default: super::DEFAULT_REFERENCE_PITCH as _,
+    unit: "Hz",
+};
+pub const MIN_NOTE: Param = Param {
+    name: "min_note",
+    label: "検出する一番低い音（MIDIの番号）",
+    min: 0,
+    max: 127,
+    default: 0,
+    unit: "",
+};
+pub const MAX_NOTE: Param = Param {
+    name: "max_note",
+    label: "検出する一番高い音（MIDIの番号）",
+    min: 0,
+    max: 127,
+    default: 127,
+    unit: "",
+};
+pub const FREQUENCY_CORRECTION: Param = Param {
+    name: "frequency_correction",
+    label: "周波数の補正（校正）",
+    min: -50_000,
+    max: 50_000,
+    default: 0,
+    unit: "ppm",
+};
+/// NOTE: `RankingStrategy::ALL`の後ろはプラグインの位置なので、上限は型の上限にしています。
+pub const RANKING_STRATEGY: Param = Param {
+    name: "ranking_strategy",
+    label: "候補の順位付けの方法",
+    min: 0,
+    max: u8::MAX as _,
+    default: 0,
+    unit: "",
+};
+pub const MIN_CONFIDENCE: Param = Param {
+    name: "min_confidence",
+    label: "検出したとみなす最低の信頼度（dB）",
+    min: 0,
+    max: 60,
+    default: 0,
+    unit: "dB",
+};
+pub const AMBIGUITY_MARGIN: Param = Param {
+    name: "ambiguity_margin",
+    label: "候補の差が小さいとみなす差（dB）",
+    min: 0,
+    max: 20,
+    default: 0,
+    unit: "dB",
+};
+pub const AMBIGUITY_POLICY: Param = Param {
+    name: "ambiguity_policy",
+    label: "候補の差が小さい時の扱い",
+    min: 0,
+    max: super::AmbiguityPolicy::ALL.len() as i32 - 1,
+    default: 0,
+    unit: "",
+};
+pub const OCTAVE_LOCK_RESET: Param = Param {
+    name: "octave_lock_reset",
+    label: "オクターブの固定をやめるまでの無音の時間（ミリ秒）",
+    min: 50,
+    max: 10_000,
+    default: 500,
+    unit: "ms",
+};
+pub const ONSET_THRESHOLD: Param = Param {
+    name: "onset_threshold",
+    label: "弾き直しとみなす変化の大きさ（%）",
+    min: 100,
+    max: 1000,
+    default: 300,
+    unit: "%",
+};
+
+/// 全ての数値の値の定義
+pub const ALL: [&Param; 24] = [
+    &MIN_VOLUME,
+    &HIGH_PASS_FREQUENCY,
+    &VOLUME_HYSTERESIS,
+    &AGC_TARGET,
+    &GATE_ATTACK,
+    &GATE_HOLD,
+    &GATE_RELEASE,
+    &NOISE_OVER_SUBTRACTION,
+    &POINT_TIMES,
+    &ANALYSIS_WINDOW_LENGTH,
+    &ANALYSIS_OVERLAP,
+    &MAX_FFT_LENGTH,
+    &WINDOW_FUNCTION,
+    &ADJUSTMENT_RATE,
+    &REFERENCE_PITCH,
+    &MIN_NOTE,
+    &MAX_NOTE,
+    &FREQUENCY_CORRECTION,
+    &RANKING_STRATEGY,
+    &MIN_CONFIDENCE,
+    &AMBIGUITY_MARGIN,
+    &AMBIGUITY_POLICY,
+    &OCTAVE_LOCK_RESET,
+    &ONSET_THRESHOLD,
+];
+
+/// 名前から定義を探します。
+pub fn find(name: &str) -> Option<&'static Param> {
+    ALL.into_iter().find(|param| param.name == name)
+}
+
+/// `名前=値`の行の値を検証します。数値でない値（真偽値等）や知らない名前の行は検証しません。
+pub fn validate_line(line: &str) -> Result<(), String> {
+    match line.split_once('=') {
+        Some((name, value)) => {
+            find(name.trim()).map_or(Ok(()), |param| param.validate(value).map(|_| ()))
+        }
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,181 @@
+//! 他の人が作った音階の検出方法を、共有ライブラリのプラグインとして読み込むためのモジュールです。
+//!
+//! プラグインは、次の関数をC言語の呼び出し規約で公開します。
+//! ```c
+//! typedef struct {
+//!     float frequency; // 周波数（Hz）
+//!     float strength;  // 候補の強さ（正の値で、大きいほど上位になる）
+//! } ASynthePluginCandidate;
+//!
+//! typedef struct {
+//!     uint32_t abi_version; // ABIの版（今は1）
+//!     const char *name;     // UTF-8でNUL終端の、UIに表示する名前
+//!     // `samples`は窓関数を掛ける前の入力、`frame_rate`は校正の補正を掛けたフレームレートです。
+//!     // 見つけた候補を`candidates`に`capacity`個まで書き込み、書き込んだ数を返します。
+//!     size_t (*detect)(const float *samples, size_t length, float frame_rate,
+//!                      ASynthePluginCandidate *candidates, size_t capacity);
+//! } ASynthePluginDescriptor;
+//!
+//! const ASynthePluginDescriptor *a_synthe_plugin_descriptor(void);
+//! ```
+//! `detect`は録音のスレッドから呼ばれます。また、返す候補の順番は問いません。
+//!
+//! 共有ライブラリを読み込むのは`plugins`の機能（初期値で有効）を有効にした場合だけです。
+//! 無効にした場合（ブラウザ向け等）は、読み込もうとするとエラーになります。
+
+use std::{fs, path::Path};
+
+#[cfg(feature = "plugins")]
+use std::ffi::{c_char, CStr};
+
+#[cfg(feature = "plugins")]
+use libloading::Library;
+
+/// 対応しているプラグインのABIの版
+pub const ABI_VERSION: u32 = 1;
+/// プラグインが公開する関数の名前
+#[cfg(feature = "plugins")]
+const ENTRY_POINT: &[u8] = b"a_synthe_plugin_descriptor\0";
+
+/// プラグインが検出した音階の候補です。
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Candidate {
+    /// 周波数（Hz）
+    pub frequency: f32,
+    /// 候補の強さ
+    pub strength: f32,
+}
+
+type DetectFn = unsafe extern "C" fn(
+    samples: *const f32,
+    length: usize,
+    frame_rate: f32,
+    candidates: *mut Candidate,
+    capacity: usize,
+) -> usize;
+
+/// プラグインが公開する情報です。
+#[cfg(feature = "plugins")]
+#[repr(C)]
+struct Descriptor {
+    abi_version: u32,
+    name: *const c_char,
+    detect: Option<DetectFn>,
+}
+
+/// 読み込んだプラグインです。
+pub struct Plugin {
+    name: String,
+    detect: DetectFn,
+    // NOTE: `detect`を呼べるように、ライブラリを読み込んだままにしておく。
+    #[cfg(feature = "plugins")]
+    _library: Library,
+}
+
+impl Plugin {
+    /// 共有ライブラリを読み込みます。
+    ///
+    /// # Safety
+    /// 読み込んだライブラリの初期化の処理と、公開された関数がABIの通りであることを信頼します。
+    #[cfg(feature = "plugins")]
+    pub unsafe fn load(path: &Path) -> Result<Self, String> {
+        let error = |error: &dyn std::fmt::Display| format!("{}：{error}", path.display());
+
+        let library = Library::new(path).map_err(|e| error(&e))?;
+        let descriptor = library
+            .get::<unsafe extern "C" fn() -> *const Descriptor>(ENTRY_POINT)
+            .map_err(|e| error(&e))?();
+        let descriptor = descriptor
+            .as_ref()
+            .ok_or_else(|| error(&"プラグインの情報がありません。"))?;
+
+        if descriptor.abi_version != ABI_VERSION {
+            return Err(error(&format!(
+                "対応していないABIの版（{}）です。",
+                descriptor.abi_version
+            )));
+        };
+        if descriptor.name.is_null() {
+            return Err(error(&"プラグインの名前がありません。"));
+        };
+        let detect = descriptor
+            .detect
+            .ok_or_else(|| error(&"検出の関数がありません。"))?;
+
+        Ok(Self {
+            name: CStr::from_ptr(descriptor.name)
+                .to_string_lossy()
+                .into_owned(),
+            detect,
+            _library: library,
+        })
+    }
+
+    /// `plugins`の機能が無効なので、読み込まずにエラーを返します。
+    ///
+    /// # Safety
+    /// 何も読み込まないので、常に安全です。（`plugins`の機能が有効な場合と同じ形にしています。）
+    #[cfg(not(feature = "plugins"))]
+    pub unsafe fn load(path: &Path) -> Result<Self, String> {
+        Err(format!(
+            "{}：プラグインを読み込む機能（plugins）を無効にしてビルドされています。",
+            path.display()
+        ))
+    }
+
+    /// UIに表示する名前を取得します。
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 音階の候補を検出して、`candidates`に入れます。
+    pub fn detect(
+        &self,
+        samples: &[f32],
+        frame_rate: f32,
+        candidates: &mut Vec<Candidate>,
+        capacity: usize,
+    ) {
+        candidates.clear();
+        candidates.resize(capacity, Candidate::default());
+
+        let count = unsafe {
+            (self.detect)(
+                samples.as_ptr(),
+                samples.len(),
+                frame_rate,
+                candidates.as_mut_ptr(),
+                capacity,
+            )
+        };
+        candidates.truncate(count.min(capacity));
+    }
+}
+
+/// フォルダにある共有ライブラリを全てプラグインとして読み込みます。
+/// 読み込めたプラグイン（ファイル名の順）と、読み込めなかったプラグインのエラーを返します。
+pub fn load_dir(dir: &Path) -> (Vec<Plugin>, Vec<String>) {
+    let mut paths = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == std::env::consts::DLL_EXTENSION)
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    paths.sort();
+
+    let (mut plugins, mut errors) = (Vec::new(), Vec::new());
+    for path in paths {
+        match unsafe { Plugin::load(&path) } {
+            Ok(plugin) => plugins.push(plugin),
+            Err(error) => errors.push(error),
+        };
+    }
+
+    (plugins, errors)
+}
@@ -0,0 +1,99 @@
+//! 一つのスレッドから別の一つのスレッドに値を送るための、大きさの決まったリングバッファです。（SPSC）
+//! 送る側ではメモリの確保もロックも行わないので、入力デバイスのコールバックから使えます。
+
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// 送る側と受け取る側で共有する状態です。
+struct Shared<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    /// 受け取った値の数（次に読む位置）
+    head: AtomicUsize,
+    /// 送った値の数（次に書く位置）
+    tail: AtomicUsize,
+}
+
+// NOTE: 各位置の値は、`head`と`tail`によって送る側か受け取る側のどちらか一方だけが触るようにしている。
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn slot(&self, position: usize) -> *mut MaybeUninit<T> {
+        self.slots[position % self.slots.len()].get()
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        // 受け取られなかった値を片付ける。
+        let (head, tail) = (*self.head.get_mut(), *self.tail.get_mut());
+        let mut position = head;
+        while position != tail {
+            unsafe { (*self.slot(position)).assume_init_drop() };
+            position = position.wrapping_add(1);
+        }
+    }
+}
+
+/// 値を送るための構造体です。
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// 値を受け取るための構造体です。
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// `capacity`個まで値をためられるリングバッファを作り、送る側と受け取る側を返します。
+pub fn channel<T: Send>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let shared = Arc::new(Shared {
+        slots: (0..capacity.max(1))
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+
+    (
+        Producer {
+            shared: Arc::clone(&shared),
+        },
+        Consumer { shared },
+    )
+}
+
+impl<T> Producer<T> {
+    /// 値を送ります。受け取る側が追いついておらず一杯の場合は、送らずに`Err`で値を返します。
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        let shared = &*self.shared;
+        let tail = shared.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(shared.head.load(Ordering::Acquire)) >= shared.slots.len() {
+            return Err(value);
+        };
+
+        unsafe { (*shared.slot(tail)).write(value) };
+        shared.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+}
+
+impl<T> Consumer<T> {
+    /// 一番古い値を受け取ります。何も届いていない場合は`None`を返します。
+    pub fn pop(&mut self) -> Option<T> {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Relaxed);
+        if head == shared.tail.load(Ordering::Acquire) {
+            return None;
+        };
+
+        let value = unsafe { (*shared.slot(head)).assume_init_read() };
+        shared.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+}
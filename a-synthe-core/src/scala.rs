@@ -0,0 +1,87 @@
+//! Scalaの音律ファイル（.scl）を読み込むためのモジュールです。
+//! 形式は次のページを参考にしています：https://www.huygens-fokker.org/scala/scl_format.html
+
+/// Scalaの音律ファイルで定義された音律です。
+#[derive(Debug, PartialEq)]
+pub struct Scale {
+    /// 音律の説明
+    pub description: String,
+    /// 一つ目の音（1/1）に対する、二つ目以降の各音の周波数の比
+    /// 最後の値は、音律が繰り返される周期（普通は2/1のオクターブ）です。
+    pub ratios: Vec<f32>,
+}
+
+impl Scale {
+    /// Scalaの音律ファイルの内容を読み込みます。
+    pub fn parse(text: &str) -> Result<Self, String> {
+        // `!`で始まる行はコメントなので飛ばす。
+        let mut lines = text.lines().filter(|line| !line.starts_with('!'));
+
+        let description = lines
+            .next()
+            .ok_or("音律の説明の行がありません。")?
+            .trim()
+            .to_string();
+        let count: usize = lines
+            .next()
+            .and_then(|line| line.split_whitespace().next())
+            .and_then(|count| count.parse().ok())
+            .ok_or("音の数の行を読み込めませんでした。")?;
+
+        let ratios = lines
+            .take(count)
+            .map(parse_pitch)
+            .collect::<Result<Vec<_>, _>>()?;
+        if ratios.len() != count || count == 0 {
+            return Err(format!(
+                "音の数は{count}個とされていますが、{}個しかありません。",
+                ratios.len()
+            ));
+        };
+
+        Ok(Self {
+            description,
+            ratios,
+        })
+    }
+
+    /// 音律が繰り返される周期の比を取得します。
+    pub fn period(&self) -> f32 {
+        *self.ratios.last().unwrap()
+    }
+
+    /// 一つ目の音から`steps`個離れた音の周波数の比を取得します。
+    pub fn ratio(&self, steps: i32) -> f32 {
+        let count = self.ratios.len() as i32;
+        let (periods, degree) = (steps.div_euclid(count), steps.rem_euclid(count));
+
+        let ratio = match degree {
+            0 => 1.,
+            degree => self.ratios[degree as usize - 1],
+        };
+        ratio * self.period().powi(periods)
+    }
+}
+
+/// 音の行を読み込み、周波数の比にします。
+/// `.`を含む場合はセント、それ以外は`3/2`のような比（`2`のような整数も可）です。
+fn parse_pitch(line: &str) -> Result<f32, String> {
+    let pitch = line.split_whitespace().next().unwrap_or_default();
+    let error = || format!("「{pitch}」は音として読み込めませんでした。");
+
+    let ratio = if pitch.contains('.') {
+        let cents: f32 = pitch.parse().map_err(|_| error())?;
+        2f32.powf(cents / 1200.)
+    } else {
+        let (numerator, denominator) = pitch.split_once('/').unwrap_or((pitch, "1"));
+        let numerator: f32 = numerator.parse().map_err(|_| error())?;
+        let denominator: f32 = denominator.parse().map_err(|_| error())?;
+        numerator / denominator
+    };
+
+    if ratio.is_finite() && ratio > 0. {
+        Ok(ratio)
+    } else {
+        Err(error())
+    }
+}
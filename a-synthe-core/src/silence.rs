@@ -0,0 +1,131 @@
+//! 検出の前にスペクトルから差し引く、無音時（部屋の雑音等）のスペクトル（無音データ）を扱うためのモジュールです。
+//! 起動する度に取り直さなくて済むように、テキストで保存して読み込めるようにしています。
+//! 一回分のFFTの結果だとばらつきが大きく、差し引いた後も雑音が残るので、何回分かを平均して無音データにします。
+
+use super::calculation::Float;
+
+/// 無音データを取る時に平均するFFTの結果の数
+pub const CAPTURE_FRAMES: u32 = 20;
+
+/// 無音データです。
+#[derive(Debug, PartialEq)]
+pub struct SilenceProfile {
+    /// 取った時の入力のフレームレート
+    pub frame_rate: f32,
+    /// 取った時のFFTのサイズ
+    pub fft_length: usize,
+    /// FFTの結果の各値（周波数が0からナイキスト周波数までの成分の大きさ）
+    pub values: Vec<Float>,
+}
+
+impl SilenceProfile {
+    /// 今の解析に使えるかどうかを調べます。
+    /// フレームレートかFFTのサイズが取った時と違う場合は、周波数の対応が取れないので使えません。
+    pub fn fits(&self, frame_rate: f32, fft_length: usize, buffer_length: usize) -> bool {
+        self.frame_rate == frame_rate
+            && self.fft_length == fft_length
+            && self.values.len() == buffer_length
+    }
+
+    /// 保存するためのテキストにします。
+    /// `frame_rate=…`と`fft_length=…`の行の後に、一行に一つずつ値を並べた形式です。
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "frame_rate={}\nfft_length={}\n",
+            self.frame_rate, self.fft_length
+        );
+        for value in &self.values {
+            text.push_str(&format!("{value}\n"));
+        }
+        text
+    }
+
+    /// `to_text`で保存したテキストを読み込みます。
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let mut header = |name: &str| {
+            lines
+                .next()
+                .and_then(|line| line.strip_prefix(name))
+                .and_then(|line| line.strip_prefix('='))
+                .map(str::to_string)
+                .ok_or_else(|| format!("「{name}」の行がありません。"))
+        };
+
+        let frame_rate = header("frame_rate")?
+            .parse::<f32>()
+            .map_err(|_| "フレームレートを読み込めませんでした。".to_string())?;
+        let fft_length = header("fft_length")?
+            .parse::<usize>()
+            .map_err(|_| "FFTのサイズを読み込めませんでした。".to_string())?;
+        let values = lines
+            .map(|line| {
+                line.parse::<Float>()
+                    .map_err(|_| format!("「{line}」は値として読み込めませんでした。"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if values.len() != fft_length / 2 + 1 {
+            return Err(format!(
+                "FFTのサイズが{fft_length}の場合、値は{}個のはずですが、{}個あります。",
+                fft_length / 2 + 1,
+                values.len()
+            ));
+        };
+
+        Ok(Self {
+            frame_rate,
+            fft_length,
+            values,
+        })
+    }
+}
+
+/// 無音データを取っている途中の状態です。
+/// FFTの結果を`CAPTURE_FRAMES`回分足していき、平均して無音データにします。
+pub struct SilenceCapture {
+    /// 取っている時のFFTのサイズ
+    fft_length: usize,
+    /// 今までのFFTの結果の各値の合計
+    sum: Vec<Float>,
+    /// 足したFFTの結果の数
+    frames: u32,
+}
+
+impl SilenceCapture {
+    pub fn new(fft_length: usize, buffer_length: usize) -> Self {
+        Self {
+            fft_length,
+            sum: vec![0.; buffer_length],
+            frames: 0,
+        }
+    }
+
+    /// FFTのサイズ等が変わって、続けて足せなくなったかどうかを調べます。
+    pub fn fits(&self, fft_length: usize, buffer_length: usize) -> bool {
+        self.fft_length == fft_length && self.sum.len() == buffer_length
+    }
+
+    /// FFTの結果を足します。
+    pub fn add(&mut self, values: &[Float]) {
+        for (sum, value) in self.sum.iter_mut().zip(values) {
+            *sum += value;
+        }
+        self.frames += 1;
+    }
+
+    /// 平均するのに足りないFFTの結果の数を取得します。
+    pub fn frames_left(&self) -> u32 {
+        CAPTURE_FRAMES.saturating_sub(self.frames)
+    }
+
+    /// 足したFFTの結果を平均して、無音データにします。
+    pub fn finish(self, frame_rate: f32) -> SilenceProfile {
+        let frames = self.frames.max(1) as Float;
+        SilenceProfile {
+            frame_rate,
+            fft_length: self.fft_length,
+            values: self.sum.into_iter().map(|sum| sum / frames).collect(),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering::SeqCst},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// 前回の呼び出しからの間隔が、受け取った音声データの長さの何倍を超えたら取りこぼしとみなすか
+const UNDERRUN_GAP_RATE: f32 = 2.;
+/// `last_number`で、音階を検出しなかったことを表す値
+const NO_NUMBER: u8 = u8::MAX;
+
+/// 入力ストリームと検出の状態を記録するための構造体です。
+/// 音声データを受け取るスレッドで記録し、他のスレッドから状態を取得できます。
+/// 音声データを受け取るスレッドで使うものは、ロックしないようにアトミックな値にしています。
+pub struct StreamStats {
+    frame_rate: f32,
+    /// 経過時間の基準とする時刻
+    created_at: Instant,
+    frames_received: AtomicU64,
+    callbacks: AtomicU64,
+    underruns: AtomicU64,
+    /// 最後に音声データを受け取った時刻の、`created_at`からの経過時間（ナノ秒、まだ受け取っていないなら0）
+    last_callback: AtomicU64,
+    last_error: Mutex<Option<String>>,
+    /// 続けて音階を検出した回数
+    consecutive_detections: AtomicU64,
+    /// 続けて検出した音階がちょうど1オクターブ離れていた回数
+    octave_jumps: AtomicU64,
+    /// 最後に検出した音階（検出しなかったなら`NO_NUMBER`）
+    last_number: AtomicU8,
+    /// 最後の解析にかかった時間（マイクロ秒）
+    processing_time: AtomicU64,
+}
+
+/// 音が入力されてから、検出の結果が出るまでの遅延の内訳です。
+pub struct Latency {
+    /// 入力デバイスから一回に受け取る音声データの長さ
+    pub buffer: Duration,
+    /// 解析の窓の長さ
+    pub window: Duration,
+    /// 次の解析までに進める長さ
+    pub hop: Duration,
+    /// 解析にかかった時間
+    pub processing: Duration,
+}
+
+impl Latency {
+    /// 遅延の合計を取得します。
+    pub fn total(&self) -> Duration {
+        self.buffer + self.window + self.hop + self.processing
+    }
+}
+
+impl StreamStats {
+    /// インスタンスを作ります。
+    pub fn new(frame_rate: f32) -> Self {
+        Self {
+            frame_rate,
+            created_at: Instant::now(),
+            frames_received: AtomicU64::new(0),
+            callbacks: AtomicU64::new(0),
+            underruns: AtomicU64::new(0),
+            last_callback: AtomicU64::new(0),
+            last_error: Mutex::new(None),
+            consecutive_detections: AtomicU64::new(0),
+            octave_jumps: AtomicU64::new(0),
+            last_number: AtomicU8::new(NO_NUMBER),
+            processing_time: AtomicU64::new(0),
+        }
+    }
+
+    /// 音声データを受け取ったことを記録します。入力ストリームのコールバックで呼んでください。
+    /// 前回からの間隔が空きすぎている場合は、音声データを取りこぼしたとみなします。
+    pub fn record_callback(&self, frames: usize) {
+        // 0はまだ受け取っていないことを表すので、0にならないようにする。
+        let now = (self.created_at.elapsed().as_nanos() as u64).max(1);
+
+        let before = self.last_callback.swap(now, SeqCst);
+        if before != 0 {
+            let expected = frames as f32 / self.frame_rate;
+            if now.saturating_sub(before) as f32 / 1_000_000_000. > expected * UNDERRUN_GAP_RATE {
+                self.underruns.fetch_add(1, SeqCst);
+            };
+        };
+
+        self.frames_received.fetch_add(frames as _, SeqCst);
+        self.callbacks.fetch_add(1, SeqCst);
+    }
+
+    /// 入力ストリームで発生したエラーを記録します。
+    /// ロックするので、入力ストリームのコールバックではなく、エラーを受け取った他のスレッドで呼んでください。
+    pub fn record_error(&self, error: impl ToString) {
+        *self.last_error.lock().unwrap() = Some(error.to_string());
+    }
+
+    /// 検出した一番目の音階を記録します。検出しなかった場合は`None`を渡してください。
+    /// 前回も検出していて、音階がちょうど±12離れていた場合はオクターブの誤検出とみなします。
+    pub fn record_detection(&self, number: Option<u8>) {
+        let before = self.last_number.swap(number.unwrap_or(NO_NUMBER), SeqCst);
+
+        if let (Some(before), Some(number)) = ((before != NO_NUMBER).then_some(before), number) {
+            self.consecutive_detections.fetch_add(1, SeqCst);
+            if before.abs_diff(number) == 12 {
+                self.octave_jumps.fetch_add(1, SeqCst);
+            };
+        };
+    }
+
+    /// 受け取った音声データのフレーム数の合計を取得します。
+    pub fn frames_received(&self) -> u64 {
+        self.frames_received.load(SeqCst)
+    }
+
+    /// 一回のコールバックで受け取った音声データのフレーム数の平均を取得します。
+    pub fn average_callback_size(&self) -> f32 {
+        match self.callbacks.load(SeqCst) {
+            0 => 0.,
+            callbacks => self.frames_received() as f32 / callbacks as f32,
+        }
+    }
+
+    /// 音声データを取りこぼしたとみなした回数を取得します。
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(SeqCst)
+    }
+
+    /// オクターブの誤検出とみなした回数を取得します。
+    pub fn octave_jumps(&self) -> u64 {
+        self.octave_jumps.load(SeqCst)
+    }
+
+    /// 続けて検出した中で、オクターブの誤検出とみなした割合（%）を取得します。
+    pub fn octave_jump_rate(&self) -> f32 {
+        match self.consecutive_detections.load(SeqCst) {
+            0 => 0.,
+            detections => self.octave_jumps() as f32 / detections as f32 * 100.,
+        }
+    }
+
+    /// 解析にかかった時間を記録します。入力ストリームのコールバックで呼んでください。
+    pub fn record_processing(&self, duration: Duration) {
+        self.processing_time
+            .store(duration.as_micros() as _, SeqCst);
+    }
+
+    /// 今の設定での遅延を求めます。
+    /// `window_length`と`overlap`は解析の窓の長さと重なりの設定で、窓の長さが0の場合は受け取った音声データごとに解析するものとします。
+    /// その場合は受け取った音声データがそのまま窓になるので、窓と間隔の遅延は入力の遅延に含めて0にします。
+    pub fn latency(&self, window_length: usize, overlap: u32) -> Latency {
+        let duration = |frames: f32| Duration::from_secs_f32(frames.max(0.) / self.frame_rate);
+        let buffer = self.average_callback_size();
+        let (window, hop) = if window_length == 0 {
+            (0., 0.)
+        } else {
+            let overlap = overlap.min(99) as usize;
+            (
+                window_length as f32,
+                (window_length * (100 - overlap) / 100).max(1) as f32,
+            )
+        };
+
+        Latency {
+            buffer: duration(buffer),
+            window: duration(window),
+            hop: duration(hop),
+            processing: Duration::from_micros(self.processing_time.load(SeqCst)),
+        }
+    }
+
+    /// 最後に発生したエラーを取得します。
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 48000Hzで480フレームずつ受け取り、解析に2ミリ秒かかった状態を作ります。
+    fn stats() -> StreamStats {
+        let stats = StreamStats::new(48000.);
+        stats.record_callback(480);
+        stats.record_callback(480);
+        stats.record_processing(Duration::from_millis(2));
+        stats
+    }
+
+    fn assert_milliseconds(duration: Duration, expected: f64) {
+        let milliseconds = duration.as_secs_f64() * 1000.;
+        assert!(
+            (milliseconds - expected).abs() < 0.01,
+            "{milliseconds}ms (expected {expected}ms)"
+        );
+    }
+
+    #[test]
+    fn latency_without_window() {
+        // 受け取った音声データごとに解析するので、入力の10ミリ秒と計算の2ミリ秒だけになる。
+        let latency = stats().latency(0, 50);
+        assert_milliseconds(latency.buffer, 10.);
+        assert_milliseconds(latency.window, 0.);
+        assert_milliseconds(latency.hop, 0.);
+        assert_milliseconds(latency.total(), 12.);
+    }
+
+    #[test]
+    fn latency_with_window() {
+        // 入力の10ミリ秒＋窓の4800フレーム（100ミリ秒）＋半分重ねた間隔（50ミリ秒）＋計算の2ミリ秒
+        let latency = stats().latency(4800, 50);
+        assert_milliseconds(latency.window, 100.);
+        assert_milliseconds(latency.hop, 50.);
+        assert_milliseconds(latency.total(), 162.);
+    }
+}
@@ -0,0 +1,106 @@
+//! 検出結果を非同期で受け取るためのモジュールです。
+//! 特定の非同期ランタイムには依存しないので、tokio等の上でスレッドを自分で管理せずに検出結果を扱えます。
+//!
+//! 音声データを受け取るスレッドで`DetectionSender::send`に`Synthesizer::synthe`の結果を渡し、
+//! 非同期のタスクで`DetectionReceiver::next_detection`を待ちます。
+//! 設定は`Config`がアトミックな値でできているため、非同期のタスクからそのまま変更できます。
+
+use std::{
+    collections::VecDeque,
+    future::poll_fn,
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+};
+
+use super::{Config, Note};
+
+/// 受け取られていない検出結果をためておく数の上限
+/// これを超えた場合は古いものから捨てます。
+const QUEUE_CAPACITY: usize = 64;
+
+/// 検出結果を受け渡すための共有の状態です。
+struct State<const N: usize> {
+    queue: VecDeque<Option<[Note; N]>>,
+    waker: Option<Waker>,
+    is_closed: bool,
+}
+
+/// 検出結果を送るための構造体です。
+pub struct DetectionSender<const N: usize> {
+    state: Arc<Mutex<State<N>>>,
+}
+
+/// 検出結果を非同期で受け取るための構造体です。
+pub struct DetectionReceiver<const N: usize> {
+    state: Arc<Mutex<State<N>>>,
+    config: Arc<Config>,
+}
+
+/// 検出結果を受け渡すための送信側と受信側を作ります。
+/// `config`には、検出に使っている`Synthesizer`の設定を渡してください。
+pub fn channel<const N: usize>(config: Arc<Config>) -> (DetectionSender<N>, DetectionReceiver<N>) {
+    let state = Arc::new(Mutex::new(State {
+        queue: VecDeque::new(),
+        waker: None,
+        is_closed: false,
+    }));
+
+    (
+        DetectionSender {
+            state: Arc::clone(&state),
+        },
+        DetectionReceiver { state, config },
+    )
+}
+
+impl<const N: usize> DetectionSender<N> {
+    /// 検出結果を送ります。待っているタスクがあれば起こします。
+    pub fn send(&self, detection: Option<[Note; N]>) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.queue.len() >= QUEUE_CAPACITY {
+            state.queue.pop_front();
+        };
+        state.queue.push_back(detection);
+
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        };
+    }
+}
+
+impl<const N: usize> Drop for DetectionSender<N> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.is_closed = true;
+
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        };
+    }
+}
+
+impl<const N: usize> DetectionReceiver<N> {
+    /// 次の検出結果を待ちます。
+    /// 送信側が無くなった場合は`None`を返します。
+    pub async fn next_detection(&mut self) -> Option<Option<[Note; N]>> {
+        poll_fn(|context| {
+            let mut state = self.state.lock().unwrap();
+
+            if let Some(detection) = state.queue.pop_front() {
+                Poll::Ready(Some(detection))
+            } else if state.is_closed {
+                Poll::Ready(None)
+            } else {
+                state.waker = Some(context.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// 検出に使っている設定を取得します。
+    pub fn config(&self) -> &Arc<Config> {
+        &self.config
+    }
+}
@@ -0,0 +1,111 @@
+//! ブラウザで、Web Audioの入力から音階を検出するためのJavaScript向けのバインディングです。
+//! `wasm`の機能を有効にして`wasm32-unknown-unknown`向けにビルドし、`wasm-pack`等でJavaScriptのモジュールにして使います。
+//!
+//! ```js
+//! import init, { WebSynthesizer } from "./pkg/a_synthe_core.js";
+//!
+//! await init();
+//! const synthesizer = new WebSynthesizer(audioContext.sampleRate);
+//! synthesizer.set("min_volume=-50");
+//! // `AnalyserNode.getFloatTimeDomainData`等で取った音声データを渡す。
+//! const note = synthesizer.process(samples);
+//! if (note) console.log(note.name, note.frequency);
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use super::{params, Note, Synthesizer};
+
+/// JavaScriptから使う、音階を検出するためのものです。
+#[wasm_bindgen]
+pub struct WebSynthesizer {
+    synthesizer: Synthesizer,
+}
+
+#[wasm_bindgen]
+impl WebSynthesizer {
+    /// Web Audioの`AudioContext.sampleRate`（Hz）を渡して作ります。
+    #[wasm_bindgen(constructor)]
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            synthesizer: Synthesizer::builder(sample_rate).build(),
+        }
+    }
+
+    /// 設定を、設定のファイルと同じ`名前=値`の形式で変えます。
+    /// 値が設定できる範囲にない場合は、エラーにします。
+    pub fn set(&mut self, line: &str) -> Result<(), JsError> {
+        params::validate_line(line).map_err(|error| JsError::new(&error))?;
+
+        let config = &self.synthesizer.config;
+        let mut snapshot = config.snapshot();
+        snapshot.apply_line(line);
+        config.restore(&snapshot);
+        Ok(())
+    }
+
+    /// 音声データ（`Float32Array`）を渡して、音階を検出します。
+    /// 解析の窓の長さが設定されている場合はためてから解析し、最後に解析した結果の一番目の音を返します。
+    /// 検出しなかった場合は`undefined`を返します。
+    pub fn process(&mut self, samples: &[f32]) -> Option<WebNote> {
+        let mut latest = None;
+        self.synthesizer.feed::<1>(samples, |_, result| {
+            latest = Some(result.map(|[note]| WebNote { note }));
+        });
+        latest.flatten()
+    }
+
+    /// 音声データを一度だけ解析して、全ての音階を順位の高い順に返します。
+    /// 前回までの解析の状態は使いません。（`Synthesizer::process_buffer`）
+    pub fn rank(&self, samples: &[f32], sample_rate: f32) -> Vec<WebNote> {
+        self.synthesizer
+            .process_buffer(samples, sample_rate)
+            .into_iter()
+            .map(|ranked| WebNote { note: ranked.note })
+            .collect()
+    }
+
+    /// 直前の解析で閾値と比べた音量（dB）を取得します。
+    pub fn volume(&self) -> f32 {
+        self.synthesizer.volume()
+    }
+}
+
+/// JavaScriptに返す、検出した音階です。
+#[wasm_bindgen]
+pub struct WebNote {
+    note: Note,
+}
+
+#[wasm_bindgen]
+impl WebNote {
+    /// MIDIの音階の番号
+    #[wasm_bindgen(getter)]
+    pub fn number(&self) -> u8 {
+        self.note.number
+    }
+
+    /// 検出した周波数（Hz）
+    #[wasm_bindgen(getter)]
+    pub fn frequency(&self) -> f32 {
+        self.note.frequency
+    }
+
+    /// 音階の周波数からのずれ（セント）
+    #[wasm_bindgen(getter)]
+    pub fn cents(&self) -> f32 {
+        self.note.cents
+    }
+
+    /// 検出の信頼度（dB）
+    #[wasm_bindgen(getter)]
+    pub fn confidence(&self) -> f32 {
+        self.note.confidence
+    }
+
+    /// 音階の名前（例：`ラ/A 4`）
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.note.get_name()
+    }
+}
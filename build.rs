@@ -10,5 +10,5 @@ fn main() {
     res.compile().unwrap();
 }
 
-#[cfg(target_os = "macos")]
+#[cfg(not(target_os = "windows"))]
 fn main() {}
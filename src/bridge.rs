@@ -0,0 +1,276 @@
+//! DAWから音階検出を使うための、ブリッジモードのモジュールです。
+//! UIを使わずに起動して、検出した音をDAWから見えるMIDIのポートに送り、設定は制御用のソケットから変えられるようにします。
+//!
+//! 制御用のソケットは`127.0.0.1`のTCPで、一行に一つの命令を送ると一行で返事をします。
+//! - `set <名前>=<値>`：設定の値を変える（名前はプリセットのファイルと同じ）
+//! - `get <名前>`：設定の値を`<名前>=<値>`で返す
+//! - `settings`：全ての設定を`<名前>=<値>`で一行ずつ返し、最後に`ok`を返す
+//! - `panic`：鳴らしている音を全て止める
+//! - `close`：接続を切る
+//! - `stop`：aSyntheを終了する
+//!
+//! 成功した場合は`ok`、失敗した場合は`error <理由>`を返します。
+//! また、検出した一番目の音階が変わる度に、`note <音階名> <番号> <周波数> <セント>`（音がない場合は`note -`）を全ての接続に送ります。
+
+use std::{
+    io::{self, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+};
+
+use crate::{config::AppConfig, midi::MidiManager};
+
+/// ブリッジモードで起動するためのコマンドライン引数
+pub const FLAG: &str = "--bridge";
+/// 制御用のソケットの既定のポート番号
+pub const DEFAULT_CONTROL_PORT: u16 = 7341;
+
+/// DAWごとの接続方法です。
+pub struct Daw {
+    /// `--daw`で指定する名前
+    pub id: &'static str,
+    /// 表示用の名前
+    pub name: &'static str,
+    /// MIDIのポートの名前
+    /// 入力の一覧の幅が狭く、長い名前が省略されるDAWでは短い名前にしています。
+    pub port_name: &'static str,
+    /// DAWで行う設定の手順
+    pub steps: &'static [&'static str],
+}
+
+/// 対応しているDAWの一覧です。最初のものが既定です。
+pub const DAWS: [Daw; 6] = [
+    Daw {
+        id: "generic",
+        name: "その他のDAW",
+        port_name: "aSynthe Out",
+        steps: &["MIDIの入力の一覧で「aSynthe Out」を有効にして、トラックの入力に選んでください。"],
+    },
+    Daw {
+        id: "ableton",
+        name: "Ableton Live",
+        port_name: "aSynthe",
+        steps: &[
+            "環境設定の「Link, Tempo & MIDI」で、入力の「aSynthe」の「トラック」をオンにします。",
+            "MIDIトラックの「MIDI From」で「aSynthe」を選び、モニターを「In」にします。",
+        ],
+    },
+    Daw {
+        id: "logic",
+        name: "Logic Pro",
+        port_name: "aSynthe",
+        steps: &[
+            "Logic Proは全てのMIDIの入力を受け取るので、ソフトウェア音源のトラックを選ぶだけで鳴ります。",
+            "他の入力と分けたい場合は、トラックのインスペクタの「MIDI入力ポート」で「aSynthe」を選んでください。",
+        ],
+    },
+    Daw {
+        id: "bitwig",
+        name: "Bitwig Studio",
+        port_name: "aSynthe",
+        steps: &[
+            "設定の「Controllers」で「Generic」の「MIDI Keyboard」を追加し、入力に「aSynthe」を選びます。",
+            "インストゥルメントトラックの入力で、追加したコントローラーを選びます。",
+        ],
+    },
+    Daw {
+        id: "reaper",
+        name: "REAPER",
+        port_name: "aSynthe Out",
+        steps: &[
+            "設定の「Audio > MIDI Inputs」で「aSynthe Out」を有効にします。",
+            "トラックの録音入力で「Input: MIDI > aSynthe Out > All channels」を選び、録音待機とモニターをオンにします。",
+        ],
+    },
+    Daw {
+        id: "cubase",
+        name: "Cubase",
+        port_name: "aSynthe Out",
+        steps: &[
+            "スタジオ設定の「MIDIポートの設定」で、「aSynthe Out」の「'All MIDI Inputs'に含める」をオンにします。",
+            "MIDIトラックかインストゥルメントトラックの入力で「aSynthe Out」を選びます。",
+        ],
+    },
+];
+
+/// `--daw`で指定された名前からDAWを探します。
+pub fn find_daw(id: &str) -> Option<&'static Daw> {
+    DAWS.iter().find(|daw| daw.id.eq_ignore_ascii_case(id))
+}
+
+/// DAWに合わせた名前のMIDIのポートに接続します。
+/// 仮想ポートが使える環境ではその名前で仮想ポートを作り、使えない環境（Windows）ではloopMIDI等で作った同じ名前のポートに接続します。
+pub fn connect(midi_manager: MidiManager, daw: &Daw) -> MidiManager {
+    #[cfg(unix)]
+    let midi_manager = midi_manager.with_virtual_port_name(daw.port_name);
+    let port_names = midi_manager.port_names();
+
+    #[cfg(unix)]
+    let port_index = Some(port_names.len());
+    #[cfg(not(unix))]
+    let port_index = port_names
+        .iter()
+        .position(|name| name.starts_with(daw.port_name))
+        .map(|index| index + 1);
+
+    match port_index {
+        Some(port_index) => midi_manager.set_midi_output(port_index),
+        None => midi_manager,
+    }
+}
+
+/// 検出した音階を知らせる行を作ります。
+pub fn note_message(note: Option<&crate::sys::Note>) -> String {
+    if let Some(note) = note {
+        format!(
+            "note {} {} {:.1} {:+.0}",
+            note.get_name(),
+            note.number,
+            note.frequency,
+            note.cents
+        )
+    } else {
+        "note -".to_string()
+    }
+}
+
+/// 制御用のソケットで受け付ける一行の長さ（バイト数）の上限です。
+const MAX_LINE_LENGTH: usize = 4096;
+
+/// 制御用のソケットに接続しているクライアントです。
+struct Client {
+    stream: TcpStream,
+    /// まだ改行が届いていない分のデータ
+    buffer: Vec<u8>,
+}
+
+/// 命令を処理した結果です。
+enum Outcome {
+    Reply(String),
+    Close,
+    Stop,
+}
+
+/// 制御用のソケットです。
+pub struct ControlServer {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+impl ControlServer {
+    /// `127.0.0.1`の指定したポート番号で接続を待ち始めます。
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+    /// 新しい接続を受け付けて、届いた命令を処理します。
+    /// 改行が届かないまま`MAX_LINE_LENGTH`を超えた接続は切ります。
+    /// `stop`の命令が届いた場合は`false`を返します。
+    pub fn poll(&mut self, config: &AppConfig, midi_manager: &mut MidiManager) -> bool {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                self.clients.push(Client {
+                    stream,
+                    buffer: Vec::new(),
+                });
+            };
+        }
+
+        let mut is_running = true;
+        self.clients.retain_mut(|client| {
+            let mut data = [0; 1024];
+            loop {
+                match client.stream.read(&mut data) {
+                    Ok(0) => return false,
+                    Ok(length) => client.buffer.extend_from_slice(&data[..length]),
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => return true,
+                    Err(_) => return false,
+                };
+
+                while let Some(end) = client.buffer.iter().position(|byte| *byte == b'\n') {
+                    let line = client.buffer.drain(..=end).collect::<Vec<_>>();
+                    let line = String::from_utf8_lossy(&line);
+
+                    match execute(line.trim(), config, midi_manager) {
+                        Outcome::Reply(reply) => {
+                            if writeln!(client.stream, "{reply}").is_err() {
+                                return false;
+                            };
+                        }
+                        Outcome::Close => return false,
+                        Outcome::Stop => {
+                            let _ = writeln!(client.stream, "ok");
+                            is_running = false;
+                            return false;
+                        }
+                    };
+                }
+
+                // 改行が届かないまま長くなり続ける接続は、メモリを使い切らないように切る。
+                if client.buffer.len() > MAX_LINE_LENGTH {
+                    let _ = writeln!(client.stream, "error 一行が長すぎます。");
+                    return false;
+                };
+            }
+        });
+
+        is_running
+    }
+
+    /// 全ての接続に一行送ります。送れなかった接続は切ります。
+    pub fn broadcast(&mut self, line: &str) {
+        self.clients
+            .retain_mut(|client| writeln!(client.stream, "{line}").is_ok());
+    }
+}
+
+/// 制御用のソケットに届いた命令を処理します。
+fn execute(line: &str, config: &AppConfig, midi_manager: &mut MidiManager) -> Outcome {
+    let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+    let argument = argument.trim();
+
+    match command {
+        "set" => {
+            let Some((name, _)) = argument.split_once('=') else {
+                return Outcome::Reply("error <名前>=<値>の形式で指定してください。".to_string());
+            };
+
+            let mut snapshot = config.snapshot();
+            if find_setting(&snapshot.to_text(), name.trim()).is_none() {
+                return Outcome::Reply(format!("error {}は知らない設定です。", name.trim()));
+            };
+            if let Err(error) = crate::params::validate_line(argument) {
+                return Outcome::Reply(format!("error {error}"));
+            };
+
+            snapshot.apply_line(argument);
+            config.restore(&snapshot);
+            Outcome::Reply("ok".to_string())
+        }
+        "get" => Outcome::Reply(
+            find_setting(&config.snapshot().to_text(), argument)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("error {argument}は知らない設定です。")),
+        ),
+        "settings" => Outcome::Reply(format!("{}ok", config.snapshot().to_text())),
+        "panic" => {
+            midi_manager.all_notes_off();
+            Outcome::Reply("ok".to_string())
+        }
+        "close" => Outcome::Close,
+        "stop" => Outcome::Stop,
+        _ => Outcome::Reply(format!("error {command}は知らない命令です。")),
+    }
+}
+
+/// 設定のテキストから、指定した名前の`<名前>=<値>`の行を探します。
+fn find_setting<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.lines().find(|line| {
+        line.split_once('=')
+            .is_some_and(|(line_name, _)| line_name == name)
+    })
+}
@@ -0,0 +1,70 @@
+//! UIから、イベントループの動くスレッドに操作を頼むためのモジュールです。
+//! UIのコールバックは`CommandBus`を複製して持ち、`AppCommand`を送ります。
+//! 送られた操作は、イベントループで`Event::Command`として受け取って実行します。
+
+use std::sync::mpsc::Sender;
+
+use crate::{player::Song, Event};
+
+/// イベントループの動くスレッドに頼む操作です。
+#[derive(Clone, Debug)]
+pub enum AppCommand {
+    /// MIDIの出力先の変更
+    UpdateMidiOutput(usize),
+    /// MIDIの音色の変更
+    UpdateMidiProgram(u8),
+    /// MIDIで鳴らしている全ての音を止める
+    MidiPanic,
+    /// ソステヌートの切り替え
+    UpdateSostenuto(bool),
+    /// スクリプトを読み込み直す
+    ReloadScript,
+    /// 伴奏の曲の読み込み
+    LoadBackingTrack(Song),
+    /// 伴奏の再生
+    PlayBackingTrack,
+    /// 伴奏の一時停止
+    PauseBackingTrack,
+    /// 伴奏の停止
+    StopBackingTrack,
+    /// 伴奏のテンポ（元の速さに対する%）の変更
+    SetBackingTrackTempo(u32),
+    /// 楽譜の追従に使うお手本の旋律（MIDIの番号）の読み込み
+    LoadScore(Vec<u8>),
+    /// 楽譜の追従を最初に戻す
+    ResetScore,
+    /// 練習の時間を最初から測り直し、止めていた検出を再開する
+    RestartPractice,
+}
+
+impl AppCommand {
+    /// タイムラインに表示する、操作の内容の要約を作ります。
+    /// 曲や旋律のような大きいデータは、中身ではなく大きさだけにします。
+    pub fn summary(&self) -> String {
+        match self {
+            Self::LoadBackingTrack(song) => {
+                format!("LoadBackingTrack({}個のイベント)", song.event_count())
+            }
+            Self::LoadScore(melody) => format!("LoadScore({}個の音)", melody.len()),
+            command => format!("{command:?}"),
+        }
+    }
+}
+
+/// 操作をイベントループに送るためのものです。
+#[derive(Clone)]
+pub struct CommandBus {
+    sender: Sender<Event>,
+}
+
+impl CommandBus {
+    /// インスタンスを作ります。`sender`にはイベントループが受け取るチャンネルを渡してください。
+    pub fn new(sender: Sender<Event>) -> Self {
+        Self { sender }
+    }
+
+    /// 操作をイベントループに送ります。イベントループが終わっている場合は何もしません。
+    pub fn dispatch(&self, command: AppCommand) {
+        let _ = self.sender.send(Event::Command(command));
+    }
+}
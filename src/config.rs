@@ -0,0 +1,408 @@
+//! アプリで使う設定をまとめたモジュールです。
+//! 音階検出の設定（`Config`）は`a_synthe_core`のものを使い、MIDIの出力や練習、表示の設定はここで持ちます。
+
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicU16, AtomicU32, AtomicU8, Ordering::SeqCst},
+        Arc,
+    },
+};
+
+use crate::{
+    params,
+    sys::{Config, ConfigSnapshot},
+};
+
+/// スレッド間で共有する、アプリの設定を入れるための構造体です。
+/// 音階検出の設定（`Config`）の値は、`Deref`でそのまま読み書きできます。
+pub struct AppConfig {
+    /// 音階検出の設定（`Synthesizer`と共有します。）
+    pub detection: Arc<Config>,
+    /// 解析する入力のチャンネル（0なら全てのチャンネルを平均する）
+    pub input_channel: AtomicU16,
+    /// 表示等のために残す履歴に使うメモリの上限（MB）
+    pub history_memory_limit: AtomicU32,
+    /// 基準音を鳴らしているかどうか
+    /// 鳴らしている間は、マイクで拾った基準音でMIDIの音が鳴らないようにします。
+    pub is_reference_tone_playing: AtomicBool,
+    /// 練習を自動で止めるまでの時間（分、0なら止めない）
+    pub auto_stop_minutes: AtomicU32,
+    /// 伸ばしている音の音程が始めからずれたら知らせるかどうか
+    pub use_drift_alarm: AtomicBool,
+    /// 音程のずれを知らせるずれの大きさ（セント）
+    pub drift_alarm_threshold: AtomicU32,
+    /// 音程のずれが続いたら知らせるまでの時間（ミリ秒）
+    pub drift_alarm_duration: AtomicU32,
+    /// 音程のずれを知らせる時にクリック音を鳴らすかどうか
+    pub use_drift_alarm_click: AtomicBool,
+    /// 音階の名前の表し方（`NoteNaming::ALL`での位置）
+    pub note_naming: AtomicU8,
+    /// 記譜の移調（半音、記譜上の音は実音よりこれだけ高い）
+    pub written_transposition: AtomicI32,
+    /// MIDIの音を検出の有無に関わらず決まった長さで止めるかどうか
+    pub use_fixed_note_length: AtomicBool,
+    /// MIDIの音の長さを固定する場合の長さ（ミリ秒）
+    pub fixed_note_length: AtomicU32,
+    /// 検出されなくなってからMIDIの音を止めるまでの猶予（ミリ秒）
+    pub release_delay: AtomicU32,
+    /// 鳴らしている音のノートオンを送り直す間隔（秒、0なら送り直さない）
+    pub note_refresh_interval: AtomicU32,
+    /// 鳴らしたままの音を止めるまでの時間（秒、0なら止めない）
+    pub max_note_duration: AtomicU32,
+    /// MIDIの音を変える時に、次の音を出してから前の音を止めるかどうか
+    pub use_legato: AtomicBool,
+    /// MIDIの音を出すまでに、同じ音が続けて検出される必要がある回数
+    pub min_note_frames: AtomicU32,
+    /// MIDIの音を出すのに必要な最低の信頼度（dB）
+    /// 検出したとみなす最低の信頼度（`min_confidence`）より低い候補は、これに関わらず検出されません。
+    pub min_midi_confidence: AtomicI32,
+    /// 同じ音が弾き直されたことを検出して、MIDIの音を鳴らし直すかどうか
+    pub use_onset_retrigger: AtomicBool,
+    /// MIDIで送る音にかける中央値フィルタの大きさ（検出回数）
+    pub median_filter_size: AtomicU32,
+    /// MIDIで送る時だけの移調（半音）
+    /// 表示する音階（`written_transposition`）とは別に、送る音だけを動かします。
+    pub midi_transposition: AtomicI32,
+    /// MIDIで一緒に鳴らす音のオクターブの差（0なら鳴らさない）
+    pub octave_doubling: AtomicI32,
+    /// MIDIで一緒に鳴らす音の強さ（%）
+    pub octave_doubling_velocity: AtomicU8,
+    /// 入力の音量を送るMIDIのコントロールチェンジの番号（0なら送らない）
+    pub envelope_control: AtomicU8,
+}
+
+/// `AppConfig`の設定の値を、ある時点で写し取ったものです。
+/// 設定の変更を元に戻したり、プリセットとして保存したりするのに使います。
+#[derive(Clone, PartialEq, Debug)]
+pub struct AppConfigSnapshot {
+    /// 音階検出の設定
+    pub detection: ConfigSnapshot,
+    pub input_channel: u16,
+    pub history_memory_limit: u32,
+    pub auto_stop_minutes: u32,
+    pub use_drift_alarm: bool,
+    pub drift_alarm_threshold: u32,
+    pub drift_alarm_duration: u32,
+    pub use_drift_alarm_click: bool,
+    pub note_naming: u8,
+    pub written_transposition: i32,
+    pub use_fixed_note_length: bool,
+    pub fixed_note_length: u32,
+    pub release_delay: u32,
+    pub note_refresh_interval: u32,
+    pub max_note_duration: u32,
+    pub use_legato: bool,
+    pub min_note_frames: u32,
+    pub min_midi_confidence: i32,
+    pub use_onset_retrigger: bool,
+    pub median_filter_size: u32,
+    pub midi_transposition: i32,
+    pub octave_doubling: i32,
+    pub octave_doubling_velocity: u8,
+    pub envelope_control: u8,
+}
+
+impl AppConfig {
+    /// 音階検出の設定を`detection`にして、その他の設定を初期値にしたものを作ります。
+    pub fn new(detection: Arc<Config>) -> Self {
+        Self {
+            detection,
+            input_channel: AtomicU16::new(0),
+            history_memory_limit: AtomicU32::new(params::HISTORY_MEMORY_LIMIT.default as _),
+            is_reference_tone_playing: AtomicBool::new(false),
+            auto_stop_minutes: AtomicU32::new(params::AUTO_STOP_MINUTES.default as _),
+            use_drift_alarm: AtomicBool::new(false),
+            drift_alarm_threshold: AtomicU32::new(params::DRIFT_ALARM_THRESHOLD.default as _),
+            drift_alarm_duration: AtomicU32::new(params::DRIFT_ALARM_DURATION.default as _),
+            use_drift_alarm_click: AtomicBool::new(false),
+            note_naming: AtomicU8::new(params::NOTE_NAMING.default as _),
+            written_transposition: AtomicI32::new(params::WRITTEN_TRANSPOSITION.default as _),
+            use_fixed_note_length: AtomicBool::new(false),
+            fixed_note_length: AtomicU32::new(params::FIXED_NOTE_LENGTH.default as _),
+            release_delay: AtomicU32::new(params::RELEASE_DELAY.default as _),
+            note_refresh_interval: AtomicU32::new(params::NOTE_REFRESH_INTERVAL.default as _),
+            max_note_duration: AtomicU32::new(params::MAX_NOTE_DURATION.default as _),
+            use_legato: AtomicBool::new(false),
+            min_note_frames: AtomicU32::new(params::MIN_NOTE_FRAMES.default as _),
+            min_midi_confidence: AtomicI32::new(params::MIN_MIDI_CONFIDENCE.default as _),
+            use_onset_retrigger: AtomicBool::new(false),
+            median_filter_size: AtomicU32::new(params::MEDIAN_FILTER_SIZE.default as _),
+            midi_transposition: AtomicI32::new(params::MIDI_TRANSPOSITION.default as _),
+            octave_doubling: AtomicI32::new(params::OCTAVE_DOUBLING.default as _),
+            octave_doubling_velocity: AtomicU8::new(params::OCTAVE_DOUBLING_VELOCITY.default as _),
+            envelope_control: AtomicU8::new(params::ENVELOPE_CONTROL.default as _),
+        }
+    }
+
+    /// 今の設定の値を写し取ります。
+    pub fn snapshot(&self) -> AppConfigSnapshot {
+        AppConfigSnapshot {
+            detection: self.detection.snapshot(),
+            input_channel: self.input_channel.load(SeqCst),
+            history_memory_limit: self.history_memory_limit.load(SeqCst),
+            auto_stop_minutes: self.auto_stop_minutes.load(SeqCst),
+            use_drift_alarm: self.use_drift_alarm.load(SeqCst),
+            drift_alarm_threshold: self.drift_alarm_threshold.load(SeqCst),
+            drift_alarm_duration: self.drift_alarm_duration.load(SeqCst),
+            use_drift_alarm_click: self.use_drift_alarm_click.load(SeqCst),
+            note_naming: self.note_naming.load(SeqCst),
+            written_transposition: self.written_transposition.load(SeqCst),
+            use_fixed_note_length: self.use_fixed_note_length.load(SeqCst),
+            fixed_note_length: self.fixed_note_length.load(SeqCst),
+            release_delay: self.release_delay.load(SeqCst),
+            note_refresh_interval: self.note_refresh_interval.load(SeqCst),
+            max_note_duration: self.max_note_duration.load(SeqCst),
+            use_legato: self.use_legato.load(SeqCst),
+            min_note_frames: self.min_note_frames.load(SeqCst),
+            min_midi_confidence: self.min_midi_confidence.load(SeqCst),
+            use_onset_retrigger: self.use_onset_retrigger.load(SeqCst),
+            median_filter_size: self.median_filter_size.load(SeqCst),
+            midi_transposition: self.midi_transposition.load(SeqCst),
+            octave_doubling: self.octave_doubling.load(SeqCst),
+            octave_doubling_velocity: self.octave_doubling_velocity.load(SeqCst),
+            envelope_control: self.envelope_control.load(SeqCst),
+        }
+    }
+
+    /// 写し取った設定の値に戻します。
+    pub fn restore(&self, snapshot: &AppConfigSnapshot) {
+        self.detection.restore(&snapshot.detection);
+        self.input_channel.store(snapshot.input_channel, SeqCst);
+        self.history_memory_limit
+            .store(snapshot.history_memory_limit, SeqCst);
+        self.auto_stop_minutes
+            .store(snapshot.auto_stop_minutes, SeqCst);
+        self.use_drift_alarm.store(snapshot.use_drift_alarm, SeqCst);
+        self.drift_alarm_threshold
+            .store(snapshot.drift_alarm_threshold, SeqCst);
+        self.drift_alarm_duration
+            .store(snapshot.drift_alarm_duration, SeqCst);
+        self.use_drift_alarm_click
+            .store(snapshot.use_drift_alarm_click, SeqCst);
+        self.note_naming.store(snapshot.note_naming, SeqCst);
+        self.written_transposition
+            .store(snapshot.written_transposition, SeqCst);
+        self.use_fixed_note_length
+            .store(snapshot.use_fixed_note_length, SeqCst);
+        self.fixed_note_length
+            .store(snapshot.fixed_note_length, SeqCst);
+        self.release_delay.store(snapshot.release_delay, SeqCst);
+        self.note_refresh_interval
+            .store(snapshot.note_refresh_interval, SeqCst);
+        self.max_note_duration
+            .store(snapshot.max_note_duration, SeqCst);
+        self.use_legato.store(snapshot.use_legato, SeqCst);
+        self.min_note_frames.store(snapshot.min_note_frames, SeqCst);
+        self.min_midi_confidence
+            .store(snapshot.min_midi_confidence, SeqCst);
+        self.use_onset_retrigger
+            .store(snapshot.use_onset_retrigger, SeqCst);
+        self.median_filter_size
+            .store(snapshot.median_filter_size, SeqCst);
+        self.midi_transposition
+            .store(snapshot.midi_transposition, SeqCst);
+        self.octave_doubling.store(snapshot.octave_doubling, SeqCst);
+        self.octave_doubling_velocity
+            .store(snapshot.octave_doubling_velocity, SeqCst);
+        self.envelope_control
+            .store(snapshot.envelope_control, SeqCst);
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self::new(Arc::default())
+    }
+}
+
+impl Deref for AppConfig {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        &self.detection
+    }
+}
+
+impl Default for AppConfigSnapshot {
+    fn default() -> Self {
+        AppConfig::default().snapshot()
+    }
+}
+
+impl Deref for AppConfigSnapshot {
+    type Target = ConfigSnapshot;
+
+    fn deref(&self) -> &ConfigSnapshot {
+        &self.detection
+    }
+}
+
+impl DerefMut for AppConfigSnapshot {
+    fn deref_mut(&mut self) -> &mut ConfigSnapshot {
+        &mut self.detection
+    }
+}
+
+impl AppConfigSnapshot {
+    /// `名前=値`の行を並べた文字列にします。
+    pub fn to_text(&self) -> String {
+        let mut text = self.detection.to_text();
+        text.push_str(&format!(
+            concat!(
+                "input_channel={}\n",
+                "history_memory_limit={}\n",
+                "auto_stop_minutes={}\n",
+                "use_drift_alarm={}\n",
+                "drift_alarm_threshold={}\n",
+                "drift_alarm_duration={}\n",
+                "use_drift_alarm_click={}\n",
+                "note_naming={}\n",
+                "written_transposition={}\n",
+                "use_fixed_note_length={}\n",
+                "fixed_note_length={}\n",
+                "release_delay={}\n",
+                "note_refresh_interval={}\n",
+                "max_note_duration={}\n",
+                "use_legato={}\n",
+                "min_note_frames={}\n",
+                "min_midi_confidence={}\n",
+                "use_onset_retrigger={}\n",
+                "median_filter_size={}\n",
+                "midi_transposition={}\n",
+                "octave_doubling={}\n",
+                "octave_doubling_velocity={}\n",
+                "envelope_control={}\n",
+            ),
+            self.input_channel,
+            self.history_memory_limit,
+            self.auto_stop_minutes,
+            self.use_drift_alarm,
+            self.drift_alarm_threshold,
+            self.drift_alarm_duration,
+            self.use_drift_alarm_click,
+            self.note_naming,
+            self.written_transposition,
+            self.use_fixed_note_length,
+            self.fixed_note_length,
+            self.release_delay,
+            self.note_refresh_interval,
+            self.max_note_duration,
+            self.use_legato,
+            self.min_note_frames,
+            self.min_midi_confidence,
+            self.use_onset_retrigger,
+            self.median_filter_size,
+            self.midi_transposition,
+            self.octave_doubling,
+            self.octave_doubling_velocity,
+            self.envelope_control,
+        ));
+        text
+    }
+
+    /// `to_text`で作った文字列の一行を読み込み、設定の値を書き換えます。
+    /// 知らない名前や読み込めない値の行は無視します。
+    pub fn apply_line(&mut self, line: &str) {
+        fn parse<T: std::str::FromStr>(value: &str, target: &mut T) {
+            if let Ok(value) = value.trim().parse() {
+                *target = value;
+            };
+        }
+
+        let Some((name, value)) = line.split_once('=') else {
+            return;
+        };
+
+        // 数値の値は、範囲の外なら範囲に丸め込む。
+        let clamped;
+        let value = match params::APP
+            .into_iter()
+            .find(|param| param.name == name.trim())
+        {
+            Some(param) => match value.trim().parse() {
+                Ok(number) => {
+                    clamped = param.clamp(number).to_string();
+                    clamped.as_str()
+                }
+                Err(_) => return,
+            },
+            None => value,
+        };
+
+        match name.trim() {
+            "input_channel" => parse(value, &mut self.input_channel),
+            "history_memory_limit" => parse(value, &mut self.history_memory_limit),
+            "auto_stop_minutes" => parse(value, &mut self.auto_stop_minutes),
+            "use_drift_alarm" => parse(value, &mut self.use_drift_alarm),
+            "drift_alarm_threshold" => parse(value, &mut self.drift_alarm_threshold),
+            "drift_alarm_duration" => parse(value, &mut self.drift_alarm_duration),
+            "use_drift_alarm_click" => parse(value, &mut self.use_drift_alarm_click),
+            "note_naming" => parse(value, &mut self.note_naming),
+            "written_transposition" => parse(value, &mut self.written_transposition),
+            "use_fixed_note_length" => parse(value, &mut self.use_fixed_note_length),
+            "fixed_note_length" => parse(value, &mut self.fixed_note_length),
+            "release_delay" => parse(value, &mut self.release_delay),
+            "note_refresh_interval" => parse(value, &mut self.note_refresh_interval),
+            "max_note_duration" => parse(value, &mut self.max_note_duration),
+            "use_legato" => parse(value, &mut self.use_legato),
+            "min_note_frames" => parse(value, &mut self.min_note_frames),
+            "min_midi_confidence" => parse(value, &mut self.min_midi_confidence),
+            "use_onset_retrigger" => parse(value, &mut self.use_onset_retrigger),
+            "median_filter_size" => parse(value, &mut self.median_filter_size),
+            "midi_transposition" => parse(value, &mut self.midi_transposition),
+            "octave_doubling" => parse(value, &mut self.octave_doubling),
+            "octave_doubling_velocity" => parse(value, &mut self.octave_doubling_velocity),
+            "envelope_control" => parse(value, &mut self.envelope_control),
+            // 音階検出の設定
+            _ => self.detection.apply_line(line),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        let mut snapshot = AppConfigSnapshot::default();
+        snapshot.apply_line("min_volume=-40");
+        snapshot.apply_line("midi_transposition=5");
+        snapshot.apply_line("use_legato=true");
+        assert_eq!(snapshot.detection.min_volume, -40);
+        assert_eq!(snapshot.midi_transposition, 5);
+        assert!(snapshot.use_legato);
+
+        let mut restored = AppConfigSnapshot::default();
+        for line in snapshot.to_text().lines() {
+            restored.apply_line(line);
+        }
+        assert_eq!(restored, snapshot);
+    }
+
+    #[test]
+    fn apply_line_clamps_app_values() {
+        let mut snapshot = AppConfigSnapshot::default();
+        snapshot.apply_line("midi_transposition=100");
+        assert_eq!(snapshot.midi_transposition, params::MIDI_TRANSPOSITION.max);
+        snapshot.apply_line("median_filter_size=abc");
+        assert_eq!(
+            snapshot.median_filter_size,
+            params::MEDIAN_FILTER_SIZE.default as u32
+        );
+    }
+
+    #[test]
+    fn restore_shares_detection_settings() {
+        let detection = Arc::new(Config::default());
+        let config = AppConfig::new(Arc::clone(&detection));
+
+        let mut snapshot = config.snapshot();
+        snapshot.apply_line("max_note=60");
+        snapshot.apply_line("release_delay=300");
+        config.restore(&snapshot);
+        assert_eq!(detection.max_note.load(SeqCst), 60);
+        assert_eq!(config.release_delay.load(SeqCst), 300);
+    }
+}
@@ -0,0 +1,75 @@
+//! 伸ばしている音の音程が、弾き始めからずれていくのを見つけるためのモジュールです。
+//! 合唱や弦楽器で、長い音を保つ練習に使うためのものです。
+
+use std::time::{Duration, Instant};
+
+use crate::sys::Note;
+
+/// 弾き始めの音程として、セントの平均を取る検出回数
+const REFERENCE_FRAMES: u32 = 5;
+
+/// 伸ばしている音の音程のずれを見つけるための構造体です。
+#[derive(Default)]
+pub struct DriftDetector {
+    /// 伸ばしている音（MIDIの番号）
+    number: Option<u8>,
+    /// 弾き始めの音程を求めるために集めているセントの合計と検出回数
+    reference_sum: f32,
+    reference_frames: u32,
+    /// 弾き始めの音程（セント）
+    reference: Option<f32>,
+    /// ずれが閾値を超え始めた時刻
+    drifted_since: Option<Instant>,
+    /// 今のずれについて、既に知らせたかどうか
+    is_alarmed: bool,
+}
+
+impl DriftDetector {
+    /// 伸ばしている音を忘れます。
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 一番目に検出した音を受け取ります。
+    /// 弾き始めの音程から`threshold`セントを超えるずれが`duration`続いたら、その時のずれ（セント）を返します。
+    /// 一度知らせたら、ずれが閾値の内側に戻るか別の音になるまでは知らせません。
+    pub fn process(
+        &mut self,
+        note: Option<&Note>,
+        threshold: f32,
+        duration: Duration,
+    ) -> Option<f32> {
+        let Some(note) = note else {
+            self.reset();
+            return None;
+        };
+        if self.number != Some(note.number) {
+            self.reset();
+            self.number = Some(note.number);
+        };
+
+        // 弾き始めの音程を求める。
+        let Some(reference) = self.reference else {
+            self.reference_sum += note.cents;
+            self.reference_frames += 1;
+            if self.reference_frames >= REFERENCE_FRAMES {
+                self.reference = Some(self.reference_sum / self.reference_frames as f32);
+            };
+            return None;
+        };
+
+        let drift = note.cents - reference;
+        if drift.abs() <= threshold {
+            self.drifted_since = None;
+            self.is_alarmed = false;
+            return None;
+        };
+
+        let drifted_since = *self.drifted_since.get_or_insert_with(Instant::now);
+        if self.is_alarmed || drifted_since.elapsed() < duration {
+            return None;
+        };
+        self.is_alarmed = true;
+        Some(drift)
+    }
+}
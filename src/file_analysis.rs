@@ -0,0 +1,400 @@
+//! 音声ファイル（WAV/FLAC）を解析して、検出した音階の並びを作るためのモジュールです。
+
+use std::{fs, path::Path};
+
+use crate::{
+    config::AppConfigSnapshot,
+    misc::prelude::*,
+    sys::{Note, Synthesizer},
+};
+
+/// 音声ファイルを解析するためのコマンドライン引数
+pub const FLAG: &str = "--analyze";
+/// フォルダの中の音声ファイルをまとめてMIDIファイルに変換するためのコマンドライン引数
+pub const CONVERT_FLAG: &str = "--convert";
+/// 設定の値を変えるためのコマンドライン引数
+const SET_FLAG: &str = "--set";
+/// MIDIファイルの四分音符あたりのティック数
+const TICKS_PER_QUARTER_NOTE: u16 = 480;
+/// MIDIファイルのテンポ（四分音符あたりのマイクロ秒、120BPM）
+const MICROSECONDS_PER_QUARTER_NOTE: u32 = 500_000;
+/// 一度に`Synthesizer`に渡すフレーム数
+/// 録音の時のコールバックで受け取る長さと同じくらいにする。
+const CHUNK_SIZE: usize = 1024;
+
+/// 読み込んだ音声ファイルです。複数のチャンネルがある場合はモノラルにしています。
+pub struct DecodedAudio {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+/// 複数のチャンネルの音声データを平均してモノラルにします。
+fn mix_down(samples: impl Iterator<Item = f32>, channels: usize) -> Vec<f32> {
+    let samples: Vec<f32> = samples.collect();
+    samples
+        .chunks(channels.max(1))
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+/// WAVまたはFLACのファイルを読み込みます。形式は拡張子で判断します。
+pub fn decode(path: &Path) -> Result<DecodedAudio, String> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+
+    match extension.as_deref() {
+        Some("wav") => {
+            let mut reader =
+                hound::WavReader::open(path).context("WAVファイルを開けませんでした。")?;
+            let spec = reader.spec();
+
+            let samples = match spec.sample_format {
+                hound::SampleFormat::Float => reader
+                    .samples::<f32>()
+                    .collect::<Result<Vec<_>, _>>()
+                    .context("WAVファイルの読み込みに失敗しました。")?,
+                hound::SampleFormat::Int => {
+                    let scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
+                    reader
+                        .samples::<i32>()
+                        .map(|sample| sample.map(|sample| sample as f32 / scale))
+                        .collect::<Result<Vec<_>, _>>()
+                        .context("WAVファイルの読み込みに失敗しました。")?
+                }
+            };
+
+            Ok(DecodedAudio {
+                sample_rate: spec.sample_rate,
+                samples: mix_down(samples.into_iter(), spec.channels as _),
+            })
+        }
+        Some("flac") => {
+            let mut reader =
+                claxon::FlacReader::open(path).context("FLACファイルを開けませんでした。")?;
+            let info = reader.streaminfo();
+            let scale = (1u64 << (info.bits_per_sample - 1)) as f32;
+
+            let samples = reader
+                .samples()
+                .map(|sample| sample.map(|sample| sample as f32 / scale))
+                .collect::<Result<Vec<_>, _>>()
+                .context("FLACファイルの読み込みに失敗しました。")?;
+
+            Ok(DecodedAudio {
+                sample_rate: info.sample_rate,
+                samples: mix_down(samples.into_iter(), info.channels as _),
+            })
+        }
+        _ => Err("WAVまたはFLACのファイルを指定してください。".to_string()),
+    }
+}
+
+/// 音声ファイルから検出した一つの音です。
+pub struct DetectedNote {
+    /// 始まりの時間（秒）
+    pub start: f32,
+    /// 終わりの時間（秒）
+    pub end: f32,
+    /// 続けて検出された回数
+    pub frames: u32,
+    pub note: Note,
+}
+
+/// 読み込んだ音声データを解析し、検出した音の並びを返します。
+/// 同じ音階が続けて検出された間を一つの音とします。
+/// `min_note_frames`の回数より短い間しか検出されなかった音と、信頼度が`min_midi_confidence`より低い間は除きます。
+pub fn analyze(audio: &DecodedAudio, snapshot: &AppConfigSnapshot) -> Vec<DetectedNote> {
+    let mut synthesizer = Synthesizer::builder(audio.sample_rate as _)
+        .settings(snapshot)
+        .build();
+
+    let mut detected_notes: Vec<DetectedNote> = Vec::new();
+    let mut is_sounding = false;
+
+    for (index, chunk) in audio.samples.chunks(CHUNK_SIZE).enumerate() {
+        let start = (index * CHUNK_SIZE) as f32 / audio.sample_rate as f32;
+        let end = start + chunk.len() as f32 / audio.sample_rate as f32;
+
+        let result = synthesizer
+            .synthe::<1>(chunk)
+            .filter(|[note]| note.confidence >= snapshot.min_midi_confidence as f32);
+        if let Some([note]) = result {
+            match detected_notes.last_mut() {
+                Some(last) if is_sounding && last.note.number == note.number => {
+                    last.end = end;
+                    last.frames += 1;
+                }
+                _ => detected_notes.push(DetectedNote {
+                    start,
+                    end,
+                    frames: 1,
+                    note,
+                }),
+            };
+            is_sounding = true;
+        } else {
+            is_sounding = false;
+        };
+    }
+
+    detected_notes.retain(|detected_note| detected_note.frames >= snapshot.min_note_frames);
+    detected_notes
+}
+
+/// MIDIファイルの可変長の数値を書き込みます。
+fn write_variable_length(data: &mut Vec<u8>, mut value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    value >>= 7;
+    while value > 0 {
+        bytes.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    data.extend(bytes.into_iter().rev());
+}
+
+/// 検出した音の並びをMIDIファイル（フォーマット0）のデータにします。
+/// MIDIの移調の設定がされている場合は移調し、オクターブ重ねの設定がされている場合は、その音も入れます。
+pub fn to_midi(detected_notes: &[DetectedNote], snapshot: &AppConfigSnapshot) -> Vec<u8> {
+    let ticks_per_second =
+        TICKS_PER_QUARTER_NOTE as f32 * 1_000_000. / MICROSECONDS_PER_QUARTER_NOTE as f32;
+    let doubled_velocity =
+        (crate::midi::VELOCITY as u32 * snapshot.octave_doubling_velocity as u32 / 100) as u8;
+
+    // 時間（ティック）とMIDIのメッセージを並べる。
+    let mut events = Vec::new();
+    for detected_note in detected_notes {
+        let start = (detected_note.start * ticks_per_second) as u32;
+        let end = (detected_note.end * ticks_per_second) as u32;
+        let Some(number) =
+            crate::midi::transposed_key(detected_note.note.number, snapshot.midi_transposition)
+        else {
+            continue;
+        };
+
+        events.push((
+            start,
+            [crate::midi::NOTE_ON_MSG, number, crate::midi::VELOCITY],
+        ));
+        events.push((
+            end,
+            [crate::midi::NOTE_OFF_MSG, number, crate::midi::VELOCITY],
+        ));
+
+        if let Some(doubled_number) = crate::midi::doubled_key(number, snapshot.octave_doubling) {
+            events.push((
+                start,
+                [crate::midi::NOTE_ON_MSG, doubled_number, doubled_velocity],
+            ));
+            events.push((
+                end,
+                [crate::midi::NOTE_OFF_MSG, doubled_number, doubled_velocity],
+            ));
+        };
+    }
+    // 同じ時間の場合は、ノートオフを先にする。
+    events.sort_by_key(|(tick, message)| (*tick, message[0] != crate::midi::NOTE_OFF_MSG));
+
+    // トラックのデータを作る。
+    let mut track = Vec::new();
+    write_variable_length(&mut track, 0);
+    track.extend([0xFF, 0x51, 0x03]);
+    track.extend(&MICROSECONDS_PER_QUARTER_NOTE.to_be_bytes()[1..]);
+
+    let mut before_tick = 0;
+    for (tick, message) in events {
+        write_variable_length(&mut track, tick - before_tick);
+        track.extend(message);
+        before_tick = tick;
+    }
+    write_variable_length(&mut track, 0);
+    track.extend([0xFF, 0x2F, 0x00]);
+
+    // ヘッダーを付ける。
+    let mut data = b"MThd".to_vec();
+    data.extend(6u32.to_be_bytes());
+    data.extend(0u16.to_be_bytes());
+    data.extend(1u16.to_be_bytes());
+    data.extend(TICKS_PER_QUARTER_NOTE.to_be_bytes());
+    data.extend(b"MTrk");
+    data.extend((track.len() as u32).to_be_bytes());
+    data.extend(track);
+
+    data
+}
+
+/// 検出した音の並びをCSVの文字列にします。
+pub fn to_csv(detected_notes: &[DetectedNote]) -> String {
+    let mut csv = "開始（秒）,終了（秒）,MIDIの番号,音階\n".to_string();
+
+    for detected_note in detected_notes {
+        csv.push_str(&format!(
+            "{:.3},{:.3},{},{}\n",
+            detected_note.start,
+            detected_note.end,
+            detected_note.note.number,
+            detected_note.note.get_name()
+        ));
+    }
+
+    csv
+}
+
+/// コマンドライン引数で指定された`--set <名前>=<値>`を反映した設定を作ります。
+fn snapshot_from_args() -> AppConfigSnapshot {
+    let mut snapshot = AppConfigSnapshot::default();
+    let mut args = std::env::args();
+
+    while let Some(arg) = args.next() {
+        if arg == SET_FLAG {
+            if let Some(setting) = args.next() {
+                snapshot.apply_line(&setting);
+            };
+        };
+    }
+
+    snapshot
+}
+
+/// コマンドライン引数で指定された`flag`の後の値を取得します。
+fn values_from_args(flag: &str, count: usize) -> Option<Vec<String>> {
+    let values: Vec<String> = std::env::args()
+        .skip_while(|arg| arg != flag)
+        .skip(1)
+        .take(count)
+        .collect();
+    (values.len() == count).then_some(values)
+}
+
+/// コマンドライン引数で解析するファイルが指定されている場合、そのパスを取得します。
+pub fn path_from_args() -> Option<String> {
+    values_from_args(FLAG, 1).map(|mut values| values.remove(0))
+}
+
+/// コマンドライン引数で変換する入力と出力のフォルダが指定されている場合、そのパスを取得します。
+pub fn convert_dirs_from_args() -> Option<(String, String)> {
+    values_from_args(CONVERT_FLAG, 2).map(|mut values| (values.remove(0), values.remove(0)))
+}
+
+/// コマンドラインから音声ファイルを解析し、結果をCSVで標準出力に出します。
+pub fn run_cli(path: &str) {
+    match decode(Path::new(path)) {
+        Ok(audio) => print!("{}", to_csv(&analyze(&audio, &snapshot_from_args()))),
+        Err(error) => {
+            tracing::error!("{error}");
+            std::process::exit(1);
+        }
+    };
+}
+
+/// フォルダの中のWAVとFLACのファイルを、まとめてMIDIファイルに変換します。
+/// 変換に失敗したファイルがあっても、残りのファイルの変換は続けます。
+pub fn run_batch(input_dir: &str, output_dir: &str) {
+    let snapshot = snapshot_from_args();
+    let entries = fs::read_dir(input_dir)
+        .and_then(|entries| fs::create_dir_all(output_dir).map(|_| entries))
+        .unwrap_or_else(|error| {
+            tracing::error!("フォルダを開けませんでした。\n{error}");
+            std::process::exit(1);
+        });
+
+    let mut has_error = false;
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if !path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| ["wav", "flac"].contains(&extension.to_lowercase().as_str()))
+        {
+            continue;
+        };
+
+        let output_path = Path::new(output_dir)
+            .join(path.file_stem().unwrap_or_default())
+            .with_extension("mid");
+        let result = decode(&path).and_then(|audio| {
+            fs::write(
+                &output_path,
+                to_midi(&analyze(&audio, &snapshot), &snapshot),
+            )
+            .context("MIDIファイルの保存に失敗しました。")
+        });
+
+        match result {
+            Ok(()) => tracing::info!("{} → {}", path.display(), output_path.display()),
+            Err(error) => {
+                tracing::error!("{}：{}", path.display(), error);
+                has_error = true;
+            }
+        };
+    }
+
+    if has_error {
+        std::process::exit(1);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable_length(value: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_variable_length(&mut data, value);
+        data
+    }
+
+    fn detected_note(number: u8, start: f32, end: f32) -> DetectedNote {
+        DetectedNote {
+            start,
+            end,
+            frames: 1,
+            note: Note {
+                number,
+                ..Note::NULL
+            },
+        }
+    }
+
+    #[test]
+    fn variable_length_bytes() {
+        assert_eq!(variable_length(0), [0x00]);
+        assert_eq!(variable_length(0x7F), [0x7F]);
+        assert_eq!(variable_length(0x80), [0x81, 0x00]);
+        assert_eq!(variable_length(0x0FFF_FFFF), [0xFF, 0xFF, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn midi_header_and_event_order() {
+        // 後の音を先に渡しても、時間順に並び、同じ時間ではノートオフが先になる。
+        let data = to_midi(
+            &[detected_note(62, 0.5, 1.), detected_note(60, 0., 0.5)],
+            &AppConfigSnapshot::default(),
+        );
+
+        assert_eq!(&data[..4], b"MThd");
+        assert_eq!(&data[4..8], 6u32.to_be_bytes());
+        assert_eq!(&data[8..10], 0u16.to_be_bytes());
+        assert_eq!(&data[10..12], 1u16.to_be_bytes());
+        assert_eq!(&data[12..14], TICKS_PER_QUARTER_NOTE.to_be_bytes());
+        assert_eq!(&data[14..18], b"MTrk");
+
+        let track = &data[22..];
+        assert_eq!(&data[18..22], (track.len() as u32).to_be_bytes());
+        #[rustfmt::skip]
+        assert_eq!(
+            track,
+            [
+                // テンポ（500000マイクロ秒）
+                0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20,
+                0x00, 0x90, 60, 0x64,
+                // 480ティック後
+                0x83, 0x60, 0x80, 60, 0x64,
+                0x00, 0x90, 62, 0x64,
+                0x83, 0x60, 0x80, 62, 0x64,
+                // トラックの終わり
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        );
+    }
+}
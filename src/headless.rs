@@ -0,0 +1,354 @@
+//! UIを使わずに、コマンドラインから音階検出を行うためのモジュールです。
+//! サーバーで動かしたり、スクリプトから使ったりするためのものです。
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering::SeqCst},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use dialog_unwrapper::rfd::{MessageButtons, MessageDialog, MessageLevel};
+use midir::MidiOutput;
+
+use crate::{
+    bridge, locale::tr, logic, midi::MidiManager, presets, script::ScriptHook, APPLICATION_NAME,
+    CPU_SLEEP_INTERVAL,
+};
+
+/// UIを使わずに起動するためのコマンドライン引数
+pub const FLAG: &str = "--headless";
+
+const USAGE: &str = "使い方：a_synthe --headless [オプション]
+  --list                  入力デバイスとMIDIの出力先の一覧を表示して終了する
+  --device <名前>         使う入力デバイスの名前（省略すると既定のデバイス）
+  --backend <名前>        使う音声のバックエンドの名前（--listで表示される名前、省略するとUIで選んだもの）
+  --midi-output <番号>    MIDIの出力先の番号（--listで表示される番号、省略するとMIDIを送らない）
+  --set <名前>=<値>       設定の値を変える（プリセットのファイルと同じ名前、何回でも指定できる）
+  --duration <秒>         指定した秒数が経ったら終了する
+  --seed <数値>           セッションの乱数のシード
+  --bridge                DAWから使うためのブリッジモードにする（--headlessは省略できる）
+  --daw <名前>            ブリッジモードで接続するDAW（generic、ableton、logic、bitwig、reaper、cubase）
+  --control-port <番号>   ブリッジモードの制御用のソケットのポート番号（省略すると7341）";
+
+/// コマンドライン引数で指定された内容です。
+#[derive(Default)]
+struct Options {
+    list: bool,
+    device: Option<String>,
+    backend: Option<String>,
+    midi_output: Option<usize>,
+    settings: Vec<String>,
+    duration: Option<Duration>,
+    bridge: bool,
+    daw: Option<String>,
+    control_port: Option<u16>,
+    /// MIDIの出力先の名前（UIを使えない時に、前回の出力先を使うためのもの）
+    midi_output_name: Option<String>,
+    /// `true`になったら終了するフラグ
+    stop_flag: Option<Arc<AtomicBool>>,
+}
+
+/// エラーを表示して終了します。
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    std::process::exit(2);
+}
+
+impl Options {
+    /// コマンドライン引数を読み込みます。
+    fn parse() -> Self {
+        let mut options = Self::default();
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .unwrap_or_else(|| exit_with_error(&format!("{arg}の値が指定されていません。")))
+            };
+
+            match arg.as_str() {
+                FLAG => (),
+                bridge::FLAG => options.bridge = true,
+                "--daw" => options.daw = Some(value()),
+                "--control-port" => {
+                    options.control_port = Some(value().parse().unwrap_or_else(|_| {
+                        exit_with_error("ポート番号は0から65535の整数で指定してください。")
+                    }))
+                }
+                "--list" => options.list = true,
+                "--device" => options.device = Some(value()),
+                "--backend" => options.backend = Some(value()),
+                "--midi-output" => {
+                    options.midi_output = Some(value().parse().unwrap_or_else(|_| {
+                        exit_with_error("MIDIの出力先の番号は整数で指定してください。")
+                    }))
+                }
+                "--set" => options.settings.push(value()),
+                "--duration" => {
+                    options.duration =
+                        Some(Duration::from_secs_f32(value().parse().unwrap_or_else(
+                            |_| exit_with_error("秒数は数値で指定してください。"),
+                        )))
+                }
+                // シードは`misc::session`で読み込む。
+                "--seed" => {
+                    value();
+                }
+                _ if arg.starts_with("--seed=") => (),
+                _ => exit_with_error(&format!("{arg}は知らないオプションです。")),
+            };
+        }
+
+        options
+    }
+}
+
+/// 検出した音階を一行で表示します。
+fn print_note(note: Option<&crate::sys::Note>) {
+    if let Some(note) = note {
+        println!(
+            "{} ({}) {:.1}Hz {:+.0}c",
+            note.get_name(),
+            note.number,
+            note.frequency,
+            note.cents
+        );
+    } else {
+        println!("-");
+    };
+}
+
+/// UIを使わずに音階検出を行います。
+pub fn run() {
+    detect(Options::parse());
+}
+
+/// UIを初期化できなかった場合に、前回の設定とMIDIの出力先で音階検出を行います。
+/// コンソールが無い環境もあるので、終了するためのダイアログを出しておきます。
+pub fn run_fallback() {
+    let mut options = Options {
+        settings: presets::load_last_settings()
+            .map(|snapshot| snapshot.to_text().lines().map(str::to_string).collect())
+            .unwrap_or_default(),
+        midi_output_name: presets::load_last_midi_output(),
+        ..Default::default()
+    };
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    std::thread::spawn({
+        let stop_flag = Arc::clone(&stop_flag);
+        move || {
+            MessageDialog::new()
+                .set_title(APPLICATION_NAME)
+                .set_description(tr("UIを使わずに動いています。「OK」を押すと終了します。"))
+                .set_level(MessageLevel::Info)
+                .set_buttons(MessageButtons::Ok)
+                .show();
+            stop_flag.store(true, SeqCst);
+        }
+    });
+    options.stop_flag = Some(stop_flag);
+
+    detect(options);
+}
+
+/// 指定された内容で音階検出を行います。
+fn detect(options: Options) {
+    tracing::info!(
+        "{} {} by tasuren (headless)",
+        APPLICATION_NAME,
+        env!("CARGO_PKG_VERSION")
+    );
+    tracing::info!("Session seed: {}", crate::misc::session::seed());
+
+    let host = crate::misc::audio_host(
+        options
+            .backend
+            .clone()
+            .or_else(presets::load_audio_backend)
+            .as_deref(),
+    );
+    tracing::info!("Audio backend: {}", host.id().name());
+    let midi_output = || {
+        MidiOutput::new(APPLICATION_NAME).unwrap_or_else(|error| {
+            exit_with_error(&format!("MIDI出力の準備に失敗しました。\n{error}"))
+        })
+    };
+
+    if options.list {
+        let midi_manager = MidiManager::new(midi_output(), Arc::new(Default::default()));
+
+        println!("音声のバックエンド：");
+        for name in crate::misc::audio_host_names() {
+            println!("  {name}");
+        }
+        println!("入力デバイス：");
+        for device in host.input_devices().into_iter().flatten() {
+            println!("  {}", device.name().unwrap_or_default());
+        }
+        println!("MIDIの出力先：");
+        for (index, name) in midi_manager.port_names().into_iter().enumerate() {
+            println!("  {}: {}", index + 1, name);
+        }
+        println!("ブリッジモードで使えるDAW：");
+        for daw in bridge::DAWS.iter() {
+            println!("  {}: {}", daw.id, daw.name);
+        }
+        return;
+    };
+
+    let daw = options.bridge.then(|| {
+        let id = options.daw.as_deref().unwrap_or(bridge::DAWS[0].id);
+        bridge::find_daw(id).unwrap_or_else(|| exit_with_error(&format!("{id}は知らないDAWです。")))
+    });
+
+    // 入力デバイスを探す。
+    let input_device = if let Some(name) = &options.device {
+        host.input_devices()
+            .into_iter()
+            .flatten()
+            .find(|device| device.name().is_ok_and(|device_name| &device_name == name))
+            .unwrap_or_else(|| {
+                exit_with_error(&format!("入力デバイス「{name}」が見つかりません。"))
+            })
+    } else {
+        host.default_input_device()
+            .unwrap_or_else(|| exit_with_error("有効なデバイスがありません。"))
+    };
+
+    let (_input_stream, config, stream_stats, mut analyses, mut stream_errors) =
+        crate::start_input_stream(&input_device);
+
+    // 設定を反映する。
+    let mut snapshot = config.snapshot();
+    for setting in options.settings.iter() {
+        if !setting.contains('=') {
+            exit_with_error(&format!("{setting}は<名前>=<値>の形式ではありません。"));
+        };
+        if let Err(error) = crate::params::validate_line(setting) {
+            exit_with_error(&error);
+        };
+        snapshot.apply_line(setting);
+    }
+    config.restore(&snapshot);
+
+    // MIDIの出力先を設定する。
+    let mut midi_manager = MidiManager::new(midi_output(), Arc::clone(&config));
+    if let Some(index) = options.midi_output {
+        midi_manager = midi_manager.set_midi_output(index);
+        if !midi_manager.is_avaliable() {
+            exit_with_error(&format!("MIDIの出力先{index}に接続できませんでした。"));
+        };
+    } else if let Some(name) = &options.midi_output_name {
+        match midi_manager
+            .port_names()
+            .iter()
+            .position(|port_name| port_name == name)
+        {
+            Some(index) => midi_manager = midi_manager.set_midi_output(index + 1),
+            None => tracing::warn!("MIDIの出力先「{name}」が見つからないため、MIDIを送りません。"),
+        };
+    } else if let Some(daw) = daw {
+        midi_manager = bridge::connect(midi_manager, daw);
+        if !midi_manager.is_avaliable() {
+            exit_with_error(&format!(
+                "MIDIの出力先「{}」を用意できませんでした。\n仮想ポートが使えない環境（Windows）では、loopMIDI等で同じ名前のポートを作ってください。",
+                daw.port_name
+            ));
+        };
+    };
+
+    // ブリッジモードの場合は、制御用のソケットを用意して、DAWでの設定の手順を表示する。
+    let mut control_server = daw.map(|daw| {
+        let port = options.control_port.unwrap_or(bridge::DEFAULT_CONTROL_PORT);
+        let control_server = bridge::ControlServer::bind(port).unwrap_or_else(|error| {
+            exit_with_error(&format!(
+                "制御用のソケットを用意できませんでした。\n{error}"
+            ))
+        });
+
+        eprintln!("MIDIの出力先：{}", daw.port_name);
+        eprintln!("制御用のソケット：127.0.0.1:{port}");
+        eprintln!("{}での設定：", daw.name);
+        for (index, step) in daw.steps.iter().enumerate() {
+            eprintln!("  {}. {step}", index + 1);
+        }
+        control_server
+    });
+
+    // スクリプトを読み込む。
+    let mut script_hook = ScriptHook::new();
+    tracing::info!("{}", script_hook.reload());
+
+    // 検出を行う。
+    let started_at = Instant::now();
+    let mut before_number = None;
+    tracing::info!("Started");
+
+    loop {
+        if options
+            .duration
+            .is_some_and(|duration| started_at.elapsed() >= duration)
+        {
+            break;
+        };
+        if options
+            .stop_flag
+            .as_ref()
+            .is_some_and(|stop_flag| stop_flag.load(SeqCst))
+        {
+            break;
+        };
+
+        if let Some(control_server) = control_server.as_mut() {
+            if !control_server.poll(&config, &mut midi_manager) {
+                break;
+            };
+        };
+
+        logic::tick(&mut midi_manager, &config);
+        crate::report_stream_errors(&mut stream_errors, &stream_stats);
+
+        while let Some(analysis) = analyses.pop() {
+            if analysis.is_onset {
+                logic::consume_onset();
+            };
+
+            // 一番目の音階が変わった時だけ表示する。
+            let notes = analysis.notes;
+            let note = notes.as_ref().map(|notes| &notes[0]);
+            let number = note.map(|note| note.number);
+            if number != before_number {
+                before_number = number;
+                print_note(note);
+                if let Some(control_server) = control_server.as_mut() {
+                    control_server.broadcast(&bridge::note_message(note));
+                };
+            };
+
+            let notes = match script_hook.process(notes.clone(), &mut midi_manager) {
+                Ok(notes) => notes,
+                Err(error) => {
+                    tracing::warn!("{error}");
+                    notes
+                }
+            };
+            logic::consume_notes(&mut midi_manager, &config, notes);
+
+            logic::consume_envelope(&mut midi_manager, &config, analysis.envelope);
+            if let Some(Some(point_times)) = analysis.point_times_limited {
+                tracing::warn!(
+                    "FFTのサイズが上限を超えるため、ポイント数の規模を{point_times}に制限しています。"
+                );
+            };
+        }
+
+        std::thread::sleep(CPU_SLEEP_INTERVAL);
+    }
+
+    midi_manager.all_notes_off();
+    tracing::info!("Stopped");
+}
@@ -0,0 +1,169 @@
+//! 表示等のために残しておく履歴を、使うメモリの量を決めて持つためのモジュールです。
+//! 長い時間使っても履歴でメモリが増え続けないように、上限を超えたら古いものから捨てます。
+
+use std::{
+    cell::Cell,
+    collections::{vec_deque, VecDeque},
+    mem::size_of,
+    rc::Rc,
+    sync::{atomic::Ordering::SeqCst, Arc},
+    time::{Duration, Instant},
+};
+
+use crate::config::AppConfig;
+
+/// 全体の上限のうち、スペクトルの履歴（スペクトログラム）に割り振る割合（%）
+pub const SPECTRUM_SHARE: usize = 80;
+/// 全体の上限のうち、音程の履歴（ビブラートの検出）に割り振る割合（%）
+pub const PITCH_SHARE: usize = 10;
+/// 全体の上限のうち、スクリプトのメッセージの履歴に割り振る割合（%）
+pub const LOG_SHARE: usize = 10;
+
+/// 履歴に残す値が、それ自体の大きさの他に確保しているメモリの量を求めるためのトレイトです。
+pub trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+impl HeapSize for f32 {
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+impl HeapSize for Vec<f32> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<f32>()
+    }
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+/// 全ての履歴で使えるメモリの上限と、使っている量を共有するための構造体です。
+#[derive(Clone)]
+pub struct MemoryBudget {
+    config: Arc<AppConfig>,
+    usage: Rc<Cell<usize>>,
+}
+
+impl MemoryBudget {
+    pub fn new(config: Arc<AppConfig>) -> Self {
+        Self {
+            config,
+            usage: Rc::new(Cell::new(0)),
+        }
+    }
+
+    /// 全ての履歴で使えるメモリの量（バイト）を取得します。
+    pub fn limit(&self) -> usize {
+        self.config.history_memory_limit.load(SeqCst) as usize * 1024 * 1024
+    }
+
+    /// 全ての履歴で使っているメモリの量（バイト）を取得します。
+    pub fn usage(&self) -> usize {
+        self.usage.get()
+    }
+}
+
+/// 時刻と値の組を、古い順に並べて残しておくリングバッファです。
+/// 残す期間を過ぎたものと、割り振られたメモリの量を超えた分は、古いものから捨てます。（一番新しいものは捨てません。）
+pub struct History<T> {
+    entries: VecDeque<(Instant, T)>,
+    /// 残す期間（`None`なら期間では捨てない）
+    max_age: Option<Duration>,
+    /// 全体の上限のうち、この履歴に割り振る割合（%）
+    share: usize,
+    /// この履歴で使っているメモリの量（バイト）
+    size: usize,
+    budget: MemoryBudget,
+}
+
+impl<T: HeapSize> History<T> {
+    pub fn new(budget: &MemoryBudget, share: usize, max_age: Option<Duration>) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_age,
+            share,
+            size: 0,
+            budget: budget.clone(),
+        }
+    }
+
+    /// 一つの値で使うメモリの量を求めます。
+    fn entry_size(value: &T) -> usize {
+        size_of::<(Instant, T)>() + value.heap_size()
+    }
+
+    /// 値を加えて、残す期間を過ぎたものと、メモリの量を超えた分を捨てます。
+    pub fn push(&mut self, time: Instant, value: T) {
+        self.resize(Self::entry_size(&value) as isize);
+        self.entries.push_back((time, value));
+
+        if let Some(max_age) = self.max_age {
+            while self
+                .entries
+                .front()
+                .is_some_and(|(front, _)| time.saturating_duration_since(*front) > max_age)
+            {
+                self.pop_front();
+            }
+        };
+
+        let limit = self.budget.limit() * self.share / 100;
+        while self.size > limit && self.entries.len() > 1 {
+            self.pop_front();
+        }
+    }
+
+    /// 一番古いものを捨てます。
+    fn pop_front(&mut self) {
+        if let Some((_, value)) = self.entries.pop_front() {
+            self.resize(-(Self::entry_size(&value) as isize));
+        };
+    }
+
+    /// 全て捨てます。
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.resize(-(self.size as isize));
+    }
+}
+
+impl<T> History<T> {
+    /// 使っているメモリの量を変えて、全体の量にも反映します。
+    fn resize(&mut self, difference: isize) {
+        self.size = self.size.saturating_add_signed(difference);
+        self.budget
+            .usage
+            .set(self.budget.usage.get().saturating_add_signed(difference));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&(Instant, T)> {
+        self.entries.get(index)
+    }
+
+    pub fn front(&self) -> Option<&(Instant, T)> {
+        self.entries.front()
+    }
+
+    pub fn back(&self) -> Option<&(Instant, T)> {
+        self.entries.back()
+    }
+
+    pub fn iter(&self) -> vec_deque::Iter<'_, (Instant, T)> {
+        self.entries.iter()
+    }
+}
+
+impl<T> Drop for History<T> {
+    fn drop(&mut self) {
+        self.resize(-(self.size as isize));
+    }
+}
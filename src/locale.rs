@@ -0,0 +1,455 @@
+//! UIに表示する文字列を翻訳するためのモジュールです。
+//! 文字列は日本語のものをそのまま鍵にして、言語ごとの表から探します。表にない文字列は日本語のまま表示します。
+//! 値を埋め込む文字列は、`{}`の位置に`tr_format`で引数を順番に埋め込みます。
+
+use std::{fmt::Display, fs, io, sync::OnceLock};
+
+use crate::misc::data_path;
+
+/// 選んだ言語を保存するファイルの名前
+const LANGUAGE_FILE_NAME: &str = "language.txt";
+
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// UIの言語です。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Language {
+    Japanese,
+    English,
+}
+
+impl Language {
+    pub const ALL: [Self; 2] = [Self::Japanese, Self::English];
+
+    /// メニューに表示する名前を取得します。どの言語でも読めるように、その言語自体で表します。
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Japanese => "日本語",
+            Self::English => "English",
+        }
+    }
+
+    /// 保存する時等に使う、言語のコードを取得します。
+    fn code(self) -> &'static str {
+        match self {
+            Self::Japanese => "ja",
+            Self::English => "en",
+        }
+    }
+
+    /// `ja_JP.UTF-8`や`en-US`のような言語のコードから取得します。
+    /// 翻訳がない言語の場合は`None`を返します。
+    fn from_code(code: &str) -> Option<Self> {
+        let code = code.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|language| code.starts_with(language.code()))
+    }
+
+    /// システムで設定されている言語を、優先する順に調べて、最初に翻訳がある言語を取得します。
+    /// （例えば「フランス語、日本語」の順に設定されていれば日本語にします。）
+    /// どれも翻訳がない場合は英語にし、システムの言語が分からない場合は日本語にします。
+    fn from_system() -> Self {
+        let mut codes = sys_locale::get_locales().peekable();
+        if codes.peek().is_none() {
+            return Self::Japanese;
+        };
+        codes
+            .find_map(|code| Self::from_code(&code))
+            .unwrap_or(Self::English)
+    }
+}
+
+/// 使う言語を取得します。
+/// メニューで選んだ言語が保存されていればそれを、なければシステムの言語を使います。
+pub fn language() -> Language {
+    *LANGUAGE.get_or_init(|| {
+        data_path(LANGUAGE_FILE_NAME)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|code| Language::from_code(&code))
+            .unwrap_or_else(Language::from_system)
+    })
+}
+
+/// 使う言語を保存します。UIは作り直さないので、次に起動した時から反映されます。
+pub fn save_language(language: Language) -> io::Result<()> {
+    let path = data_path(LANGUAGE_FILE_NAME).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            tr("保存先のフォルダが見つかりません。"),
+        )
+    })?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    };
+    fs::write(path, language.code())
+}
+
+/// 日本語の文字列を、使う言語の文字列にします。
+pub fn tr(text: &'static str) -> &'static str {
+    match language() {
+        Language::Japanese => text,
+        Language::English => english(text).unwrap_or(text),
+    }
+}
+
+/// `tr`で翻訳した文字列の`{}`の位置に、`args`を順番に埋め込みます。
+pub fn tr_format(text: &'static str, args: &[&dyn Display]) -> String {
+    let mut pieces = tr(text).split("{}");
+    let mut args = args.iter();
+    let mut result = pieces.next().unwrap_or_default().to_string();
+
+    for piece in pieces {
+        if let Some(arg) = args.next() {
+            result.push_str(&arg.to_string());
+        };
+        result.push_str(piece);
+    }
+
+    result
+}
+
+/// 英語の表です。
+fn english(text: &str) -> Option<&'static str> {
+    Some(match text {
+        // メニュー
+        "ファイル" => "File",
+        "音声ファイルを解析..." => "Analyze Audio File...",
+        "Scalaの音律ファイルを読み込む..." => "Load Scala Tuning File...",
+        "平均律に戻す" => "Reset to Equal Temperament",
+        "マイクの周波数特性を読み込む..." => "Load Microphone Response...",
+        "マイクの補正をやめる" => "Stop Microphone Compensation",
+        "終了" => "Quit",
+        "編集" => "Edit",
+        "変更を元に戻す" => "Undo",
+        "すべてリセット" => "Reset All",
+        "デフォルトに戻す" => "Reset to Default",
+        "表示" => "View",
+        "最も安定している音を一番目に表示" => "Show the Most Stable Note First",
+        "ヘルプ" => "Help",
+        "入力の状態" => "Input Status",
+        "練習のまとめ" => "Practice Summary",
+        "このアプリについて" => "About",
+        "ログのフォルダを開く" => "Open Log Folder",
+        "ログのフォルダを開けませんでした。\n{}" => "Could not open the log folder.\n{}",
+        "タイムライン（開発者向け）" => "Timeline (for Developers)",
+        "全ての出来事" => "All Events",
+        "操作" => "Command",
+        "デバイス" => "Device",
+
+        // 一列目
+        "窓関数" => "Window function",
+        "検出対象とする最低音量（dB）" => "Minimum volume to detect (dB)",
+        "最低音量" => "Minimum volume",
+        "音量をA特性で測る（聞こえ方に合わせる）" => "Measure volume with A-weighting (as heard)",
+        "解析するチャンネル（0で全てのチャンネルを平均する）" => {
+            "Channel to analyze (0 to average all channels)"
+        }
+        "解析するチャンネル" => "Channel to analyze",
+        "低い雑音を取り除く周波数（Hz、0で取り除かない）" => {
+            "High-pass cutoff (Hz, 0 to disable)"
+        }
+        "低い雑音を取り除く周波数" => "High-pass cutoff",
+        "A特性" => "A-weighting",
+        "音量を自動で調整する（AGC）" => "Adjust volume automatically (AGC)",
+        "自動音量調整" => "Automatic gain control",
+        "自動調整の目標の音量（dB）" => "AGC target volume (dB)",
+        "自動調整の目標の音量" => "AGC target volume",
+        "音量のヒステリシス（dB）" => "Volume hysteresis (dB)",
+        "音量のヒステリシス" => "Volume hysteresis",
+        "ゲートのアタック（ミリ秒）" => "Gate attack (ms)",
+        "ゲートのアタック" => "Gate attack",
+        "ゲートのホールド（ミリ秒）" => "Gate hold (ms)",
+        "ゲートのホールド" => "Gate hold",
+        "ゲートのリリース（ミリ秒）" => "Gate release (ms)",
+        "ゲートのリリース" => "Gate release",
+        "音階調節" => "Pitch adjustment",
+        "基準のラ（A4）の周波数（Hz）" => "Reference pitch of A4 (Hz)",
+        "基準のラの周波数" => "Reference pitch",
+        "検出する一番低い音（MIDIの番号）" => "Lowest note to detect (MIDI number)",
+        "検出する一番高い音（MIDIの番号）" => "Highest note to detect (MIDI number)",
+        "検出する音の範囲" => "Note range",
+        "候補の順位付けの方法" => "Candidate ranking",
+        "検出したとみなす最低の信頼度（dB）" => "Minimum confidence to detect (dB)",
+        "最低の信頼度" => "Minimum confidence",
+        "MIDIの音を出す最低の信頼度（dB）" => "Minimum confidence for MIDI output (dB)",
+        "MIDIの最低の信頼度" => "Minimum MIDI confidence",
+        "候補の差が小さいとみなす差（dB）" => "Ambiguity margin (dB)",
+        "候補の差が小さいとみなす差" => "Ambiguity margin",
+        "候補の差が小さい時の扱い" => "When candidates are ambiguous",
+        "フレーズの最初の音のオクターブに固定する" => "Lock to the octave of the first note of a phrase",
+        "オクターブの固定をやめるまでの無音の時間（ミリ秒）" => {
+            "Silence before releasing the octave lock (ms)"
+        }
+        "オクターブの固定" => "Octave lock",
+        "オクターブの固定をやめるまでの時間" => "Octave lock release time",
+        "周波数の補正（校正）" => "Frequency correction (calibration)",
+        "音階の名前の表し方" => "Note naming",
+        "記譜の移調（半音）" => "Written transposition (semitones)",
+        "記譜の移調" => "Written transposition",
+
+        // 二列目
+        "無音データを設定する" => "Set silence data",
+        "無音データを忘れる" => "Forget silence data",
+        "無音データを保存" => "Save silence data",
+        "保存した無音データを読み込む" => "Load saved silence data",
+        "雑音を追従して取り除く" => "Track and remove noise",
+        "追従した雑音を差し引く倍率（%）" => "Noise over-subtraction (%)",
+        "雑音の追従" => "Noise tracking",
+        "追従した雑音を差し引く倍率" => "Noise over-subtraction",
+        "ポイント数の規模" => "Point count scale",
+        "FFTのサイズの上限（×1024）" => "Maximum FFT size (×1024)",
+        "FFTのサイズの上限" => "Maximum FFT size",
+        "解析の窓の長さ（フレーム数、0で入力に合わせる）" => "Analysis window length (frames, 0 to follow the input)",
+        "解析の窓の長さ" => "Analysis window length",
+        "解析の窓の重なり（%）" => "Analysis window overlap (%)",
+        "解析の窓の重なり" => "Analysis window overlap",
+        "解析の遅延：約{}ミリ秒（入力{}＋窓{}＋間隔{}＋計算{}）" => {
+            "Analysis latency: about {} ms (input {} + window {} + hop {} + processing {})"
+        }
+        "履歴に使うメモリの上限（MB）" => "Memory limit for history (MB)",
+        "履歴に使うメモリの上限" => "Memory limit for history",
+        "校正に使う基準音の周波数（Hz）" => "Calibration tone frequency (Hz)",
+        "基準音を鳴らして校正する" => "Calibrate with a reference tone",
+        "校正" => "Calibration",
+        "プリセット" => "Preset",
+        "今の設定をプリセットとして保存" => "Save current settings as a preset",
+        "楽器のプロファイル" => "Instrument profile",
+        "起動時の動作" => "On startup",
+        "前回のMIDIの出力先に接続する" => "Connect to the last MIDI output",
+        "起動時に読み込むプリセット" => "Preset to load on startup",
+        "音声のバックエンド（次に起動した時から使う）" => "Audio backend (used from the next launch)",
+        "既定" => "Default",
+        "ギター" => "Guitar",
+        "ベース" => "Bass",
+        "バイオリン" => "Violin",
+        "歌声" => "Voice",
+        "口笛" => "Whistle",
+        "ピアノ" => "Piano",
+        "B♭クラリネット" => "B♭ clarinet",
+        "アルトサックス" => "Alto saxophone",
+
+        // 三列目
+        "MIDIの出力先" => "MIDI output",
+        "MIDIの音色" => "MIDI program",
+        "MIDIの音の長さを固定する" => "Fix MIDI note length",
+        "MIDIの音の長さの固定" => "Fixed MIDI note length",
+        "MIDIの音の長さ（ミリ秒）" => "MIDI note length (ms)",
+        "MIDIの音の長さ" => "MIDI note length",
+        "MIDIの音を止めるまでの猶予（ミリ秒）" => "MIDI release delay (ms)",
+        "MIDIの音を止めるまでの猶予" => "MIDI release delay",
+        "鳴らしている音を送り直す間隔（秒、0で送り直さない）" => {
+            "Note refresh interval (s, 0 to disable)"
+        }
+        "鳴らしている音を送り直す間隔" => "Note refresh interval",
+        "鳴らしたままの音を止めるまでの時間（秒、0で止めない）" => {
+            "Stuck note timeout (s, 0 to disable)"
+        }
+        "鳴らしたままの音を止めるまでの時間" => "Stuck note timeout",
+        "レガート" => "Legato",
+        "同じ音の弾き直しで鳴らし直す" => "Retrigger on repeated notes",
+        "同じ音の弾き直し" => "Retrigger",
+        "弾き直しとみなす変化の大きさ（%）" => "Retrigger threshold (%)",
+        "弾き直しとみなす変化の大きさ" => "Retrigger threshold",
+        "MIDIの音を出すまでの検出回数" => "Detections before MIDI note on",
+        "中央値フィルタの大きさ（検出回数）" => "Median filter size (detections)",
+        "中央値フィルタ" => "Median filter",
+        "MIDIの移調（半音）" => "MIDI transposition (semitones)",
+        "MIDIの移調" => "MIDI transposition",
+        "オクターブ重ね" => "Octave doubling",
+        "1オクターブ下" => "One octave below",
+        "1オクターブ上" => "One octave above",
+        "重ねる音の強さ（%）" => "Doubled note velocity (%)",
+        "重ねる音の強さ" => "Doubled note velocity",
+        "入力の音量をMIDIで送る" => "Send input volume over MIDI",
+        "入力の音量のコントロールチェンジ" => "Input volume control change",
+        "送らない" => "Don't send",
+        "ブレスコントローラー（CC2）" => "Breath controller (CC2)",
+        "エクスプレッション（CC11）" => "Expression (CC11)",
+        "ソステヌート（今の音を保持する）" => "Sostenuto (hold current notes)",
+        "パニック（全ての音を止める）" => "Panic (stop all notes)",
+
+        // 表示のタブ
+        "スペクトル" => "Spectrum",
+        "スペクトログラム" => "Spectrogram",
+        "チューナー" => "Tuner",
+        "基準音" => "Reference Tone",
+        "周辺だけを細かく解析する（ズームFFT）" => "Analyze around the peak in detail (zoom FFT)",
+        "ズームFFT" => "Zoom FFT",
+        "音階" => "Note",
+        "オクターブ" => "Octave",
+        "音量（%）" => "Volume (%)",
+        "音色" => "Timbre",
+        "鳴らす" => "Play",
+        "スクリプト" => "Script",
+        "スクリプトを読み込み直す" => "Reload script",
+        "伴奏" => "Backing Track",
+        "伴奏のMIDIファイルを開く..." => "Open a backing MIDI file...",
+        "伴奏のMIDIファイルを開いていません。" => "No backing MIDI file is open.",
+        "再生" => "Play",
+        "一時停止" => "Pause",
+        "停止" => "Stop",
+        "テンポ（%）" => "Tempo (%)",
+        "楽譜の追従" => "Score Following",
+        "お手本のMIDIファイルを開く..." => "Open a reference MIDI file...",
+        "お手本のMIDIファイルを開いていません。" => "No reference MIDI file is open.",
+        "最初に戻す" => "Back to the start",
+        "伸ばしている音の音程のずれを知らせる" => "Alert when a sustained note drifts",
+        "知らせるずれ（セント）" => "Drift to alert at (cents)",
+        "知らせるずれ" => "Drift to alert at",
+        "知らせるまでの時間（ミリ秒）" => "Time before alerting (ms)",
+        "知らせるまでの時間" => "Time before alerting",
+        "クリック音も鳴らす" => "Also play a click",
+        "練習を自動で止めるまでの時間（分、0で止めない）" => {
+            "Auto-stop practice after (min, 0 to disable)"
+        }
+        "練習を自動で止めるまでの時間" => "Practice auto-stop",
+        "練習の時間を最初から測る" => "Restart practice timer",
+        "音程のずれの知らせ" => "Drift alert",
+        "クリック音" => "Click",
+
+        // 音階モニタの列
+        "音階の名前" => "Note name",
+        "セント" => "Cents",
+        "周波数（Hz）" => "Frequency (Hz)",
+        "MIDIの番号" => "MIDI number",
+        "信頼度（dB）" => "Confidence (dB)",
+
+        // 選択肢
+        "なし" => "None",
+        "ハン窓" => "Hann",
+        "自動" => "Auto",
+        "ハミング窓" => "Hamming",
+        "ブラックマン・ハリス窓" => "Blackman-Harris",
+        "フラットトップ窓" => "Flat top",
+        "周波数帯の平均" => "Band average",
+        "周波数帯の最大" => "Band peak",
+        "倍音で重み付け" => "Harmonic weighted",
+        "前回の音を保つ" => "Keep the previous note",
+        "低い方の音を選ぶ" => "Pick the lower note",
+        "検出しない" => "Don't detect",
+        "カタカナと英語（ド/C）" => "Katakana and English (ド/C)",
+        "カタカナ（ド）" => "Katakana (ド)",
+        "英語（C）" => "English (C)",
+        "ドイツ語（C、H）" => "German (C, H)",
+        "固定ド（Do）" => "Fixed do (Do)",
+        "サイン波" => "Sine",
+        "オルガン" => "Organ",
+
+        // ファイルの種類
+        "音声ファイル" => "Audio file",
+        "Scalaの音律ファイル" => "Scala tuning file",
+        "周波数特性のファイル" => "Frequency response file",
+        "MIDIファイル" => "MIDI file",
+
+        // メッセージ
+        "初期化エラー" => "Initialization Error",
+        "UIの初期化に失敗しました。UIを使わずに、前回の設定で音階検出とMIDIの出力を続けますか？" => {
+            "Failed to initialize the UI. Continue detecting notes and sending MIDI without the UI, using the last settings?"
+        }
+        "UIを使わずに動いています。「OK」を押すと終了します。" => {
+            "aSynthe is running without the UI. Press \"OK\" to quit."
+        }
+        "MIDI出力の準備に失敗しました。" => "Failed to prepare MIDI output.",
+        "有効なデバイスがありません。" => "No input device is available.",
+        "有効なデバイスの設定がありません。" => "The input device has no usable configuration.",
+        "デバイスとの通信が異常終了しました。" => "Communication with the device ended abnormally.",
+        "対応していない形式のデバイスです。" => "The input device uses an unsupported sample format.",
+        "シードの値が指定されていません。" => "No seed value was given.",
+        "シードの値は0以上の整数で指定してください。" => "The seed must be a non-negative integer.",
+        "出力デバイスがありません。" => "No output device is available.",
+        "出力デバイスの設定を取得できませんでした。" => "Could not get the output device configuration.",
+        "出力デバイスが対応していない形式です。" => "The output device does not support a usable sample format.",
+        "出力ストリームを作れませんでした。" => "Could not create the output stream.",
+        "基準音を鳴らせませんでした。" => "Could not play the reference tone.",
+        "保存先のフォルダが見つかりません。" => "The folder to save to was not found.",
+        "そのMIDIの出力先が見つかりませんでした。" => "That MIDI output was not found.",
+        "ファイルを読み込めませんでした。" => "Could not read the file.",
+        "⚠ FFTのサイズが上限を超えるため、ポイント数の規模を{}に制限しています。" => {
+            "⚠ The point count scale is limited to {} because the FFT size exceeds the maximum."
+        }
+        "校正しました。（補正：{} ppm）" => "Calibrated. (Correction: {} ppm)",
+        "基準音を検出できなかったため、校正できませんでした。" => {
+            "Could not calibrate because the reference tone was not detected."
+        }
+        "校正中です。基準音を鳴らし続けてください。" => {
+            "Calibrating. Please keep playing the reference tone."
+        }
+        "プリセットの名前を入力してください。" => "Please enter a preset name.",
+        "プリセット「{}」を保存しました。" => "Saved the preset \"{}\".",
+        "プリセットの保存に失敗しました。\n{}" => "Failed to save the preset.\n{}",
+        "起動時の動作の設定の保存に失敗しました。\n{}" => {
+            "Failed to save the startup settings.\n{}"
+        }
+        "音声のバックエンドの保存に失敗しました。\n{}" => "Failed to save the audio backend.\n{}",
+        "音声ファイルから{}個の音を検出しました。" => "Detected {} notes in the audio file.",
+        "保存に失敗しました。\n{}" => "Failed to save.\n{}",
+        "音律「{}」（{}音）を読み込みました。" => "Loaded the tuning \"{}\" ({} notes).",
+        "平均律に戻しました。" => "Reset to equal temperament.",
+        "マイクの周波数特性（{}点）を読み込みました。" => "Loaded the microphone response ({} points).",
+        "マイクの補正をやめました。" => "Stopped the microphone compensation.",
+        "元に戻せる変更がありません。" => "There are no changes to undo.",
+        "全ての設定をデフォルトに戻しました。" => "Reset all settings to their defaults.",
+        "「{}」をデフォルトに戻しました。" => "Reset \"{}\" to its default.",
+        "「{}」の変更を元に戻しました。" => "Undid the change to \"{}\".",
+        "スクリプトのファイルがないため、スクリプトは使いません。" => {
+            "No script file was found, so no script is used."
+        }
+        "スクリプト「{}」を読み込みました。" => "Loaded the script \"{}\".",
+        "スクリプトを読み込めませんでした。\n{}" => "Could not load the script.\n{}",
+        "スクリプトでエラーが起きたため、スクリプトを止めました。\n{}" => {
+            "The script was stopped because of an error.\n{}"
+        }
+        "「{}」はMIDIの番号として使えません。" => "\"{}\" cannot be used as a MIDI number.",
+        "{}（{}秒）" => "{} ({} s)",
+        "楽譜：{}　検出：{}　{}" => "Score: {}  Detected: {}  {}",
+        "{}（{}音）" => "{} ({} notes)",
+        "{}（実音 {}）" => "{} (concert {})",
+        "音程が弾き始めから{}セントずれました。" => "The pitch drifted {} cents from where the note started.",
+        "クリック音を鳴らせませんでした。" => "Could not play the click.",
+        "保存する無音データがありません。先に無音データを設定してください。" => {
+            "There is no silence data to save. Set the silence data first."
+        }
+        "無音データを保存できませんでした。" => "Could not save the silence data.",
+        "無音データを保存しました。" => "Saved the silence data.",
+        "無音データを取っています。静かにしてください…（残り{}）" => {
+            "Capturing the silence data. Please keep quiet… ({} left)"
+        }
+        "無音データを取りました。" => "Captured the silence data.",
+        "練習時間 {} / {}" => "Practice {} / {}",
+        "練習時間 {}" => "Practice {}",
+        "練習時間：{}" => "Practice time: {}",
+        "練習の時間が終わりました。「練習の時間を最初から測る」で再開できます。" => {
+            "Practice time is up. Use \"Restart practice timer\" to continue."
+        }
+        "保存した無音データがありません。" => "No saved silence data was found.",
+        "無音データ（FFTのサイズ：{}）を読み込みました。FFTのサイズが今と違う場合は使われません。" => {
+            "Loaded the silence data (FFT size: {}). It is not used while the FFT size differs."
+        }
+        "ビブラート：{} Hz　±{}セント" => "Vibrato: {} Hz  ±{} cents",
+        "ビブラート：-" => "Vibrato: -",
+        "まだビブラートを検出していません。" => "No vibrato has been detected yet.",
+        "次の音：{}（{}/{}）" => "Next note: {} ({}/{})",
+        "最後まで弾きました。" => "You reached the end.",
+        "✓（{}音飛ばしました）" => "✓ (skipped {} notes)",
+        "✗ {}を弾きました" => "✗ Played {}",
+        "MIDIファイルではありません。" => "This is not a MIDI file.",
+        "MIDIファイルのヘッダーが壊れています。" => "The MIDI file header is broken.",
+        "MIDIファイルが途中で終わっています。" => "The MIDI file ends unexpectedly.",
+        "MIDIファイルの可変長の数値が長すぎます。" => "The MIDI file has a variable-length number that is too long.",
+        "MIDIファイルに知らないイベントがあります。" => "The MIDI file contains an unknown event.",
+        "言語の設定は、次に起動した時に反映されます。" => {
+            "The language setting will take effect the next time you start the app."
+        }
+        "受け取ったフレーム数：{}\n一回に受け取るフレーム数の平均：{}\n取りこぼしとみなした回数：{}\nオクターブの誤検出とみなした回数：{}（続けて検出した中の{}%）\n最後のエラー：{}\n履歴に使っているメモリ：{} / {} KB" => {
+            "Frames received: {}\nAverage frames per callback: {}\nUnderruns: {}\nOctave errors: {} ({}% of consecutive detections)\nLast error: {}\nMemory used by history: {} / {} KB"
+        }
+        "aSynthe v{}\n(c) 2022 Takagi Tasuku\n\nリポジトリ：https://github.com/tasuren/aSynthe\nライセンス情報：https://tasuren.github.io/a-synthe\n\nセッションのシード：{}" => {
+            "aSynthe v{}\n(c) 2022 Takagi Tasuku\n\nRepository: https://github.com/tasuren/aSynthe\nLicense information: https://tasuren.github.io/a-synthe\n\nSession seed: {}"
+        }
+        "ビブラートを検出した時間：{}秒\n速さの平均：{} Hz（{}〜{} Hz）\n深さの平均：±{}セント（最大 ±{}セント）" => {
+            "Time with vibrato: {} s\nAverage rate: {} Hz ({}-{} Hz)\nAverage depth: ±{} cents (max ±{} cents)"
+        }
+        _ => return None,
+    })
+}
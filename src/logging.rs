@@ -0,0 +1,91 @@
+//! 動作の記録（ログ）を残すためのモジュールです。
+//! 不具合の報告に添えてもらえるように、ログは`tracing`で日付ごとのファイルに書き、古いものから消します。
+//! コマンドラインから使う場合にも分かるように、同じものを標準エラー出力にも出します。
+
+use std::{io, path::PathBuf, process::Command};
+
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*};
+
+/// ログのファイルの名前の先頭（後ろに日付が付きます。）
+const LOG_FILE_PREFIX: &str = "a-synthe";
+/// 残しておくログのファイルの数（日数）
+const MAX_LOG_FILES: usize = 7;
+
+/// ログを置くフォルダを取得します。
+/// Windowsでは`%LOCALAPPDATA%\aSynthe\logs`、macOSでは`~/Library/Logs/aSynthe`、
+/// それ以外では`$XDG_STATE_HOME/a-synthe`（なければ`~/.local/state/a-synthe`）にします。
+pub fn log_dir() -> Option<PathBuf> {
+    #[cfg(windows)]
+    let dir = std::env::var_os("LOCALAPPDATA")
+        .map(|path| PathBuf::from(path).join("aSynthe").join("logs"));
+    #[cfg(target_os = "macos")]
+    let dir = std::env::var_os("HOME").map(|path| PathBuf::from(path).join("Library/Logs/aSynthe"));
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let dir = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|path| PathBuf::from(path).join(".local/state")))
+        .map(|path| path.join("a-synthe"));
+
+    dir
+}
+
+/// ログを書き始めます。起動してすぐに一度だけ呼んでください。
+/// ログのファイルを作れなかった場合は、標準エラー出力にだけ出します。
+pub fn init() {
+    let appender = log_dir()
+        .ok_or_else(|| "no log directory".to_string())
+        .and_then(|dir| {
+            RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(LOG_FILE_PREFIX)
+                .filename_suffix("log")
+                .max_log_files(MAX_LOG_FILES)
+                .build(dir)
+                .map_err(|error| error.to_string())
+        });
+
+    let (file_layer, file_error) = match appender {
+        Ok(appender) => (
+            Some(fmt::layer().with_ansi(false).with_writer(appender)),
+            None,
+        ),
+        Err(error) => (None, Some(error)),
+    };
+
+    tracing_subscriber::registry()
+        .with(LevelFilter::INFO)
+        .with(file_layer)
+        .with(stderr_layer())
+        .init();
+
+    if let Some(error) = file_error {
+        tracing::warn!("Failed to open the log file: {error}");
+    };
+}
+
+/// 標準エラー出力に出すためのレイヤーを作ります。読みやすいように時刻等は省きます。
+fn stderr_layer<S>() -> impl tracing_subscriber::Layer<S>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    fmt::layer()
+        .without_time()
+        .with_target(false)
+        .with_writer(io::stderr)
+}
+
+/// ログを置くフォルダを、ファイルマネージャーで開きます。
+pub fn reveal() -> io::Result<()> {
+    let dir = log_dir().ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+    std::fs::create_dir_all(&dir)?;
+
+    #[cfg(windows)]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let program = "xdg-open";
+
+    Command::new(program).arg(dir).spawn().map(|_| ())
+}
@@ -0,0 +1,343 @@
+use std::{
+    sync::atomic::Ordering::SeqCst,
+    time::{Duration, Instant},
+};
+
+use crate::{config::AppConfig, midi::MidiManager, sys::Note};
+
+mod before_midi_number {
+    //! 前回MIDIで送信した数値を記録するためのモジュールです。
+
+    use std::sync::atomic::{AtomicBool, AtomicU8, Ordering::SeqCst};
+
+    static BEFORE_MIDI_NUMBER: AtomicU8 = AtomicU8::new(0);
+    static BEFORE_MIDI_NUMBER_IS_FRESH: AtomicBool = AtomicBool::new(false);
+
+    pub(super) fn get() -> Option<u8> {
+        if BEFORE_MIDI_NUMBER_IS_FRESH.load(SeqCst) {
+            Some(BEFORE_MIDI_NUMBER.load(SeqCst))
+        } else {
+            None
+        }
+    }
+
+    pub(super) fn set(number: Option<u8>) {
+        if let Some(number) = number {
+            BEFORE_MIDI_NUMBER.store(number, SeqCst);
+            BEFORE_MIDI_NUMBER_IS_FRESH.store(true, SeqCst);
+        } else {
+            BEFORE_MIDI_NUMBER_IS_FRESH.store(false, SeqCst);
+        }
+    }
+}
+
+mod pending_midi_number {
+    //! まだMIDIで送信していない、検出されたばかりの音を記録するためのモジュールです。
+
+    use std::sync::atomic::{AtomicU32, AtomicU8, Ordering::SeqCst};
+
+    static PENDING_MIDI_NUMBER: AtomicU8 = AtomicU8::new(0);
+    /// 続けて検出された回数（0なら記録していない）
+    static PENDING_COUNT: AtomicU32 = AtomicU32::new(0);
+
+    /// 検出されたことを記録し、その音が続けて検出された回数を返します。
+    pub(super) fn count(number: u8) -> u32 {
+        if PENDING_COUNT.load(SeqCst) > 0 && PENDING_MIDI_NUMBER.load(SeqCst) == number {
+            PENDING_COUNT.fetch_add(1, SeqCst) + 1
+        } else {
+            PENDING_MIDI_NUMBER.store(number, SeqCst);
+            PENDING_COUNT.store(1, SeqCst);
+            1
+        }
+    }
+
+    pub(super) fn reset() {
+        PENDING_COUNT.store(0, SeqCst);
+    }
+}
+
+mod onset {
+    //! 音の立ち上がりが検出されたことを、次の検出結果の処理まで記録するためのモジュールです。
+
+    use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+
+    static ONSET: AtomicBool = AtomicBool::new(false);
+
+    pub(super) fn set() {
+        ONSET.store(true, SeqCst);
+    }
+
+    /// 記録を取り出します。
+    pub(super) fn take() -> bool {
+        ONSET.swap(false, SeqCst)
+    }
+}
+
+mod before_control_value {
+    //! 前回MIDIで送信した、入力の音量のコントロールチェンジの値を記録するためのモジュールです。
+
+    use std::sync::atomic::{AtomicU8, Ordering::SeqCst};
+
+    /// 送信していない場合は`u8::MAX`とする。
+    static BEFORE_CONTROL_VALUE: AtomicU8 = AtomicU8::new(u8::MAX);
+
+    pub(super) fn get() -> Option<u8> {
+        Some(BEFORE_CONTROL_VALUE.load(SeqCst)).filter(|value| *value != u8::MAX)
+    }
+
+    pub(super) fn set(value: Option<u8>) {
+        BEFORE_CONTROL_VALUE.store(value.unwrap_or(u8::MAX), SeqCst);
+    }
+}
+
+mod recent_midi_numbers {
+    //! 中央値フィルタのために、最近検出された音を記録するためのモジュールです。
+
+    use std::{collections::VecDeque, sync::Mutex};
+
+    static RECENT_MIDI_NUMBERS: Mutex<VecDeque<u8>> = Mutex::new(VecDeque::new());
+
+    /// 検出された音を記録し、最近`size`回分の中央値を返します。
+    pub(super) fn push_and_median(number: u8, size: usize) -> u8 {
+        let mut recent = RECENT_MIDI_NUMBERS.lock().unwrap();
+        recent.push_back(number);
+        while recent.len() > size.max(1) {
+            recent.pop_front();
+        }
+
+        let mut sorted: Vec<u8> = recent.iter().copied().collect();
+        sorted.sort_unstable();
+        sorted[sorted.len() / 2]
+    }
+
+    pub(super) fn clear() {
+        RECENT_MIDI_NUMBERS.lock().unwrap().clear();
+    }
+}
+
+mod scheduled_note_offs {
+    //! 音の長さの固定や音を止めるまでの猶予のために、音を止める予定を記録するためのモジュールです。
+
+    use std::{sync::Mutex, time::Instant};
+
+    static SCHEDULED_NOTE_OFFS: Mutex<Vec<(u8, Instant)>> = Mutex::new(Vec::new());
+
+    pub(super) fn push(number: u8, at: Instant) {
+        SCHEDULED_NOTE_OFFS.lock().unwrap().push((number, at));
+    }
+
+    pub(super) fn contains(number: u8) -> bool {
+        SCHEDULED_NOTE_OFFS
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(scheduled_number, _)| *scheduled_number == number)
+    }
+
+    /// 予定を取り消します。予定があったかどうかを返します。
+    pub(super) fn remove(number: u8) -> bool {
+        let mut scheduled = SCHEDULED_NOTE_OFFS.lock().unwrap();
+        let length = scheduled.len();
+        scheduled.retain(|(scheduled_number, _)| *scheduled_number != number);
+        length != scheduled.len()
+    }
+
+    /// 予定の時間を過ぎたものを取り出します。
+    pub(super) fn take_due(now: Instant) -> Vec<u8> {
+        let mut due = Vec::new();
+        SCHEDULED_NOTE_OFFS.lock().unwrap().retain(|(number, at)| {
+            if *at <= now {
+                due.push(*number);
+                false
+            } else {
+                true
+            }
+        });
+        due
+    }
+
+    pub(super) fn clear() {
+        SCHEDULED_NOTE_OFFS.lock().unwrap().clear();
+    }
+}
+
+/// 前回MIDIで送信した音を忘れます。
+/// 鳴らしている音を全て止めた後に使います。
+pub fn forget_midi_number() {
+    before_midi_number::set(None);
+    pending_midi_number::reset();
+    scheduled_note_offs::clear();
+}
+
+/// ソステヌートで保持している音を止めます。
+/// ただし、検出した音として鳴らしている音は止めません。
+pub fn release_held_notes(manager: &mut MidiManager) {
+    manager.release_held_notes(before_midi_number::get());
+}
+
+/// 時間経過で行う処理をします。イベントループで定期的に呼び出してください。
+pub fn tick(manager: &mut MidiManager, config: &AppConfig) {
+    // 音源が音を落としても鳴らしているつもりのままにならないように、鳴らしている音を送り直したり止めたりする。
+    // 止めた音は`before_midi_number`から消さないので、同じ音が検出され続けても鳴らし直さない。
+    if manager.is_avaliable() {
+        let seconds = |value: u32| (value > 0).then(|| Duration::from_secs(value as _));
+        for number in manager.refresh_notes(
+            seconds(config.note_refresh_interval.load(SeqCst)),
+            seconds(config.max_note_duration.load(SeqCst)),
+        ) {
+            scheduled_note_offs::remove(number);
+        }
+    };
+
+    for number in scheduled_note_offs::take_due(Instant::now()) {
+        if manager.is_avaliable() {
+            manager.down_midi(number);
+        };
+
+        // 猶予の後に音を止めたのなら、次に同じ音が検出された時に鳴らし直せるようにする。
+        if !config.use_fixed_note_length.load(SeqCst) && before_midi_number::get() == Some(number) {
+            before_midi_number::set(None);
+        };
+    }
+}
+
+/// 音の立ち上がりが検出されたことを記録します。
+/// 次の検出結果で前回と同じ音が検出された場合に、音を鳴らし直すのに使います。
+pub fn consume_onset() {
+    onset::set();
+}
+
+/// 検出した音階をもとにMIDIの送信を行います。
+/// `is_onset`は、音の立ち上がりが検出されたかどうかです。
+fn consume_midi_number(manager: &mut MidiManager, config: &AppConfig, number: u8, is_onset: bool) {
+    if !manager.is_avaliable() {
+        return;
+    };
+
+    let use_fixed_note_length = config.use_fixed_note_length.load(SeqCst);
+    let use_legato = !use_fixed_note_length && config.use_legato.load(SeqCst);
+    let before = before_midi_number::get();
+
+    // 短い間だけ検出された音で音を出さないように、決まった回数続けて検出されるまで待つ。
+    if before != Some(number)
+        && pending_midi_number::count(number) < config.min_note_frames.load(SeqCst)
+    {
+        return;
+    };
+    pending_midi_number::reset();
+
+    if let Some(before_midi_number) = before {
+        if before_midi_number == number {
+            if !use_fixed_note_length {
+                // 音を止めるまでの猶予の間に同じ音が検出されたのなら、音を止めるのをやめる。
+                scheduled_note_offs::remove(number);
+            };
+
+            if !(is_onset && config.use_onset_retrigger.load(SeqCst)) {
+                // もし前回と同じ音が出ているのなら、音階を変えない。
+                return;
+            };
+
+            // 同じ音が弾き直されたのなら、一度止めてから鳴らし直す。
+            // 音の長さを固定している場合は、下で鳴らし直す。
+            if !use_fixed_note_length {
+                manager.down_midi(number);
+            };
+        } else if !use_fixed_note_length {
+            // 前と同じじゃない音が出ているのなら、音を止める。
+            // ただし、音の長さを固定している場合は予定の時間になるまで、レガートの場合は次の音を出すまで止めない。
+            scheduled_note_offs::remove(before_midi_number);
+            if !use_legato {
+                manager.down_midi(before_midi_number);
+            };
+        };
+    };
+
+    // 音を出す。
+    if use_fixed_note_length {
+        if scheduled_note_offs::remove(number) {
+            // まだ鳴っているのなら、一度止めてから鳴らし直す。
+            manager.down_midi(number);
+        };
+
+        scheduled_note_offs::push(
+            number,
+            Instant::now() + Duration::from_millis(config.fixed_note_length.load(SeqCst) as _),
+        );
+    };
+    manager.up_midi(number);
+
+    // レガートの場合は、次の音を出してから前の音を止める。
+    if let Some(before_midi_number) = before.filter(|before| use_legato && *before != number) {
+        manager.down_midi(before_midi_number);
+    };
+
+    before_midi_number::set(Some(number));
+}
+
+/// 入力の音量の包絡線の値から、ブレスコントローラー等のコントロールチェンジを送ります。
+/// 音を鳴らしている間だけ送ります。
+pub fn consume_envelope(manager: &mut MidiManager, config: &AppConfig, envelope: f32) {
+    let control = config.envelope_control.load(SeqCst);
+    if control == 0 || !manager.is_avaliable() || before_midi_number::get().is_none() {
+        before_control_value::set(None);
+        return;
+    };
+
+    // -60dBから0dBを0から127に割り当てる。
+    let value = ((20. * envelope.log10() + 60.) / 60. * 127.).clamp(0., 127.) as u8;
+
+    if before_control_value::get() != Some(value) {
+        manager.send_control_change(control, value);
+        before_control_value::set(Some(value));
+    };
+}
+
+/// 検出した音階を使って搭載している機能の諸々の処理をします。
+pub fn consume_notes<const N: usize>(
+    midi_manager: &mut MidiManager,
+    config: &AppConfig,
+    notes: Option<[Note; N]>,
+) {
+    let is_onset = onset::take();
+
+    // 基準音を鳴らしている間と、表示はしてもMIDIの音を出すには信頼度が低い間は、検出しなかったことにする。
+    let min_midi_confidence = config.min_midi_confidence.load(SeqCst) as f32;
+    let notes = notes.filter(|notes| {
+        !config.is_reference_tone_playing.load(SeqCst) && notes[0].confidence >= min_midi_confidence
+    });
+
+    if let Some(notes) = notes {
+        // 一瞬だけ違う音が検出されても影響がないように、中央値フィルタをかける。
+        let first_midi_number = recent_midi_numbers::push_and_median(
+            notes[0].number,
+            config.median_filter_size.load(SeqCst) as _,
+        );
+        consume_midi_number(midi_manager, config, first_midi_number, is_onset);
+        return;
+    };
+
+    // 音が検出されなくなった場合
+    pending_midi_number::reset();
+    recent_midi_numbers::clear();
+
+    if let Some(before_midi_number) = before_midi_number::get() {
+        let release_delay = config.release_delay.load(SeqCst);
+
+        if config.use_fixed_note_length.load(SeqCst) {
+            // 音の長さを固定している場合は、予定の時間に止める。
+            before_midi_number::set(None);
+        } else if release_delay > 0 {
+            // 音を止めるまでの猶予がある場合は、猶予の後に止める。
+            if !scheduled_note_offs::contains(before_midi_number) {
+                scheduled_note_offs::push(
+                    before_midi_number,
+                    Instant::now() + Duration::from_millis(release_delay as _),
+                );
+            };
+        } else {
+            midi_manager.down_midi(before_midi_number);
+            before_midi_number::set(None);
+        };
+    };
+}
@@ -2,187 +2,534 @@
 #![cfg_attr(test, windows_subsystem = "console")]
 
 use std::{
-    sync::{mpsc::channel, Arc},
+    sync::{atomic::Ordering::SeqCst, mpsc::channel, Arc},
     time::Duration,
 };
 
 use cpal::{
-    default_host,
     traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SampleFormat, SizedSample,
 };
 use midir::MidiOutput;
 
+mod bridge;
+mod command;
+mod config;
+mod drift;
+mod file_analysis;
+mod headless;
+mod history;
+mod locale;
+mod logging;
+mod logic;
 mod midi;
 mod misc;
-mod sys;
+mod params;
+mod player;
+mod practice;
+mod presets;
+mod score;
+mod script;
+mod timeline;
+mod tone;
 mod ui;
+mod vibrato;
 
+// 音階検出の部分は、他のプロジェクトからも使えるように別のクレートにしている。
+use a_synthe_core as sys;
+use command::{AppCommand, CommandBus};
+use config::AppConfig;
+use locale::tr;
 use midi::MidiManager;
 use misc::prelude::*;
-use sys::{Note, NoteContainer, Synthesizer};
-use ui::make_ui;
+use sys::{ring, Note, StreamStats, Synthesizer};
+use ui::{make_ui, update_point_times_warning};
 
 /// アプリの名前
 const APPLICATION_NAME: &str = "aSynthe";
 /// 表示する音階の個数。
 const NUMBER_OF_NOTE_IN_RESULT: usize = 5;
+/// 検出方法のプラグインを置くフォルダの名前
+const PLUGINS_DIR_NAME: &str = "plugins";
+/// 受け取られていない解析の結果をためておく数の上限
+/// これを超えた場合は、受け取られるまで新しい解析の結果を捨てます。
+const ANALYSIS_QUEUE_CAPACITY: usize = 64;
+/// 受け取られていない入力ストリームのエラーをためておく数の上限
+const ERROR_QUEUE_CAPACITY: usize = 8;
+/// 入力デバイスのコールバックで使う作業用のバッファに、最初から確保しておく大きさ（サンプル数）
+/// 一回に受け取る音声データがこれより長い場合は、最初の一回だけ確保し直します。
+const CALLBACK_BUFFER_CAPACITY: usize = 4096;
 
 /// イベントループの動くスレッドに何か伝えるのに使うイベント
-pub enum BaseEvent<const NUMBER_OF_NOTE_IN_RESULT: usize> {
+pub enum Event {
+    /// UIから頼まれた操作
+    Command(AppCommand),
+}
+
+/// 入力デバイスのコールバックから、イベントループの動くスレッドに送る一回の解析の結果
+/// NOTE: コールバックでメモリを確保しないように、全て大きさの決まった値にしている。
+pub struct BaseAnalysis<const NUMBER_OF_NOTE_IN_RESULT: usize> {
     // TODO: 下記のIssueが解決次第、ここは変更を行う。
     //   それは、Syntheに定数ジェネリクスを定め、それに`NUMBER_OF_NOTE_IN_RESULT`を設定したエイリアスをここで使うというもの。
     //   そのIssueはこれ：https://github.com/rust-lang/rust/issues/8995
     /// 音階の検出
-    Synthesized(Option<[Note; NUMBER_OF_NOTE_IN_RESULT]>),
-    // MIDIの出力先の変更
-    UpdateMidiOutput(usize),
+    pub notes: Option<[Note; NUMBER_OF_NOTE_IN_RESULT]>,
+    /// 音の立ち上がりを検出したかどうか
+    pub is_onset: bool,
+    /// 入力の音量の包絡線の値
+    pub envelope: f32,
+    /// 検出の閾値と比べた入力の音量（dB）
+    pub volume: f32,
+    /// 入力が音割れしていたかどうか
+    pub is_clipped: bool,
+    /// 表示用のスペクトル（解析を行わなかった場合は`None`）
+    pub spectrum: Option<[f32; ui::SPECTRUM_BINS]>,
+    /// FFTのサイズの上限によるポイント数の規模の制限の状態が変わった場合の、新しい状態
+    pub point_times_limited: Option<Option<usize>>,
+    /// 無音データを取っている途中の、あと必要なデータの数が変わった場合の、新しい数（取り終えた場合は`Some(None)`）
+    pub silence_frames_left: Option<Option<u32>>,
 }
-pub type Event = BaseEvent<NUMBER_OF_NOTE_IN_RESULT>;
-
-mod logic {
-    use super::{ui::update_note_monitor, MidiManager, Note};
+pub type Analysis = BaseAnalysis<NUMBER_OF_NOTE_IN_RESULT>;
 
-    mod before_midi_number {
-        //! 前回MIDIで送信した数値を記録するためのモジュールです。
+/// 解析の結果はリングバッファで受け取り、届くのを待てないので、この間隔で確認する。
+const CPU_SLEEP_INTERVAL: Duration = Duration::from_millis(2);
 
-        use std::sync::atomic::{AtomicBool, AtomicU8, Ordering::SeqCst};
+/// 入力デバイスからの録音を開始します。
+/// 録音のストリームと、検出の設定、入力ストリームの状態、解析の結果およびエラーを受け取るためのものを返します。
+/// 入力デバイスのコールバックではメモリの確保もロックも行わず、解析の結果とエラーはリングバッファで送ります。
+/// エラーは`report_stream_errors`でイベントループから報告してください。
+fn start_input_stream(
+    input_device: &cpal::Device,
+) -> (
+    cpal::Stream,
+    Arc<AppConfig>,
+    Arc<StreamStats>,
+    ring::Consumer<Analysis>,
+    ring::Consumer<cpal::StreamError>,
+) {
+    let input_device_config = input_device
+        .default_input_config()
+        .context(tr("有効なデバイスの設定がありません。"))
+        .unwrap_or_dialog_with_title(tr(errors::INIT_ERROR));
+    tracing::info!(
+        "Input device: {} ({} Hz, {} channels, {:?})",
+        input_device.name().unwrap_or_default(),
+        input_device_config.sample_rate().0,
+        input_device_config.channels(),
+        input_device_config.sample_format()
+    );
 
-        static BEFORE_MIDI_NUMBER: AtomicU8 = AtomicU8::new(0);
-        static BEFORE_MIDI_NUMBER_IS_FRESH: AtomicBool = AtomicBool::new(false);
+    // シンセの用意
+    let mut builder = Synthesizer::builder(input_device_config.sample_rate().0 as _);
 
-        pub(super) fn get() -> Option<u8> {
-            if BEFORE_MIDI_NUMBER_IS_FRESH.load(SeqCst) {
-                Some(BEFORE_MIDI_NUMBER.load(SeqCst))
-            } else {
-                None
-            }
+    // 検出方法のプラグインを読み込む。
+    if let Some(dir) = misc::data_path(PLUGINS_DIR_NAME) {
+        let (plugins, errors) = sys::plugin::load_dir(&dir);
+        for error in errors {
+            tracing::warn!("Failed to load a plugin: {error}");
         }
+        builder = builder.plugins(plugins);
+    };
+    let mut synthesizer = builder.build();
+    let config = Arc::new(AppConfig::new(Arc::clone(&synthesizer.config)));
+    let stream_stats = Arc::new(StreamStats::new(input_device_config.sample_rate().0 as _));
+    let (mut producer, consumer) = ring::channel(ANALYSIS_QUEUE_CAPACITY);
+
+    // 録音および高速フーリエ変換の結果の送信を開始
+    let channels = input_device_config.channels() as usize;
+    let mut on_data = {
+        let stream_stats = Arc::clone(&stream_stats);
+        let config = Arc::clone(&config);
+        let mut before_limited_point_times = None;
+        let mut before_silence_frames_left = None;
+        let mut channel_data = Vec::with_capacity(CALLBACK_BUFFER_CAPACITY);
 
-        pub(super) fn set(number: Option<u8>) {
-            if let Some(number) = number {
-                BEFORE_MIDI_NUMBER.store(number, SeqCst);
-                BEFORE_MIDI_NUMBER_IS_FRESH.store(true, SeqCst);
+        move |data: &[f32]| {
+            // 複数のチャンネルの場合は、解析するチャンネルを取り出す。
+            let data = if channels > 1 {
+                extract_channel(
+                    data,
+                    channels,
+                    config.input_channel.load(SeqCst) as _,
+                    &mut channel_data,
+                );
+                &channel_data[..]
             } else {
-                BEFORE_MIDI_NUMBER_IS_FRESH.store(false, SeqCst);
-            }
-        }
-    }
+                data
+            };
+            stream_stats.record_callback(data.len());
 
-    /// 検出した音階をもとにMIDIの送信を行います。
-    fn consume_midi_number(manager: &mut MidiManager, number: u8) {
-        if !manager.is_avaliable() {
-            return;
-        };
+            synthesizer.feed(data, |synthesizer, notes| {
+                stream_stats.record_detection(notes.as_ref().map(|notes| notes[0].number));
+                stream_stats.record_processing(synthesizer.processing_time());
 
-        if let Some(before_midi_number) = before_midi_number::get() {
-            if before_midi_number == number {
-                // もし前回と同じ音が出ているのなら、音階を変えない。
-                return;
-            };
+                let mut spectrum = [0.; ui::SPECTRUM_BINS];
+                let is_analyzed = synthesizer.log_spectrum(
+                    ui::SPECTRUM_MIN_FREQUENCY,
+                    ui::SPECTRUM_MAX_FREQUENCY,
+                    &mut spectrum,
+                );
 
-            // 前と同じじゃない音が出ているのなら、音を止める。
-            manager.down_midi(before_midi_number);
-        };
+                // ポイント数の規模が制限されたのなら、それを伝える。
+                let limited_point_times = synthesizer.limited_point_times();
+                // 無音データを取っている途中なら、その進み具合を伝える。
+                let silence_frames_left = synthesizer.silence_frames_left();
+                let analysis = Analysis {
+                    notes,
+                    is_onset: synthesizer.is_onset(),
+                    envelope: synthesizer.envelope(),
+                    volume: synthesizer.volume(),
+                    is_clipped: synthesizer.is_clipped(),
+                    spectrum: is_analyzed.then_some(spectrum),
+                    point_times_limited: (limited_point_times != before_limited_point_times)
+                        .then_some(limited_point_times),
+                    silence_frames_left: (silence_frames_left != before_silence_frames_left)
+                        .then_some(silence_frames_left),
+                };
+
+                // イベントループが追いついていない場合は捨てる。（状態の変化は次の機会に伝える。）
+                if producer.push(analysis).is_ok() {
+                    before_limited_point_times = limited_point_times;
+                    before_silence_frames_left = silence_frames_left;
+                };
+            });
+        }
+    };
+    // エラーの記録や表示はメモリの確保やロックを伴うので、イベントループに任せる。
+    let (mut error_producer, error_consumer) = ring::channel(ERROR_QUEUE_CAPACITY);
+    let on_error = move |e: cpal::StreamError| {
+        let _ = error_producer.push(e);
+    };
 
-        // 音を出す。
-        manager.up_midi(number);
-        before_midi_number::set(Some(number));
+    // `f32`以外の形式のデバイスでは、`f32`に変換してから渡す。
+    let stream_config = input_device_config.config();
+    let input_stream = match input_device_config.sample_format() {
+        SampleFormat::F32 => Some(input_device.build_input_stream(
+            &stream_config,
+            move |data: &[f32], _| on_data(data),
+            on_error,
+            None,
+        )),
+        SampleFormat::I16 => Some(build_converted_input_stream::<i16>(
+            input_device,
+            &stream_config,
+            on_data,
+            on_error,
+        )),
+        SampleFormat::I32 => Some(build_converted_input_stream::<i32>(
+            input_device,
+            &stream_config,
+            on_data,
+            on_error,
+        )),
+        SampleFormat::U16 => Some(build_converted_input_stream::<u16>(
+            input_device,
+            &stream_config,
+            on_data,
+            on_error,
+        )),
+        SampleFormat::U8 => Some(build_converted_input_stream::<u8>(
+            input_device,
+            &stream_config,
+            on_data,
+            on_error,
+        )),
+        _ => None,
     }
+    .context(tr("対応していない形式のデバイスです。"))
+    .unwrap_or_dialog_with_title(tr(errors::INIT_ERROR))
+    .unwrap();
+    input_stream.play().unwrap();
 
-    /// 検出した音階を使って搭載している機能の諸々の処理をします。
-    pub fn consume_notes<const N: usize>(
-        midi_manager: &mut MidiManager,
-        note_labels: &mut [libui::controls::Label; N],
-        notes: Option<[Note; N]>,
-    ) {
-        if let Some(notes) = notes {
-            let first_midi_number = notes[0].0;
-            update_note_monitor::<N>(note_labels, notes);
-            consume_midi_number(midi_manager, first_midi_number);
-        } else if let Some(before_midi_number) = before_midi_number::get() {
-            midi_manager.down_midi(before_midi_number);
-            before_midi_number::set(None);
-        };
+    (input_stream, config, stream_stats, consumer, error_consumer)
+}
+
+/// 入力ストリームから届いたエラーを記録して、ダイアログで知らせます。
+fn report_stream_errors(
+    errors: &mut ring::Consumer<cpal::StreamError>,
+    stream_stats: &StreamStats,
+) {
+    while let Some(e) = errors.pop() {
+        tracing::error!("Input stream error: {e}");
+        stream_stats.record_error(&e);
+        Some(e)
+            .context(tr("デバイスとの通信が異常終了しました。"))
+            .unwrap_or_dialog();
     }
 }
 
-const CPU_SLEEP_INTERVAL: Duration = Duration::from_millis(5);
+/// インターリーブされた複数のチャンネルの音声データから、解析するチャンネルを取り出します。
+/// `channel`が0か、入力に無いチャンネルの場合は、全てのチャンネルを平均します。
+fn extract_channel(data: &[f32], channels: usize, channel: usize, output: &mut Vec<f32>) {
+    output.clear();
+
+    let frames = data.chunks_exact(channels);
+    if (1..=channels).contains(&channel) {
+        output.extend(frames.map(|frame| frame[channel - 1]));
+    } else {
+        output.extend(frames.map(|frame| frame.iter().sum::<f32>() / channels as f32));
+    };
+}
+
+/// サンプルを`f32`に変換しながら`on_data`に渡す入力ストリームを作ります。
+fn build_converted_input_stream<T>(
+    input_device: &cpal::Device,
+    stream_config: &cpal::StreamConfig,
+    mut on_data: impl FnMut(&[f32]) + Send + 'static,
+    on_error: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let mut converted = Vec::with_capacity(CALLBACK_BUFFER_CAPACITY);
+
+    input_device.build_input_stream(
+        stream_config,
+        move |data: &[T], _| {
+            converted.clear();
+            converted.extend(data.iter().map(|sample| sample.to_sample::<f32>()));
+            on_data(&converted);
+        },
+        on_error,
+        None,
+    )
+}
+
+/// UIから頼まれた操作を実行します。
+/// MIDIの出力先を変更する場合があるので、`midi_manager`を受け取って返します。
+fn execute_command(
+    command: AppCommand,
+    mut midi_manager: MidiManager,
+    input_stream: &cpal::Stream,
+    monitor: &mut ui::Monitor<NUMBER_OF_NOTE_IN_RESULT>,
+    script_hook: &mut script::ScriptHook,
+    player: &mut player::Player,
+    score_follower: &mut score::ScoreFollower,
+) -> MidiManager {
+    match command {
+        AppCommand::UpdateMidiOutput(port_index) => {
+            // 伴奏の音が前の出力先で鳴ったままにならないようにする。
+            player.release(&mut midi_manager);
+            midi_manager = midi_manager.set_midi_output(port_index);
+            logic::forget_midi_number();
+        }
+        AppCommand::UpdateMidiProgram(program) => midi_manager.set_program(program),
+        AppCommand::MidiPanic => {
+            player.release(&mut midi_manager);
+            midi_manager.all_notes_off();
+            logic::forget_midi_number();
+        }
+        AppCommand::UpdateSostenuto(true) => midi_manager.hold_active_notes(),
+        AppCommand::UpdateSostenuto(false) => logic::release_held_notes(&mut midi_manager),
+        AppCommand::ReloadScript => monitor.log_script(&script_hook.reload()),
+        AppCommand::LoadBackingTrack(song) => player.load(song, &mut midi_manager),
+        AppCommand::PlayBackingTrack => player.play(),
+        AppCommand::PauseBackingTrack => player.pause(&mut midi_manager),
+        AppCommand::StopBackingTrack => player.stop(&mut midi_manager),
+        AppCommand::SetBackingTrackTempo(percent) => player.set_tempo(percent),
+        AppCommand::LoadScore(melody) => {
+            score_follower.load(melody);
+            monitor.show_score_following(score_follower, None);
+        }
+        AppCommand::ResetScore => {
+            score_follower.reset();
+            monitor.show_score_following(score_follower, None);
+        }
+        AppCommand::RestartPractice => {
+            monitor.restart_practice();
+            let _ = input_stream.play();
+        }
+    };
+
+    midi_manager
+}
+
+/// 終了する時の後片付けをします。順番に意味があるので、変更する場合は注意してください。
+/// 1. 入力ストリームを止めて、解析の結果が新しく届かないようにする。（ストリームのスレッドが止まるまで待つ。）
+/// 2. 伴奏とMIDIの全ての音を止めて、音が鳴ったまま終了しないようにする。
+/// 3. クリック音の出力ストリームを止める。
+/// 4. 次に起動した時に戻せるように、設定を保存する。
+fn shutdown(
+    input_stream: cpal::Stream,
+    mut midi_manager: MidiManager,
+    player: &mut player::Player,
+    monitor: ui::Monitor<NUMBER_OF_NOTE_IN_RESULT>,
+    config: &AppConfig,
+) {
+    let _ = input_stream.pause();
+    drop(input_stream);
+
+    player.release(&mut midi_manager);
+    midi_manager.all_notes_off();
+    let midi_output_name = midi_manager
+        .port_index
+        .get()
+        .checked_sub(1)
+        .and_then(|index| midi_manager.port_names().into_iter().nth(index));
+    drop(midi_manager);
+
+    drop(monitor);
+
+    if let Err(error) = presets::save_last_settings(&config.snapshot()) {
+        tracing::error!("Failed to save the settings: {error}");
+    };
+    if let Err(error) = presets::save_last_midi_output(midi_output_name.as_deref()) {
+        tracing::error!("Failed to save the MIDI output: {error}");
+    };
+    tracing::info!("Stopped");
+}
 
 /// メインプログラムです。
 fn main() {
-    println!("{} by tasuren\nNow loading...", APPLICATION_NAME);
+    logging::init();
+
+    if let Some((input_dir, output_dir)) = file_analysis::convert_dirs_from_args() {
+        file_analysis::run_batch(&input_dir, &output_dir);
+        return;
+    };
+    if let Some(path) = file_analysis::path_from_args() {
+        file_analysis::run_cli(&path);
+        return;
+    };
+    if std::env::args().any(|arg| arg == headless::FLAG || arg == bridge::FLAG) {
+        headless::run();
+        return;
+    };
+
+    tracing::info!(
+        "{} {} by tasuren, now loading...",
+        APPLICATION_NAME,
+        env!("CARGO_PKG_VERSION")
+    );
+    tracing::info!("Session seed: {}", misc::session::seed());
+
+    // UIを初期化できなかった場合は、UIを使わずに続ける。
+    let Some(ui) = ui::init() else {
+        headless::run_fallback();
+        return;
+    };
 
     // MIDIの用意をする。
     let midi_output = MidiOutput::new(APPLICATION_NAME)
-        .context("MIDI出力の準備に失敗しました。")
-        .unwrap_or_dialog_with_title(errors::INIT_ERROR);
+        .context(tr("MIDI出力の準備に失敗しました。"))
+        .unwrap_or_dialog_with_title(tr(errors::INIT_ERROR));
 
     // マイクの設定を行う。
-    let input_device = default_host()
+    let host = misc::audio_host(presets::load_audio_backend().as_deref());
+    tracing::info!("Audio backend: {}", host.id().name());
+    let input_device = host
         .default_input_device()
-        .context("有効なデバイスがありません。")
-        .unwrap_or_dialog_with_title(errors::INIT_ERROR);
-    let input_device_config = input_device
-        .default_input_config()
-        .context("有効なデバイスの設定がありません。")
-        .unwrap_or_dialog_with_title(errors::INIT_ERROR);
-
-    // シンセの用意
-    let mut synthesizer = Synthesizer::new(
-        NoteContainer::new(),
-        input_device_config.sample_rate().0 as _,
-    );
-    let config = Arc::clone(&synthesizer.config);
+        .context(tr("有効なデバイスがありません。"))
+        .unwrap_or_dialog_with_title(tr(errors::INIT_ERROR));
 
     // 録音および高速フーリエ変換の結果の送信を開始
     let (tx, rx) = channel();
+    let (input_stream, config, stream_stats, mut analyses, mut stream_errors) =
+        start_input_stream(&input_device);
 
-    let input_stream = input_device
-        .build_input_stream(
-            &input_device_config.into(),
-            {
-                let tx = tx.clone();
-                move |data: &[f32], _| {
-                    let _ = tx.send(Event::Synthesized(synthesizer.synthe(data)));
-                }
-            },
-            |e| {
-                Some(e)
-                    .context("デバイスとの通信が異常終了しました。")
-                    .unwrap_or_dialog();
-            },
-            None,
-        )
-        .unwrap();
-    input_stream.play().unwrap();
+    // 操作やMIDIの出力の順番を確かめられるように、タイムラインに残す。
+    let timeline = timeline::Timeline::new();
+    timeline.record(
+        timeline::TimelineKind::Device,
+        format!("Input device: {}", input_device.name().unwrap_or_default()),
+    );
+    let mut midi_manager =
+        MidiManager::new(midi_output, Arc::clone(&config)).with_timeline(timeline.clone());
 
-    let (ui, mut window, mut note_labels) = make_ui(
-        tx,
-        config,
-        midi_output.ports().iter().map(|p| {
-            midi_output
-                .port_name(p)
-                .unwrap_or_else(|_| "不明な出力先".to_string())
-        }),
+    let (mut window, mut monitor) = make_ui(
+        ui.clone(),
+        CommandBus::new(tx),
+        Arc::clone(&config),
+        Arc::clone(&stream_stats),
+        timeline.clone(),
+        midi_manager.port_names().into_iter(),
     );
 
-    let mut midi_manager = MidiManager::new(midi_output);
+    // スクリプトを読み込む。
+    let mut script_hook = script::ScriptHook::new();
+    monitor.log_script(&script_hook.reload());
+
+    // 伴奏の用意
+    let mut player = player::Player::new();
+    let mut score_follower = score::ScoreFollower::default();
 
     // ウィンドウの表示およびイベントループの開始
     window.show();
     let mut event_loop = ui.event_loop();
-    println!("Started");
+    tracing::info!("Started");
 
     while event_loop.next_tick() {
+        logic::tick(&mut midi_manager, &config);
+        player.tick(&mut midi_manager);
+        report_stream_errors(&mut stream_errors, &stream_stats);
+        monitor.show_latency();
+        monitor.tick_timeline();
+
+        // 設定した練習の時間が経ったのなら、検出を止めて鳴っている音を止める。
+        if monitor.tick_practice() {
+            let _ = input_stream.pause();
+            player.pause(&mut midi_manager);
+            midi_manager.all_notes_off();
+            logic::forget_midi_number();
+            monitor.show_practice_summary();
+        };
+
+        while let Some(analysis) = analyses.pop() {
+            if analysis.is_onset {
+                logic::consume_onset();
+            };
+
+            let notes = analysis.notes;
+            monitor.show_notes(notes.clone());
+            if player.is_playing() {
+                monitor.show_score_comparison(
+                    player.score_note(),
+                    notes.as_ref().map(|notes| notes[0].number),
+                );
+            };
+            if let Some(judgement) =
+                score_follower.process(notes.as_ref().map(|notes| notes[0].number))
+            {
+                monitor.show_score_following(&score_follower, Some(judgement));
+            };
+            let notes = match script_hook.process(notes.clone(), &mut midi_manager) {
+                Ok(notes) => notes,
+                Err(error) => {
+                    monitor.log_script(&error);
+                    notes
+                }
+            };
+            logic::consume_notes(&mut midi_manager, &config, notes);
+
+            logic::consume_envelope(&mut midi_manager, &config, analysis.envelope);
+            monitor.show_level(analysis.volume, analysis.is_clipped);
+            monitor.show_spectrum(analysis.spectrum.as_ref().map(|spectrum| &spectrum[..]));
+            if let Some(point_times) = analysis.point_times_limited {
+                update_point_times_warning(&mut monitor.status_label, point_times);
+            };
+            if let Some(frames_left) = analysis.silence_frames_left {
+                monitor.show_silence_capture(frames_left);
+            };
+        }
+
         if let Ok(event) = rx.recv_timeout(CPU_SLEEP_INTERVAL) {
             match event {
-                Event::Synthesized(notes) => {
-                    logic::consume_notes(&mut midi_manager, &mut note_labels, notes)
-                }
-                Event::UpdateMidiOutput(port_index) => {
-                    midi_manager = midi_manager.set_midi_output(port_index)
+                Event::Command(command) => {
+                    timeline.record(timeline::TimelineKind::Command, command.summary());
+                    midi_manager = execute_command(
+                        command,
+                        midi_manager,
+                        &input_stream,
+                        &mut monitor,
+                        &mut script_hook,
+                        &mut player,
+                        &mut score_follower,
+                    )
                 }
             };
         };
     }
+
+    shutdown(input_stream, midi_manager, &mut player, monitor, &config);
 }
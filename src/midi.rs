@@ -1,69 +1,480 @@
-use std::{cell::Cell, rc::Rc};
+use std::{
+    cell::Cell,
+    rc::Rc,
+    sync::{atomic::Ordering::SeqCst, Arc},
+    time::{Duration, Instant},
+};
 
 use midir::{MidiOutput, MidiOutputConnection};
 
-const NOTE_ON_MSG: u8 = 0x90;
-const NOTE_OFF_MSG: u8 = 0x80;
-const VELOCITY: u8 = 0x64;
+use crate::{
+    config::AppConfig,
+    timeline::{describe_midi, Timeline, TimelineKind},
+};
+
+pub const NOTE_ON_MSG: u8 = 0x90;
+pub const NOTE_OFF_MSG: u8 = 0x80;
+const CONTROL_CHANGE_MSG: u8 = 0xB0;
+const PROGRAM_CHANGE_MSG: u8 = 0xC0;
+/// オールノートオフのコントロールチェンジの番号
+const ALL_NOTES_OFF_CONTROL: u8 = 123;
+pub const VELOCITY: u8 = 0x64;
+
+/// aSyntheが作る仮想MIDIポートの名前
+#[cfg(unix)]
+pub const VIRTUAL_PORT_NAME: &str = "aSynthe Out";
+
+/// MIDIのメッセージを送ります。送れなかった場合はログに残します。
+/// `timeline`を渡した場合は、送ったメッセージをタイムラインにも残します。
+fn send(connection: &mut MidiOutputConnection, timeline: Option<&Timeline>, message: &[u8]) {
+    if let Err(error) = connection.send(message) {
+        tracing::warn!("Failed to send a MIDI message {message:02X?}: {error}");
+    };
+    if let Some(timeline) = timeline {
+        timeline.record(TimelineKind::Midi, describe_midi(message));
+    };
+}
+
+/// General MIDIの音色の名前を取得します。
+pub fn gm_program_names() -> impl Iterator<Item = &'static str> {
+    include_str!("gm_programs.txt").lines()
+}
+
+/// MIDIの移調をした後のキーを取得します。
+/// `transposition`は半音の数で、移調した音がMIDIの範囲を外れる場合は`None`を返します。
+pub fn transposed_key(key: u8, transposition: i32) -> Option<u8> {
+    u8::try_from(key as i32 + transposition)
+        .ok()
+        .filter(|transposed_key| *transposed_key <= 127)
+}
+
+/// オクターブ重ねで一緒に鳴らす音のキーを取得します。
+/// `octave`はオクターブの差で、0なら鳴らしません。
+pub fn doubled_key(key: u8, octave: i32) -> Option<u8> {
+    match octave {
+        0 => None,
+        octave => u8::try_from(key as i32 + octave * 12)
+            .ok()
+            .filter(|doubled_key| *doubled_key <= 127),
+    }
+}
+
+/// 鳴らしている音です。
+struct ActiveNote {
+    key: u8,
+    /// MIDIの移調をして実際に送ったキー（範囲を外れて送らなかった場合は`None`）
+    sent_key: Option<u8>,
+    /// オクターブ重ねで一緒に鳴らしている音のキー
+    doubled_key: Option<u8>,
+    /// 鳴らし始めた時刻
+    started_at: Instant,
+    /// 最後にノートオンを送った時刻
+    sent_at: Instant,
+}
 
 /// MIDIを管理するための構造体です。
 pub struct MidiManager {
     connection: Option<MidiOutputConnection>,
+    /// 接続していない時の`MidiOutput`
+    midi_output: Option<MidiOutput>,
     pub port_index: Rc<Cell<usize>>,
     real_port_index: usize,
+    /// General MIDIの音色の番号
+    program: u8,
+    /// 鳴らしている音
+    active_notes: Vec<ActiveNote>,
+    /// ソステヌートで保持している音のキー
+    held_notes: Vec<u8>,
+    /// 仮想ポートを作る時の名前
+    #[cfg(unix)]
+    virtual_port_name: String,
+    /// 送ったメッセージや出力先の変更を残すタイムライン
+    timeline: Option<Timeline>,
+    config: Arc<AppConfig>,
 }
 
 impl MidiManager {
     /// インスタンスを作ります。
-    pub fn new(midi_output: MidiOutput) -> Self {
-        let port = &midi_output.ports()[0];
-
+    pub fn new(midi_output: MidiOutput, config: Arc<AppConfig>) -> Self {
         Self {
-            connection: if midi_output.ports().len() > 0 {
-                Some(midi_output.connect(port, crate::APPLICATION_NAME).unwrap())
-            } else {
-                None
-            },
+            connection: None,
+            midi_output: Some(midi_output),
             port_index: Rc::new(Cell::new(0)),
             real_port_index: 0,
+            program: 0,
+            active_notes: Vec::new(),
+            held_notes: Vec::new(),
+            #[cfg(unix)]
+            virtual_port_name: VIRTUAL_PORT_NAME.to_string(),
+            timeline: None,
+            config,
         }
     }
 
-    /// MIDIのデータを送ります。
+    /// 送ったメッセージや出力先の変更を、タイムラインに残すようにします。
+    pub fn with_timeline(mut self, timeline: Timeline) -> Self {
+        self.timeline = Some(timeline);
+        self
+    }
+
+    /// タイムラインに出力先の変化を残します。
+    fn record_device(&self, summary: String) {
+        if let Some(timeline) = self.timeline.as_ref() {
+            timeline.record(TimelineKind::Device, summary);
+        };
+    }
+
+    /// 仮想ポートを作る時の名前を変えます。
+    #[cfg(unix)]
+    pub fn with_virtual_port_name(mut self, name: &str) -> Self {
+        self.virtual_port_name = name.to_string();
+        self
+    }
+
+    /// MIDIの出力先の名前を取得します。
+    /// 仮想ポートが使える環境では、最後に仮想ポートの名前が入ります。
+    pub fn port_names(&self) -> Vec<String> {
+        #[cfg_attr(not(unix), allow(unused_mut))]
+        let mut port_names: Vec<String> = self
+            .midi_output
+            .as_ref()
+            .map(|midi_output| {
+                midi_output
+                    .ports()
+                    .iter()
+                    .map(|p| {
+                        midi_output
+                            .port_name(p)
+                            .unwrap_or_else(|_| "不明な出力先".to_string())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        #[cfg(unix)]
+        port_names.push(format!("{}（仮想ポート）", self.virtual_port_name));
+
+        port_names
+    }
+
+    /// オクターブ重ねの音の強さを取得します。
+    fn doubled_velocity(&self) -> u8 {
+        (VELOCITY as u32 * self.config.octave_doubling_velocity.load(SeqCst) as u32 / 100) as u8
+    }
+
+    /// MIDIのデータを送ります。接続していない場合は何もしません。
+    /// `key`は検出した音のキーで、MIDIの移調の設定がされている場合は移調してから送ります。
+    /// オクターブ重ねが有効なら、その音も一緒に送ります。
     pub fn send_data(&mut self, key: u8, is_on: bool) {
-        self.connection
-            .as_mut()
-            .unwrap()
-            .send(&[
-                if is_on { NOTE_ON_MSG } else { NOTE_OFF_MSG },
-                key,
-                VELOCITY,
-            ])
-            .unwrap();
+        let doubled_velocity = self.doubled_velocity();
+        let Some(connection) = self.connection.as_mut() else {
+            return;
+        };
+
+        if is_on {
+            let sent_key = transposed_key(key, self.config.midi_transposition.load(SeqCst));
+            let doubled_key = sent_key.and_then(|sent_key| {
+                doubled_key(sent_key, self.config.octave_doubling.load(SeqCst))
+            });
+            let active_note = self.active_notes.iter_mut().find(|note| note.key == key);
+
+            // 鳴らしている間に移調やオクターブ重ねの設定が変わった場合は、前に鳴らしたキーを止めておく。
+            if let Some(note) = active_note.as_ref() {
+                for old_key in note.sent_key.into_iter().chain(note.doubled_key) {
+                    if Some(old_key) != sent_key && Some(old_key) != doubled_key {
+                        send(
+                            connection,
+                            self.timeline.as_ref(),
+                            &[NOTE_OFF_MSG, old_key, VELOCITY],
+                        );
+                    };
+                }
+            };
+
+            if let Some(sent_key) = sent_key {
+                send(
+                    connection,
+                    self.timeline.as_ref(),
+                    &[NOTE_ON_MSG, sent_key, VELOCITY],
+                );
+            };
+            if let Some(doubled_key) = doubled_key {
+                send(
+                    connection,
+                    self.timeline.as_ref(),
+                    &[NOTE_ON_MSG, doubled_key, doubled_velocity],
+                );
+            };
+
+            let now = Instant::now();
+            if let Some(note) = active_note {
+                note.sent_key = sent_key;
+                note.doubled_key = doubled_key;
+                note.started_at = now;
+                note.sent_at = now;
+            } else {
+                self.active_notes.push(ActiveNote {
+                    key,
+                    sent_key,
+                    doubled_key,
+                    started_at: now,
+                    sent_at: now,
+                });
+            };
+        } else {
+            // 移調やオクターブ重ねの設定が鳴らした後に変わっても止められるように、鳴らした時のキーを止める。
+            let (sent_key, doubled_key) =
+                match self.active_notes.iter().position(|note| note.key == key) {
+                    Some(index) => {
+                        let note = self.active_notes.remove(index);
+                        (note.sent_key, note.doubled_key)
+                    }
+                    None => (
+                        transposed_key(key, self.config.midi_transposition.load(SeqCst)),
+                        None,
+                    ),
+                };
+
+            for key in sent_key.into_iter().chain(doubled_key) {
+                send(
+                    connection,
+                    self.timeline.as_ref(),
+                    &[NOTE_OFF_MSG, key, VELOCITY],
+                );
+            }
+        };
+    }
+
+    /// 指定したキーの音を鳴らしているかどうかを調べます。
+    fn is_active(&self, key: u8) -> bool {
+        self.active_notes.iter().any(|note| note.key == key)
     }
 
     /// 指定したキーでMIDIを有効にします。
+    /// ソステヌートで保持していて鳴っている音の場合は何もしません。
     pub fn up_midi(&mut self, key: u8) {
+        if self.held_notes.contains(&key) && self.is_active(key) {
+            return;
+        };
+
         self.send_data(key, true)
     }
 
     /// 指定したキーでMIDIを無効にします。
+    /// ソステヌートで保持している音の場合は何もしません。
     pub fn down_midi(&mut self, key: u8) {
+        if self.held_notes.contains(&key) {
+            return;
+        };
+
         self.send_data(key, false)
     }
 
+    /// 今鳴らしている音をソステヌートで保持します。
+    /// 保持した音は、`release_held_notes`を呼ぶまで止まりません。
+    pub fn hold_active_notes(&mut self) {
+        self.held_notes = self.active_notes.iter().map(|note| note.key).collect();
+    }
+
+    /// ソステヌートで保持している音を止めます。
+    /// `keep`に指定した音は、他で鳴らしている音なので止めません。
+    pub fn release_held_notes(&mut self, keep: Option<u8>) {
+        for key in std::mem::take(&mut self.held_notes) {
+            if Some(key) != keep && self.connection.is_some() {
+                self.send_data(key, false);
+            };
+        }
+    }
+
+    /// コントロールチェンジを送ります。
+    pub fn send_control_change(&mut self, control: u8, value: u8) {
+        if let Some(connection) = self.connection.as_mut() {
+            send(
+                connection,
+                self.timeline.as_ref(),
+                &[CONTROL_CHANGE_MSG, control, value.min(127)],
+            );
+        };
+    }
+
+    /// MIDIのメッセージをそのまま送ります。接続していない場合は何もしません。
+    pub fn send_message(&mut self, message: &[u8]) {
+        if let Some(connection) = self.connection.as_mut() {
+            send(connection, self.timeline.as_ref(), message);
+        };
+    }
+
+    /// 鳴らしている全ての音を止めます。
+    /// 鳴らしている音のノートオフを送った上で、オールノートオフのコントロールチェンジを送ります。
+    pub fn all_notes_off(&mut self) {
+        if let Some(connection) = self.connection.as_mut() {
+            for note in self.active_notes.iter() {
+                for key in note.sent_key.into_iter().chain(note.doubled_key) {
+                    send(
+                        connection,
+                        self.timeline.as_ref(),
+                        &[NOTE_OFF_MSG, key, VELOCITY],
+                    );
+                }
+            }
+            send(
+                connection,
+                self.timeline.as_ref(),
+                &[CONTROL_CHANGE_MSG, ALL_NOTES_OFF_CONTROL, 0],
+            );
+        };
+
+        self.active_notes.clear();
+        self.held_notes.clear();
+    }
+
+    /// 鳴らしている音のノートオンを`refresh_interval`毎に送り直し、`max_duration`より長く鳴らしている音を止めます。
+    /// 音色を変えた時等に音源が音を落とし、鳴っていない音を鳴らしているつもりのままになるのを防ぐためのものです。
+    /// 止めた音のキーを返します。
+    pub fn refresh_notes(
+        &mut self,
+        refresh_interval: Option<Duration>,
+        max_duration: Option<Duration>,
+    ) -> Vec<u8> {
+        let doubled_velocity = self.doubled_velocity();
+        let Some(connection) = self.connection.as_mut() else {
+            return Vec::new();
+        };
+
+        let now = Instant::now();
+        let mut stopped = Vec::new();
+        for note in self.active_notes.iter_mut() {
+            if max_duration.is_some_and(|duration| now - note.started_at >= duration) {
+                for key in note.sent_key.into_iter().chain(note.doubled_key) {
+                    send(
+                        connection,
+                        self.timeline.as_ref(),
+                        &[NOTE_OFF_MSG, key, VELOCITY],
+                    );
+                }
+                stopped.push(note.key);
+            } else if refresh_interval.is_some_and(|interval| now - note.sent_at >= interval) {
+                if let Some(sent_key) = note.sent_key {
+                    send(
+                        connection,
+                        self.timeline.as_ref(),
+                        &[NOTE_ON_MSG, sent_key, VELOCITY],
+                    );
+                };
+                if let Some(doubled_key) = note.doubled_key {
+                    send(
+                        connection,
+                        self.timeline.as_ref(),
+                        &[NOTE_ON_MSG, doubled_key, doubled_velocity],
+                    );
+                };
+                note.sent_at = now;
+            };
+        }
+
+        self.active_notes
+            .retain(|note| !stopped.contains(&note.key));
+        self.held_notes.retain(|key| !stopped.contains(key));
+        stopped
+    }
+
+    /// 音色を設定し、プログラムチェンジを送ります。
+    pub fn set_program(&mut self, program: u8) {
+        self.program = program;
+        self.send_program_change();
+    }
+
+    /// 現在の音色でプログラムチェンジを送ります。
+    fn send_program_change(&mut self) {
+        if let Some(connection) = self.connection.as_mut() {
+            send(
+                connection,
+                self.timeline.as_ref(),
+                &[PROGRAM_CHANGE_MSG, self.program],
+            );
+        };
+    }
+
+    /// 接続を閉じて`MidiOutput`を取り出します。
+    fn take_midi_output(&mut self) -> Option<MidiOutput> {
+        // 音が鳴ったままにならないように、閉じる前に音を止める。
+        self.all_notes_off();
+
+        match self.connection.take() {
+            Some(connection) => Some(connection.close()),
+            None => self.midi_output.take(),
+        }
+    }
+
     /// MIDIの出力先の処理を行います。
+    /// 出力先が変わった場合は、鳴らしている音を一度だけ止めます。
     pub fn set_midi_output(mut self, port_index: usize) -> Self {
-        self.port_index.replace(port_index);
+        let is_changed = self.port_index.replace(port_index) != port_index;
 
         if self.real_port_index != port_index && port_index > 0 {
-            if let Some(connection) = self.connection {
-                let midi_output = connection.close();
-                let port = &midi_output.ports()[port_index - 1];
+            // NOTE: 鳴らしている音は`take_midi_output`で止める。
+            if let Some(midi_output) = self.take_midi_output() {
+                let ports = midi_output.ports();
 
-                self.connection = Some(midi_output.connect(port, crate::APPLICATION_NAME).unwrap());
-                self.real_port_index = port_index;
+                if let Some(port) = ports.get(port_index - 1) {
+                    let port_name = midi_output.port_name(port).unwrap_or_default();
+                    match midi_output.connect(port, crate::APPLICATION_NAME) {
+                        Ok(connection) => {
+                            tracing::info!("MIDI output: {port_name}");
+                            self.record_device(format!("MIDI output: {port_name}"));
+                            self.connection = Some(connection);
+                            self.real_port_index = port_index;
+                            self.send_program_change();
+                        }
+                        Err(error) => {
+                            tracing::error!(
+                                "Failed to connect to the MIDI output {port_name}: {error}"
+                            );
+                            self.record_device(format!(
+                                "Failed to connect to the MIDI output {port_name}: {error}"
+                            ));
+                            self.midi_output = Some(error.into_inner());
+                            self.real_port_index = 0;
+                        }
+                    };
+                } else {
+                    #[cfg(unix)]
+                    if port_index == ports.len() + 1 {
+                        // 仮想ポートを作る。
+                        use midir::os::unix::VirtualOutput;
+
+                        match midi_output.create_virtual(&self.virtual_port_name) {
+                            Ok(connection) => {
+                                tracing::info!(
+                                    "MIDI output: virtual port {}",
+                                    self.virtual_port_name
+                                );
+                                self.record_device(format!(
+                                    "MIDI output: virtual port {}",
+                                    self.virtual_port_name
+                                ));
+                                self.connection = Some(connection);
+                                self.real_port_index = port_index;
+                                self.send_program_change();
+                            }
+                            Err(error) => {
+                                tracing::error!("Failed to create the virtual MIDI port: {error}");
+                                self.record_device(format!(
+                                    "Failed to create the virtual MIDI port: {error}"
+                                ));
+                                self.midi_output = Some(error.into_inner());
+                                self.real_port_index = 0;
+                            }
+                        };
+                        return self;
+                    };
+
+                    self.midi_output = Some(midi_output);
+                    self.real_port_index = 0;
+                };
             };
+        } else if is_changed {
+            self.all_notes_off();
         };
 
         self
@@ -74,3 +485,32 @@ impl MidiManager {
         self.connection.is_some() && self.port_index.get() > 0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transposed_key_boundaries() {
+        assert_eq!(transposed_key(60, 0), Some(60));
+        assert_eq!(transposed_key(0, 0), Some(0));
+        assert_eq!(transposed_key(0, -1), None);
+        assert_eq!(transposed_key(1, -1), Some(0));
+        assert_eq!(transposed_key(127, 0), Some(127));
+        assert_eq!(transposed_key(127, 1), None);
+        assert_eq!(transposed_key(126, 1), Some(127));
+        // u8の範囲には収まるが、MIDIの範囲を外れる場合
+        assert_eq!(transposed_key(127, 128), None);
+    }
+
+    #[test]
+    fn doubled_key_boundaries() {
+        assert_eq!(doubled_key(60, 0), None);
+        assert_eq!(doubled_key(0, 1), Some(12));
+        assert_eq!(doubled_key(0, -1), None);
+        assert_eq!(doubled_key(127, -1), Some(115));
+        assert_eq!(doubled_key(127, 1), None);
+        assert_eq!(doubled_key(115, 1), Some(127));
+        assert_eq!(doubled_key(116, 1), None);
+    }
+}
@@ -6,16 +6,131 @@ pub mod prelude {
     }
 }
 
+/// アプリのデータを保存するファイルのパスを取得します。
+/// WindowsではAppData、macOSではホームフォルダの下のフォルダに置きます。
+/// Linux等では`$XDG_DATA_HOME/a-synthe`（なければ`~/.local/share/a-synthe`）に置きますが、
+/// 以前の版が作った`~/.a-synthe`がある場合は、そちらを使い続けます。
+pub fn data_path(file_name: &str) -> Option<std::path::PathBuf> {
+    #[cfg(windows)]
+    let base =
+        std::env::var_os("APPDATA").map(|path| std::path::PathBuf::from(path).join("aSynthe"));
+    #[cfg(target_os = "macos")]
+    let base =
+        std::env::var_os("HOME").map(|path| std::path::PathBuf::from(path).join(".a-synthe"));
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let base = {
+        let home = std::env::var_os("HOME").map(std::path::PathBuf::from);
+        home.as_ref()
+            .map(|home| home.join(".a-synthe"))
+            .filter(|path| path.is_dir())
+            .or_else(|| {
+                std::env::var_os("XDG_DATA_HOME")
+                    .map(std::path::PathBuf::from)
+                    .filter(|path| path.is_absolute())
+                    .or_else(|| home.map(|home| home.join(".local/share")))
+                    .map(|path| path.join("a-synthe"))
+            })
+    };
+
+    base.map(|base| base.join(file_name))
+}
+
+/// 名前を指定して、音声の入出力に使うバックエンド（cpalのホスト）を取得します。
+/// 名前は`cpal::HostId::name`のもので、`None`の場合や使えない場合は既定のものを使います。
+/// JACKは`jack`の機能を有効にしてビルドした場合のみ使えます。
+pub fn audio_host(name: Option<&str>) -> cpal::Host {
+    if let Some(name) = name {
+        match cpal::available_hosts()
+            .into_iter()
+            .find(|id| id.name().eq_ignore_ascii_case(name))
+        {
+            Some(id) => match cpal::host_from_id(id) {
+                Ok(host) => return host,
+                Err(error) => tracing::warn!("Failed to use the audio backend {name}: {error}"),
+            },
+            None => tracing::warn!("Unknown audio backend: {name}"),
+        };
+    };
+
+    cpal::default_host()
+}
+
+/// 使えるバックエンドの名前を取得します。
+pub fn audio_host_names() -> Vec<&'static str> {
+    cpal::available_hosts()
+        .into_iter()
+        .map(|id| id.name())
+        .collect()
+}
+
+pub mod session {
+    //! セッションの乱数のシードを管理するためのモジュールです。
+    //! 不具合の報告等で同じ状況を再現できるように、シードはコマンドライン引数で指定できます。
+
+    use std::{
+        sync::OnceLock,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use super::prelude::*;
+    use crate::locale::tr;
+
+    static SEED: OnceLock<u64> = OnceLock::new();
+
+    /// コマンドライン引数の`--seed <数値>`からシードを読み込みます。
+    fn seed_from_args() -> Option<u64> {
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            let value = if arg == "--seed" {
+                args.next()
+            } else if let Some(value) = arg.strip_prefix("--seed=") {
+                Some(value.to_string())
+            } else {
+                continue;
+            };
+
+            return Some(
+                value
+                    .context(tr("シードの値が指定されていません。"))
+                    .and_then(|value| {
+                        value
+                            .parse()
+                            .context(tr("シードの値は0以上の整数で指定してください。"))
+                    })
+                    .unwrap_or_dialog_with_title(tr(errors::INIT_ERROR)),
+            );
+        }
+
+        None
+    }
+
+    /// このセッションの乱数のシードを取得します。
+    /// コマンドライン引数で指定されていない場合は、最初に呼んだ時の時刻から作ります。
+    pub fn seed() -> u64 {
+        *SEED.get_or_init(|| {
+            seed_from_args().unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_nanos() as u64)
+                    .unwrap_or_default()
+            })
+        })
+    }
+}
+
 pub mod app_meta {
     use dialog_unwrapper::rfd::{AsyncMessageDialog, MessageLevel};
 
+    use crate::locale::{tr, tr_format};
+
     /// アプリケーションの情報を表示します。
     pub fn show_about() {
         let _ = AsyncMessageDialog::new()
-            .set_title("このアプリについて")
-            .set_description(&format!(
-                "aSynthe v{}\n(c) 2022 Takagi Tasuku\n\nリポジトリ：https://github.com/tasuren/aSynthe\n{}",
-                env!("CARGO_PKG_VERSION"), "ライセンス情報：https://tasuren.github.io/a-synthe"
+            .set_title(tr("このアプリについて"))
+            .set_description(&tr_format(
+                "aSynthe v{}\n(c) 2022 Takagi Tasuku\n\nリポジトリ：https://github.com/tasuren/aSynthe\nライセンス情報：https://tasuren.github.io/a-synthe\n\nセッションのシード：{}",
+                &[&env!("CARGO_PKG_VERSION"), &super::session::seed()],
             ))
             .set_level(MessageLevel::Info)
             .show();
@@ -0,0 +1,191 @@
+//! アプリだけで使う数値の設定（MIDIの出力や練習、表示の設定）の名前、範囲、初期値、単位をまとめたモジュールです。
+//! 音階検出の設定の定義（`a_synthe_core::params`）もここから使えるようにしているので、アプリの中ではこのモジュールを使ってください。
+
+pub use crate::sys::params::*;
+
+pub const INPUT_CHANNEL: Param = Param {
+    name: "input_channel",
+    label: "解析するチャンネル（0で全てのチャンネルを平均する）",
+    min: 0,
+    max: 32,
+    default: 0,
+    unit: "",
+};
+pub const HISTORY_MEMORY_LIMIT: Param = Param {
+    name: "history_memory_limit",
+    label: "履歴に使うメモリの上限（MB）",
+    min: 1,
+    max: 1024,
+    default: 32,
+    unit: "MB",
+};
+pub const AUTO_STOP_MINUTES: Param = Param {
+    name: "auto_stop_minutes",
+    label: "練習を自動で止めるまでの時間（分、0で止めない）",
+    min: 0,
+    max: 240,
+    default: 0,
+    unit: "分",
+};
+pub const DRIFT_ALARM_THRESHOLD: Param = Param {
+    name: "drift_alarm_threshold",
+    label: "知らせるずれ（セント）",
+    min: 1,
+    max: 49,
+    default: 15,
+    unit: "セント",
+};
+pub const DRIFT_ALARM_DURATION: Param = Param {
+    name: "drift_alarm_duration",
+    label: "知らせるまでの時間（ミリ秒）",
+    min: 0,
+    max: 5000,
+    default: 500,
+    unit: "ms",
+};
+pub const NOTE_NAMING: Param = Param {
+    name: "note_naming",
+    label: "音階の名前の表し方",
+    min: 0,
+    max: crate::sys::NoteNaming::ALL.len() as i32 - 1,
+    default: 0,
+    unit: "",
+};
+pub const WRITTEN_TRANSPOSITION: Param = Param {
+    name: "written_transposition",
+    label: "記譜の移調（半音）",
+    min: -24,
+    max: 24,
+    default: 0,
+    unit: "半音",
+};
+pub const FIXED_NOTE_LENGTH: Param = Param {
+    name: "fixed_note_length",
+    label: "MIDIの音の長さ（ミリ秒）",
+    min: 10,
+    max: 10_000,
+    default: 200,
+    unit: "ms",
+};
+pub const RELEASE_DELAY: Param = Param {
+    name: "release_delay",
+    label: "MIDIの音を止めるまでの猶予（ミリ秒）",
+    min: 0,
+    max: 10_000,
+    default: 0,
+    unit: "ms",
+};
+pub const NOTE_REFRESH_INTERVAL: Param = Param {
+    name: "note_refresh_interval",
+    label: "鳴らしている音を送り直す間隔（秒、0で送り直さない）",
+    min: 0,
+    max: 60,
+    default: 0,
+    unit: "秒",
+};
+pub const MAX_NOTE_DURATION: Param = Param {
+    name: "max_note_duration",
+    label: "鳴らしたままの音を止めるまでの時間（秒、0で止めない）",
+    min: 0,
+    max: 600,
+    default: 0,
+    unit: "秒",
+};
+pub const MIN_NOTE_FRAMES: Param = Param {
+    name: "min_note_frames",
+    label: "MIDIの音を出すまでの検出回数",
+    min: 1,
+    max: 100,
+    default: 1,
+    unit: "",
+};
+pub const MIN_MIDI_CONFIDENCE: Param = Param {
+    name: "min_midi_confidence",
+    label: "MIDIの音を出す最低の信頼度（dB）",
+    min: 0,
+    max: 60,
+    default: 0,
+    unit: "dB",
+};
+pub const MEDIAN_FILTER_SIZE: Param = Param {
+    name: "median_filter_size",
+    label: "中央値フィルタの大きさ（検出回数）",
+    min: 1,
+    max: 15,
+    default: 1,
+    unit: "",
+};
+pub const MIDI_TRANSPOSITION: Param = Param {
+    name: "midi_transposition",
+    label: "MIDIの移調（半音）",
+    min: -24,
+    max: 24,
+    default: 0,
+    unit: "半音",
+};
+pub const OCTAVE_DOUBLING: Param = Param {
+    name: "octave_doubling",
+    label: "オクターブ重ね",
+    min: -1,
+    max: 1,
+    default: 0,
+    unit: "",
+};
+pub const OCTAVE_DOUBLING_VELOCITY: Param = Param {
+    name: "octave_doubling_velocity",
+    label: "重ねる音の強さ（%）",
+    min: 0,
+    max: 100,
+    default: 70,
+    unit: "%",
+};
+pub const ENVELOPE_CONTROL: Param = Param {
+    name: "envelope_control",
+    label: "入力の音量をMIDIで送る",
+    min: 0,
+    max: 127,
+    default: 0,
+    unit: "",
+};
+
+/// アプリだけで使う数値の値の定義
+pub const APP: [&Param; 18] = [
+    &INPUT_CHANNEL,
+    &HISTORY_MEMORY_LIMIT,
+    &AUTO_STOP_MINUTES,
+    &DRIFT_ALARM_THRESHOLD,
+    &DRIFT_ALARM_DURATION,
+    &NOTE_NAMING,
+    &WRITTEN_TRANSPOSITION,
+    &FIXED_NOTE_LENGTH,
+    &RELEASE_DELAY,
+    &NOTE_REFRESH_INTERVAL,
+    &MAX_NOTE_DURATION,
+    &MIN_NOTE_FRAMES,
+    &MIN_MIDI_CONFIDENCE,
+    &MEDIAN_FILTER_SIZE,
+    &MIDI_TRANSPOSITION,
+    &OCTAVE_DOUBLING,
+    &OCTAVE_DOUBLING_VELOCITY,
+    &ENVELOPE_CONTROL,
+];
+
+/// 音階検出の設定を含めた、全ての数値の値の定義を取得します。
+pub fn all() -> impl Iterator<Item = &'static Param> {
+    ALL.into_iter().chain(APP)
+}
+
+/// 名前から定義を探します。音階検出の設定の定義からも探します。
+pub fn find(name: &str) -> Option<&'static Param> {
+    all().find(|param| param.name == name)
+}
+
+/// `名前=値`の行の値を検証します。数値でない値（真偽値等）や知らない名前の行は検証しません。
+pub fn validate_line(line: &str) -> Result<(), String> {
+    match line.split_once('=') {
+        Some((name, value)) => {
+            find(name.trim()).map_or(Ok(()), |param| param.validate(value).map(|_| ()))
+        }
+        None => Ok(()),
+    }
+}
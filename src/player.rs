@@ -0,0 +1,348 @@
+//! 伴奏のMIDIファイル（SMF）を、検出を行いながらMIDIの出力先に流すためのモジュールです。
+//! 伴奏の音は、ファイルに書かれているチャンネルのまま送ります。
+//! 検出した音はチャンネル1で送るので、伴奏のファイルではチャンネル1を使わないことをおすすめします。
+
+use std::time::Instant;
+
+use crate::{
+    locale::tr,
+    midi::{MidiManager, NOTE_OFF_MSG, NOTE_ON_MSG},
+};
+
+/// SMFの四分音符の長さの初期値（マイクロ秒）
+const DEFAULT_MICROSECONDS_PER_QUARTER_NOTE: u32 = 500_000;
+/// General MIDIでドラムに使うチャンネル（0始まり）
+const DRUM_CHANNEL: u8 = 9;
+
+/// 読み込んだMIDIファイルです。
+#[derive(Clone, Debug)]
+pub struct Song {
+    /// 曲の始まりからの時間（秒）とMIDIのメッセージ（時間の順）
+    events: Vec<(f64, Vec<u8>)>,
+}
+
+/// バイト列を先頭から読むためのものです。
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + length)
+            .ok_or_else(|| tr("MIDIファイルが途中で終わっています。").to_string())?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.bytes(4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// 可変長の数値を読みます。
+    fn variable_length(&mut self) -> Result<u32, String> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            };
+        }
+        Err(tr("MIDIファイルの可変長の数値が長すぎます。").to_string())
+    }
+
+    fn is_end(&self) -> bool {
+        self.position >= self.data.len()
+    }
+}
+
+impl Song {
+    /// MIDIのメッセージの数を取得します。
+    pub fn event_count(&self) -> usize {
+        self.events.len()
+    }
+
+    /// SMF（フォーマット0または1）のデータを読み込みます。
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { data, position: 0 };
+        if reader.bytes(4)? != b"MThd" {
+            return Err(tr("MIDIファイルではありません。").to_string());
+        };
+        let header_length = reader.u32()? as usize;
+        let header = reader.bytes(header_length)?;
+        if header.len() < 6 {
+            return Err(tr("MIDIファイルのヘッダーが壊れています。").to_string());
+        };
+        let division = u16::from_be_bytes([header[4], header[5]]);
+
+        // ティック、同じティックの中での順番、メッセージを集める。テンポの変更は別に集める。
+        let mut messages: Vec<(u64, usize, Vec<u8>)> = Vec::new();
+        let mut tempo_changes: Vec<(u64, u32)> = Vec::new();
+
+        while !reader.is_end() {
+            let chunk_type = reader.bytes(4)?;
+            let chunk_length = reader.u32()? as usize;
+            let chunk = reader.bytes(chunk_length)?;
+            if chunk_type != b"MTrk" {
+                continue;
+            };
+
+            let mut track = Reader {
+                data: chunk,
+                position: 0,
+            };
+            let (mut tick, mut running_status) = (0u64, 0u8);
+
+            while !track.is_end() {
+                tick += track.variable_length()? as u64;
+
+                let mut status = track.byte()?;
+                let first_data = if status < 0x80 {
+                    // ランニングステータス
+                    let data = status;
+                    status = running_status;
+                    Some(data)
+                } else {
+                    None
+                };
+
+                match status {
+                    0x80..=0xEF => {
+                        running_status = status;
+                        let length = match status & 0xF0 {
+                            0xC0 | 0xD0 => 1,
+                            _ => 2,
+                        };
+                        let mut message = vec![status];
+                        if let Some(data) = first_data {
+                            message.push(data);
+                        };
+                        while message.len() < length + 1 {
+                            message.push(track.byte()?);
+                        }
+                        messages.push((tick, messages.len(), message));
+                    }
+                    0xF0 | 0xF7 => {
+                        // システムエクスクルーシブは送らない。
+                        let length = track.variable_length()? as usize;
+                        track.bytes(length)?;
+                    }
+                    0xFF => {
+                        let kind = track.byte()?;
+                        let length = track.variable_length()? as usize;
+                        let data = track.bytes(length)?;
+                        match kind {
+                            0x51 if length == 3 => tempo_changes
+                                .push((tick, u32::from_be_bytes([0, data[0], data[1], data[2]]))),
+                            0x2F => break,
+                            _ => (),
+                        };
+                    }
+                    _ => return Err(tr("MIDIファイルに知らないイベントがあります。").to_string()),
+                };
+            }
+        }
+
+        messages.sort_by_key(|(tick, order, _)| (*tick, *order));
+        tempo_changes.sort_by_key(|(tick, _)| *tick);
+
+        // ティックを秒に直す。
+        let seconds_per_tick = |microseconds_per_quarter_note: u32| {
+            if division & 0x8000 == 0 {
+                microseconds_per_quarter_note as f64 / 1_000_000. / division.max(1) as f64
+            } else {
+                // SMPTEのタイムコードの場合は、テンポに関係なく一フレームあたりのティック数で決まる。
+                let frames_per_second = -((division >> 8) as i8) as f64;
+                1. / (frames_per_second * (division & 0xFF) as f64).max(1.)
+            }
+        };
+        let mut tempo_changes = tempo_changes.into_iter().peekable();
+        let (mut before_tick, mut seconds) = (0u64, 0f64);
+        let mut current = seconds_per_tick(DEFAULT_MICROSECONDS_PER_QUARTER_NOTE);
+        let mut events = Vec::with_capacity(messages.len());
+
+        for (tick, _, message) in messages {
+            while let Some((tempo_tick, tempo)) = tempo_changes.next_if(|(t, _)| *t <= tick) {
+                seconds += (tempo_tick - before_tick) as f64 * current;
+                before_tick = tempo_tick;
+                current = seconds_per_tick(tempo);
+            }
+            seconds += (tick - before_tick) as f64 * current;
+            before_tick = tick;
+            events.push((seconds, message));
+        }
+
+        Ok(Self { events })
+    }
+
+    /// 曲の長さ（秒）を取得します。
+    pub fn duration(&self) -> f64 {
+        self.events.last().map_or(0., |(seconds, _)| *seconds)
+    }
+
+    /// 旋律として、鳴らし始める音を時間の順に並べたものを取得します。
+    /// 同時に鳴らし始める音は一番高い音だけを使い、ドラムの音は除きます。
+    pub fn melody(&self) -> Vec<u8> {
+        let mut melody: Vec<(f64, u8)> = Vec::new();
+
+        for (seconds, message) in &self.events {
+            let (kind, channel) = (message[0] & 0xF0, message[0] & 0x0F);
+            let (Some(key), Some(velocity)) = (message.get(1), message.get(2)) else {
+                continue;
+            };
+            if kind != NOTE_ON_MSG || *velocity == 0 || channel == DRUM_CHANNEL {
+                continue;
+            };
+
+            match melody.last_mut() {
+                Some((before, before_key)) if before == seconds => {
+                    *before_key = (*before_key).max(*key)
+                }
+                _ => melody.push((*seconds, *key)),
+            };
+        }
+
+        melody.into_iter().map(|(_, key)| key).collect()
+    }
+}
+
+/// 伴奏を再生するための構造体です。
+pub struct Player {
+    song: Option<Song>,
+    /// 次に送るメッセージの位置
+    next: usize,
+    /// 再生中の場合の、再生を始めた（またはテンポを変えた）時刻とその時の曲の位置（秒）
+    playing: Option<(Instant, f64)>,
+    /// 止めている時の曲の位置（秒）
+    paused_at: f64,
+    /// テンポ（元の速さに対する倍率）
+    tempo: f64,
+    /// 伴奏で鳴らしている音のチャンネルとキー
+    sounding: Vec<(u8, u8)>,
+}
+
+impl Player {
+    /// インスタンスを作ります。
+    pub fn new() -> Self {
+        Self {
+            song: None,
+            next: 0,
+            playing: None,
+            paused_at: 0.,
+            tempo: 1.,
+            sounding: Vec::new(),
+        }
+    }
+
+    /// 曲を読み込み、最初に戻します。
+    pub fn load(&mut self, song: Song, midi_manager: &mut MidiManager) {
+        self.stop(midi_manager);
+        self.song = Some(song);
+    }
+
+    /// 今の曲の位置（秒）を取得します。
+    fn position(&self) -> f64 {
+        match self.playing {
+            Some((started_at, from)) => from + started_at.elapsed().as_secs_f64() * self.tempo,
+            None => self.paused_at,
+        }
+    }
+
+    /// 再生を始めます。止めていた場合は、その位置から再生します。
+    pub fn play(&mut self) {
+        if self.song.is_some() && self.playing.is_none() {
+            self.playing = Some((Instant::now(), self.paused_at));
+        };
+    }
+
+    /// 再生を一時停止します。
+    pub fn pause(&mut self, midi_manager: &mut MidiManager) {
+        if self.playing.is_some() {
+            self.paused_at = self.position();
+            self.playing = None;
+        };
+        self.release(midi_manager);
+    }
+
+    /// 再生を止めて、最初に戻します。
+    pub fn stop(&mut self, midi_manager: &mut MidiManager) {
+        self.playing = None;
+        self.paused_at = 0.;
+        self.next = 0;
+        self.release(midi_manager);
+    }
+
+    /// テンポを元の速さに対する百分率で設定します。
+    pub fn set_tempo(&mut self, percent: u32) {
+        // 曲の位置がずれないように、今の位置を基準にし直す。
+        if self.playing.is_some() {
+            self.playing = Some((Instant::now(), self.position()));
+        };
+        self.tempo = percent.max(1) as f64 / 100.;
+    }
+
+    /// 伴奏で鳴らしている音を止めます。（MIDIの出力先を変える前等に使います。）
+    pub fn release(&mut self, midi_manager: &mut MidiManager) {
+        for (channel, key) in self.sounding.drain(..) {
+            midi_manager.send_message(&[NOTE_OFF_MSG | channel, key, 0]);
+        }
+    }
+
+    /// 時間が来たメッセージを送ります。イベントループで定期的に呼んでください。
+    /// 曲の最後まで来たら、止めて最初に戻します。
+    pub fn tick(&mut self, midi_manager: &mut MidiManager) {
+        let Some(song) = &self.song else {
+            return;
+        };
+        if self.playing.is_none() {
+            return;
+        };
+
+        let position = self.position();
+        while let Some((seconds, message)) = song.events.get(self.next) {
+            if *seconds > position {
+                break;
+            };
+            self.next += 1;
+
+            let (kind, channel) = (message[0] & 0xF0, message[0] & 0x0F);
+            match (kind, message.get(1), message.get(2)) {
+                (NOTE_ON_MSG, Some(key), Some(velocity)) if *velocity > 0 => {
+                    self.sounding.push((channel, *key))
+                }
+                (NOTE_ON_MSG | NOTE_OFF_MSG, Some(key), _) => self
+                    .sounding
+                    .retain(|sounding| *sounding != (channel, *key)),
+                _ => (),
+            };
+            midi_manager.send_message(message);
+        }
+
+        if self.next >= song.events.len() {
+            self.stop(midi_manager);
+        };
+    }
+
+    /// 再生中かどうかを取得します。
+    pub fn is_playing(&self) -> bool {
+        self.playing.is_some()
+    }
+
+    /// 楽譜で今鳴っている音のうち、一番高い音（ドラムを除く）を取得します。
+    /// 検出した音と比べるためのものです。
+    pub fn score_note(&self) -> Option<u8> {
+        self.sounding
+            .iter()
+            .filter(|(channel, _)| *channel != DRUM_CHANNEL)
+            .map(|(_, key)| *key)
+            .max()
+    }
+}
@@ -0,0 +1,58 @@
+//! 練習の時間を測り、設定した時間が経ったら止めるためのモジュールです。
+//! 先生が時間を区切って練習させる場合等に使います。
+
+use std::time::{Duration, Instant};
+
+/// 練習の時間を測るためのタイマーです。
+pub struct PracticeTimer {
+    /// 測り始めた時刻
+    started_at: Instant,
+    /// 止めた時刻（止めていない場合は`None`）
+    stopped_at: Option<Instant>,
+}
+
+impl Default for PracticeTimer {
+    fn default() -> Self {
+        Self {
+            started_at: Instant::now(),
+            stopped_at: None,
+        }
+    }
+}
+
+impl PracticeTimer {
+    /// 最初から測り直します。
+    pub fn restart(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 測り始めてからの時間を取得します。止めた後は、止めた時までの時間です。
+    pub fn elapsed(&self) -> Duration {
+        self.stopped_at
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.started_at)
+    }
+
+    /// 止めているかどうかを取得します。
+    pub fn is_stopped(&self) -> bool {
+        self.stopped_at.is_some()
+    }
+
+    /// `limit`の時間が経ったかどうかを調べ、経っていたら止めます。
+    /// 止めた時だけ`true`を返します。
+    pub fn check(&mut self, limit: Option<Duration>) -> bool {
+        match limit {
+            Some(limit) if !self.is_stopped() && self.elapsed() >= limit => {
+                self.stopped_at = Some(Instant::now());
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 時間を`分:秒`の形にします。
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
@@ -0,0 +1,327 @@
+use std::{fs, io, path::PathBuf};
+
+use crate::{config::AppConfigSnapshot, misc::data_path};
+
+/// プリセットを保存するファイルの名前
+const PRESETS_FILE_NAME: &str = "presets.txt";
+/// 終了した時の設定を保存するファイルの名前
+const LAST_SETTINGS_FILE_NAME: &str = "settings.txt";
+/// 終了した時のMIDIの出力先の名前を保存するファイルの名前
+const LAST_MIDI_OUTPUT_FILE_NAME: &str = "midi_output.txt";
+/// 起動時の動作の設定を保存するファイルの名前
+const STARTUP_FILE_NAME: &str = "startup.txt";
+/// 音声のバックエンドの名前を保存するファイルの名前
+const AUDIO_BACKEND_FILE_NAME: &str = "audio_backend.txt";
+
+/// 名前を付けて保存した解析の設定（プリセット）を管理するための構造体です。
+/// プリセットは`[名前]`の行の後に`AppConfigSnapshot::to_text`の内容を続けた形式で保存します。
+pub struct PresetStore {
+    path: Option<PathBuf>,
+    presets: Vec<(String, AppConfigSnapshot)>,
+}
+
+impl PresetStore {
+    /// 保存されているプリセットを読み込みます。
+    /// ファイルが無い場合や読み込めない場合は、プリセットが無いものとします。
+    pub fn load() -> Self {
+        let path = data_path(PRESETS_FILE_NAME);
+        let mut presets: Vec<(String, AppConfigSnapshot)> = Vec::new();
+
+        if let Some(text) = path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+            for line in text.lines() {
+                if let Some(name) = line
+                    .trim()
+                    .strip_prefix('[')
+                    .and_then(|line| line.strip_suffix(']'))
+                {
+                    presets.push((name.to_string(), AppConfigSnapshot::default()));
+                } else if let Some((_, snapshot)) = presets.last_mut() {
+                    snapshot.apply_line(line);
+                };
+            }
+        };
+
+        Self { path, presets }
+    }
+
+    /// プリセットの名前を取得します。
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.presets.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// 指定した位置のプリセットを取得します。
+    pub fn get(&self, index: usize) -> Option<&AppConfigSnapshot> {
+        self.presets.get(index).map(|(_, snapshot)| snapshot)
+    }
+
+    /// プリセットを保存します。同じ名前のプリセットがある場合は上書きします。
+    /// 新しく追加したかどうかを返します。
+    pub fn save(&mut self, name: &str, snapshot: AppConfigSnapshot) -> io::Result<bool> {
+        let is_new = if let Some((_, old)) = self.presets.iter_mut().find(|(n, _)| n == name) {
+            *old = snapshot;
+            false
+        } else {
+            self.presets.push((name.to_string(), snapshot));
+            true
+        };
+
+        let path = self.path.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "保存先のフォルダが見つかりません。",
+            )
+        })?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        };
+        fs::write(
+            path,
+            self.presets
+                .iter()
+                .map(|(name, snapshot)| format!("[{}]\n{}", name, snapshot.to_text()))
+                .collect::<String>(),
+        )?;
+
+        Ok(is_new)
+    }
+}
+
+/// 楽器に合わせた設定の組み合わせです。
+pub struct InstrumentProfile {
+    /// UIに表示する名前
+    pub name: &'static str,
+    /// 検出する音の範囲（MIDIの番号）
+    pub note_range: (u8, u8),
+    /// ポイント数の規模（低い音を扱う楽器ほど大きくする）
+    pub point_times: u16,
+    /// 検出対象とする最低音量（dB）
+    pub min_volume: i32,
+    /// MIDIの音を出すまでの検出回数
+    pub min_note_frames: u32,
+    /// 中央値フィルタの大きさ（検出回数）
+    pub median_filter_size: u32,
+    /// 記譜の移調（半音、移調楽器でない場合は0）
+    pub written_transposition: i32,
+}
+
+/// 組み込みの楽器のプロファイル
+pub const INSTRUMENT_PROFILES: [InstrumentProfile; 8] = [
+    InstrumentProfile {
+        name: "ギター",
+        // E2〜E6
+        note_range: (40, 88),
+        point_times: 8,
+        min_volume: -40,
+        min_note_frames: 2,
+        median_filter_size: 3,
+        written_transposition: 0,
+    },
+    InstrumentProfile {
+        name: "ベース",
+        // E1〜G4
+        note_range: (28, 67),
+        point_times: 16,
+        min_volume: -40,
+        min_note_frames: 2,
+        median_filter_size: 3,
+        written_transposition: 0,
+    },
+    InstrumentProfile {
+        name: "バイオリン",
+        // G3〜A7
+        note_range: (55, 105),
+        point_times: 4,
+        min_volume: -35,
+        min_note_frames: 1,
+        median_filter_size: 3,
+        written_transposition: 0,
+    },
+    InstrumentProfile {
+        name: "歌声",
+        // C2〜C6
+        note_range: (36, 84),
+        point_times: 8,
+        min_volume: -35,
+        min_note_frames: 3,
+        median_filter_size: 5,
+        written_transposition: 0,
+    },
+    InstrumentProfile {
+        name: "口笛",
+        // C5〜C8
+        note_range: (72, 108),
+        point_times: 2,
+        min_volume: -30,
+        min_note_frames: 1,
+        median_filter_size: 3,
+        written_transposition: 0,
+    },
+    InstrumentProfile {
+        name: "ピアノ",
+        // A0〜C8
+        note_range: (21, 108),
+        point_times: 16,
+        min_volume: -40,
+        min_note_frames: 1,
+        median_filter_size: 1,
+        written_transposition: 0,
+    },
+    InstrumentProfile {
+        name: "B♭クラリネット",
+        // 実音D3〜B♭6（記譜E3〜C7）
+        note_range: (50, 94),
+        point_times: 8,
+        min_volume: -35,
+        min_note_frames: 2,
+        median_filter_size: 3,
+        written_transposition: 2,
+    },
+    InstrumentProfile {
+        name: "アルトサックス",
+        // 実音D♭3〜A♭5（記譜B♭3〜F6）
+        note_range: (49, 80),
+        point_times: 8,
+        min_volume: -35,
+        min_note_frames: 2,
+        median_filter_size: 3,
+        written_transposition: 9,
+    },
+];
+
+impl InstrumentProfile {
+    /// プロファイルの設定を、それ以外の設定はそのままで`snapshot`に反映します。
+    pub fn apply(&self, snapshot: &mut AppConfigSnapshot) {
+        (snapshot.min_note, snapshot.max_note) = self.note_range;
+        snapshot.point_times = self.point_times;
+        snapshot.min_volume = self.min_volume;
+        snapshot.min_note_frames = self.min_note_frames;
+        snapshot.median_filter_size = self.median_filter_size;
+        snapshot.written_transposition = self.written_transposition;
+    }
+}
+
+/// 前回終了した時に保存した設定を読み込みます。
+/// ファイルが無い場合や読み込めない場合は`None`を返します。
+pub fn load_last_settings() -> Option<AppConfigSnapshot> {
+    let text = fs::read_to_string(data_path(LAST_SETTINGS_FILE_NAME)?).ok()?;
+    let mut snapshot = AppConfigSnapshot::default();
+    for line in text.lines() {
+        snapshot.apply_line(line);
+    }
+    Some(snapshot)
+}
+
+/// 次に起動した時に戻せるように、終了する時の設定を保存します。
+pub fn save_last_settings(snapshot: &AppConfigSnapshot) -> io::Result<()> {
+    let path = data_path(LAST_SETTINGS_FILE_NAME).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "保存先のフォルダが見つかりません。",
+        )
+    })?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    };
+    fs::write(path, snapshot.to_text())
+}
+
+/// 前回終了した時に保存したMIDIの出力先の名前を読み込みます。
+/// 出力先が無かった場合や、ファイルが無い場合は`None`を返します。
+pub fn load_last_midi_output() -> Option<String> {
+    let text = fs::read_to_string(data_path(LAST_MIDI_OUTPUT_FILE_NAME)?).ok()?;
+    let name = text.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// UIを使えない時や、起動時に前回の出力先に接続する設定の時に同じ出力先を使えるように、終了する時のMIDIの出力先の名前を保存します。
+pub fn save_last_midi_output(name: Option<&str>) -> io::Result<()> {
+    let path = data_path(LAST_MIDI_OUTPUT_FILE_NAME).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "保存先のフォルダが見つかりません。",
+        )
+    })?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    };
+    fs::write(path, name.unwrap_or_default())
+}
+
+/// 選んだ音声のバックエンドの名前を読み込みます。
+/// 既定のものを使う場合や、ファイルが無い場合は`None`を返します。
+pub fn load_audio_backend() -> Option<String> {
+    let text = fs::read_to_string(data_path(AUDIO_BACKEND_FILE_NAME)?).ok()?;
+    let name = text.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// 次に起動した時から使う音声のバックエンドの名前を保存します。`None`なら既定のものを使います。
+pub fn save_audio_backend(name: Option<&str>) -> io::Result<()> {
+    let path = data_path(AUDIO_BACKEND_FILE_NAME).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "保存先のフォルダが見つかりません。",
+        )
+    })?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    };
+    fs::write(path, name.unwrap_or_default())
+}
+
+/// 起動時の動作の設定です。DAWのテンプレート等と一緒に自動で起動する場合に使います。
+/// 解析の設定とは別のファイルに`名前=値`の行で保存するので、プリセットには含まれません。
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct StartupOptions {
+    /// 前回終了した時のMIDIの出力先に接続するかどうか
+    pub connect_last_midi_output: bool,
+    /// 起動した時に読み込むプリセットの名前（読み込まない場合は`None`）
+    pub preset: Option<String>,
+}
+
+impl StartupOptions {
+    /// 保存されている設定を読み込みます。ファイルが無い場合や読み込めない場合は、何もしない設定にします。
+    pub fn load() -> Self {
+        let mut options = Self::default();
+        let Some(text) =
+            data_path(STARTUP_FILE_NAME).and_then(|path| fs::read_to_string(path).ok())
+        else {
+            return options;
+        };
+
+        for (name, value) in text.lines().filter_map(|line| line.split_once('=')) {
+            match name.trim() {
+                "connect_last_midi_output" => {
+                    options.connect_last_midi_output = value.trim() == "1"
+                }
+                "preset" => {
+                    options.preset = (!value.trim().is_empty()).then(|| value.trim().to_string())
+                }
+                _ => (),
+            };
+        }
+
+        options
+    }
+
+    /// 設定を保存します。
+    pub fn save(&self) -> io::Result<()> {
+        let path = data_path(STARTUP_FILE_NAME).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "保存先のフォルダが見つかりません。",
+            )
+        })?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        };
+        fs::write(
+            path,
+            format!(
+                "connect_last_midi_output={}\npreset={}\n",
+                self.connect_last_midi_output as u8,
+                self.preset.as_deref().unwrap_or_default()
+            ),
+        )
+    }
+}
@@ -0,0 +1,99 @@
+//! お手本の旋律と検出した音を照らし合わせて、弾いている位置を追いかけるためのモジュールです。
+
+/// 新しく弾かれた音とみなすまでに、同じ音が続けて検出される回数
+const STABLE_FRAMES: u32 = 3;
+/// 弾き飛ばしたとみなして先に進める、お手本の音の数の上限
+const LOOK_AHEAD: usize = 3;
+
+/// 弾かれた音の判定です。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Judgement {
+    /// お手本の次の音を弾いた
+    Correct,
+    /// お手本の音を、この数だけ飛ばして弾いた
+    Skipped(usize),
+    /// お手本にない音（MIDIの番号）を弾いた
+    Wrong(u8),
+}
+
+/// お手本の旋律の中で、弾いている位置を追いかけるための構造体です。
+#[derive(Default)]
+pub struct ScoreFollower {
+    /// お手本の旋律（MIDIの番号）
+    melody: Vec<u8>,
+    /// 次に弾く音の位置
+    position: usize,
+    /// 続けて検出されている音と、その回数
+    candidate: Option<(u8, u32)>,
+    /// 最後に判定した音（音が途切れるまで、同じ音は判定し直さない）
+    judged: Option<u8>,
+}
+
+impl ScoreFollower {
+    /// お手本の旋律を設定し、最初に戻します。
+    pub fn load(&mut self, melody: Vec<u8>) {
+        self.melody = melody;
+        self.reset();
+    }
+
+    /// 最初に戻します。
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.candidate = None;
+        self.judged = None;
+    }
+
+    /// お手本の旋律が設定されているかどうかを取得します。
+    pub fn is_loaded(&self) -> bool {
+        !self.melody.is_empty()
+    }
+
+    /// 次に弾く音を取得します。最後まで弾いた場合は`None`を返します。
+    pub fn expected(&self) -> Option<u8> {
+        self.melody.get(self.position).copied()
+    }
+
+    /// 次に弾く音の位置と、お手本の音の数を取得します。
+    pub fn progress(&self) -> (usize, usize) {
+        (self.position, self.melody.len())
+    }
+
+    /// 検出した一番目の音を受け取ります。
+    /// 新しく弾かれた音とみなした場合は、お手本と照らし合わせて位置を進め、その判定を返します。
+    pub fn process(&mut self, detected: Option<u8>) -> Option<Judgement> {
+        let Some(number) = detected else {
+            self.candidate = None;
+            self.judged = None;
+            return None;
+        };
+
+        let count = match self.candidate {
+            Some((candidate, count)) if candidate == number => count + 1,
+            _ => 1,
+        };
+        self.candidate = Some((number, count));
+        if count < STABLE_FRAMES || self.judged == Some(number) || self.expected().is_none() {
+            return None;
+        };
+        self.judged = Some(number);
+
+        // 次の音から少し先までを探し、見つかったらその次に進める。
+        let end = (self.position + LOOK_AHEAD + 1).min(self.melody.len());
+        Some(
+            match self.melody[self.position..end]
+                .iter()
+                .position(|key| *key == number)
+            {
+                Some(offset) => {
+                    self.position += offset + 1;
+                    if offset == 0 {
+                        Judgement::Correct
+                    } else {
+                        Judgement::Skipped(offset)
+                    }
+                }
+                None => Judgement::Wrong(number),
+            },
+        )
+    }
+}
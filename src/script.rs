@@ -0,0 +1,148 @@
+//! 検出した音をMIDIで送る前に加工する、ユーザーのスクリプトを扱うためのモジュールです。
+//! スクリプトは[Rhai](https://rhai.rs)で書き、データのフォルダの`script.rhai`に置きます。
+//! スクリプトでは、音が検出される度に呼ばれる次の関数を定義します。
+//! ```rhai
+//! // `note`は一番目の音で、`number`（MIDIの番号）、`frequency`、`cents`、`confidence`を持つマップです。
+//! // `()`を返すと検出しなかったことに、整数を返すとその番号の音にします。
+//! // マップを返した場合は、その`number`を使います。
+//! fn on_note(note) {
+//!     if note.confidence < 10.0 { return (); }
+//!     note.number + 12
+//! }
+//! ```
+//! また、スクリプトからは`control_change(番号, 値)`でMIDIのコントロールチェンジを送れます。
+
+use std::{cell::RefCell, fs, rc::Rc};
+
+use rhai::{Dynamic, Engine, Map, Scope, AST, FLOAT, INT};
+
+use crate::{
+    locale::{tr, tr_format},
+    midi::MidiManager,
+    misc::data_path,
+    sys::Note,
+};
+
+/// スクリプトのファイルの名前
+const SCRIPT_FILE_NAME: &str = "script.rhai";
+/// 音が検出される度に呼ぶ、スクリプトの関数の名前
+const HOOK_FUNCTION_NAME: &str = "on_note";
+
+/// 読み込んだスクリプトです。
+pub struct ScriptHook {
+    engine: Engine,
+    /// 読み込んだスクリプト（読み込んでいない時やエラーで止めた時は`None`）
+    ast: Option<AST>,
+    /// スクリプトから送るように頼まれたコントロールチェンジ
+    control_changes: Rc<RefCell<Vec<(u8, u8)>>>,
+}
+
+impl ScriptHook {
+    /// インスタンスを作ります。スクリプトは`reload`で読み込みます。
+    pub fn new() -> Self {
+        let control_changes = Rc::new(RefCell::new(Vec::new()));
+        let mut engine = Engine::new();
+
+        engine.register_fn("control_change", {
+            let control_changes = Rc::clone(&control_changes);
+            move |control: INT, value: INT| {
+                control_changes
+                    .borrow_mut()
+                    .push((control.clamp(0, 127) as _, value.clamp(0, 127) as _));
+            }
+        });
+
+        Self {
+            engine,
+            ast: None,
+            control_changes,
+        }
+    }
+
+    /// スクリプトを読み込み直し、結果を表すメッセージを返します。
+    pub fn reload(&mut self) -> String {
+        self.ast = None;
+
+        let Some(path) = data_path(SCRIPT_FILE_NAME).filter(|path| path.exists()) else {
+            return tr("スクリプトのファイルがないため、スクリプトは使いません。").to_string();
+        };
+        let result = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|script| {
+                self.engine
+                    .compile(script)
+                    .map_err(|error| error.to_string())
+            });
+
+        match result {
+            Ok(ast) => {
+                self.ast = Some(ast);
+                tr_format("スクリプト「{}」を読み込みました。", &[&path.display()])
+            }
+            Err(error) => tr_format("スクリプトを読み込めませんでした。\n{}", &[&error]),
+        }
+    }
+
+    /// 一番目の音をスクリプトで加工します。
+    /// スクリプトでエラーが起きた場合は、それ以降スクリプトを使わないようにして、エラーを返します。
+    pub fn process<const N: usize>(
+        &mut self,
+        notes: Option<[Note; N]>,
+        midi_manager: &mut MidiManager,
+    ) -> Result<Option<[Note; N]>, String> {
+        let (Some(ast), Some(mut notes)) = (&self.ast, notes.clone()) else {
+            return Ok(notes);
+        };
+
+        let mut note = Map::new();
+        note.insert("number".into(), Dynamic::from(notes[0].number as INT));
+        note.insert(
+            "frequency".into(),
+            Dynamic::from(notes[0].frequency as FLOAT),
+        );
+        note.insert("cents".into(), Dynamic::from(notes[0].cents as FLOAT));
+        note.insert(
+            "confidence".into(),
+            Dynamic::from(notes[0].confidence as FLOAT),
+        );
+
+        let result =
+            self.engine
+                .call_fn::<Dynamic>(&mut Scope::new(), ast, HOOK_FUNCTION_NAME, (note,));
+
+        // エラーが起きても、それまでに頼まれたコントロールチェンジは送る。
+        for (control, value) in self.control_changes.borrow_mut().drain(..) {
+            midi_manager.send_control_change(control, value);
+        }
+
+        let result = result.map_err(|error| error.to_string()).and_then(|value| {
+            if value.is_unit() {
+                return Ok(None);
+            };
+            let number = match value.clone().try_cast::<Map>() {
+                Some(note) => note.get("number").and_then(|number| number.as_int().ok()),
+                None => value.as_int().ok(),
+            };
+
+            match number {
+                Some(number @ 0..=127) => Ok(Some(number as u8)),
+                _ => Err(tr_format("「{}」はMIDIの番号として使えません。", &[&value])),
+            }
+        });
+
+        match result {
+            Ok(Some(number)) => {
+                notes[0].number = number;
+                Ok(Some(notes))
+            }
+            Ok(None) => Ok(None),
+            Err(error) => {
+                self.ast = None;
+                Err(tr_format(
+                    "スクリプトでエラーが起きたため、スクリプトを止めました。\n{}",
+                    &[&error],
+                ))
+            }
+        }
+    }
+}
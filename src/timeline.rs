@@ -0,0 +1,133 @@
+//! UIの操作やデバイスの変化、MIDIの出力等の出来事を、起きた順に残しておくためのモジュールです。
+//! 開発者向けの「タイムライン」のウィンドウで、出来事の順番が正しいかどうかを確かめるのに使います。
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    rc::Rc,
+    time::Instant,
+};
+
+use crate::locale::tr;
+
+/// 残しておく出来事の数の上限
+const CAPACITY: usize = 500;
+
+/// 出来事の種類です。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimelineKind {
+    /// UIから頼まれた操作
+    Command,
+    /// 入力デバイスやMIDIの出力先の変化
+    Device,
+    /// 送ったMIDIのメッセージ
+    Midi,
+}
+
+impl TimelineKind {
+    pub const ALL: [Self; 3] = [Self::Command, Self::Device, Self::Midi];
+
+    /// UIに表示する名前を取得します。
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Command => "操作",
+            Self::Device => "デバイス",
+            Self::Midi => "MIDI",
+        }
+    }
+}
+
+/// 一つの出来事です。
+struct TimelineEntry {
+    time: Instant,
+    kind: TimelineKind,
+    summary: String,
+}
+
+/// 出来事を残しておくためのものです。複製したものは同じ出来事を共有します。
+#[derive(Clone)]
+pub struct Timeline {
+    entries: Rc<RefCell<VecDeque<TimelineEntry>>>,
+    /// 出来事を加える度に増やす番号（表示を更新する必要があるかどうかを調べるのに使う）
+    revision: Rc<Cell<usize>>,
+    started_at: Instant,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self {
+            entries: Rc::new(RefCell::new(VecDeque::with_capacity(CAPACITY))),
+            revision: Rc::new(Cell::new(0)),
+            started_at: Instant::now(),
+        }
+    }
+
+    /// 出来事を加えます。上限を超えた場合は古いものから捨てます。
+    pub fn record(&self, kind: TimelineKind, summary: impl Into<String>) {
+        let mut entries = self.entries.borrow_mut();
+        if entries.len() >= CAPACITY {
+            entries.pop_front();
+        };
+        entries.push_back(TimelineEntry {
+            time: Instant::now(),
+            kind,
+            summary: summary.into(),
+        });
+        self.revision.set(self.revision.get() + 1);
+    }
+
+    /// 出来事を加える度に変わる番号を取得します。
+    pub fn revision(&self) -> usize {
+        self.revision.get()
+    }
+
+    /// 出来事を、新しい順に一行ずつ`経過時間（秒） [種類] 内容`の形式で並べた文字列にします。
+    /// `kind`を指定した場合は、その種類の出来事だけにします。
+    pub fn format(&self, kind: Option<TimelineKind>) -> String {
+        self.entries
+            .borrow()
+            .iter()
+            .rev()
+            .filter(|entry| kind.is_none() || kind == Some(entry.kind))
+            .map(|entry| {
+                format!(
+                    "{:>10.3} [{}] {}",
+                    entry.time.duration_since(self.started_at).as_secs_f64(),
+                    tr(entry.kind.name()),
+                    entry.summary
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// MIDIのメッセージを、タイムラインに表示する文字列にします。
+pub fn describe_midi(message: &[u8]) -> String {
+    let bytes = message
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let name = match message.first().map(|status| status & 0xF0) {
+        Some(0x80) => "Note Off",
+        Some(0x90) if message.get(2) == Some(&0) => "Note Off",
+        Some(0x90) => "Note On",
+        Some(0xB0) => "Control Change",
+        Some(0xC0) => "Program Change",
+        Some(0xE0) => "Pitch Bend",
+        _ => "",
+    };
+
+    if name.is_empty() {
+        bytes
+    } else {
+        format!("{bytes} ({name})")
+    }
+}
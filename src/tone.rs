@@ -0,0 +1,255 @@
+//! 音程を合わせるための基準音を、出力デバイスで鳴らすためのモジュールです。
+
+use std::{
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering::SeqCst},
+        Arc,
+    },
+};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    SampleFormat,
+};
+
+use crate::{config::AppConfig, locale::tr, misc::prelude::*};
+
+/// オルガンの音色で重ねる倍音の強さ（基音から順番）
+const ORGAN_HARMONICS: [f32; 4] = [1., 0.5, 0.33, 0.25];
+/// クリック音の周波数（Hz）
+const CLICK_FREQUENCY: f32 = 1000.;
+/// クリック音の長さ（秒）
+const CLICK_DURATION: f32 = 0.03;
+/// クリック音の音量
+const CLICK_VOLUME: f32 = 0.2;
+
+/// 基準音の音色です。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Timbre {
+    /// サイン波
+    Sine,
+    /// 倍音を重ねたオルガンのような音
+    Organ,
+}
+
+impl Timbre {
+    pub const ALL: [Self; 2] = [Self::Sine, Self::Organ];
+
+    /// UIに表示する名前を取得します。
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sine => "サイン波",
+            Self::Organ => "オルガン",
+        }
+    }
+
+    /// `ALL`での位置から取得します。
+    pub fn from_index(index: u8) -> Self {
+        Self::ALL.get(index as usize).copied().unwrap_or(Self::Sine)
+    }
+
+    /// 波形の一周期の中の位置（0〜1）での値を計算します。
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Self::Sine => (2. * PI * phase).sin(),
+            Self::Organ => {
+                ORGAN_HARMONICS
+                    .iter()
+                    .enumerate()
+                    .map(|(index, weight)| weight * (2. * PI * phase * (index + 1) as f32).sin())
+                    .sum::<f32>()
+                    / ORGAN_HARMONICS.iter().sum::<f32>()
+            }
+        }
+    }
+}
+
+/// 出力ストリームと共有する、基準音の設定です。
+struct ToneSettings {
+    /// 周波数（`f32::to_bits`の値）
+    frequency: AtomicU32,
+    /// 音量（%）
+    volume: AtomicU8,
+    /// 音色（`Timbre::ALL`での位置）
+    timbre: AtomicU8,
+}
+
+/// 基準音を鳴らすための構造体です。
+/// 鳴らしている間は、マイクで拾った基準音でMIDIの音が鳴らないように、`AppConfig::is_reference_tone_playing`を立てます。
+pub struct ToneGenerator {
+    settings: Arc<ToneSettings>,
+    config: Arc<AppConfig>,
+    stream: Option<cpal::Stream>,
+}
+
+impl ToneGenerator {
+    /// インスタンスを作ります。`config`は、検出に使っている設定です。
+    pub fn new(config: Arc<AppConfig>) -> Self {
+        Self {
+            settings: Arc::new(ToneSettings {
+                frequency: AtomicU32::new((config.reference_pitch.load(SeqCst) as f32).to_bits()),
+                volume: AtomicU8::new(30),
+                timbre: AtomicU8::new(0),
+            }),
+            config,
+            stream: None,
+        }
+    }
+
+    /// 鳴らす音階をMIDIの番号で設定します。
+    /// 周波数は、今の基準のラの周波数と音律から求めます。
+    pub fn set_note(&self, number: u8) {
+        let reference_pitch = self.config.reference_pitch.load(SeqCst) as f32;
+        let steps = number as i32 - 69;
+        let ratio = match &*self.config.scale.lock().unwrap() {
+            Some(scale) => scale.ratio(steps),
+            None => 2f32.powf(steps as f32 / 12.),
+        };
+
+        self.settings
+            .frequency
+            .store((reference_pitch * ratio).to_bits(), SeqCst);
+    }
+
+    /// 音量（%）を設定します。
+    pub fn set_volume(&self, volume: u8) {
+        self.settings.volume.store(volume, SeqCst);
+    }
+
+    /// 音色を設定します。
+    pub fn set_timbre(&self, timbre: Timbre) {
+        self.settings.timbre.store(
+            Timbre::ALL.iter().position(|t| *t == timbre).unwrap() as _,
+            SeqCst,
+        );
+    }
+
+    /// 既定の出力デバイスで鳴らし始めます。
+    pub fn play(&mut self) -> Result<(), String> {
+        let device = crate::misc::audio_host(crate::presets::load_audio_backend().as_deref())
+            .default_output_device()
+            .context(tr("出力デバイスがありません。"))?;
+        let device_config = output_config(&device)?;
+        let channels = device_config.channels() as usize;
+        let frame_rate = device_config.sample_rate().0 as f32;
+
+        let settings = Arc::clone(&self.settings);
+        let mut phase = 0f32;
+        let stream = device
+            .build_output_stream(
+                &device_config.into(),
+                move |data: &mut [f32], _| {
+                    let frequency = f32::from_bits(settings.frequency.load(SeqCst));
+                    let volume = settings.volume.load(SeqCst) as f32 / 100.;
+                    let timbre = Timbre::from_index(settings.timbre.load(SeqCst));
+
+                    for frame in data.chunks_mut(channels) {
+                        frame.fill(volume * timbre.sample(phase));
+                        phase = (phase + frequency / frame_rate).fract();
+                    }
+                },
+                |error| tracing::warn!("Output stream error: {error}"),
+                None,
+            )
+            .context(tr("出力ストリームを作れませんでした。"))?;
+        stream.play().context(tr("基準音を鳴らせませんでした。"))?;
+
+        self.stream = Some(stream);
+        self.config.is_reference_tone_playing.store(true, SeqCst);
+        Ok(())
+    }
+
+    /// 鳴らすのを止めます。
+    pub fn stop(&mut self) {
+        self.stream = None;
+        self.config.is_reference_tone_playing.store(false, SeqCst);
+    }
+}
+
+/// 短く柔らかいクリック音を鳴らすための構造体です。
+/// 出力ストリームは最初に鳴らす時に作り、その後は使い回します。
+#[derive(Default)]
+pub struct ClickPlayer {
+    /// 次のクリック音を鳴らすかどうか（出力ストリームと共有）
+    is_requested: Arc<AtomicBool>,
+    stream: Option<cpal::Stream>,
+}
+
+impl ClickPlayer {
+    /// クリック音を既定の出力デバイスで鳴らします。
+    pub fn click(&mut self) -> Result<(), String> {
+        if self.stream.is_none() {
+            self.stream = Some(self.start()?);
+        };
+        self.is_requested.store(true, SeqCst);
+        Ok(())
+    }
+
+    /// クリック音を鳴らすための出力ストリームを作ります。
+    fn start(&self) -> Result<cpal::Stream, String> {
+        let device = crate::misc::audio_host(crate::presets::load_audio_backend().as_deref())
+            .default_output_device()
+            .context(tr("出力デバイスがありません。"))?;
+        let device_config = output_config(&device)?;
+        let channels = device_config.channels() as usize;
+        let frame_rate = device_config.sample_rate().0 as f32;
+        let length = (CLICK_DURATION * frame_rate) as usize;
+
+        let is_requested = Arc::clone(&self.is_requested);
+        // 鳴らし終えた状態から始める。
+        let mut position = length;
+        let stream = device
+            .build_output_stream(
+                &device_config.into(),
+                move |data: &mut [f32], _| {
+                    if is_requested.swap(false, SeqCst) {
+                        position = 0;
+                    };
+
+                    for frame in data.chunks_mut(channels) {
+                        let value = if position < length {
+                            // 耳障りにならないように、だんだん小さくする。
+                            let progress = position as f32 / length as f32;
+                            let time = position as f32 / frame_rate;
+                            position += 1;
+                            CLICK_VOLUME
+                                * (1. - progress).powi(2)
+                                * (2. * PI * CLICK_FREQUENCY * time).sin()
+                        } else {
+                            0.
+                        };
+                        frame.fill(value);
+                    }
+                },
+                |error| tracing::warn!("Output stream error: {error}"),
+                None,
+            )
+            .context(tr("出力ストリームを作れませんでした。"))?;
+        stream
+            .play()
+            .context(tr("クリック音を鳴らせませんでした。"))?;
+
+        Ok(stream)
+    }
+}
+
+/// 出力デバイスの、`f32`の形式で鳴らせる設定を取得します。
+/// ALSA等では既定の設定が`f32`でないことがあるので、その場合は既定と同じ周波数で`f32`を使える設定を探します。
+fn output_config(device: &cpal::Device) -> Result<cpal::SupportedStreamConfig, String> {
+    let default_config = device
+        .default_output_config()
+        .context(tr("出力デバイスの設定を取得できませんでした。"))?;
+    if default_config.sample_format() == SampleFormat::F32 {
+        return Ok(default_config);
+    };
+
+    let sample_rate = default_config.sample_rate();
+    device
+        .supported_output_configs()
+        .context(tr("出力デバイスの設定を取得できませんでした。"))?
+        .filter(|range| range.sample_format() == SampleFormat::F32)
+        .find(|range| (range.min_sample_rate()..=range.max_sample_rate()).contains(&sample_rate))
+        .map(|range| range.with_sample_rate(sample_rate))
+        .context(tr("出力デバイスが対応していない形式です。"))
+}
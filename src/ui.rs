@@ -1,31 +1,1369 @@
-use std::sync::{atomic::Ordering::SeqCst, mpsc::Sender, Arc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    rc::Rc,
+    sync::{atomic::Ordering::SeqCst, Arc},
+    time::{Duration, Instant},
+};
 
-use dialog_unwrapper::rfd::{AsyncMessageDialog, MessageLevel};
-use libui::{controls::*, layout, menu, prelude::*};
+use dialog_unwrapper::rfd::{
+    AsyncMessageDialog, FileDialog, MessageButtons, MessageDialog, MessageLevel,
+};
+use libui::{
+    controls::*,
+    draw::{Brush, FillMode, LineCap, LineJoin, Path, SolidBrush, StrokeParams},
+    layout, menu,
+    prelude::*,
+};
 
-use crate::misc::{app_meta, prelude::*};
+use crate::{
+    command::{AppCommand, CommandBus},
+    config::{AppConfig, AppConfigSnapshot},
+    drift::DriftDetector,
+    file_analysis,
+    history::{History, MemoryBudget, LOG_SHARE, SPECTRUM_SHARE},
+    locale::{self, tr, tr_format, Language},
+    logging,
+    misc::{app_meta, audio_host_names, data_path, prelude::*},
+    params,
+    practice::{format_duration, PracticeTimer},
+    presets::{
+        load_audio_backend, load_last_midi_output, load_last_settings, save_audio_backend,
+        PresetStore, StartupOptions, INSTRUMENT_PROFILES,
+    },
+    score::{Judgement, ScoreFollower},
+    sys::{silence::SilenceProfile, Note, NoteNaming, StreamStats},
+    timeline::{Timeline, TimelineKind},
+    tone::{ClickPlayer, Timbre, ToneGenerator},
+    vibrato::{VibratoAnalyzer, VibratoStats},
+};
+
+/// 最も安定している音を選ぶ時に見る、最近のフレームの数
+const STABLE_NOTE_FRAMES: usize = 10;
+/// スペクトルの表示の一番低い周波数（A0）
+pub const SPECTRUM_MIN_FREQUENCY: f32 = 27.5;
+/// スペクトルの表示の一番高い周波数（C8）
+pub const SPECTRUM_MAX_FREQUENCY: f32 = 4186.;
+/// スペクトルの表示の横方向の区間の数
+pub const SPECTRUM_BINS: usize = 256;
+/// スペクトルの表示の縦方向の範囲（dB）
+const SPECTRUM_RANGE: f32 = 60.;
+/// チューナーの針で表示する範囲（±セント）
+const TUNER_RANGE: f32 = 50.;
+/// チューナーで音が合っているとみなす範囲（±セント）
+const TUNER_IN_TUNE_CENTS: f32 = 5.;
+/// 音程のずれを知らせる時に、チューナーの背景の色を変える時間
+const DRIFT_ALARM_FLASH_DURATION: Duration = Duration::from_millis(400);
+/// スペクトログラムに表示する時間
+const SPECTROGRAM_DURATION: Duration = Duration::from_secs(10);
+/// スペクトログラムの縦方向の区間の数（`SPECTRUM_BINS`の約数にする）
+const SPECTROGRAM_ROWS: usize = 64;
+/// スペクトログラムの色の段階の数
+const SPECTROGRAM_LEVELS: usize = 8;
+
+/// 入力の音量のメーターで表示する範囲（0dBから下の範囲、dB）
+const LEVEL_METER_RANGE: f32 = 80.;
+/// 音割れを知らせる表示を出しておく時間
+const CLIP_HOLD_DURATION: Duration = Duration::from_secs(1);
+
+/// 音量の表示で一番長くなる文字列（表示の幅を確保するのに使う）
+const LEVEL_LABEL_TEXTS: [&str; 2] = ["-∞ dB", "-80 dB"];
+
+/// 解析の遅延の表示を更新する間隔
+const LATENCY_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// タイムラインのウィンドウの表示を更新する間隔
+const TIMELINE_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 校正のために基準音を測る時間
+const CALIBRATION_DURATION: Duration = Duration::from_secs(2);
+/// 校正で使う、基準音とみなす範囲（±セント）
+const CALIBRATION_RANGE: f32 = 100.;
+
+/// 無音データを保存するファイルの名前
+const SILENCE_FILE_NAME: &str = "silence.txt";
+
+/// 受け取った時間とスペクトルの履歴（解析を行わなかった時は空のスペクトル）
+type SpectrumHistory = Rc<RefCell<History<Vec<f32>>>>;
 
 mod texts {
     pub(super) const SET_SILENT_DATA: &str = "無音データを設定する";
 }
 
+/// 日本語と英語が混ざった表示の幅を揃えるためのモジュールです。
+/// libuiではラベルのフォントを指定できず、内容が変わるとラベルの幅も変わって他のものがずれたり、
+/// 最初の幅に収まらない分が切れたりするので、表示しうる一番長い文字列の幅を最初に確保しておきます。
+mod theme {
+    /// 全角の文字の幅を1とした時の、半角の文字の幅
+    /// フォントによって違いますが、大体のフォントで足りるように少し広めにしています。
+    const NARROW_WIDTH: f32 = 0.6;
+
+    /// 全角の文字かどうかを調べます。（CJKの文字と、全角の記号等）
+    fn is_wide(character: char) -> bool {
+        matches!(character as u32,
+            0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3040..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x20000..=0x3FFFD)
+    }
+
+    /// 文字列の幅を、全角の文字の幅を1として求めます。
+    pub(super) fn text_width(text: &str) -> f32 {
+        text.chars()
+            .map(|character| if is_wide(character) { 1. } else { NARROW_WIDTH })
+            .sum()
+    }
+
+    /// 指定した文字列のどれを表示しても収まる幅を、全角の文字の数で求めます。
+    pub(super) fn reserved_width(texts: &[&str]) -> usize {
+        texts
+            .iter()
+            .map(|text| text_width(text))
+            .fold(0., f32::max)
+            .ceil() as usize
+    }
+
+    /// 指定した文字列のどれを表示しても収まる幅の、全角の空白を並べた文字列を作ります。
+    /// 最初にラベルに設定して、ラベルの幅を確保するのに使います。
+    pub(super) fn placeholder(texts: &[&str]) -> String {
+        "　".repeat(reserved_width(texts))
+    }
+
+    /// 文字列の後ろに全角の空白を足して、`width`（全角の文字の数）の幅以上にします。
+    /// 内容が変わるラベルの幅が、変わる度に縮まないようにするのに使います。
+    pub(super) fn pad(text: &str, width: usize) -> String {
+        let missing = (width as f32 - text_width(text)).max(0.).floor() as usize;
+        format!("{text}{}", "　".repeat(missing))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// 確かめる文字の大きさ（ピクセル）
+        const FONT_SIZES: [f32; 8] = [9., 10., 11., 12., 14., 16., 20., 24.];
+
+        const ASCII: &str = "Level: -12.3 dB";
+        const CJK: &str = "練習時間";
+        const MIXED: &str = "練習時間 000:00 / 000:00";
+
+        fn assert_width(text: &str, expected: f32) {
+            let width = text_width(text);
+            assert!((width - expected).abs() < 1e-4, "{text:?}: {width}");
+        }
+
+        #[test]
+        fn text_width_of_labels() {
+            assert_width("", 0.);
+            assert_width(ASCII, 15. * NARROW_WIDTH);
+            assert_width(CJK, 4.);
+            assert_width(MIXED, 4. + 16. * NARROW_WIDTH);
+            // 全角の英数字や空白、ハングルは全角、半角のカタカナは半角として数える。
+            assert_width("ＡＢ　한", 4.);
+            assert_width("ｱｲ", 2. * NARROW_WIDTH);
+        }
+
+        #[test]
+        fn reserved_width_of_labels() {
+            assert_eq!(reserved_width(&[]), 0);
+            assert_eq!(reserved_width(&[ASCII]), 9);
+            assert_eq!(reserved_width(&[CJK]), 4);
+            assert_eq!(reserved_width(&[MIXED]), 14);
+            assert_eq!(reserved_width(&[ASCII, CJK, MIXED]), 14);
+        }
+
+        #[test]
+        fn placeholder_fits_at_each_font_size() {
+            for texts in [&[ASCII][..], &[CJK], &[MIXED], &[ASCII, CJK, MIXED]] {
+                let placeholder = placeholder(texts);
+                for font_size in FONT_SIZES {
+                    // 全角の空白の幅は、文字の大きさと同じです。
+                    let reserved = placeholder.chars().count() as f32 * font_size;
+                    for text in texts {
+                        assert!(
+                            text_width(text) * font_size <= reserved,
+                            "{text:?} at {font_size}px"
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn pad_keeps_labels_from_shrinking() {
+            for text in [ASCII, CJK, MIXED] {
+                let width = reserved_width(&[ASCII, CJK, MIXED]);
+                let padded = pad(text, width);
+                assert!(padded.starts_with(text));
+                assert!(text_width(&padded) > width as f32 - 1., "{padded:?}");
+            }
+            // 既に幅が足りている場合は、そのままにする。
+            assert_eq!(pad(MIXED, 1), MIXED);
+        }
+    }
+}
+
+/// 音階モニタに表示する列です。
+#[derive(Clone, Copy)]
+pub enum MonitorColumn {
+    Name,
+    Octave,
+    Cents,
+    Frequency,
+    MidiNumber,
+    Confidence,
+}
+
+impl MonitorColumn {
+    /// 全ての列（表示する順番）
+    pub const ALL: [Self; 6] = [
+        Self::Name,
+        Self::Octave,
+        Self::Cents,
+        Self::Frequency,
+        Self::MidiNumber,
+        Self::Confidence,
+    ];
+
+    /// 表示メニューでの名前を取得します。
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Name => "音階の名前",
+            Self::Octave => "オクターブ",
+            Self::Cents => "セント",
+            Self::Frequency => "周波数（Hz）",
+            Self::MidiNumber => "MIDIの番号",
+            Self::Confidence => "信頼度（dB）",
+        }
+    }
+
+    /// 最初から表示する列かどうかを調べます。
+    fn is_default(&self) -> bool {
+        matches!(self, Self::Name | Self::Octave)
+    }
+
+    /// 列に表示する文字列を作ります。
+    /// 音階の名前とオクターブは、`transposition`半音ずらした記譜上の音で表示します。
+    /// 音階の名前は、記譜上の音が実音と違う場合は実音も並べて表示します。
+    fn format(&self, rank: usize, note: &Note, naming: NoteNaming, transposition: i32) -> String {
+        let written = note.transposed(transposition);
+        match self {
+            Self::Name if transposition == 0 => {
+                format!("{}: {}", rank, note.get_pitch_class_name_with(naming))
+            }
+            Self::Name => format!(
+                "{}: {}（{}）",
+                rank,
+                written.get_pitch_class_name_with(naming),
+                note.get_pitch_class_name_with(naming)
+            ),
+            Self::Octave => written.get_octave().to_string(),
+            Self::Cents => format!("{:+.0}", note.cents),
+            Self::Frequency => format!("{:.1}", note.frequency),
+            Self::MidiNumber => note.number.to_string(),
+            Self::Confidence => format!("{:.1}", note.confidence),
+        }
+    }
+
+    /// 列の幅を確保するために、最初に表示しておく文字列を作ります。
+    /// どの表し方の音階の名前や、大きい値を表示しても幅が変わらないようにします。
+    fn placeholder(&self, rank: usize) -> String {
+        let mut texts = Vec::new();
+        for naming in NoteNaming::ALL {
+            for number in 60..72 {
+                let note = Note {
+                    number,
+                    frequency: 19999.9,
+                    cents: -50.,
+                    confidence: -100.,
+                };
+                // 移調している場合の、実音も並べた表示が一番長くなる。
+                texts.push(self.format(rank, &note, naming, 1));
+            }
+        }
+
+        let texts = texts.iter().map(String::as_str).collect::<Vec<_>>();
+        match self {
+            // 順位は変わらないので、幅を確保するのは音階の名前の部分だけにする。
+            Self::Name => {
+                let names = texts
+                    .iter()
+                    .map(|text| text.split_once(": ").map_or(*text, |(_, name)| name))
+                    .collect::<Vec<_>>();
+                format!("{rank}: {}", theme::placeholder(&names))
+            }
+            _ => theme::placeholder(&texts),
+        }
+    }
+}
+
+/// タイムラインのウィンドウの状態です。（メニューやウィンドウのコールバックと`Monitor`で共有します。）
+#[derive(Default)]
+struct TimelineWindowState {
+    /// ウィンドウを開いているかどうか
+    is_shown: Cell<bool>,
+    /// 表示する出来事の種類（`None`なら全て）
+    filter: Cell<Option<TimelineKind>>,
+}
+
+/// 設定の変更を元に戻すための履歴です。
+#[derive(Default)]
+struct SettingsHistory {
+    /// 変更した設定の名前と、変更する前の設定
+    snapshots: Vec<(&'static str, AppConfigSnapshot)>,
+}
+
+impl SettingsHistory {
+    /// 履歴に残す変更の数の上限
+    const CAPACITY: usize = 100;
+
+    /// 設定を変更する前に呼び、変更する前の設定を記録します。
+    /// 同じ設定を続けて変更した場合（スピンボックスを連続で動かした場合等）は、最初の変更の前の設定だけを残します。
+    fn record(&mut self, name: &'static str, config: &AppConfig) {
+        if self
+            .snapshots
+            .last()
+            .is_some_and(|(last_name, _)| *last_name == name)
+        {
+            return;
+        };
+
+        self.snapshots.push((name, config.snapshot()));
+        if self.snapshots.len() > Self::CAPACITY {
+            self.snapshots.remove(0);
+        };
+    }
+
+    /// 最後の変更を取り出します。
+    fn undo(&mut self) -> Option<(&'static str, AppConfigSnapshot)> {
+        self.snapshots.pop()
+    }
+}
+
+/// 設定のコントロールをまとめた構造体です。
+#[derive(Clone)]
+struct SettingsControls {
+    window_function_combo_box: Combobox,
+    min_detection_volume_spin_box: Spinbox,
+    a_weighting_check_box: Checkbox,
+    input_channel_spin_box: Spinbox,
+    high_pass_frequency_spin_box: Spinbox,
+    agc_check_box: Checkbox,
+    agc_target_spin_box: Spinbox,
+    noise_reduction_check_box: Checkbox,
+    noise_over_subtraction_spin_box: Spinbox,
+    volume_hysteresis_spin_box: Spinbox,
+    gate_attack_spin_box: Spinbox,
+    gate_hold_spin_box: Spinbox,
+    gate_release_spin_box: Spinbox,
+    pitch_control_spin_box: Spinbox,
+    reference_pitch_spin_box: Spinbox,
+    min_note_spin_box: Spinbox,
+    max_note_spin_box: Spinbox,
+    zoom_fft_check_box: Checkbox,
+    drift_alarm_check_box: Checkbox,
+    drift_alarm_threshold_spin_box: Spinbox,
+    drift_alarm_duration_spin_box: Spinbox,
+    drift_alarm_click_check_box: Checkbox,
+    auto_stop_spin_box: Spinbox,
+    ranking_strategy_combo_box: Combobox,
+    min_confidence_spin_box: Spinbox,
+    ambiguity_margin_spin_box: Spinbox,
+    ambiguity_policy_combo_box: Combobox,
+    octave_lock_check_box: Checkbox,
+    octave_lock_reset_spin_box: Spinbox,
+    note_naming_combo_box: Combobox,
+    written_transposition_spin_box: Spinbox,
+    point_length_size_spin_box: Spinbox,
+    analysis_window_length_spin_box: Spinbox,
+    analysis_overlap_spin_box: Spinbox,
+    history_memory_limit_spin_box: Spinbox,
+    max_fft_length_spin_box: Spinbox,
+    fixed_note_length_check_box: Checkbox,
+    fixed_note_length_spin_box: Spinbox,
+    release_delay_spin_box: Spinbox,
+    note_refresh_interval_spin_box: Spinbox,
+    max_note_duration_spin_box: Spinbox,
+    legato_check_box: Checkbox,
+    onset_retrigger_check_box: Checkbox,
+    onset_threshold_spin_box: Spinbox,
+    min_note_frames_spin_box: Spinbox,
+    min_midi_confidence_spin_box: Spinbox,
+    median_filter_size_spin_box: Spinbox,
+    midi_transposition_spin_box: Spinbox,
+    octave_doubling_combo_box: Combobox,
+    octave_doubling_velocity_spin_box: Spinbox,
+    envelope_control_combo_box: Combobox,
+}
+
+impl SettingsControls {
+    /// コントロールの値を設定の値に合わせます。
+    /// コントロールの値を変えてもイベントは発生しないので、設定自体は別に書き換える必要があります。
+    fn set(&mut self, snapshot: &AppConfigSnapshot) {
+        self.window_function_combo_box
+            .set_selected(snapshot.window_function as _);
+        self.min_detection_volume_spin_box
+            .set_value(snapshot.min_volume);
+        self.a_weighting_check_box
+            .set_checked(snapshot.use_a_weighting);
+        self.input_channel_spin_box
+            .set_value(snapshot.input_channel as _);
+        self.high_pass_frequency_spin_box
+            .set_value(snapshot.high_pass_frequency as _);
+        self.agc_check_box.set_checked(snapshot.use_agc);
+        self.agc_target_spin_box.set_value(snapshot.agc_target);
+        self.noise_reduction_check_box
+            .set_checked(snapshot.use_noise_reduction);
+        self.noise_over_subtraction_spin_box
+            .set_value(snapshot.noise_over_subtraction as _);
+        self.volume_hysteresis_spin_box
+            .set_value(snapshot.volume_hysteresis);
+        self.gate_attack_spin_box
+            .set_value(snapshot.gate_attack as _);
+        self.gate_hold_spin_box.set_value(snapshot.gate_hold as _);
+        self.gate_release_spin_box
+            .set_value(snapshot.gate_release as _);
+        self.pitch_control_spin_box
+            .set_value(snapshot.adjustment_rate);
+        self.reference_pitch_spin_box
+            .set_value(snapshot.reference_pitch as _);
+        let (min_note, max_note) = snapshot.note_range();
+        self.min_note_spin_box.set_value(min_note as _);
+        self.max_note_spin_box.set_value(max_note as _);
+        self.zoom_fft_check_box.set_checked(snapshot.use_zoom_fft);
+        self.drift_alarm_check_box
+            .set_checked(snapshot.use_drift_alarm);
+        self.drift_alarm_threshold_spin_box
+            .set_value(snapshot.drift_alarm_threshold as _);
+        self.drift_alarm_duration_spin_box
+            .set_value(snapshot.drift_alarm_duration as _);
+        self.drift_alarm_click_check_box
+            .set_checked(snapshot.use_drift_alarm_click);
+        self.auto_stop_spin_box
+            .set_value(snapshot.auto_stop_minutes as _);
+        self.ranking_strategy_combo_box
+            .set_selected(snapshot.ranking_strategy as _);
+        self.min_confidence_spin_box
+            .set_value(snapshot.min_confidence);
+        self.ambiguity_margin_spin_box
+            .set_value(snapshot.ambiguity_margin as _);
+        self.ambiguity_policy_combo_box
+            .set_selected(snapshot.ambiguity_policy as _);
+        self.octave_lock_check_box
+            .set_checked(snapshot.use_octave_lock);
+        self.octave_lock_reset_spin_box
+            .set_value(snapshot.octave_lock_reset as _);
+        self.note_naming_combo_box
+            .set_selected(snapshot.note_naming as _);
+        self.written_transposition_spin_box
+            .set_value(snapshot.written_transposition);
+        self.point_length_size_spin_box
+            .set_value(snapshot.point_times as _);
+        self.analysis_window_length_spin_box
+            .set_value(snapshot.analysis_window_length as _);
+        self.analysis_overlap_spin_box
+            .set_value(snapshot.analysis_overlap as _);
+        self.history_memory_limit_spin_box
+            .set_value(snapshot.history_memory_limit as _);
+        self.max_fft_length_spin_box
+            .set_value((snapshot.max_fft_length / 1024) as _);
+        self.fixed_note_length_check_box
+            .set_checked(snapshot.use_fixed_note_length);
+        self.fixed_note_length_spin_box
+            .set_value(snapshot.fixed_note_length as _);
+        self.release_delay_spin_box
+            .set_value(snapshot.release_delay as _);
+        self.note_refresh_interval_spin_box
+            .set_value(snapshot.note_refresh_interval as _);
+        self.max_note_duration_spin_box
+            .set_value(snapshot.max_note_duration as _);
+        self.legato_check_box.set_checked(snapshot.use_legato);
+        self.onset_retrigger_check_box
+            .set_checked(snapshot.use_onset_retrigger);
+        self.onset_threshold_spin_box
+            .set_value(snapshot.onset_threshold as _);
+        self.min_note_frames_spin_box
+            .set_value(snapshot.min_note_frames as _);
+        self.min_midi_confidence_spin_box
+            .set_value(snapshot.min_midi_confidence);
+        self.median_filter_size_spin_box
+            .set_value(snapshot.median_filter_size as _);
+        self.midi_transposition_spin_box
+            .set_value(snapshot.midi_transposition);
+        self.octave_doubling_combo_box
+            .set_selected(match snapshot.octave_doubling {
+                -1 => 1,
+                1 => 2,
+                _ => 0,
+            });
+        self.octave_doubling_velocity_spin_box
+            .set_value(snapshot.octave_doubling_velocity as _);
+        self.envelope_control_combo_box
+            .set_selected(match snapshot.envelope_control {
+                2 => 1,
+                11 => 2,
+                _ => 0,
+            });
+    }
+}
+
+/// 基準音を使った校正の途中の状態です。
+struct Calibration {
+    /// 基準音の周波数（Hz）
+    reference: f32,
+    started_at: Instant,
+    /// 測った周波数
+    frequencies: Vec<f32>,
+    config: Arc<AppConfig>,
+    history: Rc<RefCell<SettingsHistory>>,
+}
+
+impl Calibration {
+    /// 測った周波数の中央値と基準音の周波数の比から補正を求め、設定に反映します。
+    /// 基準音を検出できなかった場合は`None`を返します。
+    fn finish(mut self) -> Option<i32> {
+        if self.frequencies.is_empty() {
+            return None;
+        };
+        self.frequencies.sort_by(f32::total_cmp);
+        let measured = self.frequencies[self.frequencies.len() / 2];
+
+        // 測った周波数には今の補正が掛かっているので、それに重ねる。
+        let current = 1. + self.config.frequency_correction.load(SeqCst) as f32 / 1_000_000.;
+        let correction = (((current * self.reference / measured) - 1.) * 1_000_000.).round() as i64;
+        let correction = params::FREQUENCY_CORRECTION.clamp(correction);
+
+        self.history.borrow_mut().record("校正", &self.config);
+        self.config.frequency_correction.store(correction, SeqCst);
+        Some(correction)
+    }
+}
+
+/// 検出結果等を表示するためのコントロールをまとめた構造体です。
+pub struct Monitor<const N: usize> {
+    /// 音階モニタのラベル（`MonitorColumn::ALL`の順番の列ごと）
+    pub note_labels: Vec<[Label; N]>,
+    pub status_label: Label,
+    config: Arc<AppConfig>,
+    /// 一番目に、一番音量が高い音ではなく最も安定している音を表示するかどうか
+    use_stable_note: Rc<Cell<bool>>,
+    /// 最近のフレームで一番目に検出された音
+    recent_notes: VecDeque<Note>,
+    /// チューナーの音階、周波数、セントのラベル
+    tuner_labels: [Label; 3],
+    /// チューナーの針で示すセント（`TunerView`と共有）
+    tuner_cents: Rc<Cell<Option<f32>>>,
+    tuner_area: Area,
+    /// 伸ばしている音の音程のずれを見つけるためのもの
+    drift_detector: DriftDetector,
+    /// 音程のずれを知らせるために、チューナーの背景の色を変えておく期限（`TunerView`と共有）
+    drift_alarm_until: Rc<Cell<Option<Instant>>>,
+    click_player: ClickPlayer,
+    /// 伸ばしている音のビブラートを求めるためのもの
+    vibrato_analyzer: VibratoAnalyzer,
+    /// ビブラートの速さと深さを表示するラベル
+    vibrato_label: Label,
+    /// 起動してから検出したビブラートの統計（メニューの「練習のまとめ」と共有）
+    vibrato_stats: Rc<RefCell<VibratoStats>>,
+    /// 練習の時間を測るタイマー（メニューの「練習のまとめ」と共有）
+    practice_timer: Rc<RefCell<PracticeTimer>>,
+    /// 練習の時間を表示するラベルと、表示している秒数
+    practice_time_label: Label,
+    shown_practice_seconds: Option<u64>,
+    /// 入力の音量のラベルとメーター、音割れを知らせるラベル
+    level_label: Label,
+    level_meter: ProgressBar,
+    clip_label: Label,
+    /// 音割れを知らせる表示を出しておく期限
+    clip_until: Option<Instant>,
+    /// 表示している音量（dB）と、音割れを知らせているかどうか
+    shown_level: Option<(i32, bool)>,
+    /// 解析の遅延を表示するラベルと、最後に更新した時刻
+    latency_label: Label,
+    latency_shown_at: Option<Instant>,
+    stream_stats: Arc<StreamStats>,
+    /// 校正の途中の状態（校正していない時は`None`）
+    calibration: Rc<RefCell<Option<Calibration>>>,
+    /// 表示するスペクトルの履歴（`SpectrumView`と`SpectrogramView`と共有）
+    spectra: SpectrumHistory,
+    spectrum_area: Area,
+    spectrogram_area: Area,
+    /// スクリプトのメッセージを表示する欄
+    script_console: MultilineEntry,
+    /// スクリプトのメッセージの履歴
+    script_log: History<String>,
+    /// 伴奏の楽譜の音と検出した音を比べた結果を表示するラベル
+    score_comparison_label: Label,
+    /// 楽譜の追従で、次に弾く音と直前の判定を表示するラベル
+    score_following_labels: [Label; 2],
+    /// 出来事のタイムラインと、それを表示する開発者向けのウィンドウ
+    timeline: Timeline,
+    timeline_window_state: Rc<TimelineWindowState>,
+    timeline_entry: MultilineEntry,
+    /// 表示しているタイムラインの番号と絞り込み、最後に更新した時刻
+    shown_timeline: Option<(usize, Option<TimelineKind>)>,
+    timeline_shown_at: Option<Instant>,
+    /// 最後にタイムラインに残した入力ストリームのエラー
+    recorded_stream_error: Option<String>,
+}
+
+impl<const N: usize> Monitor<N> {
+    /// 検出結果を音階モニタに表示します。
+    pub fn show_notes(&mut self, notes: Option<[Note; N]>) {
+        self.show_tuner(notes.as_ref().map(|notes| &notes[0]));
+        self.check_drift(notes.as_ref().map(|notes| &notes[0]));
+        self.show_vibrato(notes.as_ref().map(|notes| &notes[0]));
+        self.update_calibration(notes.as_ref().map(|notes| &notes[0]));
+
+        let Some(mut notes) = notes else {
+            // 途切れる前の音が、次に検出された時に選ばれないようにする。
+            self.recent_notes.clear();
+            return;
+        };
+
+        if self.recent_notes.len() >= STABLE_NOTE_FRAMES {
+            self.recent_notes.pop_front();
+        };
+        self.recent_notes.push_back(notes[0].clone());
+
+        if self.use_stable_note.get() {
+            if let Some(stable_note) = self.stable_note() {
+                // 安定している音を一番目にして、他の音はそのまま後ろにずらす。
+                match notes
+                    .iter()
+                    .position(|note| note.number == stable_note.number)
+                {
+                    Some(index) => notes[..=index].rotate_right(1),
+                    None => {
+                        notes.rotate_right(1);
+                        notes[0] = stable_note;
+                    }
+                };
+            };
+        };
+
+        let (naming, transposition) = (self.note_naming(), self.written_transposition());
+        update_note_monitor(&mut self.note_labels, notes, naming, transposition);
+    }
+
+    /// 練習の時間を表示し、設定した時間が経ったかどうかを調べます。
+    /// 経った時だけ`true`を返すので、その場合は検出とMIDIを止めてください。
+    pub fn tick_practice(&mut self) -> bool {
+        let minutes = self.config.auto_stop_minutes.load(SeqCst);
+        let limit = (minutes > 0).then(|| Duration::from_secs(minutes as u64 * 60));
+        let (is_finished, elapsed) = {
+            let mut practice_timer = self.practice_timer.borrow_mut();
+            (practice_timer.check(limit), practice_timer.elapsed())
+        };
+
+        // 表示は一秒毎に更新する。
+        if is_finished || self.shown_practice_seconds != Some(elapsed.as_secs()) {
+            self.shown_practice_seconds = Some(elapsed.as_secs());
+            let text = match limit {
+                Some(limit) => tr_format(
+                    "練習時間 {} / {}",
+                    &[&format_duration(elapsed), &format_duration(limit)],
+                ),
+                None => tr_format("練習時間 {}", &[&format_duration(elapsed)]),
+            };
+            self.practice_time_label
+                .set_text(&theme::pad(&text, practice_time_width()));
+        };
+        if is_finished {
+            self.status_label.set_text(tr(
+                "練習の時間が終わりました。「練習の時間を最初から測る」で再開できます。",
+            ));
+        };
+
+        is_finished
+    }
+
+    /// 入力の音量のメーターを更新します。
+    /// 音割れした場合は、`CLIP_HOLD_DURATION`の間「CLIP」と表示します。
+    pub fn show_level(&mut self, volume: f32, is_clipped: bool) {
+        let now = Instant::now();
+        if is_clipped {
+            self.clip_until = Some(now + CLIP_HOLD_DURATION);
+        };
+        let is_clip_shown = self.clip_until.is_some_and(|until| now < until);
+
+        // 表示は音量が1dB変わった時か、音割れの表示が変わった時だけ更新する。
+        let level = volume.clamp(-LEVEL_METER_RANGE, 0.).round() as i32;
+        if self.shown_level == Some((level, is_clip_shown)) {
+            return;
+        };
+        self.shown_level = Some((level, is_clip_shown));
+
+        let level_text = if level as f32 <= -LEVEL_METER_RANGE {
+            "-∞ dB".to_string()
+        } else {
+            format!("{level} dB")
+        };
+        self.level_label.set_text(&theme::pad(
+            &level_text,
+            theme::reserved_width(&LEVEL_LABEL_TEXTS),
+        ));
+        self.level_meter
+            .set_value(((level as f32 + LEVEL_METER_RANGE) / LEVEL_METER_RANGE * 100.) as u32);
+        self.clip_label.set_text(&if is_clip_shown {
+            "CLIP".to_string()
+        } else {
+            theme::placeholder(&["CLIP"])
+        });
+    }
+
+    /// 今の設定での解析の遅延の表示を更新します。
+    /// 設定を変えた時に分かるように、`LATENCY_UPDATE_INTERVAL`ごとに求め直します。
+    pub fn show_latency(&mut self) {
+        let now = Instant::now();
+        if self
+            .latency_shown_at
+            .is_some_and(|shown_at| now.duration_since(shown_at) < LATENCY_UPDATE_INTERVAL)
+        {
+            return;
+        };
+        self.latency_shown_at = Some(now);
+
+        let latency = self.stream_stats.latency(
+            self.config.analysis_window_length.load(SeqCst) as _,
+            self.config.analysis_overlap.load(SeqCst),
+        );
+        let milliseconds = |duration: Duration| format!("{:.0}", duration.as_secs_f32() * 1000.);
+        self.latency_label.set_text(&tr_format(
+            "解析の遅延：約{}ミリ秒（入力{}＋窓{}＋間隔{}＋計算{}）",
+            &[
+                &milliseconds(latency.total()),
+                &milliseconds(latency.buffer),
+                &milliseconds(latency.window),
+                &milliseconds(latency.hop),
+                &milliseconds(latency.processing),
+            ],
+        ));
+    }
+
+    /// 入力ストリームのエラーをタイムラインに残し、タイムラインのウィンドウを開いていれば表示を更新します。
+    /// 表示は`TIMELINE_UPDATE_INTERVAL`ごとに、出来事が増えたか絞り込みが変わった時だけ更新します。
+    pub fn tick_timeline(&mut self) {
+        let stream_error = self.stream_stats.last_error();
+        if stream_error.is_some() && stream_error != self.recorded_stream_error {
+            self.timeline.record(
+                TimelineKind::Device,
+                format!(
+                    "Input stream error: {}",
+                    stream_error.as_deref().unwrap_or_default()
+                ),
+            );
+            self.recorded_stream_error = stream_error;
+        };
+
+        if !self.timeline_window_state.is_shown.get() {
+            return;
+        };
+        let now = Instant::now();
+        if self
+            .timeline_shown_at
+            .is_some_and(|shown_at| now.duration_since(shown_at) < TIMELINE_UPDATE_INTERVAL)
+        {
+            return;
+        };
+        self.timeline_shown_at = Some(now);
+
+        let filter = self.timeline_window_state.filter.get();
+        let shown = Some((self.timeline.revision(), filter));
+        if self.shown_timeline != shown {
+            self.shown_timeline = shown;
+            self.timeline_entry.set_value(&self.timeline.format(filter));
+        };
+    }
+
+    /// 練習の時間を最初から測り直します。
+    pub fn restart_practice(&mut self) {
+        self.practice_timer.borrow_mut().restart();
+        self.shown_practice_seconds = None;
+        self.status_label.set_text("");
+    }
+
+    /// 練習のまとめを表示します。
+    pub fn show_practice_summary(&self) {
+        show_practice_summary(&self.practice_timer.borrow(), &self.vibrato_stats.borrow());
+    }
+
+    /// 無音データを取っている途中なら、あと何回分のデータが必要かを表示します。
+    pub fn show_silence_capture(&mut self, frames_left: Option<u32>) {
+        if let Some(frames_left) = frames_left {
+            self.status_label.set_text(&tr_format(
+                "無音データを取っています。静かにしてください…（残り{}）",
+                &[&frames_left],
+            ));
+        } else if self.config.use_silent.load(SeqCst) {
+            self.status_label.set_text(tr("無音データを取りました。"));
+        } else {
+            self.status_label.set_text("");
+        };
+    }
+
+    /// 設定されている音階の名前の表し方を取得します。
+    fn note_naming(&self) -> NoteNaming {
+        NoteNaming::from_index(self.config.note_naming.load(SeqCst))
+    }
+
+    /// 設定されている記譜の移調（半音）を取得します。
+    fn written_transposition(&self) -> i32 {
+        self.config.written_transposition.load(SeqCst)
+    }
+
+    /// チューナーの表示を一番音量が高い音で更新します。
+    fn show_tuner(&mut self, note: Option<&Note>) {
+        let (naming, transposition) = (self.note_naming(), self.written_transposition());
+        let [note_label, frequency_label, cents_label] = &mut self.tuner_labels;
+
+        if let Some(note) = note {
+            // 移調楽器の場合は記譜上の音を表示し、実音も添える。
+            note_label.set_text(&if transposition == 0 {
+                note.get_name_with(naming)
+            } else {
+                tr_format(
+                    "{}（実音 {}）",
+                    &[
+                        &note.transposed(transposition).get_name_with(naming),
+                        &note.get_name_with(naming),
+                    ],
+                )
+            });
+            frequency_label.set_text(&format!("{:.2} Hz", note.frequency));
+            cents_label.set_text(&format!("{:+.1} {}", note.cents, tr("セント")));
+        } else {
+            for label in [note_label, frequency_label, cents_label] {
+                label.set_text("-");
+            }
+        };
+
+        self.tuner_cents.set(note.map(|note| note.cents));
+        self.tuner_area.queue_redraw_all();
+    }
+
+    /// 設定されていれば、伸ばしている音の音程が弾き始めからずれていないか調べ、ずれていたら知らせます。
+    fn check_drift(&mut self, note: Option<&Note>) {
+        if !self.config.use_drift_alarm.load(SeqCst) {
+            self.drift_detector.reset();
+            return;
+        };
+
+        let Some(drift) = self.drift_detector.process(
+            note,
+            self.config.drift_alarm_threshold.load(SeqCst) as _,
+            Duration::from_millis(self.config.drift_alarm_duration.load(SeqCst) as _),
+        ) else {
+            return;
+        };
+
+        self.drift_alarm_until
+            .set(Some(Instant::now() + DRIFT_ALARM_FLASH_DURATION));
+        self.tuner_area.queue_redraw_all();
+        self.status_label.set_text(&tr_format(
+            "音程が弾き始めから{}セントずれました。",
+            &[&format!("{drift:+.0}")],
+        ));
+
+        if self.config.use_drift_alarm_click.load(SeqCst) {
+            if let Err(error) = self.click_player.click() {
+                self.status_label.set_text(&error);
+            };
+        };
+    }
+
+    /// 伸ばしている音のビブラートの速さと深さを表示し、統計に加えます。
+    fn show_vibrato(&mut self, note: Option<&Note>) {
+        let vibrato = self.vibrato_analyzer.process(note);
+        self.vibrato_stats.borrow_mut().add(vibrato);
+
+        self.vibrato_label.set_text(&match vibrato {
+            Some(vibrato) => tr_format(
+                "ビブラート：{} Hz　±{}セント",
+                &[
+                    &format!("{:.1}", vibrato.rate),
+                    &format!("{:.0}", vibrato.depth),
+                ],
+            ),
+            None => tr("ビブラート：-").to_string(),
+        });
+    }
+
+    /// 校正の途中なら、一番音量が高い音の周波数を測ります。
+    /// 測る時間が経ったら校正を終えて、結果を表示します。
+    fn update_calibration(&mut self, note: Option<&Note>) {
+        let mut calibration = self.calibration.borrow_mut();
+        let Some(current) = calibration.as_mut() else {
+            return;
+        };
+
+        if let Some(note) = note {
+            if (1200. * (note.frequency / current.reference).log2()).abs() < CALIBRATION_RANGE {
+                current.frequencies.push(note.frequency);
+            };
+        };
+        if current.started_at.elapsed() < CALIBRATION_DURATION {
+            return;
+        };
+
+        self.status_label
+            .set_text(&match calibration.take().and_then(Calibration::finish) {
+                Some(correction) => tr_format(
+                    "校正しました。（補正：{} ppm）",
+                    &[&format!("{correction:+}")],
+                ),
+                None => tr("基準音を検出できなかったため、校正できませんでした。").to_string(),
+            });
+    }
+
+    /// スペクトルとスペクトログラムの表示を更新します。
+    /// 解析が行われなかった場合は、その時のスペクトルは何も描きません。
+    pub fn show_spectrum(&mut self, spectrum: Option<&[f32]>) {
+        self.spectra.borrow_mut().push(
+            Instant::now(),
+            spectrum.map(<[f32]>::to_vec).unwrap_or_default(),
+        );
+
+        self.spectrum_area.queue_redraw_all();
+        self.spectrogram_area.queue_redraw_all();
+    }
+
+    /// スクリプトの読み込みやエラーのメッセージを、スクリプトのタブの欄の最後に追加します。
+    pub fn log_script(&mut self, message: &str) {
+        // 古いメッセージは履歴の上限に合わせて捨てる。
+        self.script_log.push(Instant::now(), message.to_string());
+        let log = self
+            .script_log
+            .iter()
+            .map(|(_, message)| message.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.script_console.set_value(&log);
+    }
+
+    /// 伴奏の楽譜で今鳴っている音と、検出した一番目の音を比べて表示します。
+    pub fn show_score_comparison(&mut self, score: Option<u8>, detected: Option<u8>) {
+        let naming = self.note_naming();
+        let name = |number: Option<u8>| match number {
+            Some(number) => Note {
+                number,
+                ..Note::NULL
+            }
+            .get_name_with(naming),
+            None => "-".to_string(),
+        };
+
+        let mark = match (score, detected) {
+            (Some(score), Some(detected)) if score == detected => "✓",
+            (Some(_), _) => "✗",
+            _ => "",
+        };
+        self.score_comparison_label.set_text(&tr_format(
+            "楽譜：{}　検出：{}　{}",
+            &[&name(score), &name(detected), &mark],
+        ));
+    }
+
+    /// 楽譜の追従で、次に弾く音と直前の判定を表示します。
+    pub fn show_score_following(&mut self, follower: &ScoreFollower, judgement: Option<Judgement>) {
+        let naming = self.note_naming();
+        let name = |number: u8| {
+            Note {
+                number,
+                ..Note::NULL
+            }
+            .get_name_with(naming)
+        };
+        let [expected_label, judgement_label] = &mut self.score_following_labels;
+
+        let (position, length) = follower.progress();
+        expected_label.set_text(&match follower.expected() {
+            Some(expected) => tr_format(
+                "次の音：{}（{}/{}）",
+                &[&name(expected), &(position + 1), &length],
+            ),
+            None if follower.is_loaded() => tr("最後まで弾きました。").to_string(),
+            None => "-".to_string(),
+        });
+        judgement_label.set_text(&match judgement {
+            Some(Judgement::Correct) => "✓".to_string(),
+            Some(Judgement::Skipped(count)) => tr_format("✓（{}音飛ばしました）", &[&count]),
+            Some(Judgement::Wrong(number)) => tr_format("✗ {}を弾きました", &[&name(number)]),
+            None => String::new(),
+        });
+    }
+
+    /// 最近のフレームで一番多く一番目に検出された音を取得します。
+    /// 同じ回数の場合は、後に検出された方を選びます。
+    fn stable_note(&self) -> Option<Note> {
+        let mut stable = None;
+        let mut stable_count = 0;
+
+        for note in self.recent_notes.iter().rev() {
+            let count = self
+                .recent_notes
+                .iter()
+                .filter(|recent_note| recent_note.number == note.number)
+                .count();
+            if count > stable_count {
+                stable = Some(note);
+                stable_count = count;
+            };
+        }
+
+        stable.cloned()
+    }
+}
+
 /// 音階モニタの更新を行う。
-pub fn update_note_monitor<const N: usize>(labels: &mut [Label; N], notes: [crate::sys::Note; N]) {
-    for (i, note) in notes.into_iter().enumerate() {
-        labels[i].set_text(&format!("{}: {}", i + 1, note.get_name()))
+fn update_note_monitor<const N: usize>(
+    labels: &mut [[Label; N]],
+    notes: [Note; N],
+    naming: NoteNaming,
+    transposition: i32,
+) {
+    for (column, column_labels) in MonitorColumn::ALL.iter().zip(labels.iter_mut()) {
+        for (i, note) in notes.iter().enumerate() {
+            column_labels[i].set_text(&column.format(i + 1, note, naming, transposition))
+        }
     }
 }
 
+/// スペクトルを描くためのハンドラです。
+/// 横軸は対数の周波数で、各音階の位置に線を引きます（Cの線は濃くする）。
+/// 縦軸はその時の一番大きい値を上端とした`SPECTRUM_RANGE`の範囲の音の大きさです。
+struct SpectrumView {
+    spectra: SpectrumHistory,
+    config: Arc<AppConfig>,
+}
+
+impl AreaHandler for SpectrumView {
+    fn draw(&mut self, _area: &Area, draw_params: &AreaDrawParams) {
+        let context = &draw_params.context;
+        let (width, height) = (draw_params.area_width, draw_params.area_height);
+        let brush = |r, g, b, a| Brush::Solid(SolidBrush { r, g, b, a });
+        let stroke = |thickness| StrokeParams {
+            cap: LineCap::Flat,
+            join: LineJoin::Round,
+            thickness,
+            miter_limit: 10.,
+            dashes: Vec::new(),
+            dash_phase: 0.,
+        };
+
+        // 背景
+        let background = Path::new(context, FillMode::Winding);
+        background.add_rectangle(context, 0., 0., width, height);
+        background.end(context);
+        context.fill(&background, &brush(0.1, 0.1, 0.1, 1.));
+
+        // 音階の線
+        let x_of = |frequency: f32| {
+            ((frequency / SPECTRUM_MIN_FREQUENCY).ln()
+                / (SPECTRUM_MAX_FREQUENCY / SPECTRUM_MIN_FREQUENCY).ln()) as f64
+                * width
+        };
+        let (note_lines, c_lines) = (
+            Path::new(context, FillMode::Winding),
+            Path::new(context, FillMode::Winding),
+        );
+        let reference_pitch = self.config.reference_pitch.load(SeqCst) as f32;
+        for number in 21..=108 {
+            let x = x_of(reference_pitch * 2f32.powf((number as f32 - 69.) / 12.));
+            let path = if number % 12 == 0 {
+                &c_lines
+            } else {
+                &note_lines
+            };
+            path.new_figure(context, x, 0.);
+            path.line_to(context, x, height);
+        }
+        note_lines.end(context);
+        c_lines.end(context);
+        context.stroke(&note_lines, &brush(1., 1., 1., 0.1), &stroke(1.));
+        context.stroke(&c_lines, &brush(1., 1., 1., 0.4), &stroke(1.));
+
+        // スペクトル
+        let spectra = self.spectra.borrow();
+        let Some((_, spectrum)) = spectra.back() else {
+            return;
+        };
+        let Some(top) = spectrum.iter().copied().reduce(f32::max) else {
+            return;
+        };
+        let line = Path::new(context, FillMode::Winding);
+        for (index, value) in spectrum.iter().enumerate() {
+            let x = (index as f64 + 0.5) / spectrum.len() as f64 * width;
+            let y = ((top - value) / SPECTRUM_RANGE).min(1.) as f64 * height;
+            if index == 0 {
+                line.new_figure(context, x, y);
+            } else {
+                line.line_to(context, x, y);
+            };
+        }
+        line.end(context);
+        context.stroke(&line, &brush(0.3, 0.9, 0.5, 1.), &stroke(1.5));
+    }
+}
+
+/// チューナーの針を描くためのハンドラです。
+/// 中央が平均律の音階の周波数で、左右の端が±`TUNER_RANGE`セントです。
+/// 音程のずれを知らせている間は、背景を赤くします。
+struct TunerView {
+    cents: Rc<Cell<Option<f32>>>,
+    drift_alarm_until: Rc<Cell<Option<Instant>>>,
+}
+
+impl AreaHandler for TunerView {
+    fn draw(&mut self, _area: &Area, draw_params: &AreaDrawParams) {
+        let context = &draw_params.context;
+        let (width, height) = (draw_params.area_width, draw_params.area_height);
+        let brush = |r, g, b| Brush::Solid(SolidBrush { r, g, b, a: 1. });
+        let x_of = |cents: f32| (0.5 + (cents / TUNER_RANGE).clamp(-1., 1.) as f64 / 2.) * width;
+
+        // 背景と目盛り（5セントごと、中央とその両隣は長くする）
+        let background = Path::new(context, FillMode::Winding);
+        background.add_rectangle(context, 0., 0., width, height);
+        background.end(context);
+        let is_alarming = self
+            .drift_alarm_until
+            .get()
+            .is_some_and(|until| Instant::now() < until);
+        context.fill(
+            &background,
+            &if is_alarming {
+                brush(0.5, 0.15, 0.1)
+            } else {
+                brush(0.1, 0.1, 0.1)
+            },
+        );
+
+        let scale = Path::new(context, FillMode::Winding);
+        for step in -10..=10 {
+            let x = x_of(step as f32 * 5.);
+            let length = if step % 2 == 0 { 0.4 } else { 0.2 };
+            scale.new_figure(context, x, 0.);
+            scale.line_to(context, x, height * length);
+        }
+        scale.end(context);
+        context.stroke(
+            &scale,
+            &brush(0.6, 0.6, 0.6),
+            &StrokeParams {
+                cap: LineCap::Flat,
+                join: LineJoin::Miter,
+                thickness: 1.,
+                miter_limit: 10.,
+                dashes: Vec::new(),
+                dash_phase: 0.,
+            },
+        );
+
+        // 針（合っている時は緑、ずれている時は赤にする）
+        let Some(cents) = self.cents.get() else {
+            return;
+        };
+        let needle = Path::new(context, FillMode::Winding);
+        needle.add_rectangle(context, x_of(cents) - 2., 0., 4., height);
+        needle.end(context);
+        context.fill(
+            &needle,
+            &if cents.abs() <= TUNER_IN_TUNE_CENTS {
+                brush(0.3, 0.9, 0.5)
+            } else {
+                brush(0.9, 0.4, 0.3)
+            },
+        );
+    }
+}
+
+/// スペクトログラムを描くためのハンドラです。
+/// 横軸は時間（右端が今）、縦軸は対数の周波数で、`SPECTROGRAM_DURATION`の間のスペクトルを並べます。
+/// 色はその間の一番大きい値を最大とした`SPECTRUM_RANGE`の範囲の音の大きさで、`SPECTROGRAM_LEVELS`段階にします。
+struct SpectrogramView {
+    spectra: SpectrumHistory,
+}
+
+impl AreaHandler for SpectrogramView {
+    fn draw(&mut self, _area: &Area, draw_params: &AreaDrawParams) {
+        let context = &draw_params.context;
+        let (width, height) = (draw_params.area_width, draw_params.area_height);
+
+        // 背景
+        let background = Path::new(context, FillMode::Winding);
+        background.add_rectangle(context, 0., 0., width, height);
+        background.end(context);
+        context.fill(
+            &background,
+            &Brush::Solid(SolidBrush {
+                r: 0.,
+                g: 0.,
+                b: 0.1,
+                a: 1.,
+            }),
+        );
+
+        let spectra = self.spectra.borrow();
+        let Some(top) = spectra
+            .iter()
+            .flat_map(|(_, spectrum)| spectrum.iter().copied())
+            .reduce(f32::max)
+        else {
+            return;
+        };
+        let now = Instant::now();
+        let x_of = |time: Instant| {
+            (1. - now.duration_since(time).as_secs_f64() / SPECTROGRAM_DURATION.as_secs_f64())
+                * width
+        };
+        let row_height = height / SPECTROGRAM_ROWS as f64;
+
+        // 色の段階ごとに長方形をまとめて、一度に塗る。
+        let levels: Vec<Path> = (0..SPECTROGRAM_LEVELS)
+            .map(|_| Path::new(context, FillMode::Winding))
+            .collect();
+        for (index, (time, spectrum)) in spectra.iter().enumerate() {
+            let x = x_of(*time);
+            let column_width = spectra
+                .get(index + 1)
+                .map_or(width, |(next_time, _)| x_of(*next_time))
+                - x;
+
+            for (row, values) in spectrum
+                .chunks(SPECTRUM_BINS / SPECTROGRAM_ROWS)
+                .enumerate()
+            {
+                let value = values.iter().copied().fold(f32::MIN, f32::max);
+                let level = ((1. - (top - value) / SPECTRUM_RANGE) * SPECTROGRAM_LEVELS as f32)
+                    .min(SPECTROGRAM_LEVELS as f32 - 1.);
+                if level < 0. {
+                    continue;
+                };
+
+                levels[level as usize].add_rectangle(
+                    context,
+                    x,
+                    height - (row + 1) as f64 * row_height,
+                    column_width,
+                    row_height,
+                );
+            }
+        }
+
+        for (level, path) in levels.iter().enumerate() {
+            let strength = (level + 1) as f64 / SPECTROGRAM_LEVELS as f64;
+            path.end(context);
+            context.fill(
+                path,
+                &Brush::Solid(SolidBrush {
+                    r: strength,
+                    g: strength * strength,
+                    b: 0.4 * (1. - strength),
+                    a: 1.,
+                }),
+            );
+        }
+    }
+}
+
+/// 練習の時間とビブラートの統計を、練習のまとめとして表示する。
+fn show_practice_summary(practice_timer: &PracticeTimer, vibrato_stats: &VibratoStats) {
+    let vibrato = match vibrato_stats.summary() {
+        Some(summary) => tr_format(
+            "ビブラートを検出した時間：{}秒\n速さの平均：{} Hz（{}〜{} Hz）\n深さの平均：±{}セント（最大 ±{}セント）",
+            &[
+                &format!("{:.1}", summary.duration.as_secs_f32()),
+                &format!("{:.1}", summary.average_rate),
+                &format!("{:.1}", summary.min_rate),
+                &format!("{:.1}", summary.max_rate),
+                &format!("{:.0}", summary.average_depth),
+                &format!("{:.0}", summary.max_depth),
+            ],
+        ),
+        None => tr("まだビブラートを検出していません。").to_string(),
+    };
+    let _ = AsyncMessageDialog::new()
+        .set_title(tr("練習のまとめ"))
+        .set_description(&format!(
+            "{}\n{}",
+            tr_format(
+                "練習時間：{}",
+                &[&format_duration(practice_timer.elapsed())]
+            ),
+            vibrato
+        ))
+        .set_level(MessageLevel::Info)
+        .show();
+}
+
+/// 練習の時間の表示に確保する幅（全角の文字の数）を求めます。
+fn practice_time_width() -> usize {
+    theme::reserved_width(&[&tr_format("練習時間 {} / {}", &[&"000:00", &"000:00"])])
+}
+
+/// 起動時の動作の設定を保存する。保存できなかった場合は、そのことを表示する。
+fn save_startup_options(options: &StartupOptions) {
+    if let Err(error) = options.save() {
+        let _ = AsyncMessageDialog::new()
+            .set_title(crate::APPLICATION_NAME)
+            .set_description(&tr_format(
+                "起動時の動作の設定の保存に失敗しました。\n{}",
+                &[&error],
+            ))
+            .set_level(MessageLevel::Error)
+            .show();
+    };
+}
+
+/// ポイント数の規模が制限されているかどうかの表示を更新する。
+pub fn update_point_times_warning(label: &mut Label, point_times: Option<usize>) {
+    if let Some(point_times) = point_times {
+        label.set_text(&tr_format(
+            "⚠ FFTのサイズが上限を超えるため、ポイント数の規模を{}に制限しています。",
+            &[&point_times],
+        ));
+    } else {
+        label.set_text("");
+    }
+}
+
+/// UIを初期化します。
+/// 初期化に失敗した場合は、UIを使わずに前回の設定で続けるかを聞きます。続ける場合は`None`を返し、続けない場合は終了します。
+pub fn init() -> Option<UI> {
+    let error = match UI::init() {
+        Ok(ui) => return Some(ui),
+        Err(error) => error,
+    };
+
+    let is_continued = MessageDialog::new()
+        .set_title(tr(errors::INIT_ERROR))
+        .set_description(&format!(
+            "{}\n{error:?}",
+            tr("UIの初期化に失敗しました。UIを使わずに、前回の設定で音階検出とMIDIの出力を続けますか？")
+        ))
+        .set_level(MessageLevel::Error)
+        .set_buttons(MessageButtons::YesNo)
+        .show();
+    if !is_continued {
+        std::process::exit(1);
+    };
+    None
+}
+
 pub fn make_ui<const NUMBER_OF_NOTE_IN_RESULT: usize>(
-    event_sender: Sender<crate::Event>,
-    config: Arc<crate::sys::Config>,
+    ui: UI,
+    command_bus: CommandBus,
+    config: Arc<AppConfig>,
+    stream_stats: Arc<crate::sys::StreamStats>,
+    timeline: Timeline,
     midi_port_names: impl Iterator<Item = String>,
-) -> (UI, Window, [Label; NUMBER_OF_NOTE_IN_RESULT]) {
+) -> (Window, Monitor<NUMBER_OF_NOTE_IN_RESULT>) {
     /* UIの準備 */
-    let ui = UI::init()
-        .context("UIの初期化に失敗しました。")
-        .unwrap_or_dialog_with_title(errors::INIT_ERROR);
-
     ui.on_should_quit({
         let ui = ui.clone();
         move || ui.quit()
@@ -33,141 +1371,1827 @@ pub fn make_ui<const NUMBER_OF_NOTE_IN_RESULT: usize>(
 
     // レイアウトの作成
     layout! { &ui,
-        let layout = HorizontalBox(padded: true) {
-            Compact: let notes_group = Group("Notes", margined: true) {
-                let notes_box = HorizontalBox(padded: false) {
-                    Compact: let result_label_box = VerticalBox(padded: false) {}
-                    Compact: let spacer = Spacer()
-                }
-            }
-            Compact: let wrapped_control_box = VerticalBox(padded: true) {
-                Stretchy: let top_spacer = Spacer()
-                Compact: let control_box = HorizontalBox(padded: true) {
-                    Stretchy: let first_control_box = VerticalBox(padded: true) {
-                        Compact: let window_check_box = Checkbox("窓関数（ハン窓）を使う", checked: false)
-                        Compact: let min_detection_volume_label = Label("検出対象とする最低音量")
-                        Compact: let min_detection_volume_spin_box = Spinbox(0, 100)
-                        Compact: let pitch_control_label = Label("音階調節")
-                        Compact: let pitch_control_spin_box = Spinbox(-127, 127)
+        let layout = VerticalBox(padded: true) {
+            Compact: let main_box = HorizontalBox(padded: true) {
+                Compact: let notes_group = Group("Notes", margined: true) {
+                    let notes_box = HorizontalBox(padded: false) {
+                        Compact: let result_label_box = HorizontalBox(padded: true) {}
+                        Compact: let spacer = Spacer()
                     }
-                    Stretchy: let second_control_box = VerticalBox(padded: true) {
-                        Compact: let silent_data_button = Button(texts::SET_SILENT_DATA)
-                        Compact: let point_length_size_label = Label("ポイント数の規模")
-                        Compact: let point_length_size_spin_box = Spinbox(1, u16::MAX as _)
-                        Compact: let midi_output_label = Label("MIDIの出力先")
-                        Compact: let midi_output_combo_box = Combobox() {}
+                }
+                Compact: let wrapped_control_box = VerticalBox(padded: true) {
+                    Stretchy: let top_spacer = Spacer()
+                    Compact: let control_box = HorizontalBox(padded: true) {
+                        Stretchy: let first_control_box = VerticalBox(padded: true) {
+                            Compact: let window_function_label = Label(tr("窓関数"))
+                            Compact: let window_function_combo_box = Combobox() {}
+                            Compact: let min_detection_volume_label = Label(tr("検出対象とする最低音量（dB）"))
+                            Compact: let min_detection_volume_spin_box = Spinbox(params::MIN_VOLUME.min, params::MIN_VOLUME.max)
+                            Compact: let a_weighting_check_box = Checkbox(tr("音量をA特性で測る（聞こえ方に合わせる）"), checked: false)
+                            Compact: let input_channel_label = Label(tr("解析するチャンネル（0で全てのチャンネルを平均する）"))
+                            Compact: let input_channel_spin_box = Spinbox(params::INPUT_CHANNEL.min, params::INPUT_CHANNEL.max)
+                            Compact: let high_pass_frequency_label = Label(tr("低い雑音を取り除く周波数（Hz、0で取り除かない）"))
+                            Compact: let high_pass_frequency_spin_box = Spinbox(params::HIGH_PASS_FREQUENCY.min, params::HIGH_PASS_FREQUENCY.max)
+                            Compact: let agc_check_box = Checkbox(tr("音量を自動で調整する（AGC）"), checked: false)
+                            Compact: let agc_target_label = Label(tr("自動調整の目標の音量（dB）"))
+                            Compact: let agc_target_spin_box = Spinbox(params::AGC_TARGET.min, params::AGC_TARGET.max)
+                            Compact: let volume_hysteresis_label = Label(tr("音量のヒステリシス（dB）"))
+                            Compact: let volume_hysteresis_spin_box = Spinbox(params::VOLUME_HYSTERESIS.min, params::VOLUME_HYSTERESIS.max)
+                            Compact: let gate_attack_label = Label(tr("ゲートのアタック（ミリ秒）"))
+                            Compact: let gate_attack_spin_box = Spinbox(params::GATE_ATTACK.min, params::GATE_ATTACK.max)
+                            Compact: let gate_hold_label = Label(tr("ゲートのホールド（ミリ秒）"))
+                            Compact: let gate_hold_spin_box = Spinbox(params::GATE_HOLD.min, params::GATE_HOLD.max)
+                            Compact: let gate_release_label = Label(tr("ゲートのリリース（ミリ秒）"))
+                            Compact: let gate_release_spin_box = Spinbox(params::GATE_RELEASE.min, params::GATE_RELEASE.max)
+                            Compact: let pitch_control_label = Label(tr("音階調節"))
+                            Compact: let pitch_control_spin_box = Spinbox(params::ADJUSTMENT_RATE.min, params::ADJUSTMENT_RATE.max)
+                            Compact: let reference_pitch_label = Label(tr("基準のラ（A4）の周波数（Hz）"))
+                            Compact: let reference_pitch_spin_box = Spinbox(params::REFERENCE_PITCH.min, params::REFERENCE_PITCH.max)
+                            Compact: let min_note_label = Label(tr("検出する一番低い音（MIDIの番号）"))
+                            Compact: let min_note_spin_box = Spinbox(params::MIN_NOTE.min, params::MIN_NOTE.max)
+                            Compact: let max_note_label = Label(tr("検出する一番高い音（MIDIの番号）"))
+                            Compact: let max_note_spin_box = Spinbox(params::MAX_NOTE.min, params::MAX_NOTE.max)
+                            Compact: let ranking_strategy_label = Label(tr("候補の順位付けの方法"))
+                            Compact: let ranking_strategy_combo_box = Combobox() {}
+                            Compact: let min_confidence_label = Label(tr("検出したとみなす最低の信頼度（dB）"))
+                            Compact: let min_confidence_spin_box = Spinbox(params::MIN_CONFIDENCE.min, params::MIN_CONFIDENCE.max)
+                            Compact: let ambiguity_margin_label = Label(tr("候補の差が小さいとみなす差（dB）"))
+                            Compact: let ambiguity_margin_spin_box = Spinbox(params::AMBIGUITY_MARGIN.min, params::AMBIGUITY_MARGIN.max)
+                            Compact: let ambiguity_policy_label = Label(tr("候補の差が小さい時の扱い"))
+                            Compact: let ambiguity_policy_combo_box = Combobox() {}
+                            Compact: let octave_lock_check_box = Checkbox(tr("フレーズの最初の音のオクターブに固定する"), checked: false)
+                            Compact: let octave_lock_reset_label = Label(tr("オクターブの固定をやめるまでの無音の時間（ミリ秒）"))
+                            Compact: let octave_lock_reset_spin_box = Spinbox(params::OCTAVE_LOCK_RESET.min, params::OCTAVE_LOCK_RESET.max)
+                            Compact: let note_naming_label = Label(tr("音階の名前の表し方"))
+                            Compact: let note_naming_combo_box = Combobox() {}
+                            Compact: let written_transposition_label = Label(tr("記譜の移調（半音）"))
+                            Compact: let written_transposition_spin_box = Spinbox(params::WRITTEN_TRANSPOSITION.min, params::WRITTEN_TRANSPOSITION.max)
+                        }
+                        Stretchy: let second_control_box = VerticalBox(padded: true) {
+                            Compact: let silent_data_button = Button(tr(texts::SET_SILENT_DATA))
+                            Compact: let silence_file_box = HorizontalBox(padded: true) {
+                                Stretchy: let save_silence_button = Button(tr("無音データを保存"))
+                                Stretchy: let load_silence_button = Button(tr("保存した無音データを読み込む"))
+                            }
+                            Compact: let noise_reduction_check_box = Checkbox(tr("雑音を追従して取り除く"), checked: false)
+                            Compact: let noise_over_subtraction_label = Label(tr("追従した雑音を差し引く倍率（%）"))
+                            Compact: let noise_over_subtraction_spin_box = Spinbox(params::NOISE_OVER_SUBTRACTION.min, params::NOISE_OVER_SUBTRACTION.max)
+                            Compact: let point_length_size_label = Label(tr("ポイント数の規模"))
+                            Compact: let point_length_size_spin_box = Spinbox(params::POINT_TIMES.min, params::POINT_TIMES.max)
+                            Compact: let max_fft_length_label = Label(tr("FFTのサイズの上限（×1024）"))
+                            Compact: let max_fft_length_spin_box = Spinbox(params::MAX_FFT_LENGTH.min / 1024, params::MAX_FFT_LENGTH.max / 1024)
+                            Compact: let analysis_window_length_label = Label(tr("解析の窓の長さ（フレーム数、0で入力に合わせる）"))
+                            Compact: let analysis_window_length_spin_box = Spinbox(params::ANALYSIS_WINDOW_LENGTH.min, params::ANALYSIS_WINDOW_LENGTH.max)
+                            Compact: let analysis_overlap_label = Label(tr("解析の窓の重なり（%）"))
+                            Compact: let analysis_overlap_spin_box = Spinbox(params::ANALYSIS_OVERLAP.min, params::ANALYSIS_OVERLAP.max)
+                            Compact: let latency_label = Label("")
+                            Compact: let history_memory_limit_label = Label(tr("履歴に使うメモリの上限（MB）"))
+                            Compact: let history_memory_limit_spin_box = Spinbox(params::HISTORY_MEMORY_LIMIT.min, params::HISTORY_MEMORY_LIMIT.max)
+                            Compact: let calibration_reference_label = Label(tr("校正に使う基準音の周波数（Hz）"))
+                            Compact: let calibration_reference_spin_box = Spinbox(20, 4000)
+                            Compact: let calibration_button = Button(tr("基準音を鳴らして校正する"))
+                            Compact: let preset_label = Label(tr("プリセット"))
+                            Compact: let preset_combo_box = Combobox() {}
+                            Compact: let preset_name_entry = Entry()
+                            Compact: let save_preset_button = Button(tr("今の設定をプリセットとして保存"))
+                            Compact: let instrument_profile_label = Label(tr("楽器のプロファイル"))
+                            Compact: let instrument_profile_combo_box = Combobox() {}
+                            Compact: let startup_label = Label(tr("起動時の動作"))
+                            Compact: let startup_midi_output_check_box = Checkbox(tr("前回のMIDIの出力先に接続する"), checked: false)
+                            Compact: let startup_preset_label = Label(tr("起動時に読み込むプリセット"))
+                            Compact: let startup_preset_combo_box = Combobox() {}
+                            Compact: let audio_backend_label = Label(tr("音声のバックエンド（次に起動した時から使う）"))
+                            Compact: let audio_backend_combo_box = Combobox() {}
+                        }
+                        Stretchy: let third_control_box = VerticalBox(padded: true) {
+                            Compact: let midi_output_label = Label(tr("MIDIの出力先"))
+                            Compact: let midi_output_combo_box = Combobox() {}
+                            Compact: let midi_program_label = Label(tr("MIDIの音色"))
+                            Compact: let midi_program_combo_box = Combobox() {}
+                            Compact: let fixed_note_length_check_box = Checkbox(tr("MIDIの音の長さを固定する"), checked: false)
+                            Compact: let fixed_note_length_label = Label(tr("MIDIの音の長さ（ミリ秒）"))
+                            Compact: let fixed_note_length_spin_box = Spinbox(params::FIXED_NOTE_LENGTH.min, params::FIXED_NOTE_LENGTH.max)
+                            Compact: let release_delay_label = Label(tr("MIDIの音を止めるまでの猶予（ミリ秒）"))
+                            Compact: let release_delay_spin_box = Spinbox(params::RELEASE_DELAY.min, params::RELEASE_DELAY.max)
+                            Compact: let note_refresh_interval_label = Label(tr("鳴らしている音を送り直す間隔（秒、0で送り直さない）"))
+                            Compact: let note_refresh_interval_spin_box = Spinbox(params::NOTE_REFRESH_INTERVAL.min, params::NOTE_REFRESH_INTERVAL.max)
+                            Compact: let max_note_duration_label = Label(tr("鳴らしたままの音を止めるまでの時間（秒、0で止めない）"))
+                            Compact: let max_note_duration_spin_box = Spinbox(params::MAX_NOTE_DURATION.min, params::MAX_NOTE_DURATION.max)
+                            Compact: let legato_check_box = Checkbox(tr("レガート"), checked: false)
+                            Compact: let onset_retrigger_check_box = Checkbox(tr("同じ音の弾き直しで鳴らし直す"), checked: false)
+                            Compact: let onset_threshold_label = Label(tr("弾き直しとみなす変化の大きさ（%）"))
+                            Compact: let onset_threshold_spin_box = Spinbox(params::ONSET_THRESHOLD.min, params::ONSET_THRESHOLD.max)
+                            Compact: let min_note_frames_label = Label(tr("MIDIの音を出すまでの検出回数"))
+                            Compact: let min_note_frames_spin_box = Spinbox(params::MIN_NOTE_FRAMES.min, params::MIN_NOTE_FRAMES.max)
+                            Compact: let min_midi_confidence_label = Label(tr("MIDIの音を出す最低の信頼度（dB）"))
+                            Compact: let min_midi_confidence_spin_box = Spinbox(params::MIN_MIDI_CONFIDENCE.min, params::MIN_MIDI_CONFIDENCE.max)
+                            Compact: let median_filter_size_label = Label(tr("中央値フィルタの大きさ（検出回数）"))
+                            Compact: let median_filter_size_spin_box = Spinbox(params::MEDIAN_FILTER_SIZE.min, params::MEDIAN_FILTER_SIZE.max)
+                            Compact: let midi_transposition_label = Label(tr("MIDIの移調（半音）"))
+                            Compact: let midi_transposition_spin_box = Spinbox(params::MIDI_TRANSPOSITION.min, params::MIDI_TRANSPOSITION.max)
+                            Compact: let octave_doubling_label = Label(tr("オクターブ重ね"))
+                            Compact: let octave_doubling_combo_box = Combobox() {}
+                            Compact: let octave_doubling_velocity_label = Label(tr("重ねる音の強さ（%）"))
+                            Compact: let octave_doubling_velocity_spin_box = Spinbox(params::OCTAVE_DOUBLING_VELOCITY.min, params::OCTAVE_DOUBLING_VELOCITY.max)
+                            Compact: let envelope_control_label = Label(tr("入力の音量をMIDIで送る"))
+                            Compact: let envelope_control_combo_box = Combobox() {}
+                            Compact: let sostenuto_check_box = Checkbox(tr("ソステヌート（今の音を保持する）"), checked: false)
+                            Compact: let midi_panic_button = Button(tr("パニック（全ての音を止める）"))
+                        }
                     }
+                    Compact: let bottom_spacer = Spacer()
+                }
+            }
+            Stretchy: let visualization_box = VerticalBox(padded: false) {}
+            Compact: let status_box = HorizontalBox(padded: true) {
+                Stretchy: let status_label = Label("")
+                Compact: let level_label = Label("")
+                Compact: let level_meter = ProgressBar()
+                Compact: let clip_label = Label("")
+                Compact: let practice_time_label = Label("")
+            }
+        }
+    }
+
+    /* ここからControlの設定 */
+
+    // 状態を表示するラベルは、表示が変わっても並びがずれないように幅を確保しておく。
+    level_label.set_text(&theme::placeholder(&LEVEL_LABEL_TEXTS));
+    clip_label.set_text(&theme::placeholder(&["CLIP"]));
+    practice_time_label.set_text(&"　".repeat(practice_time_width()));
+
+    // 結果表示用のラベルの準備
+    // 列ごとに縦に並べたボックスを作り、表示メニューで列の表示を切り替えられるようにする。
+    let mut note_column_boxes = Vec::new();
+    let mut note_labels = Vec::new();
+
+    for column in MonitorColumn::ALL {
+        let mut column_box = VerticalBox::new();
+        let mut count = 0;
+        note_labels.push([(); NUMBER_OF_NOTE_IN_RESULT].map(|_| {
+            count += 1;
+            let label = Label::new(&column.placeholder(count));
+            column_box.append(label.clone(), LayoutStrategy::Stretchy);
+            label
+        }));
+
+        if !column.is_default() {
+            column_box.hide();
+        };
+        result_label_box.append(column_box.clone(), LayoutStrategy::Compact);
+        note_column_boxes.push(column_box);
+    }
+
+    // スペクトルとスペクトログラムの表示の準備
+    let memory_budget = MemoryBudget::new(Arc::clone(&config));
+    let spectra = Rc::new(RefCell::new(History::new(
+        &memory_budget,
+        SPECTRUM_SHARE,
+        Some(SPECTROGRAM_DURATION),
+    )));
+    let spectrum_area = Area::new(Box::new(SpectrumView {
+        spectra: Rc::clone(&spectra),
+        config: Arc::clone(&config),
+    }));
+    let spectrogram_area = Area::new(Box::new(SpectrogramView {
+        spectra: Rc::clone(&spectra),
+    }));
+
+    // チューナーの準備
+    layout! { &ui,
+        let tuner_box = VerticalBox(padded: true) {
+            Compact: let tuner_note_label = Label("-")
+            Compact: let tuner_frequency_label = Label("-")
+            Compact: let tuner_cents_label = Label("-")
+            Compact: let tuner_vibrato_label = Label(tr("ビブラート：-"))
+            Compact: let zoom_fft_check_box = Checkbox(tr("周辺だけを細かく解析する（ズームFFT）"), checked: false)
+            Compact: let drift_alarm_check_box = Checkbox(tr("伸ばしている音の音程のずれを知らせる"), checked: false)
+            Compact: let drift_alarm_threshold_label = Label(tr("知らせるずれ（セント）"))
+            Compact: let drift_alarm_threshold_spin_box = Spinbox(params::DRIFT_ALARM_THRESHOLD.min, params::DRIFT_ALARM_THRESHOLD.max)
+            Compact: let drift_alarm_duration_label = Label(tr("知らせるまでの時間（ミリ秒）"))
+            Compact: let drift_alarm_duration_spin_box = Spinbox(params::DRIFT_ALARM_DURATION.min, params::DRIFT_ALARM_DURATION.max)
+            Compact: let drift_alarm_click_check_box = Checkbox(tr("クリック音も鳴らす"), checked: false)
+            Compact: let auto_stop_label = Label(tr("練習を自動で止めるまでの時間（分、0で止めない）"))
+            Compact: let auto_stop_spin_box = Spinbox(params::AUTO_STOP_MINUTES.min, params::AUTO_STOP_MINUTES.max)
+            Compact: let restart_practice_button = Button(tr("練習の時間を最初から測る"))
+        }
+    }
+    let tuner_cents = Rc::new(Cell::new(None));
+    let drift_alarm_until = Rc::new(Cell::new(None));
+    let vibrato_stats = Rc::new(RefCell::new(VibratoStats::default()));
+    let practice_timer = Rc::new(RefCell::new(PracticeTimer::default()));
+    let tuner_area = Area::new(Box::new(TunerView {
+        cents: Rc::clone(&tuner_cents),
+        drift_alarm_until: Rc::clone(&drift_alarm_until),
+    }));
+    tuner_box.append(tuner_area.clone(), LayoutStrategy::Stretchy);
+
+    // 基準音の準備
+    layout! { &ui,
+        let tone_box = VerticalBox(padded: true) {
+            Compact: let tone_note_label = Label(tr("音階"))
+            Compact: let tone_note_combo_box = Combobox() {}
+            Compact: let tone_octave_label = Label(tr("オクターブ"))
+            Compact: let tone_octave_spin_box = Spinbox(0, 8)
+            Compact: let tone_volume_label = Label(tr("音量（%）"))
+            Compact: let tone_volume_spin_box = Spinbox(0, 100)
+            Compact: let tone_timbre_label = Label(tr("音色"))
+            Compact: let tone_timbre_combo_box = Combobox() {}
+            Compact: let tone_play_check_box = Checkbox(tr("鳴らす"), checked: false)
+        }
+    }
+
+    // スクリプトの準備
+    layout! { &ui,
+        let script_box = VerticalBox(padded: true) {
+            Compact: let reload_script_button = Button(tr("スクリプトを読み込み直す"))
+            Stretchy: let script_console = MultilineEntry()
+        }
+    }
+    reload_script_button.on_clicked({
+        let command_bus = command_bus.clone();
+        move |_| {
+            command_bus.dispatch(AppCommand::ReloadScript);
+        }
+    });
+
+    // 伴奏の準備
+    layout! { &ui,
+        let backing_box = VerticalBox(padded: true) {
+            Compact: let backing_open_button = Button(tr("伴奏のMIDIファイルを開く..."))
+            Compact: let backing_file_label = Label(tr("伴奏のMIDIファイルを開いていません。"))
+            Compact: let backing_transport_box = HorizontalBox(padded: true) {
+                Compact: let backing_play_button = Button(tr("再生"))
+                Compact: let backing_pause_button = Button(tr("一時停止"))
+                Compact: let backing_stop_button = Button(tr("停止"))
+            }
+            Compact: let backing_tempo_label = Label(tr("テンポ（%）"))
+            Compact: let backing_tempo_spin_box = Spinbox(25, 200)
+            Compact: let score_comparison_label = Label("")
+        }
+    }
+    backing_open_button.on_clicked({
+        let command_bus = command_bus.clone();
+        let mut backing_file_label = backing_file_label.clone();
+        move |_| {
+            let Some(path) = FileDialog::new()
+                .add_filter(tr("MIDIファイル"), &["mid", "midi"])
+                .pick_file()
+            else {
+                return;
+            };
+
+            match std::fs::read(&path)
+                .context(tr("ファイルを読み込めませんでした。"))
+                .and_then(|data| crate::player::Song::parse(&data))
+            {
+                Ok(song) => {
+                    backing_file_label.set_text(&tr_format(
+                        "{}（{}秒）",
+                        &[
+                            &path.file_name().unwrap_or_default().to_string_lossy(),
+                            &format!("{:.1}", song.duration()),
+                        ],
+                    ));
+                    command_bus.dispatch(AppCommand::LoadBackingTrack(song));
+                }
+                Err(error) => {
+                    let _ = AsyncMessageDialog::new()
+                        .set_title(crate::APPLICATION_NAME)
+                        .set_description(&error)
+                        .set_level(MessageLevel::Error)
+                        .show();
+                }
+            };
+        }
+    });
+    backing_play_button.on_clicked({
+        let command_bus = command_bus.clone();
+        move |_| command_bus.dispatch(AppCommand::PlayBackingTrack)
+    });
+    backing_pause_button.on_clicked({
+        let command_bus = command_bus.clone();
+        move |_| command_bus.dispatch(AppCommand::PauseBackingTrack)
+    });
+    backing_stop_button.on_clicked({
+        let command_bus = command_bus.clone();
+        move |_| command_bus.dispatch(AppCommand::StopBackingTrack)
+    });
+    backing_tempo_spin_box.set_value(100);
+    backing_tempo_spin_box.on_changed({
+        let command_bus = command_bus.clone();
+        move |value| command_bus.dispatch(AppCommand::SetBackingTrackTempo(value as _))
+    });
+
+    // 楽譜の追従の準備
+    layout! { &ui,
+        let score_box = VerticalBox(padded: true) {
+            Compact: let score_open_button = Button(tr("お手本のMIDIファイルを開く..."))
+            Compact: let score_file_label = Label(tr("お手本のMIDIファイルを開いていません。"))
+            Compact: let score_reset_button = Button(tr("最初に戻す"))
+            Compact: let score_expected_label = Label("-")
+            Compact: let score_judgement_label = Label("")
+        }
+    }
+    score_open_button.on_clicked({
+        let command_bus = command_bus.clone();
+        let mut score_file_label = score_file_label.clone();
+        move |_| {
+            let Some(path) = FileDialog::new()
+                .add_filter(tr("MIDIファイル"), &["mid", "midi"])
+                .pick_file()
+            else {
+                return;
+            };
+
+            match std::fs::read(&path)
+                .context(tr("ファイルを読み込めませんでした。"))
+                .and_then(|data| crate::player::Song::parse(&data))
+            {
+                Ok(song) => {
+                    let melody = song.melody();
+                    score_file_label.set_text(&tr_format(
+                        "{}（{}音）",
+                        &[
+                            &path.file_name().unwrap_or_default().to_string_lossy(),
+                            &melody.len(),
+                        ],
+                    ));
+                    command_bus.dispatch(AppCommand::LoadScore(melody));
+                }
+                Err(error) => {
+                    let _ = AsyncMessageDialog::new()
+                        .set_title(crate::APPLICATION_NAME)
+                        .set_description(&error)
+                        .set_level(MessageLevel::Error)
+                        .show();
+                }
+            };
+        }
+    });
+    score_reset_button.on_clicked({
+        let command_bus = command_bus.clone();
+        move |_| command_bus.dispatch(AppCommand::ResetScore)
+    });
+
+    let mut visualization_tab_group = TabGroup::new();
+    visualization_tab_group.append(tr("スペクトル"), spectrum_area.clone());
+    visualization_tab_group.append(tr("スペクトログラム"), spectrogram_area.clone());
+    visualization_tab_group.append(tr("チューナー"), tuner_box);
+    visualization_tab_group.append(tr("基準音"), tone_box);
+    visualization_tab_group.append(tr("スクリプト"), script_box);
+    visualization_tab_group.append(tr("伴奏"), backing_box);
+    visualization_tab_group.append(tr("楽譜の追従"), score_box);
+    visualization_box.append(visualization_tab_group, LayoutStrategy::Stretchy);
+
+    // 設定の変更の履歴
+    let history = Rc::new(RefCell::new(SettingsHistory::default()));
+
+    // 基準音
+    let tone_generator = Rc::new(RefCell::new(ToneGenerator::new(Arc::clone(&config))));
+    let update_tone_note = {
+        let tone_generator = Rc::clone(&tone_generator);
+        let tone_note_combo_box = tone_note_combo_box.clone();
+        let tone_octave_spin_box = tone_octave_spin_box.clone();
+        move || {
+            tone_generator.borrow().set_note(
+                ((tone_octave_spin_box.value() + 1) * 12 + tone_note_combo_box.selected()) as _,
+            )
+        }
+    };
+
+    for number in 0..12 {
+        tone_note_combo_box.append(
+            Note {
+                number,
+                ..Note::NULL
+            }
+            .get_pitch_class_name(),
+        );
+    }
+    tone_note_combo_box.set_selected(9);
+    tone_note_combo_box.on_selected(&ui, {
+        let update_tone_note = update_tone_note.clone();
+        move |_| update_tone_note()
+    });
+    tone_octave_spin_box.set_value(4);
+    tone_octave_spin_box.on_changed({
+        let update_tone_note = update_tone_note.clone();
+        move |_| update_tone_note()
+    });
+    tone_volume_spin_box.set_value(30);
+    tone_volume_spin_box.on_changed({
+        let tone_generator = Rc::clone(&tone_generator);
+        move |value| tone_generator.borrow().set_volume(value as _)
+    });
+    for timbre in Timbre::ALL {
+        tone_timbre_combo_box.append(tr(timbre.name()));
+    }
+    tone_timbre_combo_box.set_selected(0);
+    tone_timbre_combo_box.on_selected(&ui, {
+        let tone_generator = Rc::clone(&tone_generator);
+        move |index| {
+            tone_generator
+                .borrow()
+                .set_timbre(Timbre::from_index(index as _))
+        }
+    });
+    tone_play_check_box.on_toggled(&ui, {
+        let mut tone_play_check_box = tone_play_check_box.clone();
+        move |value| {
+            if !value {
+                tone_generator.borrow_mut().stop();
+                return;
+            };
+
+            // 基準のラの周波数や音律が変わっているかもしれないので、周波数を求め直してから鳴らす。
+            update_tone_note();
+            if let Err(error) = tone_generator.borrow_mut().play() {
+                tone_play_check_box.set_checked(false);
+                let _ = AsyncMessageDialog::new()
+                    .set_title(crate::APPLICATION_NAME)
+                    .set_description(&error)
+                    .set_level(MessageLevel::Error)
+                    .show();
+            };
+        }
+    });
+
+    // - 一列目
+
+    // 窓関数
+    for window_function in crate::sys::WindowFunction::ALL {
+        window_function_combo_box.append(tr(window_function.name()));
+    }
+    window_function_combo_box.set_selected(config.window_function.load(SeqCst) as _);
+    window_function_combo_box.on_selected(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |index| {
+            history.borrow_mut().record("窓関数", &config);
+            config.window_function.store(index as _, SeqCst)
+        }
+    });
+
+    // 最低音量
+    min_detection_volume_spin_box.set_value(config.min_volume.load(SeqCst));
+    min_detection_volume_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("最低音量", &config);
+            config.min_volume.store(value, SeqCst)
+        }
+    });
+
+    // A特性
+    a_weighting_check_box.set_checked(config.use_a_weighting.load(SeqCst));
+    a_weighting_check_box.on_toggled(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("A特性", &config);
+            config.use_a_weighting.store(value, SeqCst)
+        }
+    });
+
+    // 解析するチャンネル
+    input_channel_spin_box.set_value(config.input_channel.load(SeqCst) as _);
+    input_channel_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("解析するチャンネル", &config);
+            config.input_channel.store(value as _, SeqCst)
+        }
+    });
+
+    // 低い雑音を取り除く周波数
+    high_pass_frequency_spin_box.set_value(config.high_pass_frequency.load(SeqCst) as _);
+    high_pass_frequency_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("低い雑音を取り除く周波数", &config);
+            config.high_pass_frequency.store(value as _, SeqCst)
+        }
+    });
+
+    // 自動音量調整
+    agc_check_box.set_checked(config.use_agc.load(SeqCst));
+    agc_check_box.on_toggled(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("自動音量調整", &config);
+            config.use_agc.store(value, SeqCst)
+        }
+    });
+    agc_target_spin_box.set_value(config.agc_target.load(SeqCst));
+    agc_target_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("自動調整の目標の音量", &config);
+            config.agc_target.store(value, SeqCst)
+        }
+    });
+
+    // 音量のヒステリシス
+    volume_hysteresis_spin_box.set_value(config.volume_hysteresis.load(SeqCst));
+    volume_hysteresis_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("音量のヒステリシス", &config);
+            config.volume_hysteresis.store(value, SeqCst)
+        }
+    });
+
+    // ゲートのアタック
+    gate_attack_spin_box.set_value(config.gate_attack.load(SeqCst) as _);
+    gate_attack_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("ゲートのアタック", &config);
+            config.gate_attack.store(value as _, SeqCst)
+        }
+    });
+
+    // ゲートのホールド
+    gate_hold_spin_box.set_value(config.gate_hold.load(SeqCst) as _);
+    gate_hold_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("ゲートのホールド", &config);
+            config.gate_hold.store(value as _, SeqCst)
+        }
+    });
+
+    // ゲートのリリース
+    gate_release_spin_box.set_value(config.gate_release.load(SeqCst) as _);
+    gate_release_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("ゲートのリリース", &config);
+            config.gate_release.store(value as _, SeqCst)
+        }
+    });
+
+    // 音階調節
+    pitch_control_spin_box.set_value(config.adjustment_rate.load(SeqCst));
+    pitch_control_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("音階調節", &config);
+            config.adjustment_rate.store(value, SeqCst)
+        }
+    });
+
+    // ズームFFT
+    zoom_fft_check_box.on_toggled(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("ズームFFT", &config);
+            config.use_zoom_fft.store(value, SeqCst)
+        }
+    });
+
+    // 音程のずれの知らせ
+    drift_alarm_check_box.set_checked(config.use_drift_alarm.load(SeqCst));
+    drift_alarm_check_box.on_toggled(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("音程のずれの知らせ", &config);
+            config.use_drift_alarm.store(value, SeqCst)
+        }
+    });
+    drift_alarm_threshold_spin_box.set_value(config.drift_alarm_threshold.load(SeqCst) as _);
+    drift_alarm_threshold_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("知らせるずれ", &config);
+            config.drift_alarm_threshold.store(value as _, SeqCst)
+        }
+    });
+    drift_alarm_duration_spin_box.set_value(config.drift_alarm_duration.load(SeqCst) as _);
+    drift_alarm_duration_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("知らせるまでの時間", &config);
+            config.drift_alarm_duration.store(value as _, SeqCst)
+        }
+    });
+    drift_alarm_click_check_box.set_checked(config.use_drift_alarm_click.load(SeqCst));
+    drift_alarm_click_check_box.on_toggled(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("クリック音", &config);
+            config.use_drift_alarm_click.store(value, SeqCst)
+        }
+    });
+
+    // 練習の時間
+    auto_stop_spin_box.set_value(config.auto_stop_minutes.load(SeqCst) as _);
+    auto_stop_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("練習を自動で止めるまでの時間", &config);
+            config.auto_stop_minutes.store(value as _, SeqCst)
+        }
+    });
+    restart_practice_button.on_clicked({
+        let command_bus = command_bus.clone();
+        move |_| command_bus.dispatch(AppCommand::RestartPractice)
+    });
+
+    // 基準のラの周波数
+    reference_pitch_spin_box.set_value(config.reference_pitch.load(SeqCst) as _);
+    reference_pitch_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("基準のラの周波数", &config);
+            config.reference_pitch.store(value as _, SeqCst)
+        }
+    });
+
+    // 検出する音の範囲
+    // 一番低い音が一番高い音を超えないように、もう片方も合わせて動かす。
+    min_note_spin_box.set_value(config.min_note.load(SeqCst) as _);
+    max_note_spin_box.set_value(config.max_note.load(SeqCst) as _);
+    min_note_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        let mut max_note_spin_box = max_note_spin_box.clone();
+        move |value| {
+            history.borrow_mut().record("検出する音の範囲", &config);
+            config.min_note.store(value as _, SeqCst);
+            if value > max_note_spin_box.value() {
+                max_note_spin_box.set_value(value);
+                config.max_note.store(value as _, SeqCst);
+            };
+        }
+    });
+    max_note_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        let mut min_note_spin_box = min_note_spin_box.clone();
+        move |value| {
+            history.borrow_mut().record("検出する音の範囲", &config);
+            config.max_note.store(value as _, SeqCst);
+            if value < min_note_spin_box.value() {
+                min_note_spin_box.set_value(value);
+                config.min_note.store(value as _, SeqCst);
+            };
+        }
+    });
+
+    // 候補の順位付けの方法
+    for strategy in crate::sys::RankingStrategy::ALL {
+        ranking_strategy_combo_box.append(tr(strategy.name()));
+    }
+    for plugin in config.plugins.get().into_iter().flatten() {
+        ranking_strategy_combo_box.append(plugin.name());
+    }
+    ranking_strategy_combo_box.set_selected(0);
+    ranking_strategy_combo_box.on_selected(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |index| {
+            history.borrow_mut().record("候補の順位付けの方法", &config);
+            config.ranking_strategy.store(index as _, SeqCst)
+        }
+    });
+
+    // 最低の信頼度
+    min_confidence_spin_box.set_value(config.min_confidence.load(SeqCst));
+    min_confidence_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("最低の信頼度", &config);
+            config.min_confidence.store(value, SeqCst)
+        }
+    });
+
+    // 検出が曖昧な場合
+    ambiguity_margin_spin_box.set_value(config.ambiguity_margin.load(SeqCst) as _);
+    ambiguity_margin_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("候補の差が小さいとみなす差", &config);
+            config.ambiguity_margin.store(value as _, SeqCst)
+        }
+    });
+    for policy in crate::sys::AmbiguityPolicy::ALL {
+        ambiguity_policy_combo_box.append(tr(policy.name()));
+    }
+    ambiguity_policy_combo_box.set_selected(config.ambiguity_policy.load(SeqCst) as _);
+    ambiguity_policy_combo_box.on_selected(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |index| {
+            history
+                .borrow_mut()
+                .record("候補の差が小さい時の扱い", &config);
+            config.ambiguity_policy.store(index as _, SeqCst)
+        }
+    });
+
+    // オクターブの固定
+    octave_lock_check_box.on_toggled(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("オクターブの固定", &config);
+            config.use_octave_lock.store(value, SeqCst)
+        }
+    });
+    octave_lock_reset_spin_box.set_value(config.octave_lock_reset.load(SeqCst) as _);
+    octave_lock_reset_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("オクターブの固定をやめるまでの時間", &config);
+            config.octave_lock_reset.store(value as _, SeqCst)
+        }
+    });
+
+    // 音階の名前の表し方
+    for naming in NoteNaming::ALL {
+        note_naming_combo_box.append(tr(naming.name()));
+    }
+    note_naming_combo_box.set_selected(config.note_naming.load(SeqCst) as _);
+    note_naming_combo_box.on_selected(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |index| {
+            history.borrow_mut().record("音階の名前の表し方", &config);
+            config.note_naming.store(index as _, SeqCst)
+        }
+    });
+
+    // 記譜の移調
+    // 表示だけを記譜上の音にし、MIDIでは実音を送る。
+    written_transposition_spin_box.set_value(config.written_transposition.load(SeqCst));
+    written_transposition_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("記譜の移調", &config);
+            config.written_transposition.store(value, SeqCst)
+        }
+    });
+
+    // - 二列目
+
+    // 無音データ
+    silent_data_button.on_clicked({
+        let config = Arc::clone(&config);
+        move |button| {
+            // 設定する場合も、前の無音データを忘れてから取り直す。
+            config.set_silence(None);
+            if button.text() == tr(texts::SET_SILENT_DATA) {
+                config.use_silent.store(true, SeqCst);
+                button.set_text(tr("無音データを忘れる"));
+            } else {
+                config.use_silent.store(false, SeqCst);
+                button.set_text(tr(texts::SET_SILENT_DATA));
+            }
+        }
+    });
+    save_silence_button.on_clicked({
+        let config = Arc::clone(&config);
+        let mut status_label = status_label.clone();
+        move |_| {
+            let Some(silence) = config.silence.lock().unwrap().clone() else {
+                status_label.set_text(tr(
+                    "保存する無音データがありません。先に無音データを設定してください。",
+                ));
+                return;
+            };
+
+            let result = data_path(SILENCE_FILE_NAME)
+                .context(tr("保存先のフォルダが見つかりません。"))
+                .and_then(|path| {
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)
+                            .context(tr("無音データを保存できませんでした。"))?;
+                    };
+                    std::fs::write(path, silence.to_text())
+                        .context(tr("無音データを保存できませんでした。"))
+                });
+            match result {
+                Ok(()) => status_label.set_text(tr("無音データを保存しました。")),
+                Err(error) => {
+                    let _ = AsyncMessageDialog::new()
+                        .set_title(crate::APPLICATION_NAME)
+                        .set_description(&error)
+                        .set_level(MessageLevel::Error)
+                        .show();
+                }
+            };
+        }
+    });
+    load_silence_button.on_clicked({
+        let config = Arc::clone(&config);
+        let mut silent_data_button = silent_data_button.clone();
+        let mut status_label = status_label.clone();
+        move |_| {
+            match data_path(SILENCE_FILE_NAME)
+                .context(tr("保存した無音データがありません。"))
+                .and_then(|path| {
+                    std::fs::read_to_string(path).context(tr("保存した無音データがありません。"))
+                })
+                .and_then(|text| SilenceProfile::parse(&text))
+            {
+                Ok(silence) => {
+                    status_label.set_text(&tr_format(
+                        "無音データ（FFTのサイズ：{}）を読み込みました。FFTのサイズが今と違う場合は使われません。",
+                        &[&silence.fft_length],
+                    ));
+                    config.set_silence(Some(Arc::new(silence)));
+                    config.use_silent.store(true, SeqCst);
+                    silent_data_button.set_text(tr("無音データを忘れる"));
+                }
+                Err(error) => {
+                    let _ = AsyncMessageDialog::new()
+                        .set_title(crate::APPLICATION_NAME)
+                        .set_description(&error)
+                        .set_level(MessageLevel::Error)
+                        .show();
                 }
-                Compact: let bottom_spacer = Spacer()
-            }
+            };
         }
-    }
-
-    /* ここからControlの設定 */
-
-    // 結果表示用のラベルの準備
-    let mut count = 0;
-    let note_labels = [(); NUMBER_OF_NOTE_IN_RESULT].map(move |_| {
-        count += 1;
-        let label = Label::new(&format!("{count}: 　　　　　　　"));
-        result_label_box.append(label.clone(), LayoutStrategy::Stretchy);
-        label
     });
 
-    // - 一列目
-
-    // 窓関数
-    window_check_box.on_toggled(&ui, {
+    // 雑音の追従
+    noise_reduction_check_box.set_checked(config.use_noise_reduction.load(SeqCst));
+    noise_reduction_check_box.on_toggled(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("雑音の追従", &config);
+            config.use_noise_reduction.store(value, SeqCst)
+        }
+    });
+    noise_over_subtraction_spin_box.set_value(config.noise_over_subtraction.load(SeqCst) as _);
+    noise_over_subtraction_spin_box.on_changed({
         let config = Arc::clone(&config);
-        move |value| config.use_window_flag.store(value, SeqCst)
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("追従した雑音を差し引く倍率", &config);
+            config.noise_over_subtraction.store(value as _, SeqCst)
+        }
     });
 
-    // 最低音量
-    min_detection_volume_spin_box.set_value(62);
-    min_detection_volume_spin_box.on_changed({
+    // ポイント数
+    point_length_size_spin_box.set_value(config.point_times.load(SeqCst) as _);
+    point_length_size_spin_box.on_changed({
         let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
         move |value| {
-            config
-                .min_volume
-                .store(((value as f32 / 100. - 1.) * 80.) as _, SeqCst)
+            history.borrow_mut().record("ポイント数の規模", &config);
+            config.point_times.store(value as _, SeqCst)
         }
     });
 
-    // 音階調節
-    pitch_control_spin_box.set_value(0);
-    pitch_control_spin_box.on_changed({
+    // FFTのサイズの上限
+    max_fft_length_spin_box.set_value((config.max_fft_length.load(SeqCst) / 1024) as _);
+    max_fft_length_spin_box.on_changed({
         let config = Arc::clone(&config);
-        move |value| config.adjustment_rate.store(value, SeqCst)
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("FFTのサイズの上限", &config);
+            config.max_fft_length.store(value as u32 * 1024, SeqCst)
+        }
     });
 
-    // - 二列目
+    // 解析の窓の長さと重なり
+    analysis_window_length_spin_box.set_value(config.analysis_window_length.load(SeqCst) as _);
+    analysis_window_length_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("解析の窓の長さ", &config);
+            config.analysis_window_length.store(value as _, SeqCst)
+        }
+    });
+    analysis_overlap_spin_box.set_value(config.analysis_overlap.load(SeqCst) as _);
+    analysis_overlap_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("解析の窓の重なり", &config);
+            config.analysis_overlap.store(value as _, SeqCst)
+        }
+    });
 
-    // 無音データ
-    silent_data_button.on_clicked({
+    // 履歴に使うメモリの上限
+    history_memory_limit_spin_box.set_value(config.history_memory_limit.load(SeqCst) as _);
+    history_memory_limit_spin_box.on_changed({
         let config = Arc::clone(&config);
-        move |button| {
-            if &button.text() == texts::SET_SILENT_DATA {
-                config.use_silent.store(true, SeqCst);
-                button.set_text("無音データを忘れる");
-            } else {
-                config.use_silent.store(false, SeqCst);
-                button.set_text(texts::SET_SILENT_DATA);
-            }
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("履歴に使うメモリの上限", &config);
+            config.history_memory_limit.store(value as _, SeqCst)
         }
     });
 
-    // ポイント数
-    point_length_size_spin_box.set_value(9);
-    point_length_size_spin_box.on_changed({
+    // 基準音での校正
+    let calibration = Rc::new(RefCell::new(None));
+    calibration_reference_spin_box.set_value(crate::sys::DEFAULT_REFERENCE_PITCH as _);
+    calibration_button.on_clicked({
         let config = Arc::clone(&config);
-        move |value| config.point_times.store(value as _, SeqCst)
+        let history = Rc::clone(&history);
+        let calibration = Rc::clone(&calibration);
+        let calibration_reference_spin_box = calibration_reference_spin_box.clone();
+        let mut status_label = status_label.clone();
+
+        move |_| {
+            *calibration.borrow_mut() = Some(Calibration {
+                reference: calibration_reference_spin_box.value() as _,
+                started_at: Instant::now(),
+                frequencies: Vec::new(),
+                config: Arc::clone(&config),
+                history: Rc::clone(&history),
+            });
+            status_label.set_text(tr("校正中です。基準音を鳴らし続けてください。"));
+        }
     });
 
+    // - 三列目
+
+    // 起動時の動作の設定
+    let startup_options = Rc::new(RefCell::new(StartupOptions::load()));
+
     // MIDIの出力先
-    midi_output_combo_box.append("なし");
-    for port_name in midi_port_names {
-        midi_output_combo_box.append(&port_name);
+    let midi_port_names = midi_port_names.collect::<Vec<_>>();
+    midi_output_combo_box.append(tr("なし"));
+    for port_name in &midi_port_names {
+        midi_output_combo_box.append(port_name);
     }
     midi_output_combo_box.set_selected(0);
 
+    // 設定されていれば、前回終了した時の出力先に接続する。
+    if startup_options.borrow().connect_last_midi_output {
+        if let Some(index) = load_last_midi_output().and_then(|name| {
+            midi_port_names
+                .iter()
+                .position(|port_name| *port_name == name)
+        }) {
+            midi_output_combo_box.set_selected(index as i32 + 1);
+            command_bus.dispatch(AppCommand::UpdateMidiOutput(index + 1));
+        };
+    };
+
     if midi_output_combo_box.count() == 0 {
         // もし一つもMIDIの出力先が見つからなかったのなら、そもそも使えないようにする。
         midi_output_combo_box.disable();
     };
 
-    midi_output_combo_box
-        .clone()
-        .on_selected(&ui, move |index| {
+    midi_output_combo_box.clone().on_selected(&ui, {
+        let command_bus = command_bus.clone();
+        move |index| {
             let index = index as usize;
             if index > midi_output_combo_box.count() as _ {
                 let _ = AsyncMessageDialog::new()
                     .set_title(crate::APPLICATION_NAME)
-                    .set_description("そのMIDIの出力先が見つかりませんでした。")
+                    .set_description(tr("そのMIDIの出力先が見つかりませんでした。"))
                     .set_level(MessageLevel::Error)
                     .show();
             } else {
-                let _ = event_sender.send(crate::Event::UpdateMidiOutput(index as _));
+                command_bus.dispatch(AppCommand::UpdateMidiOutput(index as _));
             }
-        });
+        }
+    });
+
+    // MIDIの音色
+    for (number, name) in crate::midi::gm_program_names().enumerate() {
+        midi_program_combo_box.append(&format!("{}: {}", number + 1, name));
+    }
+    midi_program_combo_box.set_selected(0);
+    midi_program_combo_box.on_selected(&ui, {
+        let command_bus = command_bus.clone();
+        move |index| {
+            command_bus.dispatch(AppCommand::UpdateMidiProgram(index as _));
+        }
+    });
+
+    // MIDIの音の長さ
+    fixed_note_length_check_box.on_toggled(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("MIDIの音の長さの固定", &config);
+            config.use_fixed_note_length.store(value, SeqCst)
+        }
+    });
+    fixed_note_length_spin_box.set_value(config.fixed_note_length.load(SeqCst) as _);
+    fixed_note_length_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("MIDIの音の長さ", &config);
+            config.fixed_note_length.store(value as _, SeqCst)
+        }
+    });
+
+    // MIDIの音を止めるまでの猶予
+    release_delay_spin_box.set_value(config.release_delay.load(SeqCst) as _);
+    release_delay_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("MIDIの音を止めるまでの猶予", &config);
+            config.release_delay.store(value as _, SeqCst)
+        }
+    });
+
+    // 鳴らしている音を送り直す間隔
+    note_refresh_interval_spin_box.set_value(config.note_refresh_interval.load(SeqCst) as _);
+    note_refresh_interval_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("鳴らしている音を送り直す間隔", &config);
+            config.note_refresh_interval.store(value as _, SeqCst)
+        }
+    });
+
+    // 鳴らしたままの音を止めるまでの時間
+    max_note_duration_spin_box.set_value(config.max_note_duration.load(SeqCst) as _);
+    max_note_duration_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("鳴らしたままの音を止めるまでの時間", &config);
+            config.max_note_duration.store(value as _, SeqCst)
+        }
+    });
+
+    // レガート
+    legato_check_box.on_toggled(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("レガート", &config);
+            config.use_legato.store(value, SeqCst)
+        }
+    });
+
+    // 同じ音の弾き直し
+    onset_retrigger_check_box.on_toggled(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("同じ音の弾き直し", &config);
+            config.use_onset_retrigger.store(value, SeqCst)
+        }
+    });
+    onset_threshold_spin_box.set_value(config.onset_threshold.load(SeqCst) as _);
+    onset_threshold_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("弾き直しとみなす変化の大きさ", &config);
+            config.onset_threshold.store(value as _, SeqCst)
+        }
+    });
+
+    // MIDIの音を出すまでの検出回数
+    min_note_frames_spin_box.set_value(config.min_note_frames.load(SeqCst) as _);
+    min_note_frames_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history
+                .borrow_mut()
+                .record("MIDIの音を出すまでの検出回数", &config);
+            config.min_note_frames.store(value as _, SeqCst)
+        }
+    });
+
+    // MIDIの音を出す最低の信頼度
+    min_midi_confidence_spin_box.set_value(config.min_midi_confidence.load(SeqCst));
+    min_midi_confidence_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("MIDIの最低の信頼度", &config);
+            config.min_midi_confidence.store(value, SeqCst)
+        }
+    });
+
+    // 中央値フィルタ
+    median_filter_size_spin_box.set_value(config.median_filter_size.load(SeqCst) as _);
+    median_filter_size_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("中央値フィルタ", &config);
+            config.median_filter_size.store(value as _, SeqCst)
+        }
+    });
+
+    // MIDIの移調
+    midi_transposition_spin_box.set_value(config.midi_transposition.load(SeqCst));
+    midi_transposition_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("MIDIの移調", &config);
+            config.midi_transposition.store(value, SeqCst)
+        }
+    });
+
+    // オクターブ重ね
+    for name in ["なし", "1オクターブ下", "1オクターブ上"] {
+        octave_doubling_combo_box.append(tr(name));
+    }
+    octave_doubling_combo_box.set_selected(0);
+    octave_doubling_combo_box.on_selected(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |index| {
+            history.borrow_mut().record("オクターブ重ね", &config);
+            config.octave_doubling.store(
+                match index {
+                    1 => -1,
+                    2 => 1,
+                    _ => 0,
+                },
+                SeqCst,
+            )
+        }
+    });
+    octave_doubling_velocity_spin_box.set_value(config.octave_doubling_velocity.load(SeqCst) as _);
+    octave_doubling_velocity_spin_box.on_changed({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |value| {
+            history.borrow_mut().record("重ねる音の強さ", &config);
+            config.octave_doubling_velocity.store(value as _, SeqCst)
+        }
+    });
+
+    // 入力の音量のコントロールチェンジ
+    for name in [
+        "送らない",
+        "ブレスコントローラー（CC2）",
+        "エクスプレッション（CC11）",
+    ] {
+        envelope_control_combo_box.append(tr(name));
+    }
+    envelope_control_combo_box.set_selected(0);
+    envelope_control_combo_box.on_selected(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        move |index| {
+            history
+                .borrow_mut()
+                .record("入力の音量のコントロールチェンジ", &config);
+            config.envelope_control.store(
+                match index {
+                    1 => 2,
+                    2 => 11,
+                    _ => 0,
+                },
+                SeqCst,
+            )
+        }
+    });
+
+    // ソステヌート
+    sostenuto_check_box.on_toggled(&ui, {
+        let command_bus = command_bus.clone();
+        move |value| {
+            command_bus.dispatch(AppCommand::UpdateSostenuto(value));
+        }
+    });
+
+    // MIDIのパニック
+    midi_panic_button.on_clicked(move |_| {
+        command_bus.dispatch(AppCommand::MidiPanic);
+    });
+
+    // 設定のコントロール
+    let settings_controls = SettingsControls {
+        window_function_combo_box: window_function_combo_box.clone(),
+        min_detection_volume_spin_box: min_detection_volume_spin_box.clone(),
+        a_weighting_check_box: a_weighting_check_box.clone(),
+        input_channel_spin_box: input_channel_spin_box.clone(),
+        high_pass_frequency_spin_box: high_pass_frequency_spin_box.clone(),
+        agc_check_box: agc_check_box.clone(),
+        agc_target_spin_box: agc_target_spin_box.clone(),
+        noise_reduction_check_box: noise_reduction_check_box.clone(),
+        noise_over_subtraction_spin_box: noise_over_subtraction_spin_box.clone(),
+        volume_hysteresis_spin_box: volume_hysteresis_spin_box.clone(),
+        gate_attack_spin_box: gate_attack_spin_box.clone(),
+        gate_hold_spin_box: gate_hold_spin_box.clone(),
+        gate_release_spin_box: gate_release_spin_box.clone(),
+        pitch_control_spin_box: pitch_control_spin_box.clone(),
+        reference_pitch_spin_box: reference_pitch_spin_box.clone(),
+        min_note_spin_box: min_note_spin_box.clone(),
+        max_note_spin_box: max_note_spin_box.clone(),
+        zoom_fft_check_box: zoom_fft_check_box.clone(),
+        drift_alarm_check_box: drift_alarm_check_box.clone(),
+        drift_alarm_threshold_spin_box: drift_alarm_threshold_spin_box.clone(),
+        drift_alarm_duration_spin_box: drift_alarm_duration_spin_box.clone(),
+        drift_alarm_click_check_box: drift_alarm_click_check_box.clone(),
+        auto_stop_spin_box: auto_stop_spin_box.clone(),
+        ranking_strategy_combo_box: ranking_strategy_combo_box.clone(),
+        min_confidence_spin_box: min_confidence_spin_box.clone(),
+        ambiguity_margin_spin_box: ambiguity_margin_spin_box.clone(),
+        ambiguity_policy_combo_box: ambiguity_policy_combo_box.clone(),
+        octave_lock_check_box: octave_lock_check_box.clone(),
+        octave_lock_reset_spin_box: octave_lock_reset_spin_box.clone(),
+        note_naming_combo_box: note_naming_combo_box.clone(),
+        written_transposition_spin_box: written_transposition_spin_box.clone(),
+        point_length_size_spin_box: point_length_size_spin_box.clone(),
+        analysis_window_length_spin_box: analysis_window_length_spin_box.clone(),
+        analysis_overlap_spin_box: analysis_overlap_spin_box.clone(),
+        history_memory_limit_spin_box: history_memory_limit_spin_box.clone(),
+        max_fft_length_spin_box: max_fft_length_spin_box.clone(),
+        fixed_note_length_check_box: fixed_note_length_check_box.clone(),
+        fixed_note_length_spin_box: fixed_note_length_spin_box.clone(),
+        release_delay_spin_box: release_delay_spin_box.clone(),
+        note_refresh_interval_spin_box: note_refresh_interval_spin_box.clone(),
+        max_note_duration_spin_box: max_note_duration_spin_box.clone(),
+        legato_check_box: legato_check_box.clone(),
+        onset_retrigger_check_box: onset_retrigger_check_box.clone(),
+        onset_threshold_spin_box: onset_threshold_spin_box.clone(),
+        min_note_frames_spin_box: min_note_frames_spin_box.clone(),
+        min_midi_confidence_spin_box: min_midi_confidence_spin_box.clone(),
+        median_filter_size_spin_box: median_filter_size_spin_box.clone(),
+        midi_transposition_spin_box: midi_transposition_spin_box.clone(),
+        octave_doubling_combo_box: octave_doubling_combo_box.clone(),
+        octave_doubling_velocity_spin_box: octave_doubling_velocity_spin_box.clone(),
+        envelope_control_combo_box: envelope_control_combo_box.clone(),
+    };
+
+    // 前回終了した時の設定に戻す。
+    if let Some(snapshot) = load_last_settings() {
+        config.restore(&snapshot);
+        settings_controls.clone().set(&snapshot);
+    };
+
+    // プリセット
+    let preset_store = Rc::new(RefCell::new(PresetStore::load()));
+    for name in preset_store.borrow().names() {
+        preset_combo_box.append(name);
+    }
+    preset_combo_box.on_selected(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        let preset_store = Rc::clone(&preset_store);
+        let mut settings_controls = settings_controls.clone();
+
+        move |index| {
+            if let Some(snapshot) = preset_store.borrow().get(index as _) {
+                history.borrow_mut().record("プリセット", &config);
+                config.restore(snapshot);
+                settings_controls.set(snapshot);
+            };
+        }
+    });
+
+    // 設定されていれば、起動時にプリセットを読み込む。
+    let startup_preset_index = startup_options.borrow().preset.as_ref().and_then(|name| {
+        preset_store
+            .borrow()
+            .names()
+            .position(|preset_name| preset_name == name)
+    });
+    if let Some(index) = startup_preset_index {
+        if let Some(snapshot) = preset_store.borrow().get(index) {
+            config.restore(snapshot);
+            settings_controls.clone().set(snapshot);
+            preset_combo_box.set_selected(index as _);
+        };
+    };
+
+    save_preset_button.on_clicked({
+        let config = Arc::clone(&config);
+        let preset_store = Rc::clone(&preset_store);
+        let mut preset_combo_box = preset_combo_box.clone();
+        let startup_preset_combo_box = startup_preset_combo_box.clone();
+        let mut status_label = status_label.clone();
+
+        move |_| {
+            let name = preset_name_entry.value().trim().to_string();
+            if name.is_empty() {
+                status_label.set_text(tr("プリセットの名前を入力してください。"));
+                return;
+            };
+
+            match preset_store.borrow_mut().save(&name, config.snapshot()) {
+                Ok(is_new) => {
+                    if is_new {
+                        preset_combo_box.append(&name);
+                        preset_combo_box.set_selected(preset_combo_box.count() - 1);
+                        startup_preset_combo_box.append(&name);
+                    };
+                    status_label.set_text(&tr_format("プリセット「{}」を保存しました。", &[&name]));
+                }
+                Err(error) => {
+                    let _ = AsyncMessageDialog::new()
+                        .set_title(crate::APPLICATION_NAME)
+                        .set_description(&tr_format(
+                            "プリセットの保存に失敗しました。\n{}",
+                            &[&error],
+                        ))
+                        .set_level(MessageLevel::Error)
+                        .show();
+                }
+            };
+        }
+    });
+
+    // 楽器のプロファイル
+    for profile in &INSTRUMENT_PROFILES {
+        instrument_profile_combo_box.append(tr(profile.name));
+    }
+    instrument_profile_combo_box.on_selected(&ui, {
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        let mut settings_controls = settings_controls.clone();
+
+        move |index| {
+            if let Some(profile) = INSTRUMENT_PROFILES.get(index as usize) {
+                let mut snapshot = config.snapshot();
+                profile.apply(&mut snapshot);
+
+                history.borrow_mut().record("楽器のプロファイル", &config);
+                config.restore(&snapshot);
+                settings_controls.set(&snapshot);
+            };
+        }
+    });
+
+    // 起動時の動作
+    startup_midi_output_check_box.set_checked(startup_options.borrow().connect_last_midi_output);
+    startup_midi_output_check_box.on_toggled(&ui, {
+        let startup_options = Rc::clone(&startup_options);
+        move |value| {
+            startup_options.borrow_mut().connect_last_midi_output = value;
+            save_startup_options(&startup_options.borrow());
+        }
+    });
+
+    startup_preset_combo_box.append(tr("なし"));
+    for name in preset_store.borrow().names() {
+        startup_preset_combo_box.append(name);
+    }
+    startup_preset_combo_box.set_selected(startup_preset_index.map_or(0, |index| index as i32 + 1));
+    startup_preset_combo_box.on_selected(&ui, {
+        let startup_options = Rc::clone(&startup_options);
+        let preset_store = Rc::clone(&preset_store);
+        move |index| {
+            // 一つ目は「なし」なので、プリセットの番号は一つずれる。
+            let name = (index as usize)
+                .checked_sub(1)
+                .and_then(|index| preset_store.borrow().names().nth(index).map(str::to_string));
+            startup_options.borrow_mut().preset = name;
+            save_startup_options(&startup_options.borrow());
+        }
+    });
+
+    // 音声のバックエンド
+    let audio_backend_names = audio_host_names();
+    let audio_backend = load_audio_backend();
+    audio_backend_combo_box.append(tr("既定"));
+    for name in audio_backend_names.iter() {
+        audio_backend_combo_box.append(name);
+    }
+    audio_backend_combo_box.set_selected(
+        audio_backend
+            .and_then(|backend| {
+                audio_backend_names
+                    .iter()
+                    .position(|name| name.eq_ignore_ascii_case(&backend))
+            })
+            .map_or(0, |index| index as i32 + 1),
+    );
+    audio_backend_combo_box.on_selected(&ui, move |index| {
+        // 一つ目は「既定」なので、バックエンドの番号は一つずれる。
+        let name = (index as usize)
+            .checked_sub(1)
+            .and_then(|index| audio_backend_names.get(index).copied());
+        if let Err(error) = save_audio_backend(name) {
+            let _ = AsyncMessageDialog::new()
+                .set_title(crate::APPLICATION_NAME)
+                .set_description(&tr_format(
+                    "音声のバックエンドの保存に失敗しました。\n{}",
+                    &[&error],
+                ))
+                .set_level(MessageLevel::Error)
+                .show();
+        };
+    });
 
     /* ここからウィンドウ自体に関する設定 */
 
     // メニューを作る。
     menu! { &ui,
-        let file_menu = Menu("ファイル") {
-            let quit_menu_item = MenuItem("終了")
+        let file_menu = Menu(tr("ファイル")) {
+            let analyze_file_menu_item = MenuItem(tr("音声ファイルを解析..."))
+            let load_scale_menu_item = MenuItem(tr("Scalaの音律ファイルを読み込む..."))
+            let reset_scale_menu_item = MenuItem(tr("平均律に戻す"))
+            let load_mic_profile_menu_item = MenuItem(tr("マイクの周波数特性を読み込む..."))
+            let reset_mic_profile_menu_item = MenuItem(tr("マイクの補正をやめる"))
+            let quit_menu_item = MenuItem(tr("終了"))
+        }
+        let edit_menu = Menu(tr("編集")) {
+            let undo_menu_item = MenuItem(tr("変更を元に戻す"))
+            let reset_all_menu_item = MenuItem(tr("すべてリセット"))
         }
-        let help_menu = Menu("ヘルプ") {
-            let about_menu_item = MenuItem("このアプリについて")
+        let reset_menu = Menu(tr("デフォルトに戻す")) {}
+        let view_menu = Menu(tr("表示")) {}
+        let help_menu = Menu(tr("ヘルプ")) {
+            let stream_stats_menu_item = MenuItem(tr("入力の状態"))
+            let practice_summary_menu_item = MenuItem(tr("練習のまとめ"))
+            let reveal_log_menu_item = MenuItem(tr("ログのフォルダを開く"))
+            let timeline_menu_item = MenuItem(tr("タイムライン（開発者向け）"))
+            let about_menu_item = MenuItem(tr("このアプリについて"))
         }
+        let language_menu = Menu("言語 / Language") {}
     }
 
+    analyze_file_menu_item.on_clicked({
+        let config = Arc::clone(&config);
+        let mut status_label = status_label.clone();
+
+        move |_, _| {
+            let Some(path) = FileDialog::new()
+                .add_filter(tr("音声ファイル"), &["wav", "flac"])
+                .pick_file()
+            else {
+                return;
+            };
+
+            let detected_notes = match file_analysis::decode(&path) {
+                Ok(audio) => file_analysis::analyze(&audio, &config.snapshot()),
+                Err(error) => {
+                    let _ = AsyncMessageDialog::new()
+                        .set_title(crate::APPLICATION_NAME)
+                        .set_description(&error)
+                        .set_level(MessageLevel::Error)
+                        .show();
+                    return;
+                }
+            };
+            status_label.set_text(&tr_format(
+                "音声ファイルから{}個の音を検出しました。",
+                &[&detected_notes.len()],
+            ));
+
+            // 検出した音の並びをCSVで保存する。
+            if let Some(csv_path) = FileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .set_file_name("notes.csv")
+                .save_file()
+            {
+                if let Err(error) = std::fs::write(csv_path, file_analysis::to_csv(&detected_notes))
+                {
+                    let _ = AsyncMessageDialog::new()
+                        .set_title(crate::APPLICATION_NAME)
+                        .set_description(&tr_format("保存に失敗しました。\n{}", &[&error]))
+                        .set_level(MessageLevel::Error)
+                        .show();
+                };
+            };
+        }
+    });
+    load_scale_menu_item.on_clicked({
+        let config = Arc::clone(&config);
+        let mut status_label = status_label.clone();
+
+        move |_, _| {
+            let Some(path) = FileDialog::new()
+                .add_filter(tr("Scalaの音律ファイル"), &["scl"])
+                .pick_file()
+            else {
+                return;
+            };
+
+            match std::fs::read_to_string(path)
+                .context(tr("ファイルを読み込めませんでした。"))
+                .and_then(|text| crate::sys::scala::Scale::parse(&text))
+            {
+                Ok(scale) => {
+                    status_label.set_text(&tr_format(
+                        "音律「{}」（{}音）を読み込みました。",
+                        &[&scale.description, &scale.ratios.len()],
+                    ));
+                    config.set_scale(Some(Arc::new(scale)));
+                }
+                Err(error) => {
+                    let _ = AsyncMessageDialog::new()
+                        .set_title(crate::APPLICATION_NAME)
+                        .set_description(&error)
+                        .set_level(MessageLevel::Error)
+                        .show();
+                }
+            };
+        }
+    });
+    reset_scale_menu_item.on_clicked({
+        let config = Arc::clone(&config);
+        let mut status_label = status_label.clone();
+
+        move |_, _| {
+            config.set_scale(None);
+            status_label.set_text(tr("平均律に戻しました。"));
+        }
+    });
+    load_mic_profile_menu_item.on_clicked({
+        let config = Arc::clone(&config);
+        let mut status_label = status_label.clone();
+
+        move |_, _| {
+            let Some(path) = FileDialog::new()
+                .add_filter(tr("周波数特性のファイル"), &["csv", "txt", "cal", "frd"])
+                .pick_file()
+            else {
+                return;
+            };
+
+            match std::fs::read_to_string(path)
+                .context(tr("ファイルを読み込めませんでした。"))
+                .and_then(|text| crate::sys::mic_profile::MicProfile::parse(&text))
+            {
+                Ok(profile) => {
+                    status_label.set_text(&tr_format(
+                        "マイクの周波数特性（{}点）を読み込みました。",
+                        &[&profile.point_count()],
+                    ));
+                    config.set_mic_profile(Some(Arc::new(profile)));
+                }
+                Err(error) => {
+                    let _ = AsyncMessageDialog::new()
+                        .set_title(crate::APPLICATION_NAME)
+                        .set_description(&error)
+                        .set_level(MessageLevel::Error)
+                        .show();
+                }
+            };
+        }
+    });
+    reset_mic_profile_menu_item.on_clicked({
+        let config = Arc::clone(&config);
+        let mut status_label = status_label.clone();
+
+        move |_, _| {
+            config.set_mic_profile(None);
+            status_label.set_text(tr("マイクの補正をやめました。"));
+        }
+    });
     quit_menu_item.on_clicked({
         let ui = ui.clone();
         move |_, _| ui.quit()
     });
+    stream_stats_menu_item.on_clicked({
+        let stream_stats = Arc::clone(&stream_stats);
+        let memory_budget = memory_budget.clone();
+        move |_, _| {
+        let _ = AsyncMessageDialog::new()
+            .set_title(tr("入力の状態"))
+            .set_description(&tr_format(
+                "受け取ったフレーム数：{}\n一回に受け取るフレーム数の平均：{}\n取りこぼしとみなした回数：{}\nオクターブの誤検出とみなした回数：{}（続けて検出した中の{}%）\n最後のエラー：{}\n履歴に使っているメモリ：{} / {} KB",
+                &[
+                    &stream_stats.frames_received(),
+                    &format!("{:.1}", stream_stats.average_callback_size()),
+                    &stream_stats.underruns(),
+                    &stream_stats.octave_jumps(),
+                    &format!("{:.1}", stream_stats.octave_jump_rate()),
+                    &stream_stats
+                        .last_error()
+                        .unwrap_or_else(|| tr("なし").to_string()),
+                    &(memory_budget.usage() / 1024),
+                    &(memory_budget.limit() / 1024),
+                ],
+            ))
+            .set_level(MessageLevel::Info)
+            .show();
+        }
+    });
+    practice_summary_menu_item.on_clicked({
+        let practice_timer = Rc::clone(&practice_timer);
+        let vibrato_stats = Rc::clone(&vibrato_stats);
+        move |_, _| show_practice_summary(&practice_timer.borrow(), &vibrato_stats.borrow())
+    });
+    reveal_log_menu_item.on_clicked(|_, _| {
+        if let Err(error) = logging::reveal() {
+            let _ = AsyncMessageDialog::new()
+                .set_title(crate::APPLICATION_NAME)
+                .set_description(&tr_format(
+                    "ログのフォルダを開けませんでした。\n{}",
+                    &[&error],
+                ))
+                .set_level(MessageLevel::Error)
+                .show();
+        };
+    });
     about_menu_item.on_clicked(|_, _| app_meta::show_about());
 
+    // 設定の変更を元に戻す。
+    undo_menu_item.on_clicked({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        let mut settings_controls = settings_controls.clone();
+        let mut status_label = status_label.clone();
+
+        move |_, _| {
+            let Some((name, snapshot)) = history.borrow_mut().undo() else {
+                status_label.set_text(tr("元に戻せる変更がありません。"));
+                return;
+            };
+            config.restore(&snapshot);
+            settings_controls.set(&snapshot);
+
+            status_label.set_text(&tr_format("「{}」の変更を元に戻しました。", &[&tr(name)]));
+        }
+    });
+
+    // 全ての設定を初期値に戻す。
+    reset_all_menu_item.on_clicked({
+        let config = Arc::clone(&config);
+        let history = Rc::clone(&history);
+        let mut settings_controls = settings_controls.clone();
+        let mut status_label = status_label.clone();
+
+        move |_, _| {
+            let snapshot = AppConfigSnapshot::default();
+            history.borrow_mut().record("すべてリセット", &config);
+            config.restore(&snapshot);
+            settings_controls.set(&snapshot);
+
+            status_label.set_text(tr("全ての設定をデフォルトに戻しました。"));
+        }
+    });
+
+    // 設定の値を一つずつ初期値に戻す。
+    for param in params::all() {
+        let menu_item = reset_menu.append_item(tr(param.label));
+        menu_item.on_clicked({
+            let config = Arc::clone(&config);
+            let history = Rc::clone(&history);
+            let mut settings_controls = settings_controls.clone();
+            let mut status_label = status_label.clone();
+
+            move |_, _| {
+                let mut snapshot = config.snapshot();
+                snapshot.apply_line(&param.default_line());
+                history.borrow_mut().record(param.label, &config);
+                config.restore(&snapshot);
+                settings_controls.set(&snapshot);
+
+                status_label.set_text(&tr_format(
+                    "「{}」をデフォルトに戻しました。",
+                    &[&tr(param.label)],
+                ));
+            }
+        });
+    }
+
+    // 音階モニタの列の表示の切り替え
+    for (column, column_box) in MonitorColumn::ALL.iter().zip(note_column_boxes) {
+        let column_menu_item = view_menu.append_check_item(tr(column.name()));
+        column_menu_item.set_checked(column.is_default());
+        column_menu_item.on_clicked({
+            let mut column_box = column_box;
+            move |menu_item, _| {
+                if menu_item.checked() {
+                    column_box.show();
+                } else {
+                    column_box.hide();
+                };
+            }
+        });
+    }
+
+    // 一番目に表示する音の切り替え
+    let use_stable_note = Rc::new(Cell::new(false));
+    view_menu.append_separator();
+    let stable_note_menu_item = view_menu.append_check_item(tr("最も安定している音を一番目に表示"));
+    stable_note_menu_item.on_clicked({
+        let use_stable_note = Rc::clone(&use_stable_note);
+        move |menu_item, _| use_stable_note.set(menu_item.checked())
+    });
+
+    // 言語の切り替え
+    // 今の言語が読めなくても選べるように、メニューの名前と言語の名前は翻訳しない。
+    let language_menu_items =
+        Language::ALL.map(|language| language_menu.append_check_item(language.name()));
+    for (language, menu_item) in Language::ALL.into_iter().zip(&language_menu_items) {
+        menu_item.set_checked(language == locale::language());
+        menu_item.on_clicked({
+            let language_menu_items = language_menu_items.clone();
+            move |_, _| {
+                for (other, menu_item) in Language::ALL.iter().zip(&language_menu_items) {
+                    menu_item.set_checked(*other == language);
+                }
+
+                let (description, level) = match locale::save_language(language) {
+                    Ok(()) => (
+                        tr("言語の設定は、次に起動した時に反映されます。").to_string(),
+                        MessageLevel::Info,
+                    ),
+                    Err(error) => (
+                        tr_format("保存に失敗しました。\n{}", &[&error]),
+                        MessageLevel::Error,
+                    ),
+                };
+                let _ = AsyncMessageDialog::new()
+                    .set_title(crate::APPLICATION_NAME)
+                    .set_description(&description)
+                    .set_level(level)
+                    .show();
+            }
+        });
+    }
+
+    // 開発者向けのタイムラインのウィンドウを作る。
+    layout! { &ui,
+        let timeline_box = VerticalBox(padded: true) {
+            Compact: let timeline_filter_combo_box = Combobox() {}
+            Stretchy: let timeline_entry = MultilineEntry()
+        }
+    }
+    let timeline_window_state = Rc::new(TimelineWindowState::default());
+    timeline_filter_combo_box.append(tr("全ての出来事"));
+    for kind in TimelineKind::ALL {
+        timeline_filter_combo_box.append(tr(kind.name()));
+    }
+    timeline_filter_combo_box.set_selected(0);
+    timeline_filter_combo_box.on_selected(&ui, {
+        let timeline_window_state = Rc::clone(&timeline_window_state);
+        move |index| {
+            timeline_window_state.filter.set(
+                usize::try_from(index - 1)
+                    .ok()
+                    .and_then(|index| TimelineKind::ALL.get(index).copied()),
+            )
+        }
+    });
+
+    let mut timeline_window = Window::new(
+        &ui,
+        tr("タイムライン（開発者向け）"),
+        640,
+        400,
+        WindowType::NoMenubar,
+    );
+    timeline_window.set_margined(true);
+    timeline_window.set_child(timeline_box);
+    timeline_window.on_closing(&ui, {
+        let timeline_window_state = Rc::clone(&timeline_window_state);
+        move |window| {
+            window.hide();
+            timeline_window_state.is_shown.set(false);
+        }
+    });
+    timeline_menu_item.on_clicked({
+        let timeline_window_state = Rc::clone(&timeline_window_state);
+        move |_, _| {
+            timeline_window.show();
+            timeline_window_state.is_shown.set(true);
+        }
+    });
+
     // ウィンドウを作る。
     let mut window = Window::new(
         &ui,
@@ -177,6 +3201,54 @@ pub fn make_ui<const NUMBER_OF_NOTE_IN_RESULT: usize>(
         WindowType::HasMenubar,
     );
     window.set_child(layout);
+    // ウィンドウを閉じた場合も、メニューの「終了」と同じ終了の処理を行う。
+    window.on_closing(&ui, {
+        let ui = ui.clone();
+        move |_| ui.quit()
+    });
 
-    (ui, window, note_labels)
+    (
+        window,
+        Monitor {
+            note_labels,
+            status_label,
+            config,
+            use_stable_note,
+            recent_notes: VecDeque::with_capacity(STABLE_NOTE_FRAMES),
+            tuner_labels: [tuner_note_label, tuner_frequency_label, tuner_cents_label],
+            tuner_cents,
+            tuner_area,
+            drift_detector: DriftDetector::default(),
+            drift_alarm_until,
+            click_player: ClickPlayer::default(),
+            vibrato_analyzer: VibratoAnalyzer::new(&memory_budget),
+            vibrato_label: tuner_vibrato_label,
+            vibrato_stats,
+            practice_timer,
+            practice_time_label,
+            shown_practice_seconds: None,
+            level_label,
+            level_meter,
+            clip_label,
+            clip_until: None,
+            shown_level: None,
+            latency_label,
+            latency_shown_at: None,
+            stream_stats: Arc::clone(&stream_stats),
+            calibration,
+            spectra,
+            spectrum_area,
+            spectrogram_area,
+            script_console,
+            script_log: History::new(&memory_budget, LOG_SHARE, None),
+            score_comparison_label,
+            score_following_labels: [score_expected_label, score_judgement_label],
+            timeline,
+            timeline_window_state,
+            timeline_entry,
+            shown_timeline: None,
+            timeline_shown_at: None,
+            recorded_stream_error: None,
+        },
+    )
 }
@@ -0,0 +1,227 @@
+//! 伸ばしている音の音程の揺れから、ビブラートの速さと深さを求めるためのモジュールです。
+//! 声楽等で、ビブラートの練習に使うためのものです。
+
+use std::{
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    history::{History, MemoryBudget, PITCH_SHARE},
+    sys::Note,
+};
+
+/// ビブラートを求めるのに使う、音程の記録の長さ
+const WINDOW: Duration = Duration::from_secs(1);
+/// ビブラートを求めるのに最低限必要な、音程の記録の長さ
+const MIN_SPAN: Duration = Duration::from_millis(750);
+/// ビブラートを求めるのに最低限必要な検出回数
+const MIN_FRAMES: usize = 8;
+/// 別の音になったとみなす、一つ前の検出からの音程の変化（セント）
+const JUMP_CENTS: f32 = 150.;
+/// ビブラートとみなす深さ（セント）の下限
+const MIN_DEPTH: f32 = 5.;
+/// ビブラートとみなす速さ（Hz）の範囲
+const RATE_RANGE: RangeInclusive<f32> = 2.0..=12.0;
+/// 揺れの向きが変わったとみなすために、中心から離れる必要がある幅（深さに対する割合）
+/// 細かい雑音で向きが変わったとみなさないようにするためのものです。
+const HYSTERESIS_RATIO: f32 = 0.3;
+/// 統計で、続けて検出したとみなす検出の間隔の上限
+const MAX_STATS_GAP: Duration = Duration::from_millis(200);
+
+/// ビブラートの速さと深さです。
+#[derive(Clone, Copy, Debug)]
+pub struct Vibrato {
+    /// 速さ（一秒間に揺れる回数、Hz）
+    pub rate: f32,
+    /// 深さ（中心から上下に揺れる幅、セント）
+    pub depth: f32,
+}
+
+/// 伸ばしている音の音程の記録から、ビブラートを求めるための構造体です。
+pub struct VibratoAnalyzer {
+    /// 最近の検出時刻と音程（MIDIの番号の0の音からのセント）
+    pitches: History<f32>,
+}
+
+impl VibratoAnalyzer {
+    pub fn new(budget: &MemoryBudget) -> Self {
+        Self {
+            pitches: History::new(budget, PITCH_SHARE, Some(WINDOW)),
+        }
+    }
+
+    /// 音程の記録を忘れます。
+    pub fn reset(&mut self) {
+        self.pitches.clear();
+    }
+
+    /// 一番目に検出した音を受け取り、ビブラートがかかっていればその速さと深さを返します。
+    pub fn process(&mut self, note: Option<&Note>) -> Option<Vibrato> {
+        let Some(note) = note else {
+            self.reset();
+            return None;
+        };
+
+        // 揺れで音階の番号が変わっても続けて扱えるように、番号とセントを合わせた音程で記録する。
+        let pitch = note.number as f32 * 100. + note.cents;
+        if let Some((_, last)) = self.pitches.back() {
+            if (pitch - last).abs() > JUMP_CENTS {
+                self.reset();
+            };
+        };
+
+        self.pitches.push(Instant::now(), pitch);
+
+        self.analyze()
+    }
+
+    /// 記録した音程の揺れを調べます。
+    fn analyze(&self) -> Option<Vibrato> {
+        let (first, _) = *self.pitches.front()?;
+        let (last, _) = *self.pitches.back()?;
+        if self.pitches.len() < MIN_FRAMES || last.duration_since(first) < MIN_SPAN {
+            return None;
+        };
+
+        // 音程の全体的な上がり下がりを取り除くため、直線を当てはめて、そこからの差を揺れとする。
+        let points = || {
+            self.pitches
+                .iter()
+                .map(move |(time, pitch)| (time.duration_since(first).as_secs_f32(), *pitch))
+        };
+        let length = self.pitches.len() as f32;
+        let mean_time = points().map(|(time, _)| time).sum::<f32>() / length;
+        let mean_pitch = points().map(|(_, pitch)| pitch).sum::<f32>() / length;
+        let (covariance, variance) =
+            points().fold((0., 0.), |(covariance, variance), (time, pitch)| {
+                let time = time - mean_time;
+                (
+                    covariance + time * (pitch - mean_pitch),
+                    variance + time * time,
+                )
+            });
+        let slope = if variance > 0. {
+            covariance / variance
+        } else {
+            0.
+        };
+        let deviations = || {
+            points()
+                .map(move |(time, pitch)| (time, pitch - mean_pitch - slope * (time - mean_time)))
+        };
+
+        // 正弦波とみなして、揺れの二乗平均平方根から深さを求める。
+        let depth = (2.
+            * deviations()
+                .map(|(_, deviation)| deviation * deviation)
+                .sum::<f32>()
+            / length)
+            .sqrt();
+        if depth < MIN_DEPTH {
+            return None;
+        };
+
+        // 揺れの向きが変わった回数と、最初と最後に変わった時刻から速さを求める。
+        let threshold = depth * HYSTERESIS_RATIO;
+        let mut is_upper = None;
+        let mut turns = 0;
+        let (mut first_turn, mut last_turn) = (0., 0.);
+        for (time, deviation) in deviations() {
+            let current = if deviation > threshold {
+                true
+            } else if deviation < -threshold {
+                false
+            } else {
+                continue;
+            };
+            match is_upper {
+                Some(previous) if previous != current => {
+                    if turns == 0 {
+                        first_turn = time;
+                    };
+                    last_turn = time;
+                    turns += 1;
+                }
+                _ => {}
+            };
+            is_upper = Some(current);
+        }
+        if turns < 3 || last_turn <= first_turn {
+            return None;
+        };
+
+        // 向きが二回変わると一回揺れたことになる。
+        let rate = (turns - 1) as f32 / 2. / (last_turn - first_turn);
+        RATE_RANGE
+            .contains(&rate)
+            .then_some(Vibrato { rate, depth })
+    }
+}
+
+/// 起動してから検出したビブラートの統計です。
+#[derive(Default)]
+pub struct VibratoStats {
+    /// ビブラートを検出していた時間
+    duration: Duration,
+    /// 検出した回数と、速さと深さの合計
+    count: u32,
+    rate_sum: f32,
+    depth_sum: f32,
+    /// 速さの範囲と、深さの最大
+    min_rate: f32,
+    max_rate: f32,
+    max_depth: f32,
+    /// 最後にビブラートを検出した時刻（途切れた場合は`None`）
+    last_detected: Option<Instant>,
+}
+
+/// ビブラートの統計のまとめです。
+pub struct VibratoSummary {
+    pub duration: Duration,
+    pub average_rate: f32,
+    pub min_rate: f32,
+    pub max_rate: f32,
+    pub average_depth: f32,
+    pub max_depth: f32,
+}
+
+impl VibratoStats {
+    /// 検出の結果を受け取って統計に加えます。
+    pub fn add(&mut self, vibrato: Option<Vibrato>) {
+        let Some(vibrato) = vibrato else {
+            self.last_detected = None;
+            return;
+        };
+
+        let now = Instant::now();
+        if let Some(last_detected) = self.last_detected {
+            self.duration += now.duration_since(last_detected).min(MAX_STATS_GAP);
+        };
+        self.last_detected = Some(now);
+
+        if self.count == 0 {
+            self.min_rate = vibrato.rate;
+            self.max_rate = vibrato.rate;
+        } else {
+            self.min_rate = self.min_rate.min(vibrato.rate);
+            self.max_rate = self.max_rate.max(vibrato.rate);
+        };
+        self.max_depth = self.max_depth.max(vibrato.depth);
+        self.count += 1;
+        self.rate_sum += vibrato.rate;
+        self.depth_sum += vibrato.depth;
+    }
+
+    /// 統計をまとめます。まだビブラートを検出していない場合は`None`を返します。
+    pub fn summary(&self) -> Option<VibratoSummary> {
+        (self.count > 0).then(|| VibratoSummary {
+            duration: self.duration,
+            average_rate: self.rate_sum / self.count as f32,
+            min_rate: self.min_rate,
+            max_rate: self.max_rate,
+            average_depth: self.depth_sum / self.count as f32,
+            max_depth: self.max_depth,
+        })
+    }
+}